@@ -0,0 +1,18 @@
+use email_collector::server::{Protocol, Server};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+// try with: swaks --server 127.0.0.1:2525 --to news@feeds.example
+#[tokio::main]
+async fn main() {
+    let listener = TcpListener::bind("127.0.0.1:2525").await.unwrap();
+    let (sender, mut receiver) = mpsc::channel(10);
+    tokio::spawn(async move {
+        Server::new("feeds.example", Protocol::Smtp)
+            .run(listener, sender)
+            .await
+    });
+    while let Some(received) = receiver.recv().await {
+        println!("{:?}", received);
+    }
+}
@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate log;
+
+pub mod parser;
+pub mod result;
+pub mod server;
+pub mod types;
@@ -0,0 +1,122 @@
+use crate::result::{Error, Result};
+use crate::types::{Attachment, Email};
+use chrono::NaiveDateTime;
+use mail_parser::{MessageParser, MimeHeaders, PartType};
+
+/// Parses raw RFC 5322 message: html body is preferred, plain text is used otherwise.
+pub fn parse_email(raw: &[u8]) -> Result<Email> {
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| Error::ParseError("not a mime message".to_string()))?;
+
+    let html = message
+        .html_part(0)
+        .filter(|p| p.is_text_html())
+        .and_then(|p| p.text_contents());
+    let (content, is_html) = match html {
+        Some(html) => (html.trim().to_string(), true),
+        None => (
+            message
+                .text_part(0)
+                .and_then(|p| p.text_contents())
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            false,
+        ),
+    };
+
+    let inline_images = message
+        .parts
+        .iter()
+        .filter_map(|part| {
+            let data = match &part.body {
+                PartType::Binary(data) | PartType::InlineBinary(data) => data,
+                _ => return None,
+            };
+            let content_type = part.content_type()?;
+            if !content_type.ctype().eq_ignore_ascii_case("image") {
+                return None;
+            }
+            // only images referenced from the body, regular attachments are skipped
+            let content_id = part.content_id()?;
+            Some(Attachment {
+                content_id: Some(content_id.to_string()),
+                file_name: part.attachment_name().map(String::from),
+                content_type: format!(
+                    "{}/{}",
+                    content_type.ctype(),
+                    content_type.subtype().unwrap_or("octet-stream")
+                )
+                .to_lowercase(),
+                data: data.to_vec(),
+            })
+        })
+        .collect();
+
+    let from = message.from().and_then(|f| f.first());
+    Ok(Email {
+        message_id: message.message_id().map(String::from),
+        subject: message.subject().map(String::from),
+        from_name: from.and_then(|f| f.name()).map(String::from),
+        from_address: from.and_then(|f| f.address()).map(String::from),
+        date: message
+            .date()
+            .map(|d| NaiveDateTime::from_timestamp(d.to_timestamp(), 0)),
+        content,
+        is_html,
+        inline_images,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_email;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_html_newsletter() {
+        let email = parse_email(include_bytes!("../fixtures/newsletter.eml")).unwrap();
+        assert_eq!(
+            email.message_id,
+            Some("issue-395@this-week-in-rust.org".to_string())
+        );
+        assert_eq!(email.subject, Some("This Week in Rust 395".to_string()));
+        assert_eq!(email.from_name, Some("This Week in Rust".to_string()));
+        assert_eq!(
+            email.from_address,
+            Some("newsletter@this-week-in-rust.org".to_string())
+        );
+        assert_eq!(
+            email.date,
+            Some(NaiveDate::from_ymd(2021, 6, 16).and_hms(4, 0, 0))
+        );
+        assert!(email.is_html);
+        assert_eq!(
+            email.content,
+            "<h1>This Week in Rust 395</h1><img src=\"cid:ferris@twir\">"
+        );
+        assert_eq!(email.inline_images.len(), 1);
+        let image = &email.inline_images[0];
+        assert_eq!(image.content_id, Some("ferris@twir".to_string()));
+        assert_eq!(image.file_name, Some("ferris.png".to_string()));
+        assert_eq!(image.content_type, "image/png");
+        assert_eq!(image.data, b"PNG!".to_vec());
+    }
+
+    #[test]
+    fn test_parse_plain_text() {
+        let raw = "From: <digest@example.org>\r\n\
+            Subject: Digest\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            \r\n\
+            Plain text only\r\n";
+        let email = parse_email(raw.as_bytes()).unwrap();
+        assert!(!email.is_html);
+        assert_eq!(email.content, "Plain text only");
+        assert_eq!(email.from_name, None);
+        assert_eq!(email.message_id, None);
+        assert_eq!(email.date, None);
+        assert!(email.inline_images.is_empty());
+    }
+}
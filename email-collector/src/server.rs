@@ -0,0 +1,579 @@
+use crate::parser::parse_email;
+use crate::result::{Error, Result};
+use crate::types::ReceivedEmail;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+const MAX_LINE_LENGTH: usize = 4096;
+const MAX_RECIPIENTS: usize = 100;
+// RFC 5321 asks for 5 minutes and more, clients hanging longer are dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Smtp,
+    // RFC 2033, for delivery from a local MTA
+    Lmtp,
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "smtp" => Ok(Protocol::Smtp),
+            "lmtp" => Ok(Protocol::Lmtp),
+            _ => Err(format!("unknown protocol: {}", s)),
+        }
+    }
+}
+
+/// Tells whether mail for the token is accepted, `Err` is a temporary failure.
+pub type MailboxCheck = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = std::result::Result<bool, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Minimal SMTP/LMTP receiver.
+///
+/// Accepts mail for `<token>@<domain>` only and passes parsed messages to the channel.
+/// Without a mailbox check any valid token is accepted.
+/// It doesn't relay anything and has no authentication, so it is meant to be run
+/// behind a real MTA or on a private network.
+#[derive(Clone)]
+pub struct Server {
+    domain: String,
+    protocol: Protocol,
+    max_message_size: usize,
+    read_timeout: Duration,
+    mailbox_check: Option<MailboxCheck>,
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("domain", &self.domain)
+            .field("protocol", &self.protocol)
+            .field("max_message_size", &self.max_message_size)
+            .field("read_timeout", &self.read_timeout)
+            .finish()
+    }
+}
+
+impl Server {
+    pub fn new(domain: &str, protocol: Protocol) -> Self {
+        Self {
+            domain: domain.to_lowercase(),
+            protocol,
+            max_message_size: 10 * 1024 * 1024,
+            read_timeout: READ_TIMEOUT,
+            mailbox_check: None,
+        }
+    }
+
+    /// Unknown recipients are rejected with `550` at RCPT time.
+    pub fn with_mailbox_check(mut self, mailbox_check: MailboxCheck) -> Self {
+        self.mailbox_check = Some(mailbox_check);
+        self
+    }
+
+    async fn mailbox_reply(&self, token: &str) -> &'static str {
+        let check = match &self.mailbox_check {
+            Some(check) => check,
+            None => return "250 2.1.5 OK",
+        };
+        match check(token.to_string()).await {
+            Ok(true) => "250 2.1.5 OK",
+            Ok(false) => "550 5.1.1 mailbox unavailable",
+            Err(e) => {
+                warn!("can't check mailbox {}: {}", token, e);
+                "451 4.3.0 try again later"
+            }
+        }
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// The connection is closed once the client sends nothing for the timeout.
+    pub fn with_read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    pub async fn run(
+        self,
+        listener: TcpListener,
+        sender: mpsc::Sender<ReceivedEmail>,
+    ) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            debug!("new {:?} connection from {}", self.protocol, peer);
+            let server = self.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream, sender).await {
+                    warn!(
+                        "{:?} connection from {} failed: {}",
+                        server.protocol, peer, e
+                    );
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(
+        &self,
+        stream: TcpStream,
+        sender: mpsc::Sender<ReceivedEmail>,
+    ) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut session = Session::default();
+        reply(&mut writer, &format!("220 {} feeder ready", self.domain)).await?;
+
+        let mut line = vec![];
+        loop {
+            line.clear();
+            if read_line(&mut reader, &mut line, self.read_timeout).await? == 0 {
+                return Ok(());
+            }
+            let command = String::from_utf8_lossy(&line).trim().to_string();
+            let (verb, argument) = command.split_once(' ').unwrap_or((command.as_str(), ""));
+            let verb = verb.to_ascii_uppercase();
+            let argument = argument.trim();
+
+            match verb.as_str() {
+                "HELO" | "EHLO" if self.protocol == Protocol::Smtp => {
+                    session = Session::default();
+                    session.greeted = true;
+                    match verb.as_str() {
+                        "HELO" => reply(&mut writer, &format!("250 {}", self.domain)).await?,
+                        _ => self.reply_extensions(&mut writer).await?,
+                    }
+                }
+                "LHLO" if self.protocol == Protocol::Lmtp => {
+                    session = Session::default();
+                    session.greeted = true;
+                    self.reply_extensions(&mut writer).await?
+                }
+                "MAIL" => {
+                    if !session.greeted {
+                        reply(&mut writer, "503 5.5.1 say hello first").await?;
+                        continue;
+                    }
+                    session.reset();
+                    session.sender = Some(argument.to_string());
+                    reply(&mut writer, "250 2.1.0 OK").await?
+                }
+                "RCPT" => {
+                    if session.sender.is_none() {
+                        reply(&mut writer, "503 5.5.1 need MAIL command").await?;
+                        continue;
+                    }
+                    if session.recipients.len() >= MAX_RECIPIENTS {
+                        reply(&mut writer, "452 4.5.3 too many recipients").await?;
+                        continue;
+                    }
+                    match self.parse_recipient(argument) {
+                        Some(token) => {
+                            let response = self.mailbox_reply(token.as_str()).await;
+                            if response.starts_with('2') {
+                                session.recipients.push(token);
+                            }
+                            reply(&mut writer, response).await?
+                        }
+                        None => reply(&mut writer, "550 5.1.1 mailbox unavailable").await?,
+                    }
+                }
+                "DATA" => {
+                    if session.recipients.is_empty() {
+                        reply(&mut writer, "503 5.5.1 need RCPT command").await?;
+                        continue;
+                    }
+                    reply(&mut writer, "354 end data with <CR><LF>.<CR><LF>").await?;
+                    let data = match self.read_data(&mut reader).await? {
+                        Some(data) => data,
+                        None => {
+                            self.reply_for_recipients(
+                                &mut writer,
+                                &session,
+                                "552 5.3.4 message too big",
+                            )
+                            .await?;
+                            session.reset();
+                            continue;
+                        }
+                    };
+                    let response = match parse_email(&data) {
+                        Ok(email) => {
+                            let received = ReceivedEmail {
+                                recipients: session.recipients.clone(),
+                                email,
+                            };
+                            match sender.send(received).await {
+                                Ok(()) => "250 2.0.0 OK",
+                                Err(_) => {
+                                    error!("{}", Error::ReceiverDropped);
+                                    "451 4.3.0 try again later"
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            warn!("can't parse message: {}", e);
+                            "554 5.6.0 can't parse message"
+                        }
+                    };
+                    self.reply_for_recipients(&mut writer, &session, response)
+                        .await?;
+                    session.reset();
+                }
+                "RSET" => {
+                    session.reset();
+                    reply(&mut writer, "250 2.0.0 OK").await?
+                }
+                "NOOP" => reply(&mut writer, "250 2.0.0 OK").await?,
+                "VRFY" => reply(&mut writer, "252 2.5.0 cannot verify").await?,
+                "QUIT" => {
+                    reply(&mut writer, "221 2.0.0 bye").await?;
+                    return Ok(());
+                }
+                _ => reply(&mut writer, "502 5.5.2 command not recognized").await?,
+            }
+        }
+    }
+
+    async fn reply_extensions<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<()> {
+        reply(
+            writer,
+            &format!(
+                "250-{}\r\n250-SIZE {}\r\n250-8BITMIME\r\n250 ENHANCEDSTATUSCODES",
+                self.domain, self.max_message_size
+            ),
+        )
+        .await
+    }
+
+    // LMTP replies after DATA once per accepted recipient
+    async fn reply_for_recipients<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        session: &Session,
+        response: &str,
+    ) -> Result<()> {
+        match self.protocol {
+            Protocol::Smtp => reply(writer, response).await,
+            Protocol::Lmtp => {
+                for _ in &session.recipients {
+                    reply(writer, response).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // `TO:<token@domain>` -> `token`
+    fn parse_recipient(&self, argument: &str) -> Option<String> {
+        let address = argument
+            .get(..3)
+            .filter(|p| p.eq_ignore_ascii_case("TO:"))
+            .map(|_| argument[3..].trim())?;
+        let address = address
+            .split_whitespace()
+            .next()?
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        let (token, domain) = address.rsplit_once('@')?;
+        if !domain.eq_ignore_ascii_case(self.domain.as_str()) || !is_valid_token(token) {
+            return None;
+        }
+        Some(token.to_lowercase())
+    }
+
+    // returns `None` if the message exceeds `max_message_size`;
+    // the rest of a big message is skipped up to the same size, then the client is dropped
+    async fn read_data<R: AsyncBufRead + Unpin>(&self, reader: &mut R) -> Result<Option<Vec<u8>>> {
+        let mut data = vec![];
+        let mut too_big = false;
+        let mut skipped = 0;
+        let mut line = vec![];
+        loop {
+            line.clear();
+            if read_line(reader, &mut line, self.read_timeout).await? == 0 {
+                return Err(Error::IOError(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            if line == b".\r\n" || line == b".\n" {
+                break;
+            }
+            if too_big {
+                skipped += line.len();
+                if skipped > self.max_message_size {
+                    return Err(invalid_data("message too big"));
+                }
+                continue;
+            }
+            // dot-stuffing
+            let content = match line.starts_with(b"..") {
+                true => &line[1..],
+                false => &line[..],
+            };
+            data.extend_from_slice(content);
+            too_big = data.len() > self.max_message_size;
+        }
+        match too_big {
+            true => Ok(None),
+            false => Ok(Some(data)),
+        }
+    }
+}
+
+pub fn is_valid_token(token: &str) -> bool {
+    !token.is_empty()
+        && token.len() <= 64
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '+')
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    greeted: bool,
+    sender: Option<String>,
+    recipients: Vec<String>,
+}
+
+impl Session {
+    fn reset(&mut self) {
+        self.sender = None;
+        self.recipients.clear();
+    }
+}
+
+// reads at most one byte past `MAX_LINE_LENGTH`, so a line without the end isn't buffered
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    timeout: Duration,
+) -> Result<usize> {
+    let limit = MAX_LINE_LENGTH as u64 + 1;
+    let read = tokio::time::timeout(timeout, (&mut *reader).take(limit).read_until(b'\n', line))
+        .await
+        .map_err(|_| Error::IOError(std::io::ErrorKind::TimedOut.into()))??;
+    if line.len() > MAX_LINE_LENGTH {
+        return Err(invalid_data("line too long"));
+    }
+    Ok(read)
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::IOError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
+async fn reply<W: AsyncWrite + Unpin>(writer: &mut W, response: &str) -> Result<()> {
+    writer.write_all(response.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MailboxCheck, Protocol, Server};
+    use crate::types::ReceivedEmail;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    async fn run_server(protocol: Protocol) -> (String, mpsc::Receiver<ReceivedEmail>) {
+        run_checked_server(Server::new("feeds.example", protocol)).await
+    }
+
+    async fn run_checked_server(server: Server) -> (String, mpsc::Receiver<ReceivedEmail>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (sender, receiver) = mpsc::channel(10);
+        let server = server.with_max_message_size(1024);
+        tokio::spawn(async move { server.run(listener, sender).await });
+        (address, receiver)
+    }
+
+    // sends all commands at once and returns codes of the replies except the greeting
+    async fn talk(address: &str, commands: &[&str]) -> Vec<String> {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let mut session = commands.join("\r\n");
+        session.push_str("\r\nQUIT\r\n");
+        stream.write_all(session.as_bytes()).await.unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let mut codes = vec![];
+        while let Some(line) = lines.next_line().await.unwrap() {
+            // multiline replies look like `250-...`
+            if line.as_bytes().get(3) != Some(&b'-') {
+                codes.push(line[..3].to_string());
+            }
+        }
+        codes[1..].to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_smtp_session() {
+        let (address, mut receiver) = run_server(Protocol::Smtp).await;
+        let replies = talk(
+            address.as_str(),
+            &[
+                "MAIL FROM:<newsletter@example.org>",
+                "EHLO localhost",
+                "RCPT TO:<twir@feeds.example>",
+                "MAIL FROM:<newsletter@example.org>",
+                "RCPT TO:<TWIR@Feeds.Example>",
+                "RCPT TO:<someone@gmail.com>",
+                "DATA",
+                "Subject: hello\r\n\r\n..dot-stuffed line\r\n.",
+                "QUIT",
+            ],
+        )
+        .await;
+        assert_eq!(
+            replies,
+            vec!["503", "250", "503", "250", "250", "550", "354", "250", "221"]
+        );
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(received.recipients, vec!["twir".to_string()]);
+        assert_eq!(received.email.subject, Some("hello".to_string()));
+        assert_eq!(received.email.content, ".dot-stuffed line");
+    }
+
+    #[tokio::test]
+    async fn test_lmtp_session() {
+        let (address, mut receiver) = run_server(Protocol::Lmtp).await;
+        let big = format!("Subject: big\r\n\r\n{}\r\n.", "x".repeat(2048));
+        let replies = talk(
+            address.as_str(),
+            &[
+                "EHLO localhost",
+                "LHLO localhost",
+                "MAIL FROM:<newsletter@example.org>",
+                "RCPT TO:<first@feeds.example>",
+                "RCPT TO:<second@feeds.example>",
+                "DATA",
+                "Subject: hello\r\n\r\nbody\r\n.",
+                "MAIL FROM:<newsletter@example.org>",
+                "RCPT TO:<first@feeds.example>",
+                "DATA",
+                big.as_str(),
+            ],
+        )
+        .await;
+        assert_eq!(
+            replies,
+            // one reply per recipient after DATA
+            vec![
+                "502", "250", "250", "250", "250", "354", "250", "250", "250", "250", "354", "552",
+                "221"
+            ]
+        );
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(
+            received.recipients,
+            vec!["first".to_string(), "second".to_string()]
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_mailbox() {
+        let check: MailboxCheck = Arc::new(|token: String| {
+            Box::pin(async move {
+                match token.as_str() {
+                    "down" => Err("storage is down".to_string()),
+                    token => Ok(token == "twir"),
+                }
+            })
+        });
+        let server = Server::new("feeds.example", Protocol::Smtp).with_mailbox_check(check);
+        let (address, mut receiver) = run_checked_server(server).await;
+        let replies = talk(
+            address.as_str(),
+            &[
+                "EHLO localhost",
+                "MAIL FROM:<spam@example.org>",
+                "RCPT TO:<random@feeds.example>",
+                "RCPT TO:<down@feeds.example>",
+                "DATA",
+                "MAIL FROM:<newsletter@example.org>",
+                "RCPT TO:<twir@feeds.example>",
+                "RCPT TO:<random@feeds.example>",
+                "DATA",
+                "Subject: hello\r\n\r\nbody\r\n.",
+            ],
+        )
+        .await;
+        assert_eq!(
+            replies,
+            vec!["250", "250", "550", "451", "503", "250", "250", "550", "354", "250", "221"]
+        );
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(received.recipients, vec!["twir".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_non_ascii_command() {
+        let (address, _receiver) = run_server(Protocol::Smtp).await;
+        let replies = talk(
+            address.as_str(),
+            &["\u{fb00}lo localhost", "EHLO localhost"],
+        )
+        .await;
+        assert_eq!(replies, vec!["502", "250", "221"]);
+    }
+
+    #[tokio::test]
+    async fn test_line_too_long() {
+        let (address, _receiver) = run_server(Protocol::Smtp).await;
+        let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let mut greeting = String::new();
+        stream.read_line(&mut greeting).await.unwrap();
+        assert!(greeting.starts_with("220 "));
+        // no line end, the connection is closed once the limit is read;
+        // unread data may turn the close into a reset
+        let _ = stream
+            .get_mut()
+            .write_all("x".repeat(64 * 1024).as_bytes())
+            .await;
+        let mut rest = vec![];
+        if let Ok(read) = stream.read_to_end(&mut rest).await {
+            assert_eq!(read, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let server = Server::new("feeds.example", Protocol::Smtp)
+            .with_read_timeout(Duration::from_millis(100));
+        let (address, _receiver) = run_checked_server(server).await;
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(b"EHLO localhost\r\n").await.unwrap();
+        let mut replies = String::new();
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut replies))
+            .await
+            .expect("idle connection isn't closed")
+            .unwrap();
+        assert!(replies.contains("250 ENHANCEDSTATUSCODES"));
+    }
+}
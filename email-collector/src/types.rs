@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub content_id: Option<String>,
+    pub file_name: Option<String>,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub message_id: Option<String>,
+    pub subject: Option<String>,
+    pub from_name: Option<String>,
+    pub from_address: Option<String>,
+    pub date: Option<NaiveDateTime>,
+    // html if the message has html body, plain text otherwise
+    pub content: String,
+    pub is_html: bool,
+    // inline parts referenced from html by `cid:`
+    pub inline_images: Vec<Attachment>,
+}
+
+/// Email accepted by the server; `recipients` are local parts of the accepted addresses.
+#[derive(Debug, Clone)]
+pub struct ReceivedEmail {
+    pub recipients: Vec<String>,
+    pub email: Email,
+}
@@ -0,0 +1 @@
+ALTER TABLE records add column meta text;
@@ -0,0 +1,16 @@
+ALTER TABLE records add column content_hash text;
+ALTER TABLE records add column updated_at timestamp;
+UPDATE records SET content_hash = encode(sha256(convert_to(content, 'UTF8')), 'hex');
+
+CREATE TABLE record_versions (
+                                 id serial primary key,
+                                 record_id int not null constraint record_versions_record_id references records on delete cascade,
+                                 title text,
+                                 content text not null,
+                                 image text,
+                                 content_hash text,
+                                 -- when the version was published or edited
+                                 date timestamp not null,
+                                 replaced_at timestamp not null default now()
+);
+create index record_versions_record_id_idx on record_versions (record_id);
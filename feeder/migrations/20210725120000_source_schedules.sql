@@ -0,0 +1,5 @@
+-- existing sources are scraped once more, their intervals are adapted after it
+ALTER TABLE sources add column next_scrape_at timestamp not null default now();
+ALTER TABLE sources add column scrape_interval_secs int;
+ALTER TABLE sources add column schedule text;
+create index sources_kind_next_scrape_at_idx on sources (kind, next_scrape_at);
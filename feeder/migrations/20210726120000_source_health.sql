@@ -0,0 +1,7 @@
+ALTER TABLE sources add column last_success_at timestamp;
+ALTER TABLE sources add column last_error text;
+ALTER TABLE sources add column last_error_class text;
+ALTER TABLE sources add column consecutive_failures int not null default 0;
+-- permanent errors since the last success, the source is disabled after a few of them
+ALTER TABLE sources add column permanent_failures int not null default 0;
+ALTER TABLE sources add column disabled_at timestamp;
@@ -0,0 +1,4 @@
+-- due sources are claimed by a worker until they're scraped,
+-- they're claimed again once the lease expires
+ALTER TABLE sources add column leased_by text;
+ALTER TABLE sources add column lease_expires_at timestamp;
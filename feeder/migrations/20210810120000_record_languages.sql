@@ -0,0 +1,2 @@
+-- ISO 639-3 code detected when the record is stored, null if it isn't known
+ALTER TABLE records add column language text;
@@ -0,0 +1,38 @@
+CREATE TABLE sources (
+                         id integer primary key,
+                         name text not null,
+                         origin text not null,
+                         kind text not null,
+                         image text,
+                         last_scrape_time timestamp not null default current_timestamp,
+                         external_link text not null,
+                         constraint unique_origin_kind unique (origin, kind)
+);
+
+CREATE TABLE records (
+                         id integer primary key,
+                         title text,
+                         source_record_id text not null,
+                         source_id int not null constraint records_source_id_fk references sources,
+                         content text not null,
+                         date timestamp not null,
+                         image text,
+                         external_link text not null default '',
+                         meta text
+);
+
+create unique index records_unique_guid_source_key on records (source_record_id, source_id);
+
+CREATE TABLE files (
+                       id integer primary key,
+                       record_id int not null constraint files_record_id references records,
+                       kind text not null,
+                       local_path text,
+                       remote_path text not null,
+                       remote_id text,
+                       file_name text,
+                       type text not null,
+                       meta text
+);
+
+create unique index files_unique_remote_id on files (remote_id);
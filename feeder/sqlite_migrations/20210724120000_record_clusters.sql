@@ -0,0 +1,7 @@
+-- records stored before aren't clustered, fingerprints are computed for new records only
+ALTER TABLE records add column simhash bigint;
+ALTER TABLE records add column canonical_link text;
+ALTER TABLE records add column cluster_id int constraint records_cluster_id references records on delete set null;
+create index records_cluster_id_idx on records (cluster_id);
+create index records_date_idx on records (date);
+create index records_canonical_link_idx on records (canonical_link);
@@ -0,0 +1,5 @@
+-- new columns can't default to the current time, the epoch means "scrape now"
+ALTER TABLE sources add column next_scrape_at timestamp not null default '1970-01-01 00:00:00';
+ALTER TABLE sources add column scrape_interval_secs int;
+ALTER TABLE sources add column schedule text;
+create index sources_kind_next_scrape_at_idx on sources (kind, next_scrape_at);
@@ -0,0 +1,2 @@
+-- account of the collector which sees the source, any account is used if null
+ALTER TABLE sources add column account text;
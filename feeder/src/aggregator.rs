@@ -1,11 +1,15 @@
 // TODO: no needs for aggregator, handler can be used directly
 use crate::models;
+use crate::pipeline::{Pipeline, Pipelines, RecordProcessor};
 use crate::result::Result;
 use crate::storage::Storage;
+use crate::updates::supervisor::SourceState;
 use crate::updates::tg::CloneableBoxedParser;
-use crate::updates::Source;
+use crate::updates::{RegisteredSource, SourceProvider, SourceUpdate, UpdatesHandler};
 use crate::{config, updates};
 use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
 pub struct AggApp<S>
 where
@@ -13,31 +17,143 @@ where
 {
     handler: updates::SourcesAggregator<S>,
     storage: S,
+    pipelines: Pipelines,
+    // config the sources are running with
+    config: Mutex<config::AppConfig>,
 }
 
 impl<S> AggApp<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
-    pub fn new(handler: updates::SourcesAggregator<S>, storage: S) -> Self {
-        Self { handler, storage }
+    pub fn new(
+        handler: updates::SourcesAggregator<S>,
+        storage: S,
+        pipelines: Pipelines,
+        config: config::AppConfig,
+    ) -> Self {
+        Self {
+            handler,
+            storage,
+            pipelines,
+            config: Mutex::new(config),
+        }
+    }
+
+    /// Runs sources until the token is cancelled.
+    pub async fn run(&self, shutdown: CancellationToken) {
+        self.handler.run(shutdown).await
     }
 
-    pub async fn run(&self) {
-        self.handler.run().await
+    /// States of the enabled sources loops.
+    pub async fn source_states(&self) -> Vec<SourceState> {
+        self.handler.states().await
     }
 
     pub async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
         self.handler.search_source(query).await
     }
 
-    pub async fn synchronize(&self, secs_depth: i32, source: Option<Source>) -> Result<()> {
-        self.handler.synchronize(secs_depth, source).await
+    pub async fn synchronize(&self, secs_depth: i32, kind: Option<&str>) -> Result<()> {
+        self.handler.synchronize(secs_depth, kind).await
+    }
+
+    /// Kinds of the enabled sources.
+    pub fn source_kinds(&self) -> Vec<&'static str> {
+        self.handler.kinds()
     }
 
     pub fn storage(&self) -> S {
         self.storage.clone()
     }
+
+    /// Records pipeline of the kind for records saved outside of the sources, e.g. webhooks.
+    pub fn pipeline(&self, kind: &str) -> Pipeline {
+        self.pipelines.for_kind(kind)
+    }
+
+    pub fn pipelines(&self) -> &Pipelines {
+        &self.pipelines
+    }
+
+    /// Applies the changed settings of the collectors at runtime.
+    ///
+    /// Sources whose settings changed are restarted, enabled and disabled.
+    /// Telegram keeps its session, so its settings and record processors
+    /// take effect after restart only.
+    pub async fn reload(&self, config: config::AppConfig) -> Result<()> {
+        config.validate()?;
+        let mut current = self.config.lock().await;
+        if current.http() != config.http() {
+            let source = config
+                .http()
+                .enabled()
+                .then(|| http_source(config.http(), &self.storage, &self.pipelines));
+            self.handler
+                .replace_source(updates::http::WEB, source)
+                .await;
+        }
+        if current.telegram_web() != config.telegram_web() {
+            let source = config.telegram_web().enabled().then(|| {
+                telegram_web_source(config.telegram_web(), &self.storage, &self.pipelines)
+            });
+            self.handler
+                .replace_source(updates::tg_web::TELEGRAM_WEB, source)
+                .await;
+        }
+        if current.vk() != config.vk() {
+            let source = config
+                .vk()
+                .enabled()
+                .then(|| vk_source(config.vk(), &self.storage, &self.pipelines));
+            self.handler.replace_source(updates::vk::VK, source).await;
+        }
+        if current.mastodon() != config.mastodon() {
+            let source = config
+                .mastodon()
+                .enabled()
+                .then(|| mastodon_source(config.mastodon(), &self.storage, &self.pipelines));
+            self.handler
+                .replace_source(updates::mastodon::MASTODON, source)
+                .await;
+        }
+        if current.reddit() != config.reddit() {
+            let source = config
+                .reddit()
+                .enabled()
+                .then(|| reddit_source(config.reddit(), &self.storage, &self.pipelines));
+            self.handler
+                .replace_source(updates::reddit::REDDIT, source)
+                .await;
+        }
+        if current.email() != config.email() {
+            let source = config
+                .email()
+                .enabled()
+                .then(|| email_source(config.email(), &self.storage, &self.pipelines));
+            self.handler
+                .replace_source(updates::email::EMAIL, source)
+                .await;
+        }
+        if current.filesystem() != config.filesystem() {
+            let source = config
+                .filesystem()
+                .enabled()
+                .then(|| filesystem_source(config.filesystem(), &self.storage, &self.pipelines));
+            self.handler
+                .replace_source(updates::fs::FILESYSTEM, source)
+                .await;
+        }
+        if current.telegram() != config.telegram() {
+            warn!("telegram settings are applied after restart");
+        }
+        if current.record_processors() != config.record_processors() {
+            warn!("record processors are applied after restart");
+        }
+        // not applied settings are reported again until restart
+        *current = config.with_restart_settings_of(&current);
+        Ok(())
+    }
 }
 
 pub struct AppBuilder<'a, S>
@@ -47,6 +163,9 @@ where
     config: &'a config::AppConfig,
     storage: S,
     telegram_parser: CloneableBoxedParser,
+    // sources which aren't configured by `AppConfig`
+    sources: Vec<RegisteredSource>,
+    pipelines: Pipelines,
 }
 
 impl<'a, S> AppBuilder<'a, S>
@@ -62,21 +181,39 @@ where
             config,
             storage,
             telegram_parser,
+            sources: vec![],
+            pipelines: Pipelines::new(config.record_processors().clone()),
         }
     }
 
+    /// Registers records processor of all the source kinds,
+    /// it runs after the built-in stages configured for the kind.
+    pub fn with_processor(mut self, processor: Arc<dyn RecordProcessor>) -> Self {
+        self.pipelines = self.pipelines.with_processor(processor);
+        self
+    }
+
+    /// Registers source provided by another crate, it's started after the configured ones.
+    pub fn with_source<T, P>(mut self, provider: Arc<P>) -> Self
+    where
+        T: SourceUpdate,
+        P: SourceProvider + UpdatesHandler<T> + Send + Sync + 'static,
+    {
+        self.sources.push(RegisteredSource::new(provider));
+        self
+    }
+
     pub fn build(self) -> AggApp<S> {
         debug!("config for building: {:?}", self.config);
         let mut updates_builder =
             updates::SourcesAggregator::builder().with_storage(self.storage.clone());
 
         if self.config.http().enabled() {
-            let http_source = updates::http::HttpSource::builder()
-                .with_sleep_secs(self.config.http().sleep_secs())
-                .with_storage(self.storage.clone())
-                .build();
-            let http_source = Arc::new(http_source);
-            updates_builder = updates_builder.with_http_source(http_source);
+            updates_builder = updates_builder.with_registered_source(http_source(
+                self.config.http(),
+                &self.storage,
+                &self.pipelines,
+            ));
         }
 
         if self.config.telegram().enabled() {
@@ -92,21 +229,214 @@ where
             .with_database_directory(self.config.telegram().database_directory())
             .with_log_verbosity_level(self.config.telegram().log_verbosity_level())
             .with_storage(self.storage.clone())
-            .build();
+            .with_pipelines(&self.pipelines);
+            let tg_source = self
+                .config
+                .telegram()
+                .accounts()
+                .iter()
+                .fold(tg_source, |tg_source, account| {
+                    tg_source.with_account(
+                        account.name(),
+                        account.phone(),
+                        account.database_directory(),
+                        account.encryption_key(),
+                    )
+                })
+                .build();
             let tg_source = Arc::new(tg_source);
-            updates_builder = updates_builder.with_tg_source(tg_source);
+            updates_builder = updates_builder.with_source(tg_source);
+        }
+
+        if self.config.telegram_web().enabled() {
+            updates_builder = updates_builder.with_registered_source(telegram_web_source(
+                self.config.telegram_web(),
+                &self.storage,
+                &self.pipelines,
+            ));
         }
 
         if self.config.vk().enabled() {
-            let vk_source = updates::vk::VkSource::builder()
-                .with_storage(self.storage.clone())
-                .with_scrape_source_secs_interval(self.config.vk().scrape_source_secs_interval())
-                .with_sleep_secs(self.config.vk().sleep_secs())
-                .with_token(self.config.vk().token().to_string())
-                .build();
-            let vk_source = Arc::new(vk_source);
-            updates_builder = updates_builder.with_vk_source(vk_source);
+            updates_builder = updates_builder.with_registered_source(vk_source(
+                self.config.vk(),
+                &self.storage,
+                &self.pipelines,
+            ));
+        }
+
+        if self.config.mastodon().enabled() {
+            updates_builder = updates_builder.with_registered_source(mastodon_source(
+                self.config.mastodon(),
+                &self.storage,
+                &self.pipelines,
+            ));
+        }
+
+        if self.config.reddit().enabled() {
+            updates_builder = updates_builder.with_registered_source(reddit_source(
+                self.config.reddit(),
+                &self.storage,
+                &self.pipelines,
+            ));
+        }
+
+        if self.config.email().enabled() {
+            updates_builder = updates_builder.with_registered_source(email_source(
+                self.config.email(),
+                &self.storage,
+                &self.pipelines,
+            ));
         }
-        AggApp::new(updates_builder.build(), self.storage.clone())
+
+        if self.config.filesystem().enabled() {
+            updates_builder = updates_builder.with_registered_source(filesystem_source(
+                self.config.filesystem(),
+                &self.storage,
+                &self.pipelines,
+            ));
+        }
+        for source in self.sources {
+            updates_builder = updates_builder.with_registered_source(source);
+        }
+        AggApp::new(
+            updates_builder.build(),
+            self.storage.clone(),
+            self.pipelines,
+            self.config.clone(),
+        )
     }
 }
+
+// built-in sources which can be restarted on reload, telegram isn't among them
+// because its session lives as long as the app
+
+fn http_source<S>(
+    config: &config::HttpConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let http_source = updates::http::HttpSource::builder()
+        .with_sleep_secs(config.sleep_secs())
+        .with_scrape_source_secs_interval(config.scrape_source_secs_interval())
+        .with_max_scrape_source_secs_interval(config.max_scrape_source_secs_interval())
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .build();
+    RegisteredSource::new(Arc::new(http_source))
+}
+
+fn telegram_web_source<S>(
+    config: &config::TelegramWebConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let tg_web_source = updates::tg_web::TelegramWebSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_scrape_source_secs_interval(config.scrape_source_secs_interval())
+        .with_max_scrape_source_secs_interval(config.max_scrape_source_secs_interval())
+        .with_sleep_secs(config.sleep_secs())
+        .build();
+    RegisteredSource::new(Arc::new(tg_web_source))
+}
+
+fn vk_source<S>(config: &config::VkConfig, storage: &S, pipelines: &Pipelines) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let vk_source = updates::vk::VkSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_scrape_source_secs_interval(config.scrape_source_secs_interval())
+        .with_max_scrape_source_secs_interval(config.max_scrape_source_secs_interval())
+        .with_sleep_secs(config.sleep_secs())
+        .with_token(config.token().to_string());
+    let vk_source = config
+        .accounts()
+        .iter()
+        .fold(vk_source, |vk_source, account| {
+            vk_source.with_account(account.name().to_string(), account.token().to_string())
+        })
+        .build();
+    RegisteredSource::new(Arc::new(vk_source))
+}
+
+fn mastodon_source<S>(
+    config: &config::MastodonConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let mastodon_source = updates::mastodon::MastodonSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_scrape_source_secs_interval(config.scrape_source_secs_interval())
+        .with_max_scrape_source_secs_interval(config.max_scrape_source_secs_interval())
+        .with_sleep_secs(config.sleep_secs())
+        .build();
+    RegisteredSource::new(Arc::new(mastodon_source))
+}
+
+fn reddit_source<S>(
+    config: &config::RedditConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let reddit_source = updates::reddit::RedditSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_scrape_source_secs_interval(config.scrape_source_secs_interval())
+        .with_max_scrape_source_secs_interval(config.max_scrape_source_secs_interval())
+        .with_sleep_secs(config.sleep_secs())
+        .build();
+    RegisteredSource::new(Arc::new(reddit_source))
+}
+
+fn email_source<S>(
+    config: &config::EmailConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let email_source = updates::email::EmailSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_listen_address(config.listen_address())
+        .with_domain(config.domain())
+        .with_protocol(config.protocol().parse().expect("invalid email protocol"))
+        .with_max_message_size(config.max_message_size())
+        .with_files_directory(config.files_directory())
+        .build();
+    RegisteredSource::new(Arc::new(email_source))
+}
+
+fn filesystem_source<S>(
+    config: &config::FilesystemConfig,
+    storage: &S,
+    pipelines: &Pipelines,
+) -> RegisteredSource
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    let fs_source = updates::fs::FilesystemSource::builder()
+        .with_storage(storage.clone())
+        .with_pipelines(pipelines)
+        .with_directory(config.directory())
+        .with_poll_interval_secs(config.poll_interval_secs())
+        .with_files_directory(config.files_directory())
+        .build();
+    RegisteredSource::new(Arc::new(fs_source))
+}
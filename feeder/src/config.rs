@@ -1,9 +1,47 @@
-#[derive(Clone, Debug, Builder)]
+use crate::pipeline;
+use crate::result::{Error, Result};
+use email_collector::server::Protocol;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// Name of the account of the main vk token and telegram phone.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+// problems of the account names of a collector
+fn validate_accounts<'a>(
+    section: &str,
+    names: impl Iterator<Item = &'a str>,
+    problems: &mut Vec<String>,
+) {
+    let mut seen = vec![];
+    for name in names {
+        if name.trim().is_empty() {
+            problems.push(format!("{}.accounts: an account name is empty", section));
+        } else if name == DEFAULT_ACCOUNT {
+            problems.push(format!(
+                "{}.accounts: {:?} is the name of the main account",
+                section, DEFAULT_ACCOUNT
+            ));
+        } else if seen.contains(&name) {
+            problems.push(format!("{}.accounts: {:?} is duplicated", section, name));
+        }
+        seen.push(name);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
 #[builder(default)]
 pub struct AppConfig {
     http: HttpConfig,
     telegram: TelegramConfig,
+    telegram_web: TelegramWebConfig,
     vk: VkConfig,
+    mastodon: MastodonConfig,
+    reddit: RedditConfig,
+    email: EmailConfig,
+    filesystem: FilesystemConfig,
+    // names of the built-in record processors by source kind, see `pipeline::builtin`
+    record_processors: HashMap<String, Vec<String>>,
 }
 
 impl AppConfig {
@@ -15,9 +53,213 @@ impl AppConfig {
         &self.telegram
     }
 
+    pub fn telegram_web(&self) -> &TelegramWebConfig {
+        &self.telegram_web
+    }
+
     pub fn vk(&self) -> &VkConfig {
         &self.vk
     }
+
+    pub fn mastodon(&self) -> &MastodonConfig {
+        &self.mastodon
+    }
+
+    pub fn reddit(&self) -> &RedditConfig {
+        &self.reddit
+    }
+
+    pub fn email(&self) -> &EmailConfig {
+        &self.email
+    }
+
+    pub fn filesystem(&self) -> &FilesystemConfig {
+        &self.filesystem
+    }
+
+    pub fn record_processors(&self) -> &HashMap<String, Vec<String>> {
+        &self.record_processors
+    }
+
+    // settings applied on restart only are left as they are in the running config
+    pub(crate) fn with_restart_settings_of(mut self, running: &AppConfig) -> Self {
+        self.telegram = running.telegram.clone();
+        self.record_processors = running.record_processors.clone();
+        self
+    }
+
+    /// Checks settings of the enabled collectors, all the problems are reported at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = vec![];
+        let scrapers = [
+            (
+                "http",
+                self.http.enabled,
+                self.http.sleep_secs,
+                self.http.scrape_source_secs_interval,
+                self.http.max_scrape_source_secs_interval,
+            ),
+            (
+                "telegram_web",
+                self.telegram_web.enabled,
+                self.telegram_web.sleep_secs,
+                self.telegram_web.scrape_source_secs_interval,
+                self.telegram_web.max_scrape_source_secs_interval,
+            ),
+            (
+                "vk",
+                self.vk.enabled,
+                self.vk.sleep_secs,
+                self.vk.scrape_source_secs_interval,
+                self.vk.max_scrape_source_secs_interval,
+            ),
+            (
+                "mastodon",
+                self.mastodon.enabled,
+                self.mastodon.sleep_secs,
+                self.mastodon.scrape_source_secs_interval,
+                self.mastodon.max_scrape_source_secs_interval,
+            ),
+            (
+                "reddit",
+                self.reddit.enabled,
+                self.reddit.sleep_secs,
+                self.reddit.scrape_source_secs_interval,
+                self.reddit.max_scrape_source_secs_interval,
+            ),
+        ];
+        for (name, enabled, sleep_secs, interval, max_interval) in scrapers.iter() {
+            if !enabled {
+                continue;
+            }
+            if *sleep_secs == 0 {
+                problems.push(format!("{}.sleep_secs must be positive", name));
+            }
+            if *interval == 0 {
+                problems.push(format!(
+                    "{}.scrape_source_secs_interval must be positive",
+                    name
+                ));
+            }
+            if max_interval < interval {
+                problems.push(format!(
+                    "{}.max_scrape_source_secs_interval is less than scrape_source_secs_interval",
+                    name
+                ));
+            }
+        }
+        if self.telegram.enabled {
+            let telegram = &self.telegram;
+            if telegram.api_id == 0 {
+                problems.push("telegram.api_id is not set".to_string());
+            }
+            for (name, value) in &[
+                ("api_hash", &telegram.api_hash),
+                ("phone", &telegram.phone),
+                ("database_directory", &telegram.database_directory),
+                ("files_directory", &telegram.files_directory),
+            ] {
+                if value.trim().is_empty() {
+                    problems.push(format!("telegram.{} is empty", name));
+                }
+            }
+            if telegram.max_download_queue_size == 0 {
+                problems.push("telegram.max_download_queue_size must be positive".to_string());
+            }
+            validate_accounts(
+                "telegram",
+                telegram.accounts.iter().map(|a| a.name.as_str()),
+                &mut problems,
+            );
+            let mut directories = vec![telegram.database_directory.as_str()];
+            for account in telegram.accounts.iter() {
+                for (name, value) in &[
+                    ("phone", &account.phone),
+                    ("database_directory", &account.database_directory),
+                ] {
+                    if value.trim().is_empty() {
+                        problems.push(format!(
+                            "telegram.accounts.{}.{} is empty",
+                            account.name, name
+                        ));
+                    }
+                }
+                // tdlib locks its database, sessions can't share it
+                if directories.contains(&account.database_directory.as_str()) {
+                    problems.push(format!(
+                        "telegram.accounts.{}.database_directory is used by another account",
+                        account.name
+                    ));
+                }
+                directories.push(account.database_directory.as_str());
+            }
+        }
+        if self.vk.enabled {
+            if self.vk.token.trim().is_empty() {
+                problems.push("vk.token is empty".to_string());
+            }
+            validate_accounts(
+                "vk",
+                self.vk.accounts.iter().map(|a| a.name.as_str()),
+                &mut problems,
+            );
+            for account in self.vk.accounts.iter() {
+                if account.token.trim().is_empty() {
+                    problems.push(format!("vk.accounts.{}.token is empty", account.name));
+                }
+            }
+        }
+        if self.email.enabled {
+            let email = &self.email;
+            if email.listen_address.parse::<SocketAddr>().is_err() {
+                problems.push(format!(
+                    "email.listen_address {:?} isn't an address like 127.0.0.1:2525",
+                    email.listen_address
+                ));
+            }
+            if email.domain.trim().is_empty() {
+                problems.push("email.domain is empty".to_string());
+            }
+            if let Err(e) = email.protocol.parse::<Protocol>() {
+                problems.push(format!("email.protocol: {}, smtp or lmtp expected", e));
+            }
+            if email.max_message_size == 0 {
+                problems.push("email.max_message_size must be positive".to_string());
+            }
+            if email.files_directory.trim().is_empty() {
+                problems.push("email.files_directory is empty".to_string());
+            }
+        }
+        if self.filesystem.enabled {
+            let filesystem = &self.filesystem;
+            if filesystem.directory.trim().is_empty() {
+                problems.push("filesystem.directory is empty".to_string());
+            }
+            if filesystem.poll_interval_secs == 0 {
+                problems.push("filesystem.poll_interval_secs must be positive".to_string());
+            }
+            if filesystem.files_directory.trim().is_empty() {
+                problems.push("filesystem.files_directory is empty".to_string());
+            }
+        }
+        let mut kinds: Vec<&String> = self.record_processors.keys().collect();
+        kinds.sort();
+        for kind in kinds {
+            for name in &self.record_processors[kind] {
+                if pipeline::builtin(name).is_none() {
+                    problems.push(format!(
+                        "record processor {:?} of {} is unknown",
+                        name, kind
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(problems))
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -25,17 +267,27 @@ impl Default for AppConfig {
         Self {
             http: HttpConfig::default(),
             telegram: TelegramConfig::default(),
+            telegram_web: TelegramWebConfig::default(),
             vk: VkConfig::default(),
+            mastodon: MastodonConfig::default(),
+            reddit: RedditConfig::default(),
+            email: EmailConfig::default(),
+            filesystem: FilesystemConfig::default(),
+            record_processors: HashMap::new(),
         }
     }
 }
 
-#[derive(Clone, Debug, Builder)]
+#[derive(Clone, Debug, PartialEq, Builder)]
 pub struct VkConfig {
     enabled: bool,
     sleep_secs: u64,
     scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
     token: String,
+    // tokens pooled with the main one
+    #[builder(default)]
+    accounts: Vec<VkAccountConfig>,
 }
 
 impl VkConfig {
@@ -48,16 +300,38 @@ impl VkConfig {
     pub fn scrape_source_secs_interval(&self) -> u64 {
         self.scrape_source_secs_interval
     }
+    pub fn max_scrape_source_secs_interval(&self) -> u64 {
+        self.max_scrape_source_secs_interval
+    }
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+    pub fn accounts(&self) -> &[VkAccountConfig] {
+        &self.accounts
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct VkAccountConfig {
+    name: String,
+    token: String,
+}
+
+impl VkAccountConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     pub fn token(&self) -> &str {
         &self.token
     }
 }
 
-#[derive(Clone, Debug, Builder)]
+#[derive(Clone, Debug, PartialEq, Builder)]
 pub struct HttpConfig {
     enabled: bool,
     sleep_secs: u64,
-    scrape_source_secs_interval: i32,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
 }
 
 impl HttpConfig {
@@ -67,9 +341,12 @@ impl HttpConfig {
     pub fn sleep_secs(&self) -> u64 {
         self.sleep_secs
     }
-    pub fn scrape_source_secs_interval(&self) -> i32 {
+    pub fn scrape_source_secs_interval(&self) -> u64 {
         self.scrape_source_secs_interval
     }
+    pub fn max_scrape_source_secs_interval(&self) -> u64 {
+        self.max_scrape_source_secs_interval
+    }
 }
 
 impl Default for VkConfig {
@@ -78,7 +355,9 @@ impl Default for VkConfig {
             enabled: false,
             sleep_secs: 60,
             scrape_source_secs_interval: 60,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
             token: "".to_string(),
+            accounts: vec![],
         }
     }
 }
@@ -89,11 +368,12 @@ impl Default for HttpConfig {
             enabled: false,
             sleep_secs: 60,
             scrape_source_secs_interval: 60,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
         }
     }
 }
 
-#[derive(Clone, Debug, Builder)]
+#[derive(Clone, Debug, PartialEq, Builder)]
 pub struct TelegramConfig {
     enabled: bool,
     database_directory: String,
@@ -105,6 +385,9 @@ pub struct TelegramConfig {
     files_directory: String,
     log_download_state_secs_interval: u64,
     encryption_key: String,
+    // sessions besides the main one, each has its own database
+    #[builder(default)]
+    accounts: Vec<TelegramAccountConfig>,
 }
 
 impl TelegramConfig {
@@ -138,6 +421,33 @@ impl TelegramConfig {
     pub fn log_download_state_secs_interval(&self) -> u64 {
         self.log_download_state_secs_interval
     }
+    pub fn accounts(&self) -> &[TelegramAccountConfig] {
+        &self.accounts
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct TelegramAccountConfig {
+    name: String,
+    phone: String,
+    database_directory: String,
+    #[builder(default)]
+    encryption_key: String,
+}
+
+impl TelegramAccountConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn phone(&self) -> &str {
+        &self.phone
+    }
+    pub fn database_directory(&self) -> &str {
+        &self.database_directory
+    }
+    pub fn encryption_key(&self) -> &str {
+        &self.encryption_key
+    }
 }
 
 impl Default for TelegramConfig {
@@ -153,6 +463,190 @@ impl Default for TelegramConfig {
             files_directory: "".to_string(),
             encryption_key: "".to_string(),
             log_download_state_secs_interval: 0,
+            accounts: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct TelegramWebConfig {
+    enabled: bool,
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+}
+
+impl TelegramWebConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn sleep_secs(&self) -> u64 {
+        self.sleep_secs
+    }
+    pub fn scrape_source_secs_interval(&self) -> u64 {
+        self.scrape_source_secs_interval
+    }
+    pub fn max_scrape_source_secs_interval(&self) -> u64 {
+        self.max_scrape_source_secs_interval
+    }
+}
+
+impl Default for TelegramWebConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct MastodonConfig {
+    enabled: bool,
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+}
+
+impl MastodonConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn sleep_secs(&self) -> u64 {
+        self.sleep_secs
+    }
+    pub fn scrape_source_secs_interval(&self) -> u64 {
+        self.scrape_source_secs_interval
+    }
+    pub fn max_scrape_source_secs_interval(&self) -> u64 {
+        self.max_scrape_source_secs_interval
+    }
+}
+
+impl Default for MastodonConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct RedditConfig {
+    enabled: bool,
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+}
+
+impl RedditConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn sleep_secs(&self) -> u64 {
+        self.sleep_secs
+    }
+    pub fn scrape_source_secs_interval(&self) -> u64 {
+        self.scrape_source_secs_interval
+    }
+    pub fn max_scrape_source_secs_interval(&self) -> u64 {
+        self.max_scrape_source_secs_interval
+    }
+}
+
+impl Default for RedditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct EmailConfig {
+    enabled: bool,
+    listen_address: String,
+    domain: String,
+    // `smtp` or `lmtp`
+    protocol: String,
+    max_message_size: usize,
+    files_directory: String,
+}
+
+impl EmailConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn listen_address(&self) -> &str {
+        self.listen_address.as_str()
+    }
+    pub fn domain(&self) -> &str {
+        self.domain.as_str()
+    }
+    pub fn protocol(&self) -> &str {
+        self.protocol.as_str()
+    }
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+    pub fn files_directory(&self) -> &str {
+        self.files_directory.as_str()
+    }
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: "127.0.0.1:2525".to_string(),
+            domain: "localhost".to_string(),
+            protocol: "smtp".to_string(),
+            max_message_size: 10 * 1024 * 1024,
+            files_directory: "files".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Builder)]
+pub struct FilesystemConfig {
+    enabled: bool,
+    // every subdirectory is a source
+    directory: String,
+    // full rescan interval, in addition to change notifications
+    poll_interval_secs: u64,
+    files_directory: String,
+}
+
+impl FilesystemConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn directory(&self) -> &str {
+        self.directory.as_str()
+    }
+    pub fn poll_interval_secs(&self) -> u64 {
+        self.poll_interval_secs
+    }
+    pub fn files_directory(&self) -> &str {
+        self.files_directory.as_str()
+    }
+}
+
+impl Default for FilesystemConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "archive".to_string(),
+            poll_interval_secs: 60,
+            files_directory: "files".to_string(),
         }
     }
 }
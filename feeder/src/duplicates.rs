@@ -0,0 +1,362 @@
+// detection of the same story published by several sources, e.g. a feed item,
+// the author's Telegram post and a VK repost of it
+use crate::models;
+use std::collections::HashSet;
+
+/// Records of other sources published within the window are compared.
+pub const CLUSTER_WINDOW_SECS: i64 = 3 * 24 * 60 * 60;
+// simhashes of the same story differ by a few bits at most,
+// posts are short, so a different headline or signature changes several bits
+const MAX_SIMHASH_DISTANCE: u32 = 7;
+// records within 3 bits share at least one band, about a half of the ones within 7 bits do;
+// 16 bit bands match 1/16384 of the unrelated records of the window
+pub const SIMHASH_BANDS: usize = 4;
+const SIMHASH_BAND_BITS: usize = 64 / SIMHASH_BANDS;
+// short texts like "new video" give the same hashes for different stories
+const MIN_SIMHASH_WORDS: usize = 20;
+const MIN_TITLE_LENGTH: usize = 20;
+const TRACKING_PARAMS: [&str; 4] = ["utm_", "fbclid", "gclid", "yclid"];
+
+/// Text features compared between records.
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    // lowercase words
+    title: Option<String>,
+    simhash: Option<i64>,
+    // link of the record itself, e.g. guid of the feed item
+    canonical_link: Option<String>,
+    // links in the content
+    links: HashSet<String>,
+}
+
+impl Fingerprint {
+    pub fn new(title: Option<&str>, content: &str, source_record_id: &str) -> Self {
+        Self::with_simhash(
+            title,
+            content,
+            source_record_id,
+            simhash(&normalize_text(content)),
+        )
+    }
+
+    // stored simhash saves normalizing of the content
+    fn with_simhash(
+        title: Option<&str>,
+        content: &str,
+        source_record_id: &str,
+        simhash: Option<i64>,
+    ) -> Self {
+        let title = title
+            .map(|t| normalize_text(t).join(" "))
+            .filter(|t| t.len() >= MIN_TITLE_LENGTH);
+        Self {
+            title,
+            simhash,
+            canonical_link: canonical_link(source_record_id),
+            links: content_links(content)
+                .iter()
+                .filter_map(|l| canonical_link(l))
+                .collect(),
+        }
+    }
+
+    pub fn of_record(record: &models::Record) -> Self {
+        match record.simhash {
+            Some(simhash) => Self::with_simhash(
+                record.title.as_deref(),
+                record.content.as_str(),
+                record.source_record_id.as_str(),
+                Some(simhash),
+            ),
+            None => Self::new(
+                record.title.as_deref(),
+                record.content.as_str(),
+                record.source_record_id.as_str(),
+            ),
+        }
+    }
+
+    pub fn of_new_record(record: &models::NewRecord) -> Self {
+        Self::new(
+            record.title.as_deref(),
+            record.content.as_str(),
+            record.source_record_id.as_str(),
+        )
+    }
+
+    pub fn simhash(&self) -> Option<i64> {
+        self.simhash
+    }
+
+    pub fn canonical_link(&self) -> Option<&str> {
+        self.canonical_link.as_deref()
+    }
+
+    pub fn links(&self) -> Vec<String> {
+        self.links.iter().cloned().collect()
+    }
+
+    /// `LIKE` pattern of the content linking to the record, `\` escapes.
+    pub fn link_pattern(&self) -> Option<String> {
+        self.canonical_link
+            .as_ref()
+            .map(|link| format!("%{}%", escape_like(link)))
+    }
+
+    /// Parts of the simhash, candidates share at least one of them.
+    pub fn simhash_bands(&self) -> Option<[i64; SIMHASH_BANDS]> {
+        let simhash = self.simhash?;
+        let mut bands = [0; SIMHASH_BANDS];
+        for (i, band) in bands.iter_mut().enumerate() {
+            *band = simhash_band(simhash, i);
+        }
+        Some(bands)
+    }
+
+    pub fn is_duplicate(&self, other: &Fingerprint) -> bool {
+        let links_match = |a: &Fingerprint, b: &Fingerprint| match &a.canonical_link {
+            Some(link) => b.canonical_link.as_ref() == Some(link) || b.links.contains(link),
+            None => false,
+        };
+        if links_match(self, other) || links_match(other, self) {
+            return true;
+        }
+        if self.title.is_some() && self.title == other.title {
+            return true;
+        }
+        match (self.simhash, other.simhash) {
+            (Some(a), Some(b)) => (a ^ b).count_ones() <= MAX_SIMHASH_DISTANCE,
+            _ => false,
+        }
+    }
+}
+
+// queries compare bands with the same expression: `(simhash >> 16 * i) & 65535`
+fn simhash_band(simhash: i64, i: usize) -> i64 {
+    (simhash >> (i * SIMHASH_BAND_BITS)) & ((1 << SIMHASH_BAND_BITS) - 1)
+}
+
+// links are full of `_`, which matches any character in `LIKE`
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || c == '%' || c == '_' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Lowercase words of the text, html tags and entities are skipped.
+pub fn normalize_text(html: &str) -> Vec<String> {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut in_entity = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if in_tag => {}
+            '&' => in_entity = true,
+            ';' if in_entity => {
+                in_entity = false;
+                text.push(' ');
+            }
+            _ if in_entity && (c.is_ascii_alphanumeric() || c == '#') => {}
+            _ => {
+                in_entity = false;
+                text.push(c);
+            }
+        }
+    }
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Simhash of the words, `None` for texts too short to compare.
+///
+/// Words are hashed one by one: shingles of short posts change too much
+/// with another headline or signature.
+pub fn simhash(words: &[String]) -> Option<i64> {
+    if words.len() < MIN_SIMHASH_WORDS {
+        return None;
+    }
+    let mut weights = [0i32; 64];
+    for word in words {
+        let hash = fnv1a(word.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            match (hash >> bit) & 1 {
+                1 => *weight += 1,
+                _ => *weight -= 1,
+            }
+        }
+    }
+    let simhash = weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0u64, |hash, (bit, _)| hash | 1 << bit);
+    Some(simhash as i64)
+}
+
+// stable between releases unlike `DefaultHasher`, hashes are stored
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Http link without scheme, `www.`, fragment, tracking parameters and trailing slash.
+pub fn canonical_link(link: &str) -> Option<String> {
+    let link = link.trim();
+    let rest = link
+        .strip_prefix("https://")
+        .or_else(|| link.strip_prefix("http://"))?;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (location, query) = match rest.find('?') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    let (host, path) = match location.find('/') {
+        Some(i) => (&location[..i], &location[i..]),
+        None => (location, ""),
+    };
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if host.is_empty() {
+        return None;
+    }
+    let mut canonical = format!("{}{}", host, path.trim_end_matches('/'));
+    let params: Vec<&str> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty() && !TRACKING_PARAMS.iter().any(|t| p.starts_with(t)))
+        .collect();
+    if !params.is_empty() {
+        canonical.push('?');
+        canonical.push_str(params.join("&").as_str());
+    }
+    Some(canonical)
+}
+
+// `href` attributes of the html
+fn content_links(html: &str) -> Vec<String> {
+    let mut links = vec![];
+    let mut rest = html;
+    while let Some(i) = rest.find("href=") {
+        rest = &rest[i + "href=".len()..];
+        let quote = match rest.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => continue,
+        };
+        rest = &rest[1..];
+        if let Some(end) = rest.find(quote) {
+            links.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_link, normalize_text, simhash, Fingerprint};
+
+    const RELEASE: &str = "The Rust team is happy to announce a new version of Rust, 1.53.0. \
+        Rust is a programming language that is empowering everyone to build reliable \
+        and efficient software. This release brings IntoIterator for arrays, or patterns, \
+        unicode identifiers and HEAD branch name support in Cargo.";
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(
+            normalize_text("<p>Rust&nbsp;1.53 &amp; <b>Cargo</b></p>"),
+            vec!["rust", "1", "53", "cargo"]
+        );
+    }
+
+    #[test]
+    fn test_simhash() {
+        assert_eq!(simhash(&normalize_text("new video")), None);
+        let release = simhash(&normalize_text(RELEASE)).unwrap();
+        let repost = simhash(&normalize_text(&format!(
+            "Rust 1.53 is out {} Read more.",
+            RELEASE
+        )))
+        .unwrap();
+        let weather = simhash(&normalize_text(
+            "It was sunny all week long in the valley and the forecast for the weekend \
+            promises even more sunshine and no rain at all, which makes the farmers unhappy again.",
+        ))
+        .unwrap();
+        assert!((release ^ repost).count_ones() <= 7);
+        assert!((release ^ weather).count_ones() > 7);
+    }
+
+    #[test]
+    fn test_simhash_bands() {
+        let fingerprint = Fingerprint::new(None, RELEASE, "1");
+        let bands = fingerprint.simhash_bands().unwrap();
+        let simhash = fingerprint.simhash().unwrap();
+        // three changed bits leave at least one band as it is
+        let changed = simhash ^ (1 | 1 << 20 | 1 << 40);
+        let changed_bands = Fingerprint {
+            simhash: Some(changed),
+            ..fingerprint.clone()
+        }
+        .simhash_bands()
+        .unwrap();
+        assert_eq!(bands.len(), 4);
+        assert!(bands.iter().zip(&changed_bands).any(|(a, b)| a == b));
+        assert!(Fingerprint::new(None, "new video", "1")
+            .simhash_bands()
+            .is_none());
+    }
+
+    #[test]
+    fn test_is_duplicate() {
+        let post = Fingerprint::new(
+            Some("Announcing Rust 1.53.0"),
+            RELEASE,
+            "https://blog.rust-lang.org/2021/06/17/Rust-1.53.0.html",
+        );
+        let link = Fingerprint::new(
+            None,
+            "<a href=\"https://blog.rust-lang.org/2021/06/17/Rust-1.53.0.html?utm_source=tg\">\
+            Rust 1.53</a>",
+            "42",
+        );
+        let title = Fingerprint::new(Some("Announcing  Rust 1.53.0!"), "", "43");
+        let other = Fingerprint::new(Some("Announcing Go 1.17"), "Go 1.17 is out", "44");
+        assert!(post.is_duplicate(&link));
+        assert!(link.is_duplicate(&post));
+        assert!(post.is_duplicate(&title));
+        assert!(!post.is_duplicate(&other));
+    }
+
+    #[test]
+    fn test_canonical_link() {
+        assert_eq!(
+            canonical_link("https://www.Example.com/a/?utm_source=x&id=1#top"),
+            Some("example.com/a?id=1".to_string())
+        );
+        assert_eq!(canonical_link("ftp://example.com"), None);
+        assert_eq!(canonical_link("https://"), None);
+    }
+
+    #[test]
+    fn test_link_pattern() {
+        let fingerprint = Fingerprint::new(None, "", "https://example.com/my_post?p=100%");
+        assert_eq!(
+            fingerprint.link_pattern(),
+            Some("%example.com/my\\_post?p=100\\%%".to_string())
+        );
+        assert_eq!(Fingerprint::new(None, "", "42").link_pattern(), None);
+    }
+}
@@ -0,0 +1,109 @@
+// health of the scraped sources: failed sources are retried later and later,
+// sources which are gone for good are disabled
+use crate::models;
+use crate::result::Error;
+use chrono::{Duration, NaiveDateTime};
+use http_collector::result::Error as HttpError;
+use mastodon_collector::result::Error as MastodonError;
+use reddit_collector::result::Error as RedditError;
+use tg_web_collector::result::Error as TgWebError;
+use vk_collector::result::Error as VkError;
+
+/// Sources are disabled after this number of permanent errors without a success.
+pub const MAX_PERMANENT_FAILURES: i32 = 5;
+const MIN_FAILURE_DELAY_SECS: i64 = 60;
+const MAX_FAILURE_DELAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    // network errors, timeouts, rate limits and server errors
+    Transient,
+    // the source is removed, private or isn't a feed anymore
+    Permanent,
+}
+
+impl ErrorClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transient => "TRANSIENT",
+            Self::Permanent => "PERMANENT",
+        }
+    }
+}
+
+pub fn classify(error: &Error) -> ErrorClass {
+    match error {
+        Error::SourceFailed { error, .. } => classify(error),
+        Error::HttpCollectorError(HttpError::HttpError(status))
+        | Error::MastodonCollectorError(MastodonError::HttpError(status))
+        | Error::RedditCollectorError(RedditError::HttpError(status)) => classify_status(*status),
+        Error::HttpCollectorError(
+            HttpError::NoFeed
+            | HttpError::SourceNotSupported
+            | HttpError::InvalidUrl(_)
+            | HttpError::DecodeError
+            | HttpError::ParseError,
+        )
+        | Error::TgWebCollectorError(TgWebError::ChannelNotFound(_) | TgWebError::ParseError(_))
+        | Error::MastodonCollectorError(
+            MastodonError::InvalidHandle(_) | MastodonError::NotFound,
+        )
+        | Error::RedditCollectorError(
+            RedditError::InvalidSource(_) | RedditError::NotFound | RedditError::Forbidden,
+        ) => ErrorClass::Permanent,
+        Error::VkCollectorError(VkError::VkError(e)) if e.is_access_denied() => {
+            ErrorClass::Permanent
+        }
+        _ => ErrorClass::Transient,
+    }
+}
+
+fn classify_status(status: u16) -> ErrorClass {
+    match status {
+        // timeout and rate limit
+        408 | 429 => ErrorClass::Transient,
+        400..=499 => ErrorClass::Permanent,
+        _ => ErrorClass::Transient,
+    }
+}
+
+/// Health of the source after one more failed scrape.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub error: String,
+    pub class: ErrorClass,
+    pub consecutive_failures: i32,
+    pub permanent_failures: i32,
+    pub next_scrape_at: NaiveDateTime,
+    // set once there are too many permanent errors
+    pub disabled_at: Option<NaiveDateTime>,
+}
+
+/// The delay before the next scrape doubles with every failure in a row
+/// starting from the usual interval of the source.
+pub fn plan_failure(source: &models::Source, error: &Error, now: NaiveDateTime) -> Failure {
+    let class = classify(error);
+    let consecutive_failures = source.consecutive_failures + 1;
+    let permanent_failures = match class {
+        ErrorClass::Permanent => source.permanent_failures + 1,
+        ErrorClass::Transient => source.permanent_failures,
+    };
+    Failure {
+        error: error.to_string(),
+        class,
+        consecutive_failures,
+        permanent_failures,
+        next_scrape_at: now + failure_delay(source.scrape_interval_secs, consecutive_failures),
+        disabled_at: Some(now).filter(|_| permanent_failures >= MAX_PERMANENT_FAILURES),
+    }
+}
+
+fn failure_delay(interval_secs: Option<i32>, failures: i32) -> Duration {
+    let base = i64::from(interval_secs.unwrap_or_default()).max(MIN_FAILURE_DELAY_SECS);
+    // the delay reaches the maximum long before the shift overflows
+    let exponent = (failures - 1).max(0).min(16) as u32;
+    Duration::seconds(
+        base.saturating_mul(2i64.pow(exponent))
+            .min(MAX_FAILURE_DELAY_SECS),
+    )
+}
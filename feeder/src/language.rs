@@ -0,0 +1,25 @@
+// language of the records, detected offline from their text when they're stored
+use crate::duplicates::normalize_text;
+use crate::models;
+
+// a couple of words can be read as almost any language
+const MIN_WORDS: usize = 3;
+
+/// ISO 639-3 code of the language of the text like `eng`, `rus` or `ukr`,
+/// `None` if the language isn't detected reliably.
+pub fn detect(title: Option<&str>, content: &str) -> Option<String> {
+    let mut words = title.map(normalize_text).unwrap_or_default();
+    words.extend(normalize_text(content));
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    let info = whatlang::detect(words.join(" ").as_str())?;
+    if !info.is_reliable() {
+        return None;
+    }
+    Some(info.lang().code().to_string())
+}
+
+pub fn of_new_record(record: &models::NewRecord) -> Option<String> {
+    detect(record.title.as_deref(), record.content.as_str())
+}
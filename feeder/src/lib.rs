@@ -4,13 +4,30 @@ extern crate log;
 #[macro_use]
 extern crate derive_builder;
 
+#[macro_use]
+extern crate lazy_static;
+
+#[macro_use]
+extern crate prometheus;
+
 extern crate futures;
 extern crate serde;
 
 pub mod aggregator;
 pub mod config;
+pub mod duplicates;
+pub mod health;
+pub mod language;
+pub mod metrics;
 pub mod models;
+pub mod net;
+pub mod pipeline;
+pub mod queue;
 pub mod result;
+pub mod schedule;
 pub mod storage;
 mod updates;
-pub use updates::Source;
+pub use tokio_util::sync::CancellationToken;
+pub use updates::supervisor::{LoopState, SourceState};
+pub use updates::tg::TelegramExportImporter;
+pub use updates::{SourceData, SourceProvider, SourceUpdate, UpdatesHandler};
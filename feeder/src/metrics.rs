@@ -0,0 +1,83 @@
+// metrics of the sources in the default prometheus registry,
+// the interface exposes them on `/metrics`
+use crate::health::{self, ErrorClass};
+use crate::result::Error;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{HistogramVec, IntCounterVec, IntGauge};
+use std::time::Duration;
+
+lazy_static! {
+    static ref SCRAPES: IntCounterVec = register_int_counter_vec!(
+        "feeder_scrapes_total",
+        "Scrapes of the sources by kind and outcome",
+        &["kind", "outcome"]
+    )
+    .unwrap();
+    static ref SCRAPE_DURATION: HistogramVec = register_histogram_vec!(
+        "feeder_scrape_duration_seconds",
+        "Time of the source scrape",
+        &["kind"]
+    )
+    .unwrap();
+    static ref RECORDS_INSERTED: IntCounterVec = register_int_counter_vec!(
+        "feeder_records_inserted_total",
+        "Records stored from the updates of the sources",
+        &["kind"]
+    )
+    .unwrap();
+}
+
+/// Counts the scrape of the source, `error` is the scrape failure if any.
+pub fn observe_scrape(kind: &str, error: Option<&Error>, elapsed: Duration) {
+    let outcome = match error.map(health::classify) {
+        None => "success",
+        Some(ErrorClass::Transient) => "transient_error",
+        Some(ErrorClass::Permanent) => "permanent_error",
+    };
+    SCRAPES.with_label_values(&[kind, outcome]).inc();
+    SCRAPE_DURATION
+        .with_label_values(&[kind])
+        .observe(elapsed.as_secs_f64());
+}
+
+pub fn observe_records(kind: &str, inserted: usize) {
+    RECORDS_INSERTED
+        .with_label_values(&[kind])
+        .inc_by(inserted as u64);
+}
+
+/// Registers the gauge which is read on every collection, e.g. length of a queue.
+///
+/// `None` keeps the previous value, e.g. when the queue is locked.
+pub fn register_probe<F>(name: &str, help: &str, read: F)
+where
+    F: Fn() -> Option<i64> + Send + Sync + 'static,
+{
+    let registered = IntGauge::new(name, help)
+        .and_then(|gauge| prometheus::register(Box::new(Probe { gauge, read })));
+    if let Err(e) = registered {
+        warn!("metric {} isn't registered: {}", name, e);
+    }
+}
+
+struct Probe<F> {
+    gauge: IntGauge,
+    read: F,
+}
+
+impl<F> Collector for Probe<F>
+where
+    F: Fn() -> Option<i64> + Send + Sync,
+{
+    fn desc(&self) -> Vec<&Desc> {
+        self.gauge.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        if let Some(value) = (self.read)() {
+            self.gauge.set(value);
+        }
+        self.gauge.collect()
+    }
+}
@@ -3,5 +3,5 @@ mod record;
 mod source;
 
 pub use file::{File, NewFile};
-pub use record::{NewRecord, Record};
+pub use record::{content_hash, NewRecord, Record, RecordVersion, SavedRecords};
 pub use source::{NewSource, Source};
@@ -1,5 +1,6 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
@@ -11,6 +12,32 @@ pub struct Record {
     pub date: NaiveDateTime,
     pub image: Option<String>,
     pub external_link: String,
+    // source specific json, e.g. score and comments count
+    pub meta: Option<String>,
+    // sha256 of the content, null for records stored before the hashes
+    pub content_hash: Option<String>,
+    // set on the first edit, previous versions are in `record_versions`
+    pub updated_at: Option<NaiveDateTime>,
+    // fingerprint of the text, see `duplicates`
+    pub simhash: Option<i64>,
+    pub canonical_link: Option<String>,
+    // id of the first record of the same story, set when duplicates are found
+    pub cluster_id: Option<i32>,
+    // see `language::detect`
+    pub language: Option<String>,
+}
+
+impl Record {
+    pub fn content_hash(&self) -> String {
+        self.content_hash
+            .clone()
+            .unwrap_or_else(|| content_hash(self.content.as_str()))
+    }
+
+    /// Title and content are versioned, meta (e.g. score) changes aren't edits.
+    pub fn is_changed_by(&self, record: &NewRecord) -> bool {
+        self.title != record.title || self.content_hash() != content_hash(record.content.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,4 +49,32 @@ pub struct NewRecord {
     pub content: String,
     pub date: Option<NaiveDateTime>,
     pub image: Option<String>,
+    pub meta: Option<String>,
+}
+
+/// Result of `Storage::save_records`, records which were stored before aren't changed.
+#[derive(Debug, Clone, Default)]
+pub struct SavedRecords {
+    pub created: Vec<Record>,
+    // edited records, filled by `Storage::save_or_update_records` only
+    pub updated: Vec<Record>,
+    pub existing: Vec<Record>,
+}
+
+/// Title and content of the record before an edit.
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct RecordVersion {
+    pub id: i32,
+    pub record_id: i32,
+    pub title: Option<String>,
+    pub content: String,
+    pub image: Option<String>,
+    pub content_hash: Option<String>,
+    // when the version was published or edited
+    pub date: NaiveDateTime,
+    pub replaced_at: NaiveDateTime,
+}
+
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
 }
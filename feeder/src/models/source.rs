@@ -10,6 +10,24 @@ pub struct Source {
     pub image: Option<String>,
     pub last_scrape_time: NaiveDateTime,
     pub external_link: String,
+    pub next_scrape_at: NaiveDateTime,
+    // adapted to publishing frequency
+    pub scrape_interval_secs: Option<i32>,
+    // overrides the adapted interval, see `schedule::Schedule`
+    pub schedule: Option<String>,
+    pub last_success_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    // see `health::ErrorClass`
+    pub last_error_class: Option<String>,
+    pub consecutive_failures: i32,
+    pub permanent_failures: i32,
+    // disabled sources aren't scraped
+    pub disabled_at: Option<NaiveDateTime>,
+    // name of the collector account which sees the source, any account if not set
+    pub account: Option<String>,
+    // worker scraping the source now, see `queue`
+    pub leased_by: Option<String>,
+    pub lease_expires_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,124 @@
+// checks of the hosts given by users, so requests made on their behalf
+// can't reach the services of the server network
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+// loopback, private, link-local and other addresses which aren't reachable from the internet
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // shared address space of carrier-grade nat
+        || (a == 100 && (64..128).contains(&b))
+        // benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240
+}
+
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || matches!(ipv4_of(ip), Some(ip) if is_internal_v4(ip))
+        }
+    }
+}
+
+// ipv4 address embedded into ipv4-mapped or ipv4-compatible ipv6 one
+fn ipv4_of(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, ..] | [0, 0, 0, 0, 0, 0, ..] => {
+            let [.., a, b, c, d] = ip.octets();
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the host, which is a domain or an ip address, and fails
+/// if any of its addresses is internal.
+///
+/// The returned address is the one to connect to, resolving the host again
+/// may give another one.
+pub async fn public_address(host: &str, port: u16) -> Result<SocketAddr, String> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return match is_internal(ip) {
+            true => Err(format!("{} is internal address", ip)),
+            false => Ok(SocketAddr::new(ip, port)),
+        };
+    }
+    let addresses = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("can't resolve {}: {}", host, e))?
+        .collect::<Vec<_>>();
+    if let Some(address) = addresses.iter().find(|address| is_internal(address.ip())) {
+        return Err(format!(
+            "{} resolves to internal address {}",
+            host,
+            address.ip()
+        ));
+    }
+    addresses
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("can't resolve {}", host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_internal, public_address};
+    use std::net::IpAddr;
+
+    fn internal(ip: &str) -> bool {
+        is_internal(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn test_is_internal() {
+        for ip in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(internal(ip), "{} is internal", ip);
+        }
+        for ip in &["1.1.1.1", "93.184.216.34", "100.128.0.1", "2606:4700::1111"] {
+            assert!(!internal(ip), "{} is public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_address() {
+        assert!(public_address("127.0.0.1", 80).await.is_err());
+        assert!(public_address("[::1]", 80).await.is_err());
+        assert!(public_address("localhost", 80).await.is_err());
+        assert_eq!(
+            public_address("1.1.1.1", 443).await.unwrap().to_string(),
+            "1.1.1.1:443"
+        );
+    }
+}
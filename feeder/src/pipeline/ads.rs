@@ -0,0 +1,79 @@
+use super::RecordProcessor;
+use crate::models;
+use crate::result::Result;
+use async_trait::async_trait;
+use regex::Regex;
+
+pub(super) const NAME: &str = "drop_ads";
+
+// ad hashtags and the russian "advertising" disclaimer
+const AD_MARKERS: &str =
+    r"(?i)(?:^|\W)#(?:ad|ads|sponsored|promo|реклама|промо)\b|на\s+правах\s+рекламы";
+
+/// Drops records marked as advertisement by the author.
+pub struct DropAds {
+    markers: Regex,
+}
+
+impl DropAds {
+    pub fn new() -> Self {
+        Self {
+            markers: Regex::new(AD_MARKERS).unwrap(),
+        }
+    }
+}
+
+impl Default for DropAds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RecordProcessor for DropAds {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn process(
+        &self,
+        _kind: &str,
+        record: models::NewRecord,
+    ) -> Result<Option<models::NewRecord>> {
+        let is_ad = self.markers.is_match(record.content.as_str())
+            || record.title.iter().any(|t| self.markers.is_match(t));
+        Ok(Some(record).filter(|_| !is_ad))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AD_MARKERS;
+    use regex::Regex;
+
+    #[test]
+    fn test_ad_markers() {
+        let markers = Regex::new(AD_MARKERS).unwrap();
+        for text in &[
+            "#ad",
+            "new phone #Ads",
+            "launch (#sponsored)",
+            "<p>#promo</p>",
+            "скидки #реклама",
+            "#промо",
+            "Опубликовано на  правах рекламы",
+        ] {
+            assert!(markers.is_match(text), "{} is an ad", text);
+        }
+        for text in &[
+            "#adventure",
+            "road#ad",
+            "#advertising is hard",
+            "the ad was removed",
+            "#рекламации",
+            "на правах автора",
+        ] {
+            assert!(!markers.is_match(text), "{} isn't an ad", text);
+        }
+    }
+}
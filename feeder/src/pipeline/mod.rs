@@ -0,0 +1,299 @@
+// post-processing of the records: every new or edited record passes the stages
+// of its source kind before it's saved, the stages are notified when it's saved
+use crate::models;
+use crate::result::Result;
+use crate::storage::Storage;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+mod ads;
+mod sanitize;
+
+pub use ads::DropAds;
+pub use sanitize::Sanitize;
+
+/// Stage of the records pipeline, e.g. sanitization, enrichment or filtering.
+#[async_trait]
+pub trait RecordProcessor: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Returns the changed record, `None` drops it.
+    /// Failed stage is skipped, the record goes on unchanged.
+    async fn process(
+        &self,
+        kind: &str,
+        record: models::NewRecord,
+    ) -> Result<Option<models::NewRecord>>;
+
+    /// Called for every created or updated record.
+    async fn saved(&self, _kind: &str, _record: &models::Record) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Built-in stage by its name in the config.
+pub fn builtin(name: &str) -> Option<Arc<dyn RecordProcessor>> {
+    match name {
+        sanitize::NAME => Some(Arc::new(Sanitize::new())),
+        ads::NAME => Some(Arc::new(DropAds::new())),
+        _ => None,
+    }
+}
+
+/// Stages of one source kind, saves records through the storage.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    processors: Vec<Arc<dyn RecordProcessor>>,
+}
+
+impl Pipeline {
+    pub fn new(processors: Vec<Arc<dyn RecordProcessor>>) -> Self {
+        Self { processors }
+    }
+
+    /// Records which are left after all the stages.
+    pub async fn process(
+        &self,
+        kind: &str,
+        records: Vec<models::NewRecord>,
+    ) -> Vec<models::NewRecord> {
+        let mut processed = Vec::with_capacity(records.len());
+        for record in records {
+            if let Some(record) = self.process_record(kind, record).await {
+                processed.push(record);
+            }
+        }
+        processed
+    }
+
+    async fn process_record(
+        &self,
+        kind: &str,
+        mut record: models::NewRecord,
+    ) -> Option<models::NewRecord> {
+        for processor in &self.processors {
+            match processor.process(kind, record.clone()).await {
+                Ok(Some(processed)) => record = processed,
+                Ok(None) => {
+                    debug!(
+                        "record {} of source {} dropped by {}",
+                        record.source_record_id,
+                        record.source_id,
+                        processor.name()
+                    );
+                    return None;
+                }
+                Err(e) => warn!(
+                    "{} failed for record {} of source {}: {}",
+                    processor.name(),
+                    record.source_record_id,
+                    record.source_id,
+                    e
+                ),
+            }
+        }
+        Some(record)
+    }
+
+    pub async fn saved(&self, kind: &str, records: &[models::Record]) {
+        for processor in &self.processors {
+            for record in records {
+                if let Err(e) = processor.saved(kind, record).await {
+                    warn!(
+                        "{} failed for saved record {}: {}",
+                        processor.name(),
+                        record.id,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// `Storage::save_records` of the processed records.
+    pub async fn save_records<S: Storage + Sync>(
+        &self,
+        storage: &S,
+        kind: &str,
+        records: Vec<models::NewRecord>,
+    ) -> Result<models::SavedRecords> {
+        let records = self.process(kind, records).await;
+        if records.is_empty() {
+            return Ok(models::SavedRecords::default());
+        }
+        let saved = storage.save_records(records).await?;
+        self.saved(kind, &saved.created).await;
+        Ok(saved)
+    }
+
+    /// `Storage::save_or_update_records` of the processed records.
+    pub async fn save_or_update_records<S: Storage + Sync>(
+        &self,
+        storage: &S,
+        kind: &str,
+        records: Vec<models::NewRecord>,
+    ) -> Result<models::SavedRecords> {
+        let records = self.process(kind, records).await;
+        if records.is_empty() {
+            return Ok(models::SavedRecords::default());
+        }
+        let saved = storage.save_or_update_records(records).await?;
+        self.saved(kind, &saved.created).await;
+        self.saved(kind, &saved.updated).await;
+        Ok(saved)
+    }
+
+    /// `Storage::update_record` of the processed record, `None` if it's dropped.
+    pub async fn update_record<S: Storage + Sync>(
+        &self,
+        storage: &S,
+        kind: &str,
+        record: models::NewRecord,
+    ) -> Result<Option<models::Record>> {
+        let record = match self.process_record(kind, record).await {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let updated = storage.update_record(record).await?;
+        if let Some(updated) = &updated {
+            self.saved(kind, std::slice::from_ref(updated)).await;
+        }
+        Ok(updated)
+    }
+}
+
+/// Pipelines of all the source kinds: configured built-in stages of the kind
+/// followed by the processors registered for every kind.
+#[derive(Clone, Default)]
+pub struct Pipelines {
+    // built-in stage names by source kind
+    stages: HashMap<String, Vec<String>>,
+    processors: Vec<Arc<dyn RecordProcessor>>,
+}
+
+impl Pipelines {
+    pub fn new(stages: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            stages,
+            processors: vec![],
+        }
+    }
+
+    pub fn with_processor(mut self, processor: Arc<dyn RecordProcessor>) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    pub fn for_kind(&self, kind: &str) -> Pipeline {
+        let mut processors: Vec<Arc<dyn RecordProcessor>> = self
+            .stages
+            .iter()
+            // kinds are case insensitive, config keys may be lowercased
+            .filter(|(k, _)| k.eq_ignore_ascii_case(kind))
+            .flat_map(|(_, stages)| stages.iter())
+            .map(|name| match builtin(name) {
+                Some(processor) => processor,
+                None => panic!("unknown record processor {} for {}", name, kind),
+            })
+            .collect();
+        processors.extend(self.processors.iter().cloned());
+        Pipeline::new(processors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pipeline, Pipelines, RecordProcessor};
+    use crate::models::NewRecord;
+    use crate::result::{Error, Result};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn record(content: &str) -> NewRecord {
+        NewRecord {
+            title: None,
+            source_record_id: content.to_string(),
+            source_id: 1,
+            content: content.to_string(),
+            date: None,
+            image: None,
+            meta: None,
+        }
+    }
+
+    // appends its name to the content, drops records containing `drop`
+    // and fails for records containing `fail`
+    struct Append(&'static str);
+
+    #[async_trait]
+    impl RecordProcessor for Append {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn process(&self, _kind: &str, mut record: NewRecord) -> Result<Option<NewRecord>> {
+            if record.content.contains(&format!("fail {}", self.0)) {
+                return Err(Error::DbError("failed".to_string()));
+            }
+            if record.content.contains(&format!("drop {}", self.0)) {
+                return Ok(None);
+            }
+            record.content = format!("{} {}", record.content, self.0);
+            Ok(Some(record))
+        }
+    }
+
+    fn pipeline() -> Pipeline {
+        Pipeline::new(vec![Arc::new(Append("a")), Arc::new(Append("b"))])
+    }
+
+    async fn contents(pipeline: &Pipeline, records: &[&str]) -> Vec<String> {
+        pipeline
+            .process("WEB", records.iter().map(|r| record(r)).collect())
+            .await
+            .into_iter()
+            .map(|r| r.content)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stages_order() {
+        assert_eq!(contents(&pipeline(), &["x", "y"]).await, ["x a b", "y a b"]);
+        assert_eq!(contents(&Pipeline::default(), &["x"]).await, ["x"]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped() {
+        assert_eq!(
+            contents(&pipeline(), &["x", "drop a", "drop b", "y"]).await,
+            ["x a b", "y a b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_stage_skipped() {
+        assert_eq!(
+            contents(&pipeline(), &["fail a", "fail b"]).await,
+            ["fail a b", "fail b a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_for_kind() {
+        let stages: HashMap<String, Vec<String>> =
+            vec![("web".to_string(), vec!["drop_ads".to_string()])]
+                .into_iter()
+                .collect();
+        let pipelines = Pipelines::new(stages).with_processor(Arc::new(Append("a")));
+        assert_eq!(
+            contents(&pipelines.for_kind("WEB"), &["x", "#ad y"]).await,
+            ["x a"]
+        );
+        assert_eq!(
+            contents(&pipelines.for_kind("EMAIL"), &["x", "#ad y"]).await,
+            ["x a", "#ad y a"]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use super::RecordProcessor;
+use crate::models;
+use crate::result::Result;
+use async_trait::async_trait;
+
+pub(super) const NAME: &str = "sanitize";
+
+/// Removes scripts, styles, event handlers and other unsafe markup from the content,
+/// trims the title.
+pub struct Sanitize {
+    cleaner: ammonia::Builder<'static>,
+}
+
+impl Sanitize {
+    pub fn new() -> Self {
+        let mut cleaner = ammonia::Builder::default();
+        // inline images of emails are referred by content id
+        cleaner.add_url_schemes(&["cid"]);
+        Self { cleaner }
+    }
+}
+
+impl Default for Sanitize {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RecordProcessor for Sanitize {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    async fn process(
+        &self,
+        _kind: &str,
+        mut record: models::NewRecord,
+    ) -> Result<Option<models::NewRecord>> {
+        record.content = self.cleaner.clean(record.content.as_str()).to_string();
+        record.title = record
+            .title
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+        Ok(Some(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sanitize;
+    use crate::models::NewRecord;
+    use crate::pipeline::RecordProcessor;
+
+    #[tokio::test]
+    async fn test_sanitize() {
+        let record = NewRecord {
+            title: Some("  title \n".to_string()),
+            source_record_id: "1".to_string(),
+            source_id: 1,
+            content: r#"<p onclick="steal()">text<script>steal()</script></p><img src="cid:logo">"#
+                .to_string(),
+            date: None,
+            image: None,
+            meta: None,
+        };
+        let record = Sanitize::new()
+            .process("EMAIL", record)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.title.as_deref(), Some("title"));
+        assert_eq!(record.content, r#"<p>text</p><img src="cid:logo">"#);
+    }
+}
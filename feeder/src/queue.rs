@@ -0,0 +1,47 @@
+// scrape queue shared by the worker processes: due sources are claimed with
+// `FOR UPDATE SKIP LOCKED`, so every source is scraped by one worker at a time;
+// the lease is released once the source is scheduled or its failure is stored,
+// otherwise (no new records, the worker died) the source is claimed again after
+// the lease expires
+use chrono::{Duration, NaiveDateTime, Utc};
+
+/// How long claimed sources stay with the worker, it covers the slowest scrape.
+pub const LEASE_SECS: i64 = 10 * 60;
+/// Most sources of a kind claimed at once, the rest is left to other workers.
+pub const CLAIM_LIMIT: i64 = 500;
+
+lazy_static! {
+    /// Name of the worker process in the queue.
+    pub static ref WORKER: String = format!(
+        "{}:{}",
+        std::env::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "localhost".to_string()),
+        std::process::id()
+    );
+}
+
+/// Claim of the due sources by this worker.
+#[derive(Debug, Clone)]
+pub struct Lease {
+    pub worker: String,
+    pub expires_at: NaiveDateTime,
+    pub limit: i64,
+}
+
+impl Lease {
+    pub fn new(now: NaiveDateTime) -> Self {
+        Self {
+            worker: WORKER.clone(),
+            expires_at: now + Duration::seconds(LEASE_SECS),
+            limit: CLAIM_LIMIT,
+        }
+    }
+
+    pub fn now() -> Self {
+        Self::new(Utc::now().naive_utc())
+    }
+}
@@ -6,10 +6,23 @@ pub enum Error {
     HttpCollectorError(http_collector::result::Error),
     VkCollectorError(vk_collector::result::Error),
     TgCollectorError(tg_collector::result::Error),
+    TgWebCollectorError(tg_web_collector::result::Error),
+    MastodonCollectorError(mastodon_collector::result::Error),
+    RedditCollectorError(reddit_collector::result::Error),
+    FsCollectorError(fs_collector::result::Error),
     UpdateNotSupported(String),
     SourceKindConflict(String),
     SourceNotFound,
     SourceCreationError,
+    InvalidSchedule(String),
+    // every problem of the config found by `AppConfig::validate`
+    InvalidConfig(Vec<String>),
+    // scrape of the particular source failed
+    SourceFailed {
+        kind: &'static str,
+        origin: String,
+        error: Box<Error>,
+    },
     IOError(std::io::Error),
 }
 
@@ -31,6 +44,30 @@ impl From<tg_collector::result::Error> for Error {
     }
 }
 
+impl From<tg_web_collector::result::Error> for Error {
+    fn from(err: tg_web_collector::result::Error) -> Self {
+        Self::TgWebCollectorError(err)
+    }
+}
+
+impl From<mastodon_collector::result::Error> for Error {
+    fn from(err: mastodon_collector::result::Error) -> Self {
+        Self::MastodonCollectorError(err)
+    }
+}
+
+impl From<reddit_collector::result::Error> for Error {
+    fn from(err: reddit_collector::result::Error) -> Self {
+        Self::RedditCollectorError(err)
+    }
+}
+
+impl From<fs_collector::result::Error> for Error {
+    fn from(err: fs_collector::result::Error) -> Self {
+        Self::FsCollectorError(err)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Self::IOError(err)
@@ -43,4 +80,14 @@ impl From<vk_collector::result::Error> for Error {
     }
 }
 
+impl Error {
+    pub fn source_failed(kind: &'static str, origin: String, error: impl Into<Error>) -> Self {
+        Self::SourceFailed {
+            kind,
+            origin,
+            error: Box::new(error.into()),
+        }
+    }
+}
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
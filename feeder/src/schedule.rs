@@ -0,0 +1,338 @@
+// when sources are scraped: explicit schedules of the sources or
+// intervals adapted to how often they publish
+use crate::models;
+use crate::result::{Error, Result};
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+use std::str::FromStr;
+
+/// Publishing dates of the latest records are used to adapt the interval.
+pub const RECENT_RECORDS: i64 = 20;
+// a source is checked a few times per expected record
+const SCRAPES_PER_RECORD: i64 = 2;
+// longer intervals are surely mistakes and don't fit into dates
+const MAX_INTERVAL_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Bounds of the adapted interval between scrapes of a source.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrapeBounds {
+    min_secs: i64,
+    max_secs: i64,
+}
+
+impl ScrapeBounds {
+    pub fn new(min_secs: u64, max_secs: u64) -> Self {
+        let min_secs = min_secs as i64;
+        Self {
+            min_secs,
+            max_secs: (max_secs as i64).max(min_secs),
+        }
+    }
+
+    fn clamp(&self, secs: i64) -> i64 {
+        secs.max(self.min_secs).min(self.max_secs)
+    }
+}
+
+/// Explicit schedule of the source, overrides the adapted interval.
+///
+/// Either an interval like `3600`, `90s`, `30m`, `6h`, `1d` up to `365d`, or a cron
+/// expression: classic crontab `0 9 * * 1-5`, where 0 or 7 is Sunday,
+/// or with seconds, `0 0 9 * * Mon-Fri`, where weekdays are numbered from 1 for Sunday.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(secs) = parse_interval_secs(s) {
+            return match secs {
+                0 => Err(Error::InvalidSchedule(format!("empty interval: {}", s))),
+                secs if secs > MAX_INTERVAL_SECS => Err(Error::InvalidSchedule(format!(
+                    "interval is longer than a year: {}",
+                    s
+                ))),
+                _ => Ok(Self::Every(Duration::seconds(secs))),
+            };
+        }
+        // classic crontab has no seconds and numbers weekdays from 0
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let expression = match fields.as_slice() {
+            [minute, hour, day, month, weekday] => format!(
+                "0 {} {} {} {} {}",
+                minute,
+                hour,
+                day,
+                month,
+                crontab_weekdays(weekday).unwrap_or_else(|| weekday.to_string())
+            ),
+            _ => s.to_string(),
+        };
+        cron::Schedule::from_str(expression.as_str())
+            .map(|schedule| Self::Cron(Box::new(schedule)))
+            .map_err(|e| Error::InvalidSchedule(format!("{}: {}", s, e)))
+    }
+}
+
+impl Schedule {
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Self::Every(interval) => after.checked_add_signed(*interval),
+            Self::Cron(schedule) => schedule
+                .after(&Utc.from_utc_datetime(&after))
+                .next()
+                .map(|next| next.naive_utc()),
+        }
+    }
+}
+
+// numeric crontab weekdays, 0-7 from Sunday, as the list of the cron crate ones, 1-7 from Sunday;
+// `None` for names and anything else, the cron crate parses them as they are
+fn crontab_weekdays(field: &str) -> Option<String> {
+    if field == "*" {
+        return None;
+    }
+    let mut days = vec![];
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().ok().filter(|s| *s > 0)?),
+            None => (item, 1),
+        };
+        let (from, to) = match (range, range.split_once('-')) {
+            ("*", _) => (0, 6),
+            (_, Some((from, to))) => (from.parse::<u32>().ok()?, to.parse::<u32>().ok()?),
+            // `1/2` is every second day from Monday
+            (day, None) if step > 1 => (day.parse::<u32>().ok()?, 6),
+            (day, None) => (day.parse::<u32>().ok()?, day.parse::<u32>().ok()?),
+        };
+        if from > to || to > 7 {
+            return None;
+        }
+        days.extend((from..=to).step_by(step).map(|day| day % 7 + 1));
+    }
+    days.sort_unstable();
+    days.dedup();
+    Some(
+        days.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+fn parse_interval_secs(s: &str) -> Option<i64> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    number.parse::<i64>().ok()?.checked_mul(multiplier)
+}
+
+/// Interval between scrapes, so the source is checked a few times per record
+/// it usually publishes; the interval grows while the source is silent.
+///
+/// `dates` are publishing dates of the latest records.
+pub fn adapted_interval_secs(
+    dates: &[NaiveDateTime],
+    bounds: ScrapeBounds,
+    now: NaiveDateTime,
+) -> i64 {
+    let oldest = match dates.iter().min() {
+        Some(oldest) => *oldest,
+        // nothing is published yet
+        None => return bounds.max_secs,
+    };
+    let period = (now - oldest).num_seconds().max(0);
+    bounds.clamp(period / (dates.len() as i64 * SCRAPES_PER_RECORD))
+}
+
+/// Adapted interval and the time of the next scrape of the source scraped now.
+///
+/// The schedule of the source wins over the interval,
+/// but sources aren't scraped more often than the minimal interval anyway.
+pub fn plan_scrape(
+    source: &models::Source,
+    dates: &[NaiveDateTime],
+    bounds: ScrapeBounds,
+    now: NaiveDateTime,
+) -> (i64, NaiveDateTime) {
+    let interval_secs = adapted_interval_secs(dates, bounds, now);
+    let earliest = now + Duration::seconds(bounds.min_secs);
+    let scheduled =
+        source
+            .schedule
+            .as_deref()
+            .and_then(|schedule| match Schedule::from_str(schedule) {
+                Ok(schedule) => schedule.next_after(now),
+                Err(e) => {
+                    warn!("schedule of source {} ignored: {}", source.id, e);
+                    None
+                }
+            });
+    let next_scrape_at = match scheduled {
+        Some(scheduled) => scheduled.max(earliest),
+        None => now + Duration::seconds(interval_secs),
+    };
+    (interval_secs, next_scrape_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        adapted_interval_secs, crontab_weekdays, parse_interval_secs, plan_scrape, Schedule,
+        ScrapeBounds,
+    };
+    use crate::models::Source;
+    use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+    use std::str::FromStr;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2021-08-02 is Monday
+        NaiveDate::from_ymd(2021, 8, day).and_hms(hour, minute, 0)
+    }
+
+    fn source(schedule: Option<&str>) -> Source {
+        Source {
+            id: 1,
+            name: "source".to_string(),
+            origin: "origin".to_string(),
+            kind: "WEB".to_string(),
+            image: None,
+            last_scrape_time: at(1, 0, 0),
+            external_link: "link".to_string(),
+            next_scrape_at: at(1, 0, 0),
+            scrape_interval_secs: None,
+            schedule: schedule.map(String::from),
+            last_success_at: None,
+            last_error: None,
+            last_error_class: None,
+            consecutive_failures: 0,
+            permanent_failures: 0,
+            disabled_at: None,
+            account: None,
+            leased_by: None,
+            lease_expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_interval_secs() {
+        assert_eq!(parse_interval_secs("3600"), Some(3600));
+        assert_eq!(parse_interval_secs("90s"), Some(90));
+        assert_eq!(parse_interval_secs("30m"), Some(30 * 60));
+        assert_eq!(parse_interval_secs("6h"), Some(6 * 60 * 60));
+        assert_eq!(parse_interval_secs("1d"), Some(24 * 60 * 60));
+        assert_eq!(parse_interval_secs("0"), Some(0));
+        assert_eq!(parse_interval_secs("5w"), None);
+        assert_eq!(parse_interval_secs("h"), None);
+        assert_eq!(parse_interval_secs("0 9 * * *"), None);
+        assert_eq!(parse_interval_secs("99999999999999999999d"), None);
+    }
+
+    #[test]
+    fn test_crontab_weekdays() {
+        assert_eq!(crontab_weekdays("*"), None);
+        assert_eq!(crontab_weekdays("0"), Some("1".to_string()));
+        assert_eq!(crontab_weekdays("7"), Some("1".to_string()));
+        assert_eq!(crontab_weekdays("1-5"), Some("2,3,4,5,6".to_string()));
+        assert_eq!(crontab_weekdays("5-7"), Some("1,6,7".to_string()));
+        assert_eq!(crontab_weekdays("0,6"), Some("1,7".to_string()));
+        assert_eq!(crontab_weekdays("*/2"), Some("1,3,5,7".to_string()));
+        assert_eq!(crontab_weekdays("1/2"), Some("2,4,6".to_string()));
+        assert_eq!(crontab_weekdays("Mon-Fri"), None);
+        assert_eq!(crontab_weekdays("8"), None);
+        assert_eq!(crontab_weekdays("5-1"), None);
+    }
+
+    #[test]
+    fn test_crontab_schedule() {
+        // Friday evening, the next weekday run is on Monday
+        let friday = at(6, 10, 0);
+        let weekdays = Schedule::from_str("0 9 * * 1-5").unwrap();
+        let next = weekdays.next_after(friday).unwrap();
+        assert_eq!(next, at(9, 9, 0));
+        assert_eq!(next.weekday(), Weekday::Mon);
+
+        let sunday = Schedule::from_str("30 8 * * 0").unwrap();
+        assert_eq!(sunday.next_after(friday).unwrap(), at(8, 8, 30));
+        let sunday = Schedule::from_str("30 8 * * 7").unwrap();
+        assert_eq!(sunday.next_after(friday).unwrap(), at(8, 8, 30));
+
+        let names = Schedule::from_str("0 9 * * Mon-Fri").unwrap();
+        assert_eq!(names.next_after(friday).unwrap(), at(9, 9, 0));
+
+        // cron crate syntax with seconds is kept
+        let seconds = Schedule::from_str("0 0 9 * * 2-6").unwrap();
+        assert_eq!(seconds.next_after(friday).unwrap(), at(9, 9, 0));
+
+        let every = Schedule::from_str("30m").unwrap();
+        assert_eq!(every.next_after(friday).unwrap(), at(6, 10, 30));
+
+        assert!(Schedule::from_str("0m").is_err());
+        assert!(Schedule::from_str("365d").is_ok());
+        assert!(Schedule::from_str("366d").is_err());
+        assert!(Schedule::from_str("100000000d").is_err());
+        assert!(Schedule::from_str("9999999999999999s").is_err());
+        let year = Schedule::from_str("365d").unwrap();
+        assert_eq!(year.next_after(chrono::naive::MAX_DATETIME), None);
+        assert!(Schedule::from_str("0 9 * *").is_err());
+        assert!(Schedule::from_str("every day").is_err());
+    }
+
+    #[test]
+    fn test_adapted_interval_secs() {
+        let bounds = ScrapeBounds::new(60, 24 * 60 * 60);
+        let now = at(10, 12, 0);
+        assert_eq!(adapted_interval_secs(&[], bounds, now), 24 * 60 * 60);
+        // 10 records in 10 hours, checked twice per record
+        let dates: Vec<_> = (1..=10).map(|h| now - Duration::hours(h)).collect();
+        assert_eq!(adapted_interval_secs(&dates, bounds, now), 30 * 60);
+        // busy sources aren't scraped more often than the minimum
+        let dates: Vec<_> = (1..=10).map(|m| now - Duration::seconds(m)).collect();
+        assert_eq!(adapted_interval_secs(&dates, bounds, now), 60);
+        // silent ones are scraped at least once per the maximum
+        assert_eq!(
+            adapted_interval_secs(&[now - Duration::days(30)], bounds, now),
+            24 * 60 * 60
+        );
+        // dates from the future don't make the interval negative
+        assert_eq!(
+            adapted_interval_secs(&[now + Duration::hours(1)], bounds, now),
+            60
+        );
+    }
+
+    #[test]
+    fn test_plan_scrape() {
+        let bounds = ScrapeBounds::new(600, 24 * 60 * 60);
+        let now = at(6, 10, 0);
+        let dates: Vec<_> = (1..=10).map(|h| now - Duration::hours(h)).collect();
+
+        let (interval, next) = plan_scrape(&source(None), &dates, bounds, now);
+        assert_eq!(interval, 30 * 60);
+        assert_eq!(next, now + Duration::minutes(30));
+
+        let (interval, next) = plan_scrape(&source(Some("0 9 * * 1-5")), &dates, bounds, now);
+        assert_eq!(interval, 30 * 60);
+        assert_eq!(next, at(9, 9, 0));
+
+        // the schedule can't make scrapes more frequent than the minimal interval
+        let (_, next) = plan_scrape(&source(Some("1m")), &dates, bounds, now);
+        assert_eq!(next, now + Duration::minutes(10));
+
+        // invalid schedule falls back to the adapted interval
+        let (_, next) = plan_scrape(&source(Some("sometimes")), &dates, bounds, now);
+        assert_eq!(next, now + Duration::minutes(30));
+    }
+}
@@ -1,14 +1,27 @@
+use crate::duplicates::{Fingerprint, CLUSTER_WINDOW_SECS, SIMHASH_BANDS};
+use crate::health;
 use crate::models;
-use crate::result::Result;
+use crate::queue;
+use crate::result::{Error, Result};
+use crate::schedule::{self, ScrapeBounds};
 use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::collections::HashMap;
+use std::str::FromStr;
 
 pub mod pg;
+pub mod sqlite;
 
 #[async_trait]
 pub trait Storage {
     async fn save_file(&self, file: models::File) -> Result<()>;
     async fn get_file_by_remote_id(&self, remote_id: String) -> Result<Option<models::File>>;
+    // files which can't be saved don't stop the rest, `Err` tells how many are lost
     async fn save_files(&self, files: Vec<models::NewFile>) -> Result<()>;
+    // returns local paths of the deleted files
+    async fn delete_record_files(&self, record_id: i32) -> Result<Vec<String>>;
 
     async fn set_record_external_link(
         &self,
@@ -16,7 +29,91 @@ pub trait Storage {
         source_id: i32,
         external_link: String,
     ) -> Result<u64>;
-    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<Vec<models::Record>>;
+    // duplicates (same source and source record id) are returned as existing, not as errors
+    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<models::SavedRecords>;
+    async fn get_record(
+        &self,
+        source_id: i32,
+        source_record_id: String,
+    ) -> Result<Option<models::Record>>;
+    // replaces content of the existing record keeping the previous version,
+    // `None` if there is no such record or neither title nor content is changed
+    async fn update_record(&self, record: models::NewRecord) -> Result<Option<models::Record>>;
+
+    /// Like `save_records`, but existing records with another title or content are updated.
+    async fn save_or_update_records(
+        &self,
+        records: Vec<models::NewRecord>,
+    ) -> Result<models::SavedRecords> {
+        let mut saved = self.save_records(records.clone()).await?;
+        let mut existing = Vec::with_capacity(saved.existing.len());
+        for record in saved.existing {
+            let edited = records.iter().find(|r| {
+                r.source_id == record.source_id && r.source_record_id == record.source_record_id
+            });
+            match edited {
+                Some(edited) if record.is_changed_by(edited) => {
+                    match self.update_record(edited.clone()).await? {
+                        Some(updated) => saved.updated.push(updated),
+                        None => existing.push(record),
+                    }
+                }
+                _ => existing.push(record),
+            }
+        }
+        saved.existing = existing;
+        Ok(saved)
+    }
+
+    // records of other sources published around any of the records which may be
+    // the same story: with a common simhash band, link or title; ordered by id
+    async fn get_cluster_candidates(
+        &self,
+        records: &[models::Record],
+        window_secs: i64,
+    ) -> Result<Vec<models::Record>>;
+    async fn set_records_cluster(&self, record_ids: Vec<i32>, cluster_id: i32) -> Result<()>;
+
+    /// Attaches records to clusters of the same story published by other sources.
+    async fn cluster_records(&self, records: &[models::Record]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        // candidates of the whole batch, each record is compared with the ones of its window
+        let candidates: Vec<(models::Record, Fingerprint)> = self
+            .get_cluster_candidates(records, CLUSTER_WINDOW_SECS)
+            .await?
+            .into_iter()
+            .map(|c| {
+                let fingerprint = Fingerprint::of_record(&c);
+                (c, fingerprint)
+            })
+            .collect();
+        // clusters set by the previous records of the batch
+        let mut clusters = HashMap::new();
+        for record in records {
+            let fingerprint = Fingerprint::of_record(record);
+            let duplicate = candidates.iter().find(|(c, f)| {
+                c.source_id != record.source_id
+                    && c.id != record.id
+                    && (c.date - record.date).num_seconds().abs() <= CLUSTER_WINDOW_SECS
+                    && fingerprint.is_duplicate(f)
+            });
+            if let Some((duplicate, _)) = duplicate {
+                debug!("record {} is a duplicate of {}", record.id, duplicate.id);
+                let cluster_id = clusters
+                    .get(&duplicate.id)
+                    .copied()
+                    .or(duplicate.cluster_id)
+                    .unwrap_or(duplicate.id);
+                self.set_records_cluster(vec![duplicate.id, record.id], cluster_id)
+                    .await?;
+                clusters.insert(duplicate.id, cluster_id);
+                clusters.insert(record.id, cluster_id);
+            }
+        }
+        Ok(())
+    }
 
     async fn set_source_scraped_now(&self, source: models::Source) -> Result<()>;
     async fn search_source(&self, query: &str) -> Result<Vec<models::Source>>;
@@ -26,10 +123,355 @@ pub trait Storage {
         origin: String,
     ) -> Result<Option<models::Source>>;
     async fn get_sources_by_kind(&self, kind: String) -> Result<Vec<models::Source>>;
-    async fn get_sources_by_kind_for_scrape(
+    // leases the sources of the kind which next scrape time has come and which
+    // aren't leased by another worker, see `queue`
+    async fn claim_sources_for_scrape(
         &self,
         kind: String,
-        check_secs_interval: u64,
+        lease: &queue::Lease,
     ) -> Result<Vec<models::Source>>;
     async fn save_sources(&self, sources: Vec<models::NewSource>) -> Result<Vec<models::Source>>;
+
+    // publishing dates of the latest records of the source
+    async fn get_record_dates(&self, source_id: i32, limit: i64) -> Result<Vec<NaiveDateTime>>;
+    // marks the source scraped now
+    async fn set_source_next_scrape(
+        &self,
+        source_id: i32,
+        scrape_interval_secs: i64,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<()>;
+    // stores the error and the next scrape time of the failed source
+    async fn set_source_failure(&self, source_id: i32, failure: &health::Failure) -> Result<()>;
+    // resets failures of the source and scrapes it again, `None` if there is no such source
+    async fn enable_source(&self, source_id: i32) -> Result<Option<models::Source>>;
+    // assigns the source to the collector account, `None` if there is no such source
+    async fn set_source_account(
+        &self,
+        source_id: i32,
+        account: Option<String>,
+    ) -> Result<Option<models::Source>>;
+    // `None` if there is no such source
+    async fn update_source_schedule(
+        &self,
+        source_id: i32,
+        schedule: Option<String>,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<Option<models::Source>>;
+
+    /// Marks the source scraped now and plans its next scrape,
+    /// see `schedule::plan_scrape`.
+    async fn schedule_source(&self, source: models::Source, bounds: ScrapeBounds) -> Result<()> {
+        let dates = self
+            .get_record_dates(source.id, schedule::RECENT_RECORDS)
+            .await?;
+        let (interval_secs, next_scrape_at) =
+            schedule::plan_scrape(&source, &dates, bounds, Utc::now().naive_utc());
+        debug!(
+            "source {} is scraped every {}s, next scrape at {}",
+            source.id, interval_secs, next_scrape_at
+        );
+        self.set_source_next_scrape(source.id, interval_secs, next_scrape_at)
+            .await
+    }
+
+    /// Stores the error of the source and postpones its next scrape,
+    /// see `health::plan_failure`.
+    async fn record_source_failure(&self, kind: &str, origin: String, error: &Error) -> Result<()> {
+        let source = match self.get_exact_source(kind.to_string(), origin).await? {
+            Some(source) => source,
+            // searched sources aren't stored before the first update
+            None => return Ok(()),
+        };
+        let failure = health::plan_failure(&source, error, Utc::now().naive_utc());
+        match failure.disabled_at {
+            Some(_) => warn!(
+                "source {} disabled after {} permanent errors, last one: {}",
+                source.id, failure.permanent_failures, failure.error
+            ),
+            None => debug!(
+                "source {} failed {} times in a row, next scrape at {}",
+                source.id, failure.consecutive_failures, failure.next_scrape_at
+            ),
+        }
+        self.set_source_failure(source.id, &failure).await
+    }
+
+    /// Sets or removes (`None`) explicit schedule of the source.
+    async fn set_source_schedule(
+        &self,
+        source_id: i32,
+        schedule: Option<&str>,
+    ) -> Result<Option<models::Source>> {
+        let now = Utc::now().naive_utc();
+        let next_scrape_at = match schedule {
+            Some(schedule) => schedule::Schedule::from_str(schedule)?
+                .next_after(now)
+                .ok_or_else(|| {
+                    Error::InvalidSchedule(format!("{} has no upcoming time", schedule))
+                })?,
+            // interval is adapted again after the next scrape
+            None => now,
+        };
+        self.update_source_schedule(
+            source_id,
+            schedule.map(|s| s.trim().to_string()),
+            next_scrape_at,
+        )
+        .await
+    }
+}
+
+// columns of the records searched for cluster candidates, one row per record
+#[derive(Default)]
+struct ClusterInput {
+    ids: Vec<i32>,
+    source_ids: Vec<i32>,
+    from_dates: Vec<NaiveDateTime>,
+    to_dates: Vec<NaiveDateTime>,
+    bands: [Vec<Option<i64>>; SIMHASH_BANDS],
+    link_patterns: Vec<Option<String>>,
+    titles: Vec<Option<String>>,
+    // links of all the records, candidates are checked by their own window anyway
+    links: Vec<String>,
+}
+
+impl ClusterInput {
+    fn new(records: &[models::Record], window_secs: i64) -> Self {
+        let window = chrono::Duration::seconds(window_secs);
+        let mut input = Self::default();
+        for record in records {
+            let fingerprint = Fingerprint::of_record(record);
+            let bands = fingerprint.simhash_bands();
+            input.ids.push(record.id);
+            input.source_ids.push(record.source_id);
+            input.from_dates.push(record.date - window);
+            input.to_dates.push(record.date + window);
+            for (i, band) in input.bands.iter_mut().enumerate() {
+                band.push(bands.map(|b| b[i]));
+            }
+            input.link_patterns.push(fingerprint.link_pattern());
+            input.titles.push(record.title.clone());
+            input.links.extend(fingerprint.links());
+            input
+                .links
+                .extend(fingerprint.canonical_link().map(String::from));
+        }
+        input
+    }
+
+    // bounds of all the windows for the date index
+    fn dates(&self) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        (
+            self.from_dates.iter().min().copied(),
+            self.to_dates.iter().max().copied(),
+        )
+    }
+
+    fn limit(&self) -> i64 {
+        100 * self.ids.len() as i64
+    }
+}
+
+/// Connection pool of the database selected by the url scheme.
+#[derive(Clone, Debug)]
+pub enum Pool {
+    Pg(pg::Pool),
+    Sqlite(sqlite::Pool),
+}
+
+impl Pool {
+    /// `postgres://` (or `postgresql://`) and `sqlite://` urls are supported,
+    /// sqlite database file is created if it doesn't exist.
+    pub async fn connect(url: &str) -> Result<Self> {
+        if url.starts_with("sqlite:") {
+            let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+            return Ok(Pool::Sqlite(
+                SqlitePoolOptions::new()
+                    .max_connections(5)
+                    .connect_with(options)
+                    .await?,
+            ));
+        }
+        if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+            return Ok(Pool::Pg(
+                PgPoolOptions::new().max_connections(5).connect(url).await?,
+            ));
+        }
+        Err(Error::DbError(format!("unsupported database url: {}", url)))
+    }
+}
+
+/// Storage of the database selected by the url scheme.
+#[derive(Clone)]
+pub enum AnyStorage {
+    Pg(pg::PgStorage),
+    Sqlite(sqlite::SqliteStorage),
+}
+
+impl AnyStorage {
+    pub fn new(pool: Pool) -> Self {
+        match pool {
+            Pool::Pg(pool) => AnyStorage::Pg(pg::PgStorage::new(pool)),
+            Pool::Sqlite(pool) => AnyStorage::Sqlite(sqlite::SqliteStorage::new(pool)),
+        }
+    }
+
+    pub async fn migrate(&self) {
+        match self {
+            AnyStorage::Pg(storage) => storage.migrate().await,
+            AnyStorage::Sqlite(storage) => storage.migrate().await,
+        }
+    }
+
+    pub fn pool(&self) -> Pool {
+        match self {
+            AnyStorage::Pg(storage) => Pool::Pg(storage.pool()),
+            AnyStorage::Sqlite(storage) => Pool::Sqlite(storage.pool()),
+        }
+    }
+}
+
+macro_rules! delegate {
+    ($storage:expr, $method:ident($($arg:expr),*)) => {
+        match $storage {
+            AnyStorage::Pg(storage) => storage.$method($($arg),*).await,
+            AnyStorage::Sqlite(storage) => storage.$method($($arg),*).await,
+        }
+    };
+}
+
+#[async_trait]
+impl Storage for AnyStorage {
+    async fn save_file(&self, file: models::File) -> Result<()> {
+        delegate!(self, save_file(file))
+    }
+
+    async fn get_file_by_remote_id(&self, remote_id: String) -> Result<Option<models::File>> {
+        delegate!(self, get_file_by_remote_id(remote_id))
+    }
+
+    async fn save_files(&self, files: Vec<models::NewFile>) -> Result<()> {
+        delegate!(self, save_files(files))
+    }
+
+    async fn delete_record_files(&self, record_id: i32) -> Result<Vec<String>> {
+        delegate!(self, delete_record_files(record_id))
+    }
+
+    async fn set_record_external_link(
+        &self,
+        source_record_id: String,
+        source_id: i32,
+        external_link: String,
+    ) -> Result<u64> {
+        delegate!(
+            self,
+            set_record_external_link(source_record_id, source_id, external_link)
+        )
+    }
+
+    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<models::SavedRecords> {
+        delegate!(self, save_records(records))
+    }
+
+    async fn get_record(
+        &self,
+        source_id: i32,
+        source_record_id: String,
+    ) -> Result<Option<models::Record>> {
+        delegate!(self, get_record(source_id, source_record_id))
+    }
+
+    async fn update_record(&self, record: models::NewRecord) -> Result<Option<models::Record>> {
+        delegate!(self, update_record(record))
+    }
+
+    async fn get_cluster_candidates(
+        &self,
+        records: &[models::Record],
+        window_secs: i64,
+    ) -> Result<Vec<models::Record>> {
+        delegate!(self, get_cluster_candidates(records, window_secs))
+    }
+
+    async fn set_records_cluster(&self, record_ids: Vec<i32>, cluster_id: i32) -> Result<()> {
+        delegate!(self, set_records_cluster(record_ids, cluster_id))
+    }
+
+    async fn set_source_scraped_now(&self, source: models::Source) -> Result<()> {
+        delegate!(self, set_source_scraped_now(source))
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        delegate!(self, search_source(query))
+    }
+
+    async fn get_exact_source(
+        &self,
+        kind: String,
+        origin: String,
+    ) -> Result<Option<models::Source>> {
+        delegate!(self, get_exact_source(kind, origin))
+    }
+
+    async fn get_sources_by_kind(&self, kind: String) -> Result<Vec<models::Source>> {
+        delegate!(self, get_sources_by_kind(kind))
+    }
+
+    async fn claim_sources_for_scrape(
+        &self,
+        kind: String,
+        lease: &queue::Lease,
+    ) -> Result<Vec<models::Source>> {
+        delegate!(self, claim_sources_for_scrape(kind, lease))
+    }
+
+    async fn save_sources(&self, sources: Vec<models::NewSource>) -> Result<Vec<models::Source>> {
+        delegate!(self, save_sources(sources))
+    }
+
+    async fn get_record_dates(&self, source_id: i32, limit: i64) -> Result<Vec<NaiveDateTime>> {
+        delegate!(self, get_record_dates(source_id, limit))
+    }
+
+    async fn set_source_next_scrape(
+        &self,
+        source_id: i32,
+        scrape_interval_secs: i64,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<()> {
+        delegate!(
+            self,
+            set_source_next_scrape(source_id, scrape_interval_secs, next_scrape_at)
+        )
+    }
+
+    async fn update_source_schedule(
+        &self,
+        source_id: i32,
+        schedule: Option<String>,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<Option<models::Source>> {
+        delegate!(
+            self,
+            update_source_schedule(source_id, schedule, next_scrape_at)
+        )
+    }
+
+    async fn set_source_failure(&self, source_id: i32, failure: &health::Failure) -> Result<()> {
+        delegate!(self, set_source_failure(source_id, failure))
+    }
+
+    async fn enable_source(&self, source_id: i32) -> Result<Option<models::Source>> {
+        delegate!(self, enable_source(source_id))
+    }
+
+    async fn set_source_account(
+        &self,
+        source_id: i32,
+        account: Option<String>,
+    ) -> Result<Option<models::Source>> {
+        delegate!(self, set_source_account(source_id, account))
+    }
 }
@@ -1,10 +1,15 @@
-use super::Storage;
+use super::{ClusterInput, Storage};
+use crate::duplicates::Fingerprint;
+use crate::health;
+use crate::language;
 use crate::models;
+use crate::queue;
 use crate::result::{Error, Result};
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use sqlx::migrate::Migrator;
 use sqlx::postgres::PgPool;
-use std::time::Duration;
+use sqlx::{FromRow, Row};
 
 pub type Pool = PgPool;
 
@@ -49,27 +54,94 @@ impl Storage for PgStorage {
     }
 
     async fn save_files(&self, files: Vec<models::NewFile>) -> Result<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+        let mut record_ids = Vec::with_capacity(files.len());
+        let mut kinds = Vec::with_capacity(files.len());
+        let mut local_paths = Vec::with_capacity(files.len());
+        let mut remote_paths = Vec::with_capacity(files.len());
+        let mut remote_ids = Vec::with_capacity(files.len());
+        let mut file_names = Vec::with_capacity(files.len());
+        let mut types = Vec::with_capacity(files.len());
+        let mut metas = Vec::with_capacity(files.len());
+        for file in &files {
+            record_ids.push(file.record_id);
+            kinds.push(file.kind.clone());
+            local_paths.push(file.local_path.clone());
+            remote_paths.push(file.remote_path.clone());
+            remote_ids.push(file.remote_id.clone());
+            file_names.push(file.file_name.clone());
+            types.push(file.type_.clone());
+            metas.push(file.meta.clone());
+        }
+        let batch = sqlx::query(
+            "INSERT INTO files \
+            (record_id, kind, local_path, remote_path, remote_id, file_name, type, meta) \
+            SELECT * FROM UNNEST($1::int[], $2::text[], $3::text[], $4::text[], $5::text[], \
+            $6::text[], $7::text[], $8::text[]) \
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(record_ids)
+        .bind(kinds)
+        .bind(local_paths)
+        .bind(remote_paths)
+        .bind(remote_ids)
+        .bind(file_names)
+        .bind(types)
+        .bind(metas)
+        .execute(&self.pool)
+        .await;
+        let e = match batch {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        // one bad row fails the whole batch, the rest is saved one by one
+        let count = files.len();
+        warn!(
+            "batch of {} files failed, saving them one by one: {}",
+            count, e
+        );
+        let mut failed = 0;
         for file in files {
-            if let Err(e) = sqlx::query!(
+            let record_id = file.record_id;
+            if let Err(e) = sqlx::query(
                 "INSERT INTO files \
                 (record_id, kind, local_path, remote_path, remote_id, file_name, type, meta) \
                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING",
-                file.record_id,
-                file.kind,
-                file.local_path,
-                file.remote_path,
-                file.remote_id,
-                file.file_name,
-                file.type_,
-                file.meta
             )
+            .bind(file.record_id)
+            .bind(file.kind)
+            .bind(file.local_path)
+            .bind(file.remote_path)
+            .bind(file.remote_id)
+            .bind(file.file_name)
+            .bind(file.type_)
+            .bind(file.meta)
             .execute(&self.pool)
             .await
             {
-                error!("{}", e);
+                error!("can't save file of record {}: {}", record_id, e);
+                failed += 1;
             }
         }
-        Ok(())
+        match failed {
+            0 => Ok(()),
+            _ => Err(Error::DbError(format!(
+                "{} of {} files not saved",
+                failed, count
+            ))),
+        }
+    }
+
+    async fn delete_record_files(&self, record_id: i32) -> Result<Vec<String>> {
+        let paths = sqlx::query_scalar!(
+            "DELETE FROM files WHERE record_id = $1 RETURNING local_path",
+            record_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(paths.into_iter().flatten().collect())
     }
 
     async fn set_record_external_link(
@@ -89,31 +161,226 @@ impl Storage for PgStorage {
         .rows_affected())
     }
 
-    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<Vec<models::Record>> {
-        let mut result: Vec<models::Record> = Vec::with_capacity(records.len());
+    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<models::SavedRecords> {
+        let mut saved = models::SavedRecords::default();
+        if records.is_empty() {
+            return Ok(saved);
+        }
+        let mut titles = Vec::with_capacity(records.len());
+        let mut source_record_ids = Vec::with_capacity(records.len());
+        let mut source_ids = Vec::with_capacity(records.len());
+        let mut contents = Vec::with_capacity(records.len());
+        let mut dates = Vec::with_capacity(records.len());
+        let mut images = Vec::with_capacity(records.len());
+        let mut metas = Vec::with_capacity(records.len());
+        let mut hashes = Vec::with_capacity(records.len());
+        let mut simhashes = Vec::with_capacity(records.len());
+        let mut canonical_links = Vec::with_capacity(records.len());
+        let mut languages = Vec::with_capacity(records.len());
         for record in records {
-            let new_rec = sqlx::query_as!(
-                models::Record,
-                "INSERT INTO records (title, source_record_id, source_id, content, date, image) \
-                VALUES ($1, $2, $3, $4, $5, $6)\
-                RETURNING *",
-                record.title,
-                record.source_record_id,
-                record.source_id,
-                record.content,
-                record.date,
-                record.image,
-            )
-            .fetch_one(&self.pool)
-            .await?;
-            result.push(new_rec);
+            hashes.push(models::content_hash(record.content.as_str()));
+            let fingerprint = Fingerprint::of_new_record(&record);
+            simhashes.push(fingerprint.simhash());
+            canonical_links.push(fingerprint.canonical_link().map(String::from));
+            languages.push(language::of_new_record(&record));
+            titles.push(record.title);
+            source_record_ids.push(record.source_record_id);
+            source_ids.push(record.source_id);
+            contents.push(record.content);
+            dates.push(record.date);
+            images.push(record.image);
+            metas.push(record.meta);
         }
-        Ok(result)
+        // the last select sees the snapshot taken before the insert, so it returns
+        // only records which existed; concurrently inserted ones aren't returned at all.
+        // meta of the existing records (scores, views) is refreshed, the rest stays as it is
+        let rows = sqlx::query(
+            "WITH input AS ( \
+                SELECT * FROM UNNEST($1::text[], $2::text[], $3::int[], $4::text[], \
+                $5::timestamp[], $6::text[], $7::text[], $8::text[], $9::bigint[], $10::text[], \
+                $11::text[]) \
+                AS i (title, source_record_id, source_id, content, date, image, meta, content_hash, \
+                simhash, canonical_link, language) \
+            ), inserted AS ( \
+                INSERT INTO records \
+                (title, source_record_id, source_id, content, date, image, meta, content_hash, \
+                simhash, canonical_link, language) \
+                SELECT title, source_record_id, source_id, content, coalesce(date, now()), image, \
+                meta, content_hash, simhash, canonical_link, language \
+                FROM input \
+                ON CONFLICT (source_record_id, source_id) DO NOTHING \
+                RETURNING * \
+            ), updated AS ( \
+                UPDATE records r SET meta = i.meta FROM input i \
+                WHERE r.source_record_id = i.source_record_id AND r.source_id = i.source_id \
+                AND i.meta IS NOT NULL AND r.meta IS DISTINCT FROM i.meta \
+                RETURNING r.* \
+            ) \
+            SELECT *, true AS created FROM inserted \
+            UNION ALL \
+            SELECT *, false AS created FROM updated \
+            UNION ALL \
+            SELECT *, false AS created FROM records \
+            WHERE (source_record_id, source_id) IN (SELECT source_record_id, source_id FROM input) \
+            AND id NOT IN (SELECT id FROM updated)",
+        )
+        .bind(titles)
+        .bind(source_record_ids)
+        .bind(source_ids)
+        .bind(contents)
+        .bind(dates)
+        .bind(images)
+        .bind(metas)
+        .bind(hashes)
+        .bind(simhashes)
+        .bind(canonical_links)
+        .bind(languages)
+        .fetch_all(&self.pool)
+        .await?;
+        for row in rows {
+            let record = models::Record::from_row(&row)?;
+            match row.try_get("created")? {
+                true => saved.created.push(record),
+                false => saved.existing.push(record),
+            }
+        }
+        // duplicates don't prevent saving
+        if let Err(e) = self.cluster_records(&saved.created).await {
+            error!("can't cluster records: {}", e);
+        }
+        Ok(saved)
+    }
+
+    async fn get_record(
+        &self,
+        source_id: i32,
+        source_record_id: String,
+    ) -> Result<Option<models::Record>> {
+        Ok(sqlx::query_as!(
+            models::Record,
+            "SELECT * FROM records WHERE source_id = $1 AND source_record_id = $2",
+            source_id,
+            source_record_id
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn update_record(&self, record: models::NewRecord) -> Result<Option<models::Record>> {
+        let mut tx = self.pool.begin().await?;
+        let existing = sqlx::query_as!(
+            models::Record,
+            "SELECT * FROM records WHERE source_id = $1 AND source_record_id = $2 FOR UPDATE",
+            record.source_id,
+            record.source_record_id,
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+        let existing = match existing {
+            Some(existing) if existing.is_changed_by(&record) => existing,
+            _ => return Ok(None),
+        };
+        sqlx::query!(
+            "INSERT INTO record_versions (record_id, title, content, image, content_hash, date) \
+            VALUES ($1, $2, $3, $4, $5, $6)",
+            existing.id,
+            existing.title,
+            existing.content,
+            existing.image,
+            existing.content_hash(),
+            existing.updated_at.unwrap_or(existing.date),
+        )
+        .execute(&mut tx)
+        .await?;
+        let updated = sqlx::query_as!(
+            models::Record,
+            "UPDATE records SET title = $1, content = $2, date = coalesce($3, date), \
+            image = $4, meta = $5, content_hash = $6, simhash = $7, language = $8, \
+            updated_at = now() \
+            WHERE id = $9 \
+            RETURNING *",
+            record.title,
+            record.content,
+            record.date,
+            record.image,
+            record.meta,
+            models::content_hash(record.content.as_str()),
+            Fingerprint::of_new_record(&record).simhash(),
+            language::of_new_record(&record),
+            existing.id,
+        )
+        .fetch_one(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(updated))
+    }
+
+    async fn get_cluster_candidates(
+        &self,
+        records: &[models::Record],
+        window_secs: i64,
+    ) -> Result<Vec<models::Record>> {
+        let input = ClusterInput::new(records, window_secs);
+        let (from_date, to_date) = input.dates();
+        let limit = input.limit();
+        let [band0, band1, band2, band3] = input.bands;
+        Ok(sqlx::query_as(
+            "WITH input AS ( \
+                SELECT * FROM UNNEST($1::int[], $2::int[], $3::timestamp[], $4::timestamp[], \
+                $5::bigint[], $6::bigint[], $7::bigint[], $8::bigint[], $9::text[], $10::text[]) \
+                AS i (id, source_id, from_date, to_date, band0, band1, band2, band3, \
+                link_pattern, title) \
+            ) \
+            SELECT * FROM records r \
+            WHERE r.date BETWEEN $11 AND $12 AND EXISTS ( \
+                SELECT 1 FROM input i \
+                WHERE r.source_id <> i.source_id AND r.id <> i.id \
+                AND r.date BETWEEN i.from_date AND i.to_date AND ( \
+                    r.simhash & 65535 = i.band0 OR (r.simhash >> 16) & 65535 = i.band1 \
+                    OR (r.simhash >> 32) & 65535 = i.band2 OR (r.simhash >> 48) & 65535 = i.band3 \
+                    OR r.canonical_link = ANY($13) \
+                    OR r.content LIKE i.link_pattern ESCAPE '\\' \
+                    OR lower(r.title) = lower(i.title) \
+                ) \
+            ) \
+            ORDER BY r.id \
+            LIMIT $14",
+        )
+        .bind(input.ids)
+        .bind(input.source_ids)
+        .bind(input.from_dates)
+        .bind(input.to_dates)
+        .bind(band0)
+        .bind(band1)
+        .bind(band2)
+        .bind(band3)
+        .bind(input.link_patterns)
+        .bind(input.titles)
+        .bind(from_date)
+        .bind(to_date)
+        .bind(input.links)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn set_records_cluster(&self, record_ids: Vec<i32>, cluster_id: i32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE records SET cluster_id = $1 WHERE id = ANY($2)",
+            cluster_id,
+            &record_ids,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
     }
 
     async fn set_source_scraped_now(&self, source: models::Source) -> Result<()> {
         sqlx::query!(
-            "UPDATE sources SET last_scrape_time = NOW() WHERE id = $1",
+            "UPDATE sources \
+            SET last_scrape_time = NOW(), last_success_at = NOW(), \
+                consecutive_failures = 0, permanent_failures = 0 \
+            WHERE id = $1",
             source.id
         )
         .execute(&self.pool)
@@ -156,41 +423,165 @@ impl Storage for PgStorage {
         .await?)
     }
 
-    async fn get_sources_by_kind_for_scrape(
+    async fn claim_sources_for_scrape(
         &self,
         kind: String,
-        check_secs_interval: u64,
+        lease: &queue::Lease,
     ) -> Result<Vec<models::Source>> {
-        Ok(sqlx::query_as(
-            "SELECT * FROM sources WHERE kind = $1 AND (last_scrape_time < NOW() - $2::interval)",
+        Ok(sqlx::query_as!(
+            models::Source,
+            "UPDATE sources SET leased_by = $2, lease_expires_at = $3 \
+            WHERE id IN ( \
+                SELECT id FROM sources \
+                WHERE kind = $1 AND next_scrape_at <= NOW() AND disabled_at IS NULL \
+                    AND (lease_expires_at IS NULL OR lease_expires_at <= NOW()) \
+                ORDER BY next_scrape_at LIMIT $4 \
+                FOR UPDATE SKIP LOCKED \
+            ) RETURNING *",
+            kind,
+            lease.worker,
+            lease.expires_at,
+            lease.limit
         )
-        .bind(kind)
-        .bind(Duration::from_secs(check_secs_interval))
         .fetch_all(&self.pool)
         .await?)
     }
 
+    async fn get_record_dates(&self, source_id: i32, limit: i64) -> Result<Vec<NaiveDateTime>> {
+        Ok(sqlx::query!(
+            "SELECT date FROM records WHERE source_id = $1 ORDER BY date DESC LIMIT $2",
+            source_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| r.date)
+        .collect())
+    }
+
+    async fn set_source_next_scrape(
+        &self,
+        source_id: i32,
+        scrape_interval_secs: i64,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query!(
+            "UPDATE sources \
+            SET last_scrape_time = NOW(), scrape_interval_secs = $2, next_scrape_at = $3, \
+                last_success_at = NOW(), consecutive_failures = 0, permanent_failures = 0, \
+                leased_by = NULL, lease_expires_at = NULL \
+            WHERE id = $1",
+            source_id,
+            scrape_interval_secs as i32,
+            next_scrape_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_source_failure(&self, source_id: i32, failure: &health::Failure) -> Result<()> {
+        sqlx::query!(
+            "UPDATE sources \
+            SET last_scrape_time = NOW(), last_error = $2, last_error_class = $3, \
+                consecutive_failures = $4, permanent_failures = $5, next_scrape_at = $6, \
+                disabled_at = $7, leased_by = NULL, lease_expires_at = NULL \
+            WHERE id = $1",
+            source_id,
+            failure.error,
+            failure.class.as_str(),
+            failure.consecutive_failures,
+            failure.permanent_failures,
+            failure.next_scrape_at,
+            failure.disabled_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enable_source(&self, source_id: i32) -> Result<Option<models::Source>> {
+        Ok(sqlx::query_as!(
+            models::Source,
+            "UPDATE sources \
+            SET disabled_at = NULL, consecutive_failures = 0, permanent_failures = 0, \
+                next_scrape_at = NOW() \
+            WHERE id = $1 RETURNING *",
+            source_id
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn set_source_account(
+        &self,
+        source_id: i32,
+        account: Option<String>,
+    ) -> Result<Option<models::Source>> {
+        Ok(sqlx::query_as!(
+            models::Source,
+            "UPDATE sources SET account = $2 WHERE id = $1 RETURNING *",
+            source_id,
+            account
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn update_source_schedule(
+        &self,
+        source_id: i32,
+        schedule: Option<String>,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<Option<models::Source>> {
+        Ok(sqlx::query_as!(
+            models::Source,
+            "UPDATE sources SET schedule = $2, next_scrape_at = $3 WHERE id = $1 RETURNING *",
+            source_id,
+            schedule,
+            next_scrape_at
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
     async fn save_sources(&self, sources: Vec<models::NewSource>) -> Result<Vec<models::Source>> {
-        let mut res = Vec::with_capacity(sources.len());
-        for s in sources {
-            let n = sqlx::query_as!(
-                models::Source,
-                "INSERT INTO sources (name, origin, kind, image, external_link) VALUES \
-                ($1, $2, $3, $4, $5) \
-                ON CONFLICT (origin, kind) DO UPDATE 
-                    SET name = EXCLUDED.name
-                RETURNING *",
-                s.name,
-                s.origin,
-                s.kind,
-                s.image,
-                s.external_link
-            )
-            .fetch_one(&self.pool)
-            .await?;
-            res.push(n)
+        if sources.is_empty() {
+            return Ok(vec![]);
         }
-        Ok(res)
+        // the same row can't be updated twice by one statement
+        let mut unique: Vec<models::NewSource> = Vec::with_capacity(sources.len());
+        for source in sources {
+            unique.retain(|s| s.origin != source.origin || s.kind != source.kind);
+            unique.push(source);
+        }
+        let mut names = Vec::with_capacity(unique.len());
+        let mut origins = Vec::with_capacity(unique.len());
+        let mut kinds = Vec::with_capacity(unique.len());
+        let mut images = Vec::with_capacity(unique.len());
+        let mut external_links = Vec::with_capacity(unique.len());
+        for s in unique {
+            names.push(s.name);
+            origins.push(s.origin);
+            kinds.push(s.kind);
+            images.push(s.image);
+            external_links.push(s.external_link);
+        }
+        Ok(sqlx::query_as(
+            "INSERT INTO sources (name, origin, kind, image, external_link) \
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::text[]) \
+            ON CONFLICT (origin, kind) DO UPDATE \
+                SET name = EXCLUDED.name \
+            RETURNING *",
+        )
+        .bind(names)
+        .bind(origins)
+        .bind(kinds)
+        .bind(images)
+        .bind(external_links)
+        .fetch_all(&self.pool)
+        .await?)
     }
 }
 
@@ -0,0 +1,505 @@
+use super::{ClusterInput, Storage};
+use crate::duplicates::Fingerprint;
+use crate::health;
+use crate::language;
+use crate::models;
+use crate::queue;
+use crate::result::{Error, Result};
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::sqlite::SqlitePool;
+
+pub type Pool = SqlitePool;
+
+/// Single file storage for personal deployments and tests.
+///
+/// Queries aren't checked at compile time, macros are bound to the postgres schema.
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: Pool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn migrate(&self) {
+        migrate(&self.pool).await;
+    }
+
+    pub fn pool(&self) -> Pool {
+        self.pool.clone()
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn save_file(&self, file: models::File) -> Result<()> {
+        sqlx::query("UPDATE files SET local_path = $1, file_name = $2 WHERE id = $3")
+            .bind(file.local_path)
+            .bind(file.file_name)
+            .bind(file.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_file_by_remote_id(&self, remote_id: String) -> Result<Option<models::File>> {
+        Ok(sqlx::query_as("SELECT * FROM files WHERE remote_id = $1")
+            .bind(remote_id)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    async fn save_files(&self, files: Vec<models::NewFile>) -> Result<()> {
+        // single transaction, sqlite has no array binds;
+        // a failed insert doesn't roll back the others
+        let count = files.len();
+        let mut failed = 0;
+        let mut tx = self.pool.begin().await?;
+        for file in files {
+            let record_id = file.record_id;
+            if let Err(e) = sqlx::query(
+                "INSERT INTO files \
+                (record_id, kind, local_path, remote_path, remote_id, file_name, type, meta) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING",
+            )
+            .bind(file.record_id)
+            .bind(file.kind)
+            .bind(file.local_path)
+            .bind(file.remote_path)
+            .bind(file.remote_id)
+            .bind(file.file_name)
+            .bind(file.type_)
+            .bind(file.meta)
+            .execute(&mut tx)
+            .await
+            {
+                error!("can't save file of record {}: {}", record_id, e);
+                failed += 1;
+            }
+        }
+        tx.commit().await?;
+        match failed {
+            0 => Ok(()),
+            _ => Err(Error::DbError(format!(
+                "{} of {} files not saved",
+                failed, count
+            ))),
+        }
+    }
+
+    async fn delete_record_files(&self, record_id: i32) -> Result<Vec<String>> {
+        let paths: Vec<Option<String>> =
+            sqlx::query_scalar("DELETE FROM files WHERE record_id = $1 RETURNING local_path")
+                .bind(record_id)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(paths.into_iter().flatten().collect())
+    }
+
+    async fn set_record_external_link(
+        &self,
+        source_record_id: String,
+        source_id: i32,
+        external_link: String,
+    ) -> Result<u64> {
+        Ok(sqlx::query(
+            "UPDATE records SET external_link = $1 WHERE source_record_id = $2 AND source_id = $3",
+        )
+        .bind(external_link)
+        .bind(source_record_id)
+        .bind(source_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected())
+    }
+
+    async fn save_records(&self, records: Vec<models::NewRecord>) -> Result<models::SavedRecords> {
+        let mut saved = models::SavedRecords::default();
+        let mut tx = self.pool.begin().await?;
+        for record in records {
+            let content_hash = models::content_hash(record.content.as_str());
+            let fingerprint = Fingerprint::of_new_record(&record);
+            let language = language::of_new_record(&record);
+            let new_rec = sqlx::query_as(
+                "INSERT INTO records \
+                (title, source_record_id, source_id, content, date, image, meta, content_hash, \
+                simhash, canonical_link, language) \
+                VALUES ($1, $2, $3, $4, coalesce($5, CURRENT_TIMESTAMP), $6, $7, $8, $9, $10, $11) \
+                ON CONFLICT (source_record_id, source_id) DO NOTHING \
+                RETURNING *",
+            )
+            .bind(record.title)
+            .bind(record.source_record_id.clone())
+            .bind(record.source_id)
+            .bind(record.content)
+            .bind(record.date)
+            .bind(record.image)
+            .bind(record.meta.clone())
+            .bind(content_hash)
+            .bind(fingerprint.simhash())
+            .bind(fingerprint.canonical_link().map(String::from))
+            .bind(language)
+            .fetch_optional(&mut tx)
+            .await?;
+            if let Some(new_rec) = new_rec {
+                saved.created.push(new_rec);
+                continue;
+            }
+            // meta of the existing record (scores, views) is refreshed
+            let existing: models::Record = sqlx::query_as(
+                "UPDATE records SET meta = coalesce($3, meta) \
+                WHERE source_id = $1 AND source_record_id = $2 RETURNING *",
+            )
+            .bind(record.source_id)
+            .bind(record.source_record_id)
+            .bind(record.meta)
+            .fetch_one(&mut tx)
+            .await?;
+            // the same record may be passed twice
+            let is_saved = |r: &models::Record| r.id == existing.id;
+            if !saved.created.iter().any(is_saved) && !saved.existing.iter().any(is_saved) {
+                saved.existing.push(existing);
+            }
+        }
+        tx.commit().await?;
+        // duplicates don't prevent saving
+        if let Err(e) = self.cluster_records(&saved.created).await {
+            error!("can't cluster records: {}", e);
+        }
+        Ok(saved)
+    }
+
+    async fn get_record(
+        &self,
+        source_id: i32,
+        source_record_id: String,
+    ) -> Result<Option<models::Record>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM records WHERE source_id = $1 AND source_record_id = $2")
+                .bind(source_id)
+                .bind(source_record_id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn update_record(&self, record: models::NewRecord) -> Result<Option<models::Record>> {
+        let mut tx = self.pool.begin().await?;
+        let existing: Option<models::Record> =
+            sqlx::query_as("SELECT * FROM records WHERE source_id = $1 AND source_record_id = $2")
+                .bind(record.source_id)
+                .bind(record.source_record_id.clone())
+                .fetch_optional(&mut tx)
+                .await?;
+        let existing = match existing {
+            Some(existing) if existing.is_changed_by(&record) => existing,
+            _ => return Ok(None),
+        };
+        sqlx::query(
+            "INSERT INTO record_versions (record_id, title, content, image, content_hash, date) \
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(existing.id)
+        .bind(existing.title.clone())
+        .bind(existing.content.clone())
+        .bind(existing.image.clone())
+        .bind(existing.content_hash())
+        .bind(existing.updated_at.unwrap_or(existing.date))
+        .execute(&mut tx)
+        .await?;
+        let content_hash = models::content_hash(record.content.as_str());
+        let simhash = Fingerprint::of_new_record(&record).simhash();
+        let language = language::of_new_record(&record);
+        let updated = sqlx::query_as(
+            "UPDATE records SET title = $1, content = $2, date = coalesce($3, date), \
+            image = $4, meta = $5, content_hash = $6, simhash = $7, language = $8, \
+            updated_at = CURRENT_TIMESTAMP \
+            WHERE id = $9 \
+            RETURNING *",
+        )
+        .bind(record.title)
+        .bind(record.content)
+        .bind(record.date)
+        .bind(record.image)
+        .bind(record.meta)
+        .bind(content_hash)
+        .bind(simhash)
+        .bind(language)
+        .bind(existing.id)
+        .fetch_one(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(updated))
+    }
+
+    async fn get_cluster_candidates(
+        &self,
+        records: &[models::Record],
+        window_secs: i64,
+    ) -> Result<Vec<models::Record>> {
+        let input = ClusterInput::new(records, window_secs);
+        let (from_date, to_date) = input.dates();
+        // rows and arrays are passed as json, dates are compared as julian days
+        // as json has them in another format
+        let rows: Vec<serde_json::Value> = (0..input.ids.len())
+            .map(|i| {
+                serde_json::json!({
+                    "id": input.ids[i],
+                    "source_id": input.source_ids[i],
+                    "from_date": input.from_dates[i].format("%F %T").to_string(),
+                    "to_date": input.to_dates[i].format("%F %T").to_string(),
+                    "band0": input.bands[0][i],
+                    "band1": input.bands[1][i],
+                    "band2": input.bands[2][i],
+                    "band3": input.bands[3][i],
+                    "link_pattern": input.link_patterns[i],
+                    "title": input.titles[i],
+                })
+            })
+            .collect();
+        Ok(sqlx::query_as(
+            "WITH input AS ( \
+                SELECT json_extract(value, '$.id') AS id, \
+                json_extract(value, '$.source_id') AS source_id, \
+                julianday(json_extract(value, '$.from_date')) AS from_day, \
+                julianday(json_extract(value, '$.to_date')) AS to_day, \
+                json_extract(value, '$.band0') AS band0, json_extract(value, '$.band1') AS band1, \
+                json_extract(value, '$.band2') AS band2, json_extract(value, '$.band3') AS band3, \
+                json_extract(value, '$.link_pattern') AS link_pattern, \
+                json_extract(value, '$.title') AS title \
+                FROM json_each($1) \
+            ) \
+            SELECT * FROM records r \
+            WHERE r.date BETWEEN $2 AND $3 AND EXISTS ( \
+                SELECT 1 FROM input i \
+                WHERE r.source_id <> i.source_id AND r.id <> i.id \
+                AND julianday(r.date) BETWEEN i.from_day AND i.to_day AND ( \
+                    r.simhash & 65535 = i.band0 OR (r.simhash >> 16) & 65535 = i.band1 \
+                    OR (r.simhash >> 32) & 65535 = i.band2 OR (r.simhash >> 48) & 65535 = i.band3 \
+                    OR r.canonical_link IN (SELECT value FROM json_each($4)) \
+                    OR r.content LIKE i.link_pattern ESCAPE '\\' \
+                    OR lower(r.title) = lower(i.title) \
+                ) \
+            ) \
+            ORDER BY r.id \
+            LIMIT $5",
+        )
+        .bind(serde_json::Value::from(rows).to_string())
+        .bind(from_date)
+        .bind(to_date)
+        .bind(serde_json::json!(input.links).to_string())
+        .bind(input.limit())
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn set_records_cluster(&self, record_ids: Vec<i32>, cluster_id: i32) -> Result<()> {
+        sqlx::query(
+            "UPDATE records SET cluster_id = $1 WHERE id IN (SELECT value FROM json_each($2))",
+        )
+        .bind(cluster_id)
+        .bind(serde_json::json!(record_ids).to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_source_scraped_now(&self, source: models::Source) -> Result<()> {
+        sqlx::query(
+            "UPDATE sources \
+            SET last_scrape_time = CURRENT_TIMESTAMP, last_success_at = CURRENT_TIMESTAMP, \
+                consecutive_failures = 0, permanent_failures = 0 \
+            WHERE id = $1",
+        )
+        .bind(source.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        // LIKE is case insensitive in sqlite
+        Ok(sqlx::query_as(
+            "SELECT * FROM sources \
+            WHERE origin LIKE $1 OR external_link LIKE $1 OR name LIKE $1",
+        )
+        .bind(format!("%{}%", query))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_exact_source(
+        &self,
+        kind: String,
+        origin: String,
+    ) -> Result<Option<models::Source>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM sources WHERE kind = $1 AND origin = $2")
+                .bind(kind)
+                .bind(origin)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_sources_by_kind(&self, kind: String) -> Result<Vec<models::Source>> {
+        Ok(sqlx::query_as("SELECT * FROM sources WHERE kind = $1")
+            .bind(kind)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    // sqlite locks the whole database for writes, the update claims the sources atomically
+    async fn claim_sources_for_scrape(
+        &self,
+        kind: String,
+        lease: &queue::Lease,
+    ) -> Result<Vec<models::Source>> {
+        Ok(sqlx::query_as(
+            "UPDATE sources SET leased_by = $3, lease_expires_at = $4 \
+            WHERE id IN ( \
+                SELECT id FROM sources \
+                WHERE kind = $1 AND next_scrape_at <= $2 AND disabled_at IS NULL \
+                    AND (lease_expires_at IS NULL OR lease_expires_at <= $2) \
+                ORDER BY next_scrape_at LIMIT $5 \
+            ) RETURNING *",
+        )
+        .bind(kind)
+        .bind(Utc::now().naive_utc())
+        .bind(&lease.worker)
+        .bind(lease.expires_at)
+        .bind(lease.limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn get_record_dates(&self, source_id: i32, limit: i64) -> Result<Vec<NaiveDateTime>> {
+        Ok(sqlx::query_scalar(
+            "SELECT date FROM records WHERE source_id = $1 ORDER BY date DESC LIMIT $2",
+        )
+        .bind(source_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn set_source_next_scrape(
+        &self,
+        source_id: i32,
+        scrape_interval_secs: i64,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE sources \
+            SET last_scrape_time = CURRENT_TIMESTAMP, scrape_interval_secs = $2, next_scrape_at = $3, \
+                last_success_at = CURRENT_TIMESTAMP, consecutive_failures = 0, permanent_failures = 0, \
+                leased_by = NULL, lease_expires_at = NULL \
+            WHERE id = $1",
+        )
+        .bind(source_id)
+        .bind(scrape_interval_secs)
+        .bind(next_scrape_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_source_failure(&self, source_id: i32, failure: &health::Failure) -> Result<()> {
+        sqlx::query(
+            "UPDATE sources \
+            SET last_scrape_time = CURRENT_TIMESTAMP, last_error = $2, last_error_class = $3, \
+                consecutive_failures = $4, permanent_failures = $5, next_scrape_at = $6, \
+                disabled_at = $7, leased_by = NULL, lease_expires_at = NULL \
+            WHERE id = $1",
+        )
+        .bind(source_id)
+        .bind(failure.error.as_str())
+        .bind(failure.class.as_str())
+        .bind(failure.consecutive_failures)
+        .bind(failure.permanent_failures)
+        .bind(failure.next_scrape_at)
+        .bind(failure.disabled_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enable_source(&self, source_id: i32) -> Result<Option<models::Source>> {
+        Ok(sqlx::query_as(
+            "UPDATE sources \
+            SET disabled_at = NULL, consecutive_failures = 0, permanent_failures = 0, \
+                next_scrape_at = $2 \
+            WHERE id = $1 RETURNING *",
+        )
+        .bind(source_id)
+        .bind(Utc::now().naive_utc())
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn set_source_account(
+        &self,
+        source_id: i32,
+        account: Option<String>,
+    ) -> Result<Option<models::Source>> {
+        Ok(
+            sqlx::query_as("UPDATE sources SET account = $2 WHERE id = $1 RETURNING *")
+                .bind(source_id)
+                .bind(account)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn update_source_schedule(
+        &self,
+        source_id: i32,
+        schedule: Option<String>,
+        next_scrape_at: NaiveDateTime,
+    ) -> Result<Option<models::Source>> {
+        Ok(sqlx::query_as(
+            "UPDATE sources SET schedule = $2, next_scrape_at = $3 WHERE id = $1 RETURNING *",
+        )
+        .bind(source_id)
+        .bind(schedule)
+        .bind(next_scrape_at)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn save_sources(&self, sources: Vec<models::NewSource>) -> Result<Vec<models::Source>> {
+        let mut res = Vec::with_capacity(sources.len());
+        let mut tx = self.pool.begin().await?;
+        for s in sources {
+            let n = sqlx::query_as(
+                "INSERT INTO sources (name, origin, kind, image, external_link) VALUES \
+                ($1, $2, $3, $4, $5) \
+                ON CONFLICT (origin, kind) DO UPDATE \
+                    SET name = excluded.name \
+                RETURNING *",
+            )
+            .bind(s.name)
+            .bind(s.origin)
+            .bind(s.kind)
+            .bind(s.image)
+            .bind(s.external_link)
+            .fetch_one(&mut tx)
+            .await?;
+            res.push(n)
+        }
+        tx.commit().await?;
+        Ok(res)
+    }
+}
+
+async fn migrate(db_pool: &Pool) {
+    // interface migrations are applied to the same database
+    sqlx::migrate!("./sqlite_migrations")
+        .set_ignore_missing(true)
+        .run(db_pool)
+        .await
+        .expect("can't migrate");
+}
@@ -0,0 +1,396 @@
+use async_trait::async_trait;
+use email_collector::{
+    server::{is_valid_token, MailboxCheck, Protocol, Server},
+    types::{Email, ReceivedEmail},
+};
+
+use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::result::{Error, Result};
+use crate::storage::Storage;
+
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+
+// TODO: enum?
+pub(crate) const EMAIL: &str = "EMAIL";
+// longest file name kept from the attachment, most file systems allow 255 bytes
+const MAX_FILE_NAME_LEN: usize = 100;
+
+/// Email source: newsletters sent to `<token>@<domain>` become records of the `<token>` source.
+///
+/// Sources are created by searching for their address, mail for other tokens
+/// is rejected, so spam to random addresses doesn't create sources.
+pub struct EmailSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    listen_address: String,
+    domain: String,
+    protocol: Protocol,
+    max_message_size: usize,
+    files_directory: String,
+    storage: S,
+    pipeline: Pipeline,
+}
+
+impl<S> EmailSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn builder() -> EmailSourceBuilder<S> {
+        EmailSourceBuilder::new()
+    }
+
+    fn address(&self, token: &str) -> String {
+        format!("{}@{}", token, self.domain)
+    }
+
+    async fn get_source(&self, token: &str) -> Result<Option<models::Source>> {
+        self.storage
+            .get_exact_source(EMAIL.to_string(), token.to_string())
+            .await
+    }
+
+    async fn save_email(&self, source: &models::Source, email: &Email) -> Result<usize> {
+        let content = match email.is_html {
+            true => email.content.clone(),
+            false => format!("<pre>{}</pre>", escape_html(email.content.as_str())),
+        };
+        let created = self
+            .pipeline
+            .save_records(
+                &self.storage,
+                EMAIL,
+                vec![models::NewRecord {
+                    title: email.subject.clone(),
+                    source_record_id: email_id(email),
+                    source_id: source.id,
+                    meta: None,
+                    content,
+                    date: email.date,
+                    image: None,
+                }],
+            )
+            .await?
+            .created;
+        for record in &created {
+            let mut files = vec![];
+            for (i, image) in email.inline_images.iter().enumerate() {
+                let file_name = image
+                    .file_name
+                    .as_deref()
+                    .and_then(safe_file_name)
+                    .unwrap_or_else(|| format!("image_{}", i));
+                let local_path = Path::new(self.files_directory.as_str())
+                    .join(format!("email_{}_{}_{}", record.id, i, file_name));
+                // the record is already saved, the file is skipped rather than the rest lost
+                if let Err(e) = tokio::fs::write(&local_path, &image.data).await {
+                    warn!("can't save {:?} of record {}: {}", local_path, record.id, e);
+                    continue;
+                }
+                files.push(models::NewFile {
+                    record_id: record.id,
+                    kind: EMAIL.to_string(),
+                    local_path: local_path.to_str().map(String::from),
+                    // html refers inline images by `cid:<content id>`
+                    remote_path: format!("cid:{}", image.content_id.clone().unwrap_or_default()),
+                    remote_id: None,
+                    file_name: Some(file_name),
+                    type_: match image.is_image() {
+                        true => "IMAGE",
+                        false => "DOCUMENT",
+                    }
+                    .to_string(),
+                    meta: Some(
+                        serde_json::json!({ "content_type": image.content_type }).to_string(),
+                    ),
+                });
+            }
+            if !files.is_empty() {
+                self.storage.save_files(files).await?;
+            }
+        }
+        Ok(created.len())
+    }
+}
+
+// Message-ID is optional, so fall back to something stable for the same message
+fn email_id(email: &Email) -> String {
+    match &email.message_id {
+        Some(id) => id.clone(),
+        None => format!(
+            "{}:{}:{}",
+            email.from_address.clone().unwrap_or_default(),
+            email.date.map(|d| d.timestamp()).unwrap_or_default(),
+            email.subject.clone().unwrap_or_default()
+        ),
+    }
+}
+
+// the attachment name is chosen by the sender, only its last component is kept
+// without control characters, so the file stays in the files directory;
+// long names are cut keeping the extension
+fn safe_file_name(name: &str) -> Option<String> {
+    let name: String = name
+        .rsplit(&['/', '\\'][..])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() || c == ':' => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && extension.len() <= 16 => (stem, extension),
+        _ => (name, ""),
+    };
+    let mut safe = String::with_capacity(MAX_FILE_NAME_LEN);
+    for c in stem.chars() {
+        if safe.len() + c.len_utf8() + extension.len() + 1 > MAX_FILE_NAME_LEN {
+            break;
+        }
+        safe.push(c);
+    }
+    if !extension.is_empty() {
+        safe.push('.');
+        safe.push_str(extension);
+    }
+    match stem.is_empty() {
+        true => None,
+        false => Some(safe),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub struct EmailSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    listen_address: String,
+    domain: String,
+    protocol: Protocol,
+    max_message_size: usize,
+    files_directory: String,
+    storage: Option<S>,
+    pipeline: Pipeline,
+}
+
+impl<S> Default for EmailSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> EmailSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            listen_address: "127.0.0.1:2525".to_string(),
+            domain: "localhost".to_string(),
+            protocol: Protocol::Smtp,
+            max_message_size: 10 * 1024 * 1024,
+            files_directory: "files".to_string(),
+            storage: None,
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    pub fn with_listen_address(mut self, listen_address: &str) -> Self {
+        self.listen_address = listen_address.to_string();
+        self
+    }
+
+    pub fn with_domain(mut self, domain: &str) -> Self {
+        self.domain = domain.to_lowercase();
+        self
+    }
+
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    pub fn with_files_directory(mut self, files_directory: &str) -> Self {
+        self.files_directory = files_directory.to_string();
+        self
+    }
+
+    pub fn with_storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(EMAIL);
+        self
+    }
+
+    pub fn build(self) -> EmailSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not specified")
+        }
+        EmailSource {
+            listen_address: self.listen_address,
+            domain: self.domain,
+            protocol: self.protocol,
+            max_message_size: self.max_message_size,
+            files_directory: self.files_directory,
+            storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailUpdate {
+    // local parts of the recipients, the same message may be sent to several sources
+    pub tokens: Vec<String>,
+    pub email: Email,
+}
+
+impl From<ReceivedEmail> for EmailUpdate {
+    fn from(received: ReceivedEmail) -> Self {
+        Self {
+            tokens: received.recipients,
+            email: received.email,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> UpdatesHandler<EmailUpdate> for EmailSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn create_source(&self, updates: &EmailUpdate) -> Result<models::Source> {
+        let token = updates.tokens.first().unwrap();
+        self.get_source(token).await?.ok_or(Error::SourceNotFound)
+    }
+
+    async fn process_updates(&self, updates: &EmailUpdate) -> Result<usize> {
+        let mut affected = 0;
+        for token in &updates.tokens {
+            // the source may be removed since the recipient was accepted
+            let source = match self.get_source(token).await? {
+                Some(source) => source,
+                None => {
+                    warn!("no email source for {}", self.address(token));
+                    continue;
+                }
+            };
+            affected += self.save_email(&source, &updates.email).await?;
+            self.storage.set_source_scraped_now(source).await?;
+        }
+        Ok(affected)
+    }
+}
+
+#[async_trait]
+impl<S> SourceProvider for EmailSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn kind(&self) -> &'static str {
+        EMAIL
+    }
+
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        let listener = TcpListener::bind(self.listen_address.as_str()).await?;
+        info!(
+            "{:?} listens on {} for *@{}",
+            self.protocol, self.listen_address, self.domain
+        );
+        let (emails_sender, mut emails_receiver) = mpsc::channel(100);
+        let storage = self.storage.clone();
+        let mailbox_check: MailboxCheck = Arc::new(move |token: String| {
+            let storage = storage.clone();
+            Box::pin(async move {
+                storage
+                    .get_exact_source(EMAIL.to_string(), token)
+                    .await
+                    .map(|source| source.is_some())
+                    .map_err(|e| e.to_string())
+            })
+        });
+        let server = Server::new(self.domain.as_str(), self.protocol)
+            .with_max_message_size(self.max_message_size)
+            .with_mailbox_check(mailbox_check);
+        let server_handle = tokio::spawn(async move {
+            if let Err(e) = server.run(listener, emails_sender).await {
+                error!("email server stopped: {}", e)
+            }
+        });
+        let updates_handle = tokio::spawn(async move {
+            while let Some(received) = emails_receiver.recv().await {
+                let update = Ok(SourceData::new(EMAIL, EmailUpdate::from(received)));
+                let local = updates_sender.lock().await;
+                if local.send(update).await.is_err() {
+                    error!("updates receiver dropped");
+                    return;
+                }
+            }
+        });
+        Ok(vec![server_handle, updates_handle])
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        // only own addresses, `<token>@<domain>`
+        let token = match query.trim().rsplit_once('@') {
+            Some((token, domain)) if domain.eq_ignore_ascii_case(self.domain.as_str()) => {
+                token.to_lowercase()
+            }
+            _ => return Ok(vec![]),
+        };
+        if !is_valid_token(token.as_str()) {
+            return Ok(vec![]);
+        }
+        if let Some(source) = self
+            .storage
+            .get_exact_source(EMAIL.to_string(), token.clone())
+            .await?
+        {
+            return Ok(vec![source]);
+        }
+        Ok(self
+            .storage
+            .save_sources(vec![models::NewSource {
+                name: self.address(token.as_str()),
+                origin: token.clone(),
+                kind: EMAIL.to_string(),
+                image: None,
+                external_link: format!("mailto:{}", self.address(token.as_str())),
+            }])
+            .await?)
+    }
+
+    async fn synchronize(&self, _secs_depth: i32) -> Result<()> {
+        // emails are pushed to us, there is nothing to fetch
+        debug!("{} has nothing to sync", self.kind());
+        Ok(())
+    }
+}
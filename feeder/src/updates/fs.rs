@@ -0,0 +1,387 @@
+use async_trait::async_trait;
+use fs_collector::{
+    parser::parse_document,
+    types::{Attachment, Document},
+    watcher::Watcher,
+};
+
+use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::result::Result;
+use crate::storage::Storage;
+
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+// TODO: enum?
+pub(crate) const FILESYSTEM: &str = "FILESYSTEM";
+
+/// Filesystem source: every subdirectory of the watched directory is a source,
+/// Markdown, HTML and `.eml` files (or Maildir messages) inside of it are records.
+///
+/// Changed files update their records, sources are created on the first file.
+pub struct FilesystemSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    directory: PathBuf,
+    poll_interval_secs: u64,
+    files_directory: String,
+    storage: S,
+    pipeline: Pipeline,
+}
+
+impl<S> FilesystemSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn builder() -> FilesystemSourceBuilder<S> {
+        FilesystemSourceBuilder::new()
+    }
+
+    async fn get_or_create_source(&self, name: &str) -> Result<models::Source> {
+        if let Some(source) = self
+            .storage
+            .get_exact_source(FILESYSTEM.to_string(), name.to_string())
+            .await?
+        {
+            return Ok(source);
+        }
+        let directory = self.directory.join(name);
+        let directory = directory.canonicalize().unwrap_or(directory);
+        Ok(self
+            .storage
+            .save_sources(vec![models::NewSource {
+                name: name.to_string(),
+                origin: name.to_string(),
+                kind: FILESYSTEM.to_string(),
+                image: None,
+                external_link: format!("file://{}", directory.to_string_lossy()),
+            }])
+            .await?
+            .pop()
+            .unwrap())
+    }
+
+    async fn save_document(&self, source: &models::Source, document: &Document) -> Result<usize> {
+        let record = models::NewRecord {
+            title: document.title.clone(),
+            source_record_id: document.id.clone(),
+            source_id: source.id,
+            content: document.content.clone(),
+            date: Some(document.date),
+            image: None,
+            meta: None,
+        };
+        let saved = match self
+            .storage
+            .get_record(source.id, document.id.clone())
+            .await?
+        {
+            // directory is rescanned on start, so most of the files are already known
+            Some(existing)
+                if existing.title == record.title && existing.content == record.content =>
+            {
+                return Ok(0)
+            }
+            Some(_) => match self
+                .pipeline
+                .update_record(&self.storage, FILESYSTEM, record)
+                .await?
+            {
+                Some(updated) => {
+                    let paths = self.storage.delete_record_files(updated.id).await?;
+                    self.remove_files(paths).await;
+                    updated
+                }
+                None => return Ok(0),
+            },
+            None => match self
+                .pipeline
+                .save_records(&self.storage, FILESYSTEM, vec![record])
+                .await?
+                .created
+                .pop()
+            {
+                Some(created) => created,
+                None => return Ok(0),
+            },
+        };
+        let mut files = vec![];
+        for (i, attachment) in document.attachments.iter().enumerate() {
+            let type_ = file_type(attachment.content_type().as_str()).to_string();
+            let meta =
+                Some(serde_json::json!({ "content_type": attachment.content_type() }).to_string());
+            match attachment {
+                // archive stays where it is, files are referenced in place
+                Attachment::Local { path, reference } => files.push(models::NewFile {
+                    record_id: saved.id,
+                    kind: FILESYSTEM.to_string(),
+                    local_path: path.to_str().map(String::from),
+                    remote_path: reference.clone(),
+                    remote_id: None,
+                    file_name: attachment.file_name(),
+                    type_,
+                    meta,
+                }),
+                Attachment::Inline(inline) => {
+                    let file_name = attachment
+                        .file_name()
+                        .unwrap_or_else(|| format!("image_{}", i));
+                    let local_path = Path::new(self.files_directory.as_str())
+                        .join(format!("fs_{}_{}_{}", saved.id, i, file_name));
+                    tokio::fs::write(&local_path, &inline.data).await?;
+                    files.push(models::NewFile {
+                        record_id: saved.id,
+                        kind: FILESYSTEM.to_string(),
+                        local_path: local_path.to_str().map(String::from),
+                        remote_path: format!(
+                            "cid:{}",
+                            inline.content_id.clone().unwrap_or_default()
+                        ),
+                        remote_id: None,
+                        file_name: Some(file_name),
+                        type_,
+                        meta,
+                    })
+                }
+            }
+        }
+        if !files.is_empty() {
+            self.storage.save_files(files).await?;
+        }
+        Ok(1)
+    }
+
+    // only the files saved to the files directory are removed,
+    // local attachments are the documents of the watched directory itself
+    async fn remove_files(&self, paths: Vec<String>) {
+        let directory = match tokio::fs::canonicalize(self.files_directory.as_str()).await {
+            Ok(directory) => directory,
+            Err(e) => {
+                warn!("can't find {}: {}", self.files_directory, e);
+                return;
+            }
+        };
+        for path in paths {
+            let path = match tokio::fs::canonicalize(path.as_str()).await {
+                Ok(path) if path.starts_with(&directory) => path,
+                _ => continue,
+            };
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                warn!("can't remove {}: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+fn file_type(content_type: &str) -> &'static str {
+    match content_type.split('/').next() {
+        Some("image") => "IMAGE",
+        Some("video") => "VIDEO",
+        Some("audio") => "AUDIO",
+        _ => "DOCUMENT",
+    }
+}
+
+// files of the watched directory itself don't belong to any source
+fn read_update(directory: &Path, path: &Path) -> Result<Option<FilesystemUpdate>> {
+    let source = match path.strip_prefix(directory).map(|p| p.iter()) {
+        Ok(mut components) => match (components.next(), components.next()) {
+            (Some(source), Some(_)) => source.to_string_lossy().to_string(),
+            _ => return Ok(None),
+        },
+        Err(_) => return Ok(None),
+    };
+    Ok(parse_document(&directory.join(&source), path)?
+        .map(|document| FilesystemUpdate { source, document }))
+}
+
+pub struct FilesystemSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    directory: String,
+    poll_interval_secs: u64,
+    files_directory: String,
+    storage: Option<S>,
+    pipeline: Pipeline,
+}
+
+impl<S> Default for FilesystemSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> FilesystemSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            directory: "archive".to_string(),
+            poll_interval_secs: 60,
+            files_directory: "files".to_string(),
+            storage: None,
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    pub fn with_directory(mut self, directory: &str) -> Self {
+        self.directory = directory.to_string();
+        self
+    }
+
+    pub fn with_poll_interval_secs(mut self, poll_interval_secs: u64) -> Self {
+        self.poll_interval_secs = poll_interval_secs;
+        self
+    }
+
+    pub fn with_files_directory(mut self, files_directory: &str) -> Self {
+        self.files_directory = files_directory.to_string();
+        self
+    }
+
+    pub fn with_storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(FILESYSTEM);
+        self
+    }
+
+    pub fn build(self) -> FilesystemSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not specified")
+        }
+        FilesystemSource {
+            directory: PathBuf::from(self.directory),
+            poll_interval_secs: self.poll_interval_secs,
+            files_directory: self.files_directory,
+            storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FilesystemUpdate {
+    // name of the subdirectory
+    pub source: String,
+    pub document: Document,
+}
+
+#[async_trait]
+impl<S> UpdatesHandler<FilesystemUpdate> for FilesystemSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn create_source(&self, updates: &FilesystemUpdate) -> Result<models::Source> {
+        self.get_or_create_source(updates.source.as_str()).await
+    }
+
+    async fn process_updates(&self, updates: &FilesystemUpdate) -> Result<usize> {
+        let source = self.get_or_create_source(updates.source.as_str()).await?;
+        let affected = self.save_document(&source, &updates.document).await?;
+        self.storage.set_source_scraped_now(source).await?;
+        Ok(affected)
+    }
+}
+
+#[async_trait]
+impl<S> SourceProvider for FilesystemSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn kind(&self) -> &'static str {
+        FILESYSTEM
+    }
+
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+        info!("watching {:?}", self.directory);
+        let (paths_sender, paths_receiver) = std::sync::mpsc::channel();
+        let watcher = Watcher::new(&self.directory)
+            .with_poll_interval(Duration::from_secs(self.poll_interval_secs));
+        // blocking tasks can't be aborted, the watcher checks the token instead,
+        // the reader stops with the watcher
+        let stopped = CancellationToken::new();
+        let watcher_stopped = stopped.clone();
+        let watcher_handle = tokio::task::spawn_blocking(move || {
+            if let Err(e) = watcher.run_until(paths_sender, || watcher_stopped.is_cancelled()) {
+                error!("filesystem watcher stopped: {}", e)
+            }
+        });
+        let directory = self.directory.clone();
+        let reader_handle = tokio::task::spawn_blocking(move || {
+            for path in paths_receiver {
+                let update = match read_update(&directory, &path) {
+                    Ok(Some(update)) => Ok(SourceData::new(FILESYSTEM, update)),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("can't read {:?}: {}", path, e);
+                        continue;
+                    }
+                };
+                let local = updates_sender.blocking_lock();
+                if local.blocking_send(update).is_err() {
+                    error!("updates receiver dropped");
+                    return;
+                }
+            }
+        });
+        // aborting of the handle drops the guard
+        Ok(vec![tokio::spawn(async move {
+            let _stop_guard = stopped.drop_guard();
+            let _ = futures::future::join(watcher_handle, reader_handle).await;
+        })])
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        // only existing subdirectories, `name` or `<directory>/name`
+        let name = query
+            .trim()
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+        if name.is_empty() || name.starts_with('.') || !self.directory.join(name).is_dir() {
+            return Ok(vec![]);
+        }
+        Ok(vec![self.get_or_create_source(name).await?])
+    }
+
+    async fn synchronize(&self, secs_depth: i32) -> Result<()> {
+        let directory = self.directory.clone();
+        let paths = tokio::task::spawn_blocking(move || Watcher::new(&directory).scan())
+            .await
+            .expect("can't scan directory")?;
+        let since = Utc::now().naive_utc() - chrono::Duration::seconds(secs_depth.into());
+        let mut affected = 0;
+        for path in paths {
+            match read_update(&self.directory, &path)? {
+                Some(update) if update.document.date >= since => {
+                    affected += self.process_updates(&update).await?
+                }
+                _ => {}
+            }
+        }
+        info!("{} filesystem records synchronized", affected);
+        Ok(())
+    }
+}
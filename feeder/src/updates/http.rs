@@ -5,19 +5,23 @@ use http_collector::result::Result as HttpResult;
 use std::sync::Arc;
 
 use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::metrics;
 use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::queue;
 use crate::result::{Error, Result};
+use crate::schedule::ScrapeBounds;
 use crate::storage::Storage;
 
-use crate::updates::Source;
 use http_collector::collector::{CacheStub, HttpCollector, ResultsHandler};
 use http_collector::result::Error as CollectorError;
 use serde::Serialize;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time::Duration;
 
 // TODO: enum?
-const WEB: &str = "WEB";
+pub(crate) const WEB: &str = "WEB";
 
 impl From<Feed> for FeedUpdate {
     fn from(feed_update: Feed) -> Self {
@@ -59,11 +63,12 @@ impl Handler {
 
 #[async_trait]
 impl ResultsHandler for Handler {
-    async fn process(&self, result: HttpResult<(&Feed, FeedKind, String)>) {
+    async fn process(&self, link: &str, result: HttpResult<&Feed>, elapsed: Duration) {
         let update = match result {
-            Ok((updates, _, _)) => Ok(SourceData::WebFeed(FeedUpdate::from(updates.clone()))),
-            Err(err) => Err(Error::HttpCollectorError(err)),
+            Ok(updates) => Ok(SourceData::new(WEB, FeedUpdate::from(updates.clone()))),
+            Err(err) => Err(Error::source_failed(WEB, link.to_string(), err)),
         };
+        metrics::observe_scrape(WEB, update.as_ref().err(), elapsed);
         let local = self.sender.lock().await;
         if local.send(update).await.is_err() {
             error!("updates receiver dropped");
@@ -96,7 +101,9 @@ where
 {
     sleep_secs: u64,
     scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
     storage: Option<S>,
+    pipeline: Pipeline,
 }
 
 impl<S> Default for HttpSourceBuilder<S>
@@ -115,7 +122,9 @@ where
         Self {
             sleep_secs: 60,
             scrape_source_secs_interval: 60,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
             storage: None,
+            pipeline: Pipeline::default(),
         }
     }
 
@@ -129,19 +138,36 @@ where
         self
     }
 
+    pub fn with_max_scrape_source_secs_interval(
+        mut self,
+        max_scrape_source_secs_interval: u64,
+    ) -> Self {
+        self.max_scrape_source_secs_interval = max_scrape_source_secs_interval;
+        self
+    }
+
     pub fn with_storage(mut self, storage: S) -> Self {
         self.storage = Some(storage);
         self
     }
 
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(WEB);
+        self
+    }
+
     pub fn build(self) -> HttpSource<S> {
         if self.storage.is_none() {
             panic!("storage not specified")
         }
         HttpSource {
             sleep_secs: self.sleep_secs,
-            scrape_source_secs_interval: self.scrape_source_secs_interval,
+            scrape_bounds: ScrapeBounds::new(
+                self.scrape_source_secs_interval,
+                self.max_scrape_source_secs_interval,
+            ),
             storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
             collector: Arc::new(HttpCollector::new()),
         }
     }
@@ -152,9 +178,10 @@ where
     S: Storage + Send + Sync + Clone + 'static,
 {
     sleep_secs: u64,
-    scrape_source_secs_interval: u64,
+    scrape_bounds: ScrapeBounds,
     collector: Arc<HttpCollector<CacheStub>>,
     storage: S,
+    pipeline: Pipeline,
 }
 
 impl<S> HttpSource<S>
@@ -194,9 +221,11 @@ where
             0 => self.create_source(updates).await?,
             _ => sources.pop().unwrap(),
         };
-        let affected = self
-            .storage
-            .save_records(
+        let saved = self
+            .pipeline
+            .save_or_update_records(
+                &self.storage,
+                WEB,
                 updates
                     .updates
                     .iter()
@@ -205,12 +234,14 @@ where
                         title: u.title.clone(),
                         source_record_id: u.guid.clone(),
                         source_id: source.id,
+                        meta: None,
                         content: u.content.clone(),
                         image: u.image_link.clone(),
                     })
                     .collect::<Vec<models::NewRecord>>(),
             )
             .await?;
+        let affected = saved.created;
         if affected.is_empty() {
             let mut tasks = vec![];
             updates.updates.iter().for_each(|u| {
@@ -227,8 +258,10 @@ where
             });
             futures::future::join_all(tasks).await;
         }
-        self.storage.set_source_scraped_now(source).await?;
-        Ok(affected.len())
+        self.storage
+            .schedule_source(source, self.scrape_bounds)
+            .await?;
+        Ok(affected.len() + saved.updated.len())
     }
 }
 
@@ -237,8 +270,8 @@ impl<S> SourceProvider for HttpSource<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
-    fn get_source(&self) -> Source {
-        Source::Web
+    fn kind(&self) -> &'static str {
+        WEB
     }
 
     async fn synchronize(&self, _secs_depth: i32) -> Result<()> {
@@ -249,18 +282,17 @@ where
     async fn run(
         &self,
         updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
-    ) -> Result<()> {
+    ) -> Result<Vec<JoinHandle<()>>> {
         let (sources_sender, sources_receiver) = mpsc::channel(2000);
         let sleep_secs = self.sleep_secs;
-        let scrape_source_secs_interval = self.scrape_source_secs_interval;
         let st = self.storage.clone();
-        tokio::spawn(async move {
-            sources_gen(st, scrape_source_secs_interval, sleep_secs, sources_sender).await
-        });
+        let sources_gen_handle =
+            tokio::spawn(async move { sources_gen(st, sleep_secs, sources_sender).await });
         let http_handler = Handler::new(updates_sender);
         let http_runner = self.collector.clone();
-        tokio::spawn(async move { http_runner.run(sources_receiver, &http_handler).await });
-        Ok(())
+        let collector_handle =
+            tokio::spawn(async move { http_runner.run(sources_receiver, &http_handler).await });
+        Ok(vec![sources_gen_handle, collector_handle])
     }
 
     async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
@@ -270,7 +302,7 @@ where
         }
         let feeds = match self.collector.detect_feeds(query.as_str()).await {
             Ok(feeds) => feeds,
-            Err(CollectorError::RequestError) => vec![],
+            Err(CollectorError::RequestError) | Err(CollectorError::HttpError(_)) => vec![],
             Err(e) => return Err(e.into()),
         };
         let new_sources = self
@@ -300,13 +332,12 @@ where
 
 async fn sources_gen<S: Storage>(
     storage: S,
-    source_check_period: u64,
     sleep_period: u64,
     sender: mpsc::Sender<Vec<(Option<FeedKind>, String)>>,
 ) {
     let sleep_period = Duration::from_secs(sleep_period);
     loop {
-        match get_sources(&storage, source_check_period).await {
+        match get_sources(&storage).await {
             Ok(sources) => {
                 debug!("found sources for scrape: {:?}", sources);
                 if let Err(err) = sender.send(sources).await {
@@ -321,12 +352,9 @@ async fn sources_gen<S: Storage>(
     }
 }
 
-async fn get_sources<S: Storage>(
-    storage: &S,
-    source_check_period_secs: u64,
-) -> Result<Vec<(Option<FeedKind>, String)>> {
+async fn get_sources<S: Storage>(storage: &S) -> Result<Vec<(Option<FeedKind>, String)>> {
     Ok(storage
-        .get_sources_by_kind_for_scrape(WEB.to_string(), source_check_period_secs)
+        .claim_sources_for_scrape(WEB.to_string(), &queue::Lease::now())
         .await?
         .iter()
         .map(|r| (None, r.origin.clone()))
@@ -0,0 +1,426 @@
+use async_trait::async_trait;
+use mastodon_collector::{
+    client::{MastodonClient, Timeline},
+    result::{Error as CollectorError, Result as MastodonResult},
+    types::{Account, AttachmentKind, Handle, Status},
+};
+
+use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::metrics;
+use crate::models;
+use crate::net;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::queue;
+use crate::result::{Error, Result};
+use crate::schedule::ScrapeBounds;
+use crate::storage::Storage;
+
+use chrono::{Duration, Utc};
+use futures::future::join_all;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+// TODO: enum?
+pub(crate) const MASTODON: &str = "MASTODON";
+
+/// Mastodon source which polls public timelines of accounts and hashtags.
+///
+/// Sources are identified by handles: `@user@instance` or `#tag@instance`.
+pub struct MastodonSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_bounds: ScrapeBounds,
+    client: Arc<MastodonClient>,
+    storage: S,
+    pipeline: Pipeline,
+}
+
+impl<S> MastodonSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn builder() -> MastodonSourceBuilder<S> {
+        MastodonSourceBuilder::new()
+    }
+
+    async fn save_statuses(&self, source: &models::Source, statuses: &[Status]) -> Result<usize> {
+        let created = self
+            .pipeline
+            .save_records(
+                &self.storage,
+                MASTODON,
+                statuses
+                    .iter()
+                    .map(|s| {
+                        let original = s.reblog().unwrap_or(s);
+                        models::NewRecord {
+                            title: status_title(s),
+                            source_record_id: s.id().to_string(),
+                            source_id: source.id,
+                            meta: None,
+                            content: original.content().to_string(),
+                            date: Some(s.created_at().naive_utc()),
+                            image: original
+                                .media_attachments()
+                                .iter()
+                                .find(|a| a.kind() == AttachmentKind::Image)
+                                .map(|a| a.preview_url().unwrap_or_else(|| a.url()).to_string()),
+                        }
+                    })
+                    .collect(),
+            )
+            .await?
+            .created;
+        for record in &created {
+            let status = match statuses.iter().find(|s| s.id() == record.source_record_id) {
+                None => continue,
+                Some(s) => s,
+            };
+            let original = status.reblog().unwrap_or(status);
+            self.storage
+                .set_record_external_link(
+                    record.source_record_id.clone(),
+                    record.source_id,
+                    original.url().to_string(),
+                )
+                .await?;
+            if !original.media_attachments().is_empty() {
+                self.storage
+                    .save_files(
+                        original
+                            .media_attachments()
+                            .iter()
+                            .map(|a| models::NewFile {
+                                record_id: record.id,
+                                kind: MASTODON.to_string(),
+                                local_path: None,
+                                remote_path: a.url().to_string(),
+                                remote_id: None,
+                                file_name: None,
+                                type_: match a.kind() {
+                                    AttachmentKind::Image => "IMAGE",
+                                    AttachmentKind::Gifv => "ANIMATION",
+                                    AttachmentKind::Video => "VIDEO",
+                                    AttachmentKind::Audio => "AUDIO",
+                                    AttachmentKind::Unknown => "DOCUMENT",
+                                }
+                                .to_string(),
+                                meta: a
+                                    .description()
+                                    .map(|d| serde_json::json!({ "description": d }).to_string()),
+                            })
+                            .collect(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(created.len())
+    }
+}
+
+// content warning if any, otherwise the author of a boosted status
+fn status_title(status: &Status) -> Option<String> {
+    let original = status.reblog().unwrap_or(status);
+    match (original.spoiler_text(), status.reblog()) {
+        (Some(cw), _) => Some(cw.to_string()),
+        (None, Some(reblog)) => Some(format!("boosted from @{}", reblog.account().acct())),
+        (None, None) => None,
+    }
+}
+
+pub struct MastodonSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+    storage: Option<S>,
+    pipeline: Pipeline,
+}
+
+impl<S> Default for MastodonSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> MastodonSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+            storage: None,
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    pub fn with_sleep_secs(mut self, sleep_secs: u64) -> Self {
+        self.sleep_secs = sleep_secs;
+        self
+    }
+
+    pub fn with_scrape_source_secs_interval(mut self, scrape_source_secs_interval: u64) -> Self {
+        self.scrape_source_secs_interval = scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_max_scrape_source_secs_interval(
+        mut self,
+        max_scrape_source_secs_interval: u64,
+    ) -> Self {
+        self.max_scrape_source_secs_interval = max_scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(MASTODON);
+        self
+    }
+
+    pub fn build(self) -> MastodonSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not specified")
+        }
+        MastodonSource {
+            sleep_secs: self.sleep_secs,
+            scrape_bounds: ScrapeBounds::new(
+                self.scrape_source_secs_interval,
+                self.max_scrape_source_secs_interval,
+            ),
+            client: Arc::new(MastodonClient::default()),
+            storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MastodonUpdate {
+    pub handle: Handle,
+    pub account: Option<Account>,
+    pub statuses: Vec<Status>,
+}
+
+impl From<Timeline> for MastodonUpdate {
+    fn from(timeline: Timeline) -> Self {
+        Self {
+            handle: timeline.handle,
+            account: timeline.account,
+            statuses: timeline.statuses,
+        }
+    }
+}
+
+impl From<&MastodonUpdate> for models::NewSource {
+    fn from(update: &MastodonUpdate) -> Self {
+        let (name, image, external_link) = match &update.account {
+            Some(account) => (
+                account.display_name().to_string(),
+                account.avatar().map(String::from),
+                account.url().to_string(),
+            ),
+            None => (update.handle.to_string(), None, update.handle.link()),
+        };
+        models::NewSource {
+            name,
+            origin: update.handle.to_string(),
+            kind: MASTODON.to_string(),
+            image,
+            external_link,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> UpdatesHandler<MastodonUpdate> for MastodonSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn create_source(&self, updates: &MastodonUpdate) -> Result<models::Source> {
+        Ok(self
+            .storage
+            .save_sources(vec![updates.into()])
+            .await?
+            .pop()
+            .unwrap())
+    }
+
+    async fn process_updates(&self, updates: &MastodonUpdate) -> Result<usize> {
+        let source = match self
+            .storage
+            .get_exact_source(MASTODON.to_string(), updates.handle.to_string())
+            .await?
+        {
+            None => self.create_source(updates).await?,
+            Some(s) => s,
+        };
+        let affected = self.save_statuses(&source, &updates.statuses).await?;
+        self.storage
+            .schedule_source(source, self.scrape_bounds)
+            .await?;
+        Ok(affected)
+    }
+}
+
+#[async_trait]
+impl<S> SourceProvider for MastodonSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn kind(&self) -> &'static str {
+        MASTODON
+    }
+
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        let (sources_sender, sources_receiver) = mpsc::channel(2000);
+
+        let sleep_secs = self.sleep_secs;
+        let st = self.storage.clone();
+        let sources_gen_handle =
+            tokio::spawn(async move { sources_gen(st, sleep_secs, sources_sender).await });
+
+        let cl = self.client.clone();
+        let handler = Handler::new(updates_sender);
+        let scrapper_handle =
+            tokio::spawn(async move { run_scrapper(cl.as_ref(), sources_receiver, handler).await });
+        Ok(vec![sources_gen_handle, scrapper_handle])
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        // only exact handles can be resolved, plain text queries are not supported
+        let handle = match Handle::parse(query) {
+            Ok(handle) => handle,
+            Err(_) => return Ok(vec![]),
+        };
+        if let Err(e) = net::public_address(handle.instance(), 443).await {
+            warn!("skip search in {}: {}", handle.instance(), e);
+            return Ok(vec![]);
+        }
+        let timeline = match self.client.get_timeline(&handle, None).await {
+            Ok(timeline) => timeline,
+            Err(CollectorError::NotFound) => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let update = MastodonUpdate::from(timeline);
+        let source = self.create_source(&update).await?;
+        if let Err(e) = self.save_statuses(&source, &update.statuses).await {
+            error!("{:?}", e)
+        }
+        Ok(vec![source])
+    }
+
+    async fn synchronize(&self, secs_depth: i32) -> Result<()> {
+        debug!("start syncing {}", self.kind());
+        let until = Utc::now() - Duration::seconds(secs_depth.into());
+        let sources = self
+            .storage
+            .get_sources_by_kind(MASTODON.to_string())
+            .await?;
+        for source in sources {
+            let handle = Handle::parse(source.origin.as_str())?;
+            let statuses = self.client.get_history(&handle, until).await?;
+            debug!("get {} statuses for {}", statuses.len(), source.name);
+            self.save_statuses(&source, &statuses).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn get_timeline_for_source(client: &MastodonClient, origin: String, handler: &Handler) {
+    let started = time::Instant::now();
+    let update = match Handle::parse(origin.as_str()) {
+        Ok(handle) => client.get_timeline(&handle, None).await,
+        Err(e) => Err(e),
+    };
+    handler.process(origin, update, started.elapsed()).await;
+}
+
+// TODO: generic scrapper. trait?
+async fn run_scrapper(
+    client: &MastodonClient,
+    mut sources_receiver: mpsc::Receiver<Vec<String>>,
+    handler: Handler,
+) {
+    while let Some(sources) = sources_receiver.recv().await {
+        let mut tasks = vec![];
+        for source in sources {
+            tasks.push(get_timeline_for_source(client, source, &handler));
+        }
+        join_all(tasks).await;
+    }
+}
+
+// TODO: generic generator
+async fn sources_gen<S: Storage>(storage: S, sleep_period: u64, sender: mpsc::Sender<Vec<String>>) {
+    let sleep_period = time::Duration::from_secs(sleep_period);
+    loop {
+        match get_sources(&storage).await {
+            Ok(sources) => {
+                debug!("found sources for scrape: {:?}", sources);
+                if let Err(err) = sender.send(sources).await {
+                    error!("{}", err)
+                };
+            }
+            Err(e) => error!("{}", e),
+        };
+
+        debug!("send sources delayed for {:?}", sleep_period);
+        tokio::time::sleep(sleep_period).await;
+    }
+}
+
+async fn get_sources<S: Storage>(storage: &S) -> Result<Vec<String>> {
+    Ok(storage
+        .claim_sources_for_scrape(MASTODON.to_string(), &queue::Lease::now())
+        .await?
+        .iter()
+        .map(|r| r.origin.clone())
+        .collect())
+}
+
+struct Handler {
+    sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+}
+
+impl Handler {
+    pub fn new(sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>) -> Self {
+        Self { sender }
+    }
+
+    async fn process(
+        &self,
+        origin: String,
+        result: MastodonResult<Timeline>,
+        elapsed: time::Duration,
+    ) {
+        let update = match result {
+            Ok(timeline) => Ok(SourceData::new(MASTODON, MastodonUpdate::from(timeline))),
+            Err(err) => Err(Error::source_failed(MASTODON, origin, err)),
+        };
+        metrics::observe_scrape(MASTODON, update.as_ref().err(), elapsed);
+        let local = self.sender.lock().await;
+        if local.send(update).await.is_err() {
+            error!("updates receiver dropped");
+        }
+    }
+}
@@ -1,28 +1,64 @@
+use crate::metrics;
 use crate::models;
 use crate::result::{Error, Result};
 use crate::storage::Storage;
 use async_trait::async_trait;
 use futures::future::join_all;
-use std::sync::Arc;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use supervisor::{SourceState, Supervisor};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
+const UPDATES_QUEUE_SIZE: usize = 2000;
+
+pub mod email;
+pub mod fs;
 pub mod http;
+pub mod mastodon;
+pub mod reddit;
+pub mod supervisor;
 pub mod tg;
+pub mod tg_web;
 pub mod vk;
 
+/// Update of any source kind, it's passed to the handler registered for the same kind.
 #[derive(Debug)]
-pub enum SourceData {
-    WebFeed(http::FeedUpdate),
-    Telegram(tg::TelegramUpdate),
-    Vk(vk::VkUpdate),
+pub struct SourceData {
+    kind: &'static str,
+    update: Box<dyn SourceUpdate>,
+}
+
+impl SourceData {
+    pub fn new<T: SourceUpdate>(kind: &'static str, update: T) -> Self {
+        Self {
+            kind,
+            update: Box::new(update),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub fn downcast_ref<T: SourceUpdate>(&self) -> Option<&T> {
+        SourceUpdate::as_any(&*self.update).downcast_ref()
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Source {
-    Web,
-    Telegram,
-    Vk,
+pub trait SourceUpdate: Any + Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + Debug + Send + Sync> SourceUpdate for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
 #[async_trait]
@@ -35,28 +71,110 @@ pub trait UpdatesHandler<T> {
 
 #[async_trait]
 pub trait SourceProvider {
-    // returns source name of the provider
-    fn get_source(&self) -> Source;
-    // TODO: receive join handle from here
-    // starts provider internal routines
-    async fn run(&self, updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>)
-        -> Result<()>;
+    // returns source kind of the provider, sources of the kind are stored with it
+    fn kind(&self) -> &'static str;
+    // starts provider internal routines, returns handles of its loops;
+    // loops are aborted and started again once any of them stops
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>>;
     // here provider searches sources; sources can be stored here
     async fn search_source(&self, query: &str) -> Result<Vec<models::Source>>;
     // on-demand synchronization
     async fn synchronize(&self, secs_depth: i32) -> Result<()>;
 }
 
+// handler of the particular updates type, called with updates of its kind only
+#[async_trait]
+trait AnyUpdatesHandler {
+    async fn process_updates(&self, updates: &SourceData) -> Result<usize>;
+}
+
+struct TypedUpdatesHandler<T, H> {
+    handler: Arc<H>,
+    updates: PhantomData<fn(&T)>,
+}
+
+#[async_trait]
+impl<T, H> AnyUpdatesHandler for TypedUpdatesHandler<T, H>
+where
+    T: SourceUpdate,
+    H: UpdatesHandler<T> + Send + Sync,
+{
+    async fn process_updates(&self, updates: &SourceData) -> Result<usize> {
+        match updates.downcast_ref::<T>() {
+            Some(updates) => self.handler.process_updates(updates).await,
+            None => Err(Error::UpdateNotSupported(format!(
+                "unexpected {} updates: {:?}",
+                updates.kind(),
+                updates
+            ))),
+        }
+    }
+}
+
+/// Provider together with the handler of its updates.
+///
+/// Any type implementing both traits can be registered, so collectors don't
+/// have to be known to the aggregator.
+#[derive(Clone)]
+pub struct RegisteredSource {
+    provider: Arc<dyn SourceProvider + Send + Sync>,
+    handler: Arc<dyn AnyUpdatesHandler + Send + Sync>,
+}
+
+impl RegisteredSource {
+    pub fn new<T, P>(provider: Arc<P>) -> Self
+    where
+        T: SourceUpdate,
+        P: SourceProvider + UpdatesHandler<T> + Send + Sync + 'static,
+    {
+        Self {
+            provider: provider.clone(),
+            handler: Arc::new(TypedUpdatesHandler {
+                handler: provider,
+                updates: PhantomData,
+            }),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.provider.kind()
+    }
+}
+
+// supervised sources of the running aggregator, every source is stopped by its own token
+struct Running {
+    shutdown: CancellationToken,
+    sources: HashMap<&'static str, (CancellationToken, JoinHandle<()>)>,
+}
+
+impl Running {
+    fn spawn(
+        &mut self,
+        supervisor: &Supervisor,
+        source: RegisteredSource,
+        updates_sender: Arc<Mutex<Sender<Result<SourceData>>>>,
+    ) {
+        let stop = self.shutdown.child_token();
+        let kind = source.kind();
+        let handle = supervisor.spawn(source, updates_sender, stop.clone());
+        self.sources.insert(kind, (stop, handle));
+    }
+}
+
 pub struct SourcesAggregator<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
-    http_source: Option<Arc<http::HttpSource<S>>>,
-    tg_source: Option<Arc<tg::TelegramSource<S>>>,
-    vk_source: Option<Arc<vk::VkSource<S>>>,
+    // in registration order, it's the order of start
+    sources: RwLock<Vec<RegisteredSource>>,
+    running: Mutex<Option<Running>>,
     updates_sender: Arc<Mutex<Sender<Result<SourceData>>>>,
     updates_receiver: Mutex<Receiver<Result<SourceData>>>,
     storage: S,
+    supervisor: Supervisor,
 }
 
 impl<S> SourcesAggregator<S>
@@ -67,29 +185,76 @@ where
         UpdatesHandlerBuilder::default()
     }
 
-    pub async fn synchronize(&self, secs_depth: i32, source: Option<Source>) -> Result<()> {
+    /// Kinds of the enabled sources.
+    pub fn kinds(&self) -> Vec<&'static str> {
+        self.sources().iter().map(RegisteredSource::kind).collect()
+    }
+
+    fn sources(&self) -> Vec<RegisteredSource> {
+        self.sources.read().expect("sources lock poisoned").clone()
+    }
+
+    fn get_source(&self, kind: &str) -> Option<RegisteredSource> {
+        self.sources().into_iter().find(|s| s.kind() == kind)
+    }
+
+    /// Replaces the source of the kind, `None` removes it.
+    ///
+    /// Loops of the previous source are stopped, the new source is started
+    /// if the aggregator is running.
+    pub async fn replace_source(&self, kind: &'static str, source: Option<RegisteredSource>) {
+        let mut running = self.running.lock().await;
+        if let Some(running) = running.as_mut() {
+            if let Some((stop, handle)) = running.sources.remove(kind) {
+                stop.cancel();
+                if let Err(e) = handle.await {
+                    error!("{} supervisor failed: {}", kind, e);
+                }
+            }
+        }
+        {
+            let mut sources = self.sources.write().expect("sources lock poisoned");
+            let position = sources.iter().position(|s| s.kind() == kind);
+            match (position, source.clone()) {
+                (Some(position), Some(source)) => sources[position] = source,
+                (Some(position), None) => {
+                    sources.remove(position);
+                }
+                (None, Some(source)) => sources.push(source),
+                (None, None) => {}
+            }
+        }
+        match (running.as_mut(), source) {
+            (Some(running), Some(source)) if !running.shutdown.is_cancelled() => {
+                info!("{} started", kind);
+                running.spawn(&self.supervisor, source, self.updates_sender.clone());
+            }
+            (_, None) => info!("{} disabled", kind),
+            _ => {}
+        }
+    }
+
+    pub async fn synchronize(&self, secs_depth: i32, kind: Option<&str>) -> Result<()> {
         // TODO: wait all tg files downloaded
-        let source_providers = self.get_enabled_sources();
+        let sources = self.sources();
         let mut tasks = vec![];
-        match source {
-            Some(source) => {
-                for provider in &source_providers {
-                    if provider.get_source() == source {
-                        debug!("going to sync {:?}", provider.get_source());
-                        tasks.push(provider.synchronize(secs_depth))
-                    }
+        match kind {
+            Some(kind) => match sources.iter().find(|s| s.kind() == kind) {
+                Some(source) => {
+                    debug!("going to sync {}", kind);
+                    tasks.push(source.provider.synchronize(secs_depth))
                 }
-                if tasks.is_empty() {
+                None => {
                     return Err(Error::SourceKindConflict(format!(
-                        "can't find source {:?} in enabled sources list",
-                        source
+                        "can't find source {} in enabled sources list",
+                        kind
                     )));
                 }
-            }
+            },
             None => {
-                for provider in &source_providers {
-                    debug!("going to sync {:?}", provider.get_source());
-                    tasks.push(provider.synchronize(secs_depth))
+                for source in &sources {
+                    debug!("going to sync {}", source.kind());
+                    tasks.push(source.provider.synchronize(secs_depth))
                 }
             }
         }
@@ -104,12 +269,12 @@ where
     }
 
     pub async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
-        let source_providers = self.get_enabled_sources();
+        let sources = self.sources();
         let mut tasks = vec![];
-        for provider in &source_providers {
+        for source in &sources {
             tasks.push(async move {
-                let found = provider.search_source(query).await;
-                debug!("{:?} found {:?}", provider.get_source(), found);
+                let found = source.provider.search_source(query).await;
+                debug!("{} found {:?}", source.kind(), found);
                 found
             })
         }
@@ -127,83 +292,89 @@ where
         Ok(results)
     }
 
-    fn get_enabled_sources(&self) -> Vec<Box<Arc<dyn SourceProvider + Send + Sync>>> {
-        let mut enabled: Vec<Box<Arc<dyn SourceProvider + Send + Sync>>> = vec![];
-        macro_rules! push_if_enabled {
-            ($source:expr) => {
-                match &$source {
-                    Some(source) => enabled.push(Box::new(source.clone())),
-                    None => {}
-                }
+    /// Runs providers and stores their updates until the shutdown.
+    ///
+    /// On shutdown providers are stopped first, then updates which are
+    /// already received are stored.
+    pub async fn run(&self, shutdown: CancellationToken) {
+        {
+            let mut running = Running {
+                shutdown: shutdown.clone(),
+                sources: HashMap::new(),
+            };
+            for source in self.sources() {
+                running.spawn(&self.supervisor, source, self.updates_sender.clone());
+            }
+            *self.running.lock().await = Some(running);
+        }
+        let mut updates_receiver = self.updates_receiver.lock().await;
+        loop {
+            let updates = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                updates = updates_receiver.recv() => updates,
             };
+            match updates {
+                Some(updates) => self.process_updates(updates).await,
+                None => break,
+            }
+        }
+        if let Some(running) = self.running.lock().await.take() {
+            join_all(running.sources.into_iter().map(|(_, (_, handle))| handle)).await;
         }
-        push_if_enabled!(self.http_source);
-        push_if_enabled!(self.tg_source);
-        push_if_enabled!(self.vk_source);
-        enabled
+        let mut drained = 0;
+        while let Ok(updates) = updates_receiver.try_recv() {
+            self.process_updates(updates).await;
+            drained += 1;
+        }
+        info!("stopped, {} pending updates stored", drained);
     }
 
-    pub async fn run(&self) {
-        macro_rules! run_source {
-            ($source:expr) => {
-                match &$source {
-                    None => {}
-                    Some(source) => {
-                        let s = source.clone();
-                        let sender = self.updates_sender.clone();
-                        s.run(sender).await.unwrap();
-                    }
-                }
-            };
-        }
-        run_source!(self.tg_source);
-        log::debug!("tg started");
-        run_source!(self.http_source);
-        log::debug!("http started");
-        run_source!(self.vk_source);
-        log::debug!("vk started");
-        self.process_updates().await;
+    /// States of the enabled sources loops.
+    pub async fn states(&self) -> Vec<SourceState> {
+        self.supervisor.states().await
     }
 
-    async fn process_updates(&self) {
-        loop {
-            while let Some(updates) = self.updates_receiver.lock().await.recv().await {
-                debug!("new updates: {:?}", updates);
-                let updates_result = match &updates {
-                    Ok(update) => match update {
-                        SourceData::WebFeed(feed_data) => match &self.http_source {
-                            None => {
-                                debug!("http source disabled");
-                                Ok(0)
-                            }
-                            Some(source) => source.process_updates(feed_data).await,
-                        },
-                        SourceData::Telegram(telegram_update) => match &self.tg_source {
-                            None => {
-                                debug!("http source disabled");
-                                Ok(0)
-                            }
-                            Some(source) => source.process_updates(telegram_update).await,
-                        },
-                        SourceData::Vk(vk_update) => match &self.vk_source {
-                            None => {
-                                debug!("vk source disabled");
-                                Ok(0)
-                            }
-                            Some(source) => source.process_updates(vk_update).await,
-                        },
-                    },
-                    Err(err) => Err(Error::DbError(err.to_string())),
-                };
-                match updates_result {
-                    Ok(ok_processed) => {
-                        debug!("processed updates: {}", ok_processed);
-                        trace!("updates: {:?}", updates);
-                    }
-                    Err(err) => {
-                        error!("{}", err);
+    async fn process_updates(&self, updates: Result<SourceData>) {
+        debug!("new updates: {:?}", updates);
+        if let Err(Error::SourceFailed {
+            kind,
+            origin,
+            error,
+        }) = &updates
+        {
+            error!("{} source {} failed: {}", kind, origin, error);
+            if let Err(e) = self
+                .storage
+                .record_source_failure(kind, origin.clone(), error)
+                .await
+            {
+                error!("can't store failure of {} source {}: {}", kind, origin, e);
+            }
+            return;
+        }
+        let updates_result = match &updates {
+            Ok(update) => match self.get_source(update.kind()) {
+                None => {
+                    debug!("{} source disabled", update.kind());
+                    Ok(0)
+                }
+                Some(source) => {
+                    let processed = source.handler.process_updates(update).await;
+                    if let Ok(inserted) = &processed {
+                        metrics::observe_records(update.kind(), *inserted);
                     }
+                    processed
                 }
+            },
+            Err(err) => Err(Error::DbError(err.to_string())),
+        };
+        match updates_result {
+            Ok(ok_processed) => {
+                debug!("processed updates: {}", ok_processed);
+                trace!("updates: {:?}", updates);
+            }
+            Err(err) => {
+                error!("{}", err);
             }
         }
     }
@@ -213,9 +384,7 @@ pub struct UpdatesHandlerBuilder<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
-    http_source: Option<Arc<http::HttpSource<S>>>,
-    tg_source: Option<Arc<tg::TelegramSource<S>>>,
-    vk_source: Option<Arc<vk::VkSource<S>>>,
+    sources: Vec<RegisteredSource>,
     storage: Option<S>,
 }
 
@@ -225,9 +394,7 @@ where
 {
     fn default() -> Self {
         Self {
-            http_source: None,
-            tg_source: None,
-            vk_source: None,
+            sources: vec![],
             storage: None,
         }
     }
@@ -241,18 +408,20 @@ where
         self.storage = Some(storage);
         self
     }
-    pub fn with_http_source(mut self, http_source: Arc<http::HttpSource<S>>) -> Self {
-        self.http_source = Some(http_source);
-        self
-    }
 
-    pub fn with_tg_source(mut self, tg_source: Arc<tg::TelegramSource<S>>) -> Self {
-        self.tg_source = Some(tg_source);
-        self
+    pub fn with_source<T, P>(self, provider: Arc<P>) -> Self
+    where
+        T: SourceUpdate,
+        P: SourceProvider + UpdatesHandler<T> + Send + Sync + 'static,
+    {
+        self.with_registered_source(RegisteredSource::new(provider))
     }
 
-    pub fn with_vk_source(mut self, vk_source: Arc<vk::VkSource<S>>) -> Self {
-        self.vk_source = Some(vk_source);
+    pub fn with_registered_source(mut self, source: RegisteredSource) -> Self {
+        if self.sources.iter().any(|s| s.kind() == source.kind()) {
+            panic!("source {} already registered", source.kind());
+        }
+        self.sources.push(source);
         self
     }
 
@@ -260,13 +429,21 @@ where
         if self.storage.is_none() {
             panic!("storage not passed");
         }
-        let (updates_sender, updates_receiver) = mpsc::channel::<Result<SourceData>>(2000);
+        let (updates_sender, updates_receiver) =
+            mpsc::channel::<Result<SourceData>>(UPDATES_QUEUE_SIZE);
+        let queue = updates_sender.clone();
+        metrics::register_probe(
+            "feeder_updates_queue_depth",
+            "Updates of the sources waiting to be stored",
+            move || Some((UPDATES_QUEUE_SIZE - queue.capacity()) as i64),
+        );
         let updates_sender = Arc::new(Mutex::new(updates_sender));
         let updates_receiver = Mutex::new(updates_receiver);
+        let kinds: Vec<&'static str> = self.sources.iter().map(RegisteredSource::kind).collect();
         SourcesAggregator {
-            http_source: self.http_source,
-            tg_source: self.tg_source,
-            vk_source: self.vk_source,
+            supervisor: Supervisor::new(&kinds),
+            sources: RwLock::new(self.sources),
+            running: Mutex::new(None),
             storage: self.storage.unwrap(),
             updates_sender,
             updates_receiver,
@@ -0,0 +1,442 @@
+use async_trait::async_trait;
+use reddit_collector::{
+    client::{ListingInfo, Page, RedditClient},
+    result::{Error as CollectorError, Result as RedditResult},
+    types::{Listing, MediaKind, Post, Subreddit},
+};
+
+use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::metrics;
+use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::queue;
+use crate::result::{Error, Result};
+use crate::schedule::ScrapeBounds;
+use crate::storage::Storage;
+
+use chrono::{NaiveDateTime, Utc};
+use futures::future::join_all;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+// TODO: enum?
+pub(crate) const REDDIT: &str = "REDDIT";
+
+/// Reddit source which reads subreddits (`r/<name>`) and user submissions (`u/<name>`)
+/// through public JSON listings.
+pub struct RedditSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_bounds: ScrapeBounds,
+    client: Arc<RedditClient>,
+    storage: S,
+    pipeline: Pipeline,
+}
+
+impl<S> RedditSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn builder() -> RedditSourceBuilder<S> {
+        RedditSourceBuilder::new()
+    }
+
+    async fn save_posts(&self, source: &models::Source, posts: &[Post]) -> Result<usize> {
+        let created = self
+            .pipeline
+            .save_records(
+                &self.storage,
+                REDDIT,
+                posts
+                    .iter()
+                    .map(|p| {
+                        let media = p.media();
+                        models::NewRecord {
+                            title: Some(p.title.clone()),
+                            source_record_id: p.id.clone(),
+                            source_id: source.id,
+                            meta: Some(post_meta(p)),
+                            content: match &p.selftext_html {
+                                Some(html) => html.clone(),
+                                None => format!("<a href=\"{0}\">{0}</a>", p.url),
+                            },
+                            date: Some(NaiveDateTime::from_timestamp(p.created_utc(), 0)),
+                            image: media
+                                .iter()
+                                .find(|m| m.kind == MediaKind::Image)
+                                .map(|m| m.url.clone()),
+                        }
+                    })
+                    .collect(),
+            )
+            .await?
+            .created;
+        for record in &created {
+            let post = match posts.iter().find(|p| p.id == record.source_record_id) {
+                None => continue,
+                Some(p) => p,
+            };
+            self.storage
+                .set_record_external_link(
+                    record.source_record_id.clone(),
+                    record.source_id,
+                    post.link(),
+                )
+                .await?;
+            let media = post.media();
+            if !media.is_empty() {
+                self.storage
+                    .save_files(
+                        media
+                            .into_iter()
+                            .map(|m| models::NewFile {
+                                record_id: record.id,
+                                kind: REDDIT.to_string(),
+                                local_path: None,
+                                remote_path: m.url,
+                                remote_id: None,
+                                file_name: None,
+                                type_: match m.kind {
+                                    MediaKind::Image => "IMAGE",
+                                    MediaKind::Animation => "ANIMATION",
+                                    MediaKind::Video => "VIDEO",
+                                }
+                                .to_string(),
+                                meta: m
+                                    .caption
+                                    .map(|c| serde_json::json!({ "caption": c }).to_string()),
+                            })
+                            .collect(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(created.len())
+    }
+}
+
+fn post_meta(post: &Post) -> String {
+    serde_json::json!({
+        "author": post.author,
+        "score": post.score,
+        "num_comments": post.num_comments,
+        "flair": post.link_flair_text,
+        "over_18": post.over_18,
+        "url": post.url,
+    })
+    .to_string()
+}
+
+pub struct RedditSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+    storage: Option<S>,
+    pipeline: Pipeline,
+}
+
+impl<S> Default for RedditSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> RedditSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+            storage: None,
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    pub fn with_sleep_secs(mut self, sleep_secs: u64) -> Self {
+        self.sleep_secs = sleep_secs;
+        self
+    }
+
+    pub fn with_scrape_source_secs_interval(mut self, scrape_source_secs_interval: u64) -> Self {
+        self.scrape_source_secs_interval = scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_max_scrape_source_secs_interval(
+        mut self,
+        max_scrape_source_secs_interval: u64,
+    ) -> Self {
+        self.max_scrape_source_secs_interval = max_scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(REDDIT);
+        self
+    }
+
+    pub fn build(self) -> RedditSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not specified")
+        }
+        RedditSource {
+            sleep_secs: self.sleep_secs,
+            scrape_bounds: ScrapeBounds::new(
+                self.scrape_source_secs_interval,
+                self.max_scrape_source_secs_interval,
+            ),
+            client: Arc::new(RedditClient::default()),
+            storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RedditUpdate {
+    pub listing: Listing,
+    pub posts: Vec<Post>,
+}
+
+impl From<Page> for RedditUpdate {
+    fn from(page: Page) -> Self {
+        Self {
+            listing: page.listing,
+            posts: page.posts,
+        }
+    }
+}
+
+impl From<&Listing> for models::NewSource {
+    fn from(listing: &Listing) -> Self {
+        models::NewSource {
+            name: listing.to_string(),
+            origin: listing.to_string(),
+            kind: REDDIT.to_string(),
+            image: None,
+            external_link: listing.link(),
+        }
+    }
+}
+
+impl From<&ListingInfo> for models::NewSource {
+    fn from(info: &ListingInfo) -> Self {
+        models::NewSource {
+            name: info.title.clone(),
+            image: info.icon.clone(),
+            ..(&info.listing).into()
+        }
+    }
+}
+
+impl From<&Subreddit> for models::NewSource {
+    fn from(subreddit: &Subreddit) -> Self {
+        let listing = Listing::Subreddit(subreddit.display_name.to_lowercase());
+        models::NewSource {
+            name: subreddit.title.clone(),
+            image: subreddit.icon(),
+            ..(&listing).into()
+        }
+    }
+}
+
+#[async_trait]
+impl<S> UpdatesHandler<RedditUpdate> for RedditSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn create_source(&self, updates: &RedditUpdate) -> Result<models::Source> {
+        Ok(self
+            .storage
+            .save_sources(vec![(&updates.listing).into()])
+            .await?
+            .pop()
+            .unwrap())
+    }
+
+    async fn process_updates(&self, updates: &RedditUpdate) -> Result<usize> {
+        let source = match self
+            .storage
+            .get_exact_source(REDDIT.to_string(), updates.listing.to_string())
+            .await?
+        {
+            None => self.create_source(updates).await?,
+            Some(s) => s,
+        };
+        let affected = self.save_posts(&source, &updates.posts).await?;
+        self.storage
+            .schedule_source(source, self.scrape_bounds)
+            .await?;
+        Ok(affected)
+    }
+}
+
+#[async_trait]
+impl<S> SourceProvider for RedditSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn kind(&self) -> &'static str {
+        REDDIT
+    }
+
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        let (sources_sender, sources_receiver) = mpsc::channel(2000);
+
+        let sleep_secs = self.sleep_secs;
+        let st = self.storage.clone();
+        let sources_gen_handle =
+            tokio::spawn(async move { sources_gen(st, sleep_secs, sources_sender).await });
+
+        let cl = self.client.clone();
+        let handler = Handler::new(updates_sender);
+        let scrapper_handle =
+            tokio::spawn(async move { run_scrapper(cl.as_ref(), sources_receiver, handler).await });
+        Ok(vec![sources_gen_handle, scrapper_handle])
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        // exact `r/<name>` or `u/<name>`, full text search over subreddits otherwise
+        if let Ok(listing) = Listing::parse(query) {
+            let info = match self.client.get_info(&listing).await {
+                Ok(info) => info,
+                Err(CollectorError::NotFound) | Err(CollectorError::Forbidden) => {
+                    return Ok(vec![])
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let source = self
+                .storage
+                .save_sources(vec![(&info).into()])
+                .await?
+                .pop()
+                .unwrap();
+            match self.client.get_posts(&listing, None).await {
+                Ok(page) => {
+                    if let Err(e) = self.save_posts(&source, &page.posts).await {
+                        error!("{:?}", e)
+                    }
+                }
+                Err(e) => error!("{:?}", e),
+            }
+            return Ok(vec![source]);
+        }
+        let subreddits = self.client.search_subreddits(query, 20).await?;
+        let mut sources = vec![];
+        for subreddit in subreddits {
+            match self.storage.save_sources(vec![(&subreddit).into()]).await {
+                Ok(s) => sources.extend(s),
+                Err(e) => error!("{:?}", e),
+            }
+        }
+        Ok(sources)
+    }
+
+    async fn synchronize(&self, secs_depth: i32) -> Result<()> {
+        debug!("start syncing {}", self.kind());
+        let until = Utc::now().timestamp() - i64::from(secs_depth);
+        let sources = self.storage.get_sources_by_kind(REDDIT.to_string()).await?;
+        for source in sources {
+            let listing = Listing::parse(source.origin.as_str())?;
+            let posts = self.client.get_history(&listing, until).await?;
+            debug!("get {} posts for {}", posts.len(), source.name);
+            self.save_posts(&source, &posts).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn get_posts_for_source(client: &RedditClient, origin: String, handler: &Handler) {
+    let started = time::Instant::now();
+    let update = match Listing::parse(origin.as_str()) {
+        Ok(listing) => client.get_posts(&listing, None).await,
+        Err(e) => Err(e),
+    };
+    handler.process(origin, update, started.elapsed()).await;
+}
+
+// TODO: generic scrapper. trait?
+async fn run_scrapper(
+    client: &RedditClient,
+    mut sources_receiver: mpsc::Receiver<Vec<String>>,
+    handler: Handler,
+) {
+    while let Some(sources) = sources_receiver.recv().await {
+        let mut tasks = vec![];
+        for source in sources {
+            tasks.push(get_posts_for_source(client, source, &handler));
+        }
+        join_all(tasks).await;
+    }
+}
+
+// TODO: generic generator
+async fn sources_gen<S: Storage>(storage: S, sleep_period: u64, sender: mpsc::Sender<Vec<String>>) {
+    let sleep_period = time::Duration::from_secs(sleep_period);
+    loop {
+        match get_sources(&storage).await {
+            Ok(sources) => {
+                debug!("found sources for scrape: {:?}", sources);
+                if let Err(err) = sender.send(sources).await {
+                    error!("{}", err)
+                };
+            }
+            Err(e) => error!("{}", e),
+        };
+
+        debug!("send sources delayed for {:?}", sleep_period);
+        tokio::time::sleep(sleep_period).await;
+    }
+}
+
+async fn get_sources<S: Storage>(storage: &S) -> Result<Vec<String>> {
+    Ok(storage
+        .claim_sources_for_scrape(REDDIT.to_string(), &queue::Lease::now())
+        .await?
+        .iter()
+        .map(|r| r.origin.clone())
+        .collect())
+}
+
+struct Handler {
+    sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+}
+
+impl Handler {
+    pub fn new(sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>) -> Self {
+        Self { sender }
+    }
+
+    async fn process(&self, origin: String, result: RedditResult<Page>, elapsed: time::Duration) {
+        let update = match result {
+            Ok(page) => Ok(SourceData::new(REDDIT, RedditUpdate::from(page))),
+            Err(err) => Err(Error::source_failed(REDDIT, origin, err)),
+        };
+        metrics::observe_scrape(REDDIT, update.as_ref().err(), elapsed);
+        let local = self.sender.lock().await;
+        if local.send(update).await.is_err() {
+            error!("updates receiver dropped");
+        }
+    }
+}
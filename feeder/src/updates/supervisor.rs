@@ -0,0 +1,267 @@
+// keeps loops of the providers running until shutdown
+use super::{RegisteredSource, SourceData};
+use crate::result::Result;
+use chrono::{NaiveDateTime, Utc};
+use futures::future::select_all;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LoopState {
+    Starting,
+    Running,
+    // crashed, waits for the restart delay
+    Restarting,
+    Stopped,
+}
+
+/// State of the source provider loops.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceState {
+    pub kind: &'static str,
+    pub state: LoopState,
+    // running loops of the provider
+    pub loops: usize,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub changed_at: NaiveDateTime,
+}
+
+impl SourceState {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            state: LoopState::Stopped,
+            loops: 0,
+            restarts: 0,
+            last_error: None,
+            changed_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// Owns loops of the providers and restarts them with backoff when any of them stops.
+///
+/// Loops of the provider are started together by `SourceProvider::run`,
+/// so all of them are aborted and started again.
+#[derive(Clone)]
+pub struct Supervisor {
+    states: Arc<RwLock<HashMap<&'static str, SourceState>>>,
+}
+
+impl Supervisor {
+    pub fn new(kinds: &[&'static str]) -> Self {
+        Self {
+            states: Arc::new(RwLock::new(
+                kinds
+                    .iter()
+                    .map(|kind| (*kind, SourceState::new(kind)))
+                    .collect(),
+            )),
+        }
+    }
+
+    pub async fn states(&self) -> Vec<SourceState> {
+        let mut states: Vec<SourceState> = self.states.read().await.values().cloned().collect();
+        states.sort_by_key(|s| s.kind);
+        states
+    }
+
+    /// Starts supervising of the source, returned handle finishes after
+    /// the shutdown once loops of the source are aborted.
+    pub fn spawn(
+        &self,
+        source: RegisteredSource,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
+        let supervisor = self.clone();
+        tokio::spawn(async move { supervisor.supervise(source, updates_sender, shutdown).await })
+    }
+
+    async fn supervise(
+        &self,
+        source: RegisteredSource,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+        shutdown: CancellationToken,
+    ) {
+        let kind = source.kind();
+        let mut restart_delay = MIN_RESTART_DELAY;
+        loop {
+            self.set_state(kind, LoopState::Starting, 0, None).await;
+            let started_at = Instant::now();
+            let started = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                started = source.provider.run(updates_sender.clone()) => started,
+            };
+            let stop_reason = match started {
+                Ok(mut handles) if !handles.is_empty() => {
+                    debug!("{} started {} loops", kind, handles.len());
+                    self.set_state(kind, LoopState::Running, handles.len(), None)
+                        .await;
+                    let stopped = tokio::select! {
+                        _ = shutdown.cancelled() => None,
+                        (stopped, _, _) = select_all(handles.iter_mut()) => Some(stopped),
+                    };
+                    handles.iter().for_each(JoinHandle::abort);
+                    match stopped {
+                        Some(stopped) => describe_stop(stopped),
+                        None => break,
+                    }
+                }
+                // nothing to supervise
+                Ok(_) => {
+                    self.set_state(kind, LoopState::Running, 0, None).await;
+                    shutdown.cancelled().await;
+                    break;
+                }
+                Err(e) => format!("can't start: {}", e),
+            };
+            // loops worked for a while, it's a new failure
+            if started_at.elapsed() > MAX_RESTART_DELAY {
+                restart_delay = MIN_RESTART_DELAY;
+            }
+            error!(
+                "{} stopped: {}, restarting in {:?}",
+                kind, stop_reason, restart_delay
+            );
+            self.set_state(kind, LoopState::Restarting, 0, Some(stop_reason))
+                .await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(restart_delay) => {}
+            }
+            restart_delay = (restart_delay * 2).min(MAX_RESTART_DELAY);
+        }
+        self.set_state(kind, LoopState::Stopped, 0, None).await;
+        info!("{} stopped", kind);
+    }
+
+    async fn set_state(
+        &self,
+        kind: &'static str,
+        state: LoopState,
+        loops: usize,
+        error: Option<String>,
+    ) {
+        let mut states = self.states.write().await;
+        let source_state = states.entry(kind).or_insert_with(|| SourceState::new(kind));
+        if state == LoopState::Restarting {
+            source_state.restarts += 1;
+        }
+        if error.is_some() {
+            source_state.last_error = error;
+        }
+        source_state.state = state;
+        source_state.loops = loops;
+        source_state.changed_at = Utc::now().naive_utc();
+    }
+}
+
+fn describe_stop(stopped: std::result::Result<(), JoinError>) -> String {
+    match stopped {
+        Ok(()) => "loop finished".to_string(),
+        Err(e) if e.is_panic() => {
+            let panic = e.into_panic();
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|m| m.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            format!("loop panicked: {}", message)
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoopState, Supervisor, MIN_RESTART_DELAY};
+    use crate::models;
+    use crate::result::Result;
+    use crate::updates::{RegisteredSource, SourceData, SourceProvider, UpdatesHandler};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, Mutex};
+    use tokio::task::JoinHandle;
+    use tokio_util::sync::CancellationToken;
+
+    // its first loop finishes at once, loops of the next runs keep running
+    #[derive(Default)]
+    struct Flaky {
+        runs: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SourceProvider for Flaky {
+        fn kind(&self) -> &'static str {
+            "FLAKY"
+        }
+
+        async fn run(
+            &self,
+            _updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+        ) -> Result<Vec<JoinHandle<()>>> {
+            let first = self.runs.fetch_add(1, Ordering::SeqCst) == 0;
+            Ok(vec![tokio::spawn(async move {
+                if !first {
+                    futures::future::pending::<()>().await;
+                }
+            })])
+        }
+
+        async fn search_source(&self, _query: &str) -> Result<Vec<models::Source>> {
+            Ok(vec![])
+        }
+
+        async fn synchronize(&self, _secs_depth: i32) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl UpdatesHandler<()> for Flaky {
+        async fn create_source(&self, _updates: &()) -> Result<models::Source> {
+            unimplemented!()
+        }
+
+        async fn process_updates(&self, _updates: &()) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restart() {
+        let provider = Arc::new(Flaky::default());
+        let supervisor = Supervisor::new(&["FLAKY"]);
+        let (sender, _receiver) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+        let handle = supervisor.spawn(
+            RegisteredSource::new::<(), _>(provider.clone()),
+            Arc::new(Mutex::new(sender)),
+            shutdown.clone(),
+        );
+
+        tokio::time::sleep(MIN_RESTART_DELAY * 2).await;
+        assert_eq!(provider.runs.load(Ordering::SeqCst), 2);
+        let state = supervisor.states().await.remove(0);
+        assert_eq!(state.state, LoopState::Running);
+        assert_eq!(state.loops, 1);
+        assert_eq!(state.restarts, 1);
+        assert_eq!(state.last_error.as_deref(), Some("loop finished"));
+
+        shutdown.cancel();
+        handle.await.unwrap();
+        assert_eq!(supervisor.states().await[0].state, LoopState::Stopped);
+        assert_eq!(provider.runs.load(Ordering::SeqCst), 2);
+    }
+}
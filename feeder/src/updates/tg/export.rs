@@ -0,0 +1,227 @@
+use super::source::{file_type_with_meta, TELEGRAM};
+use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::result::Result;
+use crate::storage::Storage;
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+use tg_collector::export::{parse_export, ExportChat, ExportMessage};
+
+/// Imports channel history from Telegram Desktop exports (`result.json`).
+///
+/// Works without tdlib session: channels are matched by chat id and messages keep
+/// tdlib ids, so already imported messages aren't duplicated by later live updates.
+pub struct TelegramExportImporter<S>
+where
+    S: Storage + Send + Sync,
+{
+    storage: S,
+    pipeline: Pipeline,
+    files_directory: String,
+}
+
+impl<S> TelegramExportImporter<S>
+where
+    S: Storage + Send + Sync,
+{
+    pub fn new(storage: S, files_directory: &str) -> Self {
+        Self {
+            storage,
+            pipeline: Pipeline::default(),
+            files_directory: files_directory.to_string(),
+        }
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(TELEGRAM);
+        self
+    }
+
+    /// `path` is `result.json` or the export directory, returns the number of created records.
+    pub async fn import(&self, path: &Path) -> Result<usize> {
+        let result_path = match path.is_dir() {
+            true => path.join("result.json"),
+            false => path.to_path_buf(),
+        };
+        let export_directory = result_path.parent().unwrap_or_else(|| Path::new("."));
+        let export_directory = tokio::fs::canonicalize(export_directory).await?;
+        let data = tokio::fs::read_to_string(&result_path).await?;
+        let mut created = 0;
+        for chat in parse_export(data.as_str())? {
+            if !chat.is_channel() {
+                info!("skip {:?}, only channels are imported", chat.name);
+                continue;
+            }
+            let source = self.get_or_create_source(&chat).await?;
+            let mut chat_created = 0;
+            for message in &chat.messages {
+                chat_created += self
+                    .import_message(&source, &export_directory, message)
+                    .await?;
+            }
+            info!("{} messages imported to {}", chat_created, source.name);
+            created += chat_created;
+        }
+        Ok(created)
+    }
+
+    async fn get_or_create_source(&self, chat: &ExportChat) -> Result<models::Source> {
+        let origin = chat.chat_id().to_string();
+        if let Some(source) = self
+            .storage
+            .get_exact_source(TELEGRAM.to_string(), origin.clone())
+            .await?
+        {
+            return Ok(source);
+        }
+        // username isn't exported, it's set when the channel is found by the live session
+        Ok(self
+            .storage
+            .save_sources(vec![models::NewSource {
+                name: chat.name.clone().unwrap_or_else(|| origin.clone()),
+                origin,
+                kind: TELEGRAM.to_string(),
+                image: None,
+                external_link: "".to_string(),
+            }])
+            .await?
+            .pop()
+            .unwrap())
+    }
+
+    async fn import_message(
+        &self,
+        source: &models::Source,
+        export_directory: &Path,
+        message: &ExportMessage,
+    ) -> Result<usize> {
+        if message.is_service() {
+            return Ok(0);
+        }
+        let content = message.content();
+        let files = message.files();
+        if content.is_none() && files.is_empty() {
+            return Ok(0);
+        }
+        let source_record_id = message.message_id().to_string();
+        if self
+            .storage
+            .get_record(source.id, source_record_id.clone())
+            .await?
+            .is_some()
+        {
+            return Ok(0);
+        }
+        let record = match self
+            .pipeline
+            .save_records(
+                &self.storage,
+                TELEGRAM,
+                vec![models::NewRecord {
+                    title: None,
+                    image: None,
+                    date: message
+                        .timestamp()
+                        .map(|t| NaiveDateTime::from_timestamp(t, 0)),
+                    source_record_id: source_record_id.clone(),
+                    source_id: source.id,
+                    meta: None,
+                    content: content.unwrap_or_default(),
+                }],
+            )
+            .await?
+            .created
+            .pop()
+        {
+            Some(record) => record,
+            None => return Ok(0),
+        };
+        if !source.external_link.is_empty() {
+            self.storage
+                .set_record_external_link(
+                    source_record_id,
+                    source.id,
+                    format!("https://t.me/{}/{}", source.external_link, message.id),
+                )
+                .await?;
+        }
+
+        let mut db_files = vec![];
+        for file in files {
+            let exported_path = match exported_file(export_directory, file.path.as_str()).await {
+                Some(exported_path) => exported_path,
+                None => {
+                    warn!("skip exported file {:?} outside of the export", file.path);
+                    continue;
+                }
+            };
+            let file_name = match exported_path.file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let local_path = Path::new(self.files_directory.as_str())
+                .join(format!("tg_{}_{}_{}", source.origin, message.id, file_name));
+            if let Err(e) = tokio::fs::copy(&exported_path, &local_path).await {
+                warn!("can't copy exported file {:?}: {}", exported_path, e);
+                continue;
+            }
+            let (type_, meta) = file_type_with_meta(&file.file_type);
+            db_files.push(models::NewFile {
+                kind: TELEGRAM.to_string(),
+                local_path: local_path.to_str().map(String::from),
+                remote_path: file.path,
+                // tdlib ids aren't exported
+                remote_id: None,
+                file_name: file.file_name,
+                record_id: record.id,
+                type_,
+                meta,
+            })
+        }
+        if !db_files.is_empty() {
+            self.storage.save_files(db_files).await?;
+        }
+        Ok(1)
+    }
+}
+
+// paths of `result.json` are relative to the export directory, the ones leading
+// out of it (absolute, `..` or symlinks) aren't copied; `None` for missing files too
+async fn exported_file(export_directory: &Path, path: &str) -> Option<PathBuf> {
+    if Path::new(path).is_absolute() {
+        return None;
+    }
+    tokio::fs::canonicalize(export_directory.join(path))
+        .await
+        .ok()
+        .filter(|p| p.starts_with(export_directory))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::exported_file;
+
+    #[tokio::test]
+    async fn test_exported_file() {
+        let root = std::env::temp_dir().join(format!("feeder_export_{}", std::process::id()));
+        let export = root.join("export");
+        std::fs::create_dir_all(export.join("photos")).unwrap();
+        std::fs::write(export.join("photos").join("1.jpg"), "").unwrap();
+        std::fs::write(root.join("secret"), "").unwrap();
+        let export = export.canonicalize().unwrap();
+
+        assert_eq!(
+            exported_file(&export, "photos/1.jpg").await,
+            Some(export.join("photos").join("1.jpg"))
+        );
+        assert_eq!(exported_file(&export, "../secret").await, None);
+        assert_eq!(exported_file(&export, "photos/../../secret").await, None);
+        let absolute = root.join("secret");
+        assert_eq!(
+            exported_file(&export, absolute.to_str().unwrap()).await,
+            None
+        );
+        assert_eq!(exported_file(&export, "photos/2.jpg").await, None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
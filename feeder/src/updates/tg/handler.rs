@@ -1,16 +1,18 @@
-use super::CloneableBoxedParser;
+use super::source::TELEGRAM;
+use super::{AccountUpdate, CloneableBoxedParser};
 use crate::result::{Error, Result};
 use crate::updates::SourceData;
 use std::sync::Arc;
 use tg_collector::tg_client::{TgClient, TgUpdate};
 use tokio::sync::{mpsc, Mutex, RwLock};
-use tokio::task::spawn;
+use tokio::task::{spawn, JoinHandle};
 
 /// Handler interacts with tdlib using `tg_collector` crate.
 /// It initializes updates listener and pass all updates from `tg_collector` to specified sender
 
 #[derive(Clone)]
 pub struct Handler {
+    account: String,
     sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
     tg: Arc<RwLock<TgClient>>,
     orig_sender: mpsc::Sender<TgUpdate>,
@@ -21,6 +23,7 @@ pub struct Handler {
 impl Handler {
     /// Creates new Handler with specified
     pub fn new(
+        account: String,
         sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
         tg: Arc<RwLock<TgClient>>,
         parser: CloneableBoxedParser,
@@ -28,6 +31,7 @@ impl Handler {
         // TODO: configure channel size
         let (orig_sender, orig_receiver) = mpsc::channel::<TgUpdate>(2000);
         Self {
+            account,
             sender,
             tg,
             orig_sender,
@@ -36,7 +40,8 @@ impl Handler {
         }
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    /// Starts the client, returns handle of the updates loop.
+    pub async fn run(&mut self) -> Result<JoinHandle<()>> {
         let mut guard = self.tg.write().await;
         let handle = match guard.start().await {
             Ok(h) => h,
@@ -45,14 +50,21 @@ impl Handler {
         let recv = self.orig_receiver.clone();
         let sender = self.sender.clone();
         let parser = self.parser.clone();
-        spawn(async move {
+        let account = self.account.clone();
+        Ok(spawn(async move {
             tokio::select! {
-                h = handle => info!("telegram client closed: {:?}", h),
+                h = handle => info!("telegram client of {} closed: {:?}", account, h),
                 _ = async {
                     loop {
                         while let Some(update) = recv.lock().await.recv().await {
                             let parsed_update = match parser.parse_update(&update).await {
-                                Ok(Some(update)) => Ok(SourceData::Telegram(update)),
+                                Ok(Some(update)) => Ok(SourceData::new(
+                                    TELEGRAM,
+                                    AccountUpdate {
+                                        account: account.clone(),
+                                        update,
+                                    },
+                                )),
                                 Err(e) => Err(Error::TgCollectorError(e)),
 
                                 Ok(None) => continue,
@@ -66,7 +78,6 @@ impl Handler {
                     }
                 } => {info!("updates loop closed")}
             };
-        });
-        Ok(())
+        }))
     }
 }
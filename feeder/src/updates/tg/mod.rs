@@ -1,11 +1,13 @@
+pub use export::*;
 pub use source::*;
 pub use source_provider::*;
 use std::sync::Arc;
 use tg_collector::parsers;
 use tg_collector::types::Channel;
-pub use tg_collector::types::TelegramUpdate;
 pub use updates_handler::*;
 
+// Telegram Desktop exports importer
+mod export;
 // module reads from tdlib stream and pass updates to common app stream
 mod handler;
 // telegram source struct and methods
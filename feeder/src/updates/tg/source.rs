@@ -1,8 +1,12 @@
 use super::CloneableBoxedParser;
+use crate::config::DEFAULT_ACCOUNT;
+use crate::metrics;
 /// Telegram source struct and builder
 use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
 use crate::result::{Error, Result};
 use crate::storage::Storage;
+use std::ffi::OsString;
 use std::path::Path;
 use std::sync::Arc;
 use tg_collector::tg_client::{ApiId, TgClient};
@@ -10,7 +14,7 @@ use tg_collector::types::FileType;
 use tg_collector::types::{TelegramFile, TelegramFileWithMeta};
 use tokio::sync::RwLock;
 
-pub(super) const TELEGRAM: &str = "TELEGRAM";
+pub(crate) const TELEGRAM: &str = "TELEGRAM";
 
 pub struct TelegramSourceBuilder<S>
 where
@@ -27,6 +31,9 @@ where
     log_download_state_secs_interval: u64,
     files_directory: String,
     storage: Option<S>,
+    pipeline: Pipeline,
+    // name, phone number, database directory and encryption key of the other accounts
+    accounts: Vec<(String, String, String, String)>,
 }
 
 impl<S> TelegramSourceBuilder<S>
@@ -54,6 +61,8 @@ where
             encryption_key: "".to_string(),
             database_directory: "tdlib".to_string(),
             storage: None,
+            pipeline: Pipeline::default(),
+            accounts: vec![],
         }
     }
 
@@ -62,6 +71,11 @@ where
         self
     }
 
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(TELEGRAM);
+        self
+    }
+
     pub fn with_parser(mut self, parser: CloneableBoxedParser) -> Self {
         self.parser = parser;
         self
@@ -77,36 +91,117 @@ where
         self
     }
 
-    pub fn build(self) -> TelegramSource<S> {
-        if self.storage.is_none() {
-            panic!("storage not set")
-        }
+    /// Adds the session of one more account, every session needs its own database.
+    pub fn with_account(
+        mut self,
+        name: &str,
+        phone_number: &str,
+        database_directory: &str,
+        encryption_key: &str,
+    ) -> Self {
+        self.accounts.push((
+            name.to_string(),
+            phone_number.to_string(),
+            database_directory.to_string(),
+            encryption_key.to_string(),
+        ));
+        self
+    }
+
+    fn account(
+        &self,
+        name: String,
+        phone_number: String,
+        database_directory: String,
+        encryption_key: String,
+    ) -> TelegramAccount {
         let client = TgClient::builder()
             .with_log_verbosity_level(self.log_verbosity_level)
-            .with_database_directory(self.database_directory)
+            .with_database_directory(database_directory)
             .with_api_id(self.api_id)
-            .with_api_hash(self.api_hash)
+            .with_api_hash(self.api_hash.clone())
             .with_log_download_state_secs_interval(self.log_download_state_secs_interval)
-            .with_encryption_key(self.encryption_key)
-            .with_phone_number(self.phone_number)
+            .with_encryption_key(encryption_key)
+            .with_phone_number(phone_number)
             .with_max_download_queue_size(self.max_download_queue_size)
             .build()
             .unwrap();
-        TelegramSource {
+        TelegramAccount {
+            name,
             collector: Arc::new(RwLock::new(client)),
+        }
+    }
+
+    pub fn build(self) -> TelegramSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not set")
+        }
+        let mut accounts = vec![self.account(
+            DEFAULT_ACCOUNT.to_string(),
+            self.phone_number.clone(),
+            self.database_directory.clone(),
+            self.encryption_key.clone(),
+        )];
+        for (name, phone_number, database_directory, encryption_key) in self.accounts.iter() {
+            accounts.push(self.account(
+                name.clone(),
+                phone_number.clone(),
+                database_directory.clone(),
+                encryption_key.clone(),
+            ));
+        }
+        let collectors: Vec<_> = accounts.iter().map(|a| a.collector.clone()).collect();
+        metrics::register_probe(
+            "feeder_tg_downloads_in_progress",
+            "Telegram files downloaded now",
+            move || {
+                let mut in_progress = 0;
+                for collector in collectors.iter() {
+                    in_progress += collector
+                        .try_read()
+                        .ok()?
+                        .download_queue_state()
+                        .in_progress;
+                }
+                Some(in_progress as i64)
+            },
+        );
+        let collectors: Vec<_> = accounts.iter().map(|a| a.collector.clone()).collect();
+        metrics::register_probe(
+            "feeder_tg_downloads_queued",
+            "Telegram files waiting for the download",
+            move || {
+                let mut queued = 0;
+                for collector in collectors.iter() {
+                    queued += collector.try_read().ok()?.download_queue_state().queued;
+                }
+                Some(queued as i64)
+            },
+        );
+        TelegramSource {
+            accounts,
             files_directory: self.files_directory.clone(),
             storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
             parser: self.parser,
         }
     }
 }
+/// Session of a telegram account, the main one is `DEFAULT_ACCOUNT`.
+pub(super) struct TelegramAccount {
+    pub(super) name: String,
+    pub(super) collector: Arc<RwLock<TgClient>>,
+}
+
 pub struct TelegramSource<S>
 where
     S: Storage + Send + Sync,
 {
-    pub(super) collector: Arc<RwLock<TgClient>>,
+    // the main account goes first
+    pub(super) accounts: Vec<TelegramAccount>,
     pub(super) files_directory: String,
     pub(super) storage: S,
+    pub(super) pipeline: Pipeline,
     pub(super) parser: CloneableBoxedParser,
 }
 
@@ -134,6 +229,13 @@ where
         )
     }
 
+    pub(super) fn account(&self, name: &str) -> Result<&TelegramAccount> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| Error::UpdateNotSupported(format!("unknown telegram account {}", name)))
+    }
+
     /// Handles new `TelegramFile`.
     ///
     /// Here is `TelegramFile` lifecycle:
@@ -144,6 +246,7 @@ where
 
     pub(super) async fn handle_new_files(
         &self,
+        account: &TelegramAccount,
         files: &[TelegramFileWithMeta],
         record_id: i32,
     ) -> Result<()> {
@@ -151,25 +254,8 @@ where
             .iter()
             .filter(|f| self.file_may_be_download(f))
             .map(|file| {
-                let meta: Option<String>;
-                let type_: String;
-
                 // TODO add posibility to disable particular types from config
-                match &file.file_type {
-                    FileType::Document => {
-                        type_ = "DOCUMENT".to_string();
-                        meta = None;
-                    }
-                    FileType::Animation(animation_meta) => {
-                        type_ = "ANIMATION".to_string();
-                        meta = serde_json::to_string(animation_meta).ok();
-                    }
-                    FileType::Image(image_meta) => {
-                        type_ = "IMAGE".to_string();
-                        meta = serde_json::to_string(image_meta).ok();
-                    }
-                    _ => panic!("invalid file type passed"),
-                };
+                let (type_, meta) = file_type_with_meta(&file.file_type);
                 models::NewFile {
                     kind: TELEGRAM.to_string(),
                     local_path: file.path.local_path.clone(),
@@ -184,7 +270,7 @@ where
             .collect();
         self.storage.save_files(db_files).await?;
         for f in files {
-            match self
+            match account
                 .collector
                 .write()
                 .await
@@ -209,7 +295,11 @@ where
         // }
     }
 
-    pub(super) async fn handle_file_downloaded(&self, file: &TelegramFile) -> Result<()> {
+    pub(super) async fn handle_file_downloaded(
+        &self,
+        account: &TelegramAccount,
+        file: &TelegramFile,
+    ) -> Result<()> {
         let db_file = self
             .storage
             .get_file_by_remote_id(file.remote_id.clone())
@@ -217,15 +307,24 @@ where
         match db_file {
             None => warn!("unknown telegram file: {:?}", file),
             Some(mut db_file) => {
-                let file_name = Path::new(file.local_path.as_str()).file_name().unwrap();
+                let file_name = Path::new(file.local_path.as_str())
+                    .file_name()
+                    .unwrap()
+                    .to_os_string();
+                // sessions name their files alike
+                let file_name = if account.name == DEFAULT_ACCOUNT {
+                    file_name
+                } else {
+                    let mut prefixed = OsString::from(format!("{}_", account.name));
+                    prefixed.push(file_name);
+                    prefixed
+                };
                 let new_path = Path::new(self.files_directory.as_str()).join(&file_name);
                 tokio::fs::rename(&file.local_path, &new_path).await?;
                 // TODO: cross-platform?
                 db_file.local_path = Some(new_path.into_os_string().into_string().unwrap());
                 match db_file.file_name {
-                    None => {
-                        db_file.file_name = Some(file_name.to_os_string().into_string().unwrap())
-                    }
+                    None => db_file.file_name = Some(file_name.into_string().unwrap()),
                     Some(_) => {}
                 }
                 self.storage.save_file(db_file).await?;
@@ -236,6 +335,7 @@ where
 
     pub(super) async fn handle_record_inserted(
         &self,
+        account: &TelegramAccount,
         chat_id: i64,
         message_id: i64,
         created: Vec<(String, i32)>,
@@ -243,7 +343,7 @@ where
         match created.len() {
             0 => Ok(0),
             1 => {
-                let message_link = self
+                let message_link = account
                     .collector
                     .read()
                     .await
@@ -262,3 +362,23 @@ where
         }
     }
 }
+
+/// Type and json meta of the file as they're stored in `files`.
+pub(super) fn file_type_with_meta(file_type: &FileType) -> (String, Option<String>) {
+    match file_type {
+        FileType::Document => ("DOCUMENT".to_string(), None),
+        FileType::Animation(animation_meta) => (
+            "ANIMATION".to_string(),
+            serde_json::to_string(animation_meta).ok(),
+        ),
+        FileType::Image(image_meta) => {
+            ("IMAGE".to_string(), serde_json::to_string(image_meta).ok())
+        }
+        FileType::Video(video_meta) => {
+            ("VIDEO".to_string(), serde_json::to_string(video_meta).ok())
+        }
+        FileType::Audio(audio_meta) => {
+            ("AUDIO".to_string(), serde_json::to_string(audio_meta).ok())
+        }
+    }
+}
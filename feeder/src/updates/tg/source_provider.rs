@@ -1,9 +1,10 @@
 use super::handler::Handler;
+use super::source::TELEGRAM;
 use crate::models;
 use crate::result::{Error, Result};
 use crate::storage::Storage;
-use crate::updates::tg::TelegramSource;
-use crate::updates::{Source, SourceData, SourceProvider};
+use crate::updates::tg::{TelegramAccount, TelegramSource};
+use crate::updates::{SourceData, SourceProvider};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use std::collections::HashMap;
@@ -12,6 +13,7 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tg_collector::tg_client::TgClient;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tokio_stream::StreamExt;
 
 #[async_trait]
@@ -19,22 +21,30 @@ impl<S> SourceProvider for TelegramSource<S>
 where
     S: Storage + Send + Sync,
 {
-    fn get_source(&self) -> Source {
-        Source::Telegram
+    fn kind(&self) -> &'static str {
+        TELEGRAM
     }
 
     async fn run(
         &self,
         updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
-    ) -> Result<()> {
-        let mut tg_handler =
-            Handler::new(updates_sender, self.collector.clone(), self.parser.clone());
-        tg_handler.run().await?;
-        Ok(())
+    ) -> Result<Vec<JoinHandle<()>>> {
+        let mut handles = vec![];
+        for account in self.accounts.iter() {
+            let mut tg_handler = Handler::new(
+                account.name.clone(),
+                updates_sender.clone(),
+                account.collector.clone(),
+                self.parser.clone(),
+            );
+            handles.push(tg_handler.run().await?);
+        }
+        Ok(handles)
     }
 
     async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
-        let channels = self
+        // public chats are seen by any account
+        let channels = self.accounts[0]
             .collector
             .read()
             .await
@@ -52,9 +62,22 @@ where
     }
 
     async fn synchronize(&self, secs_depth: i32) -> Result<()> {
-        debug!("start syncing {:?}", self.get_source());
+        debug!("start syncing {}", self.kind());
+        for account in self.accounts.iter() {
+            debug!("syncing channels of telegram account {}", account.name);
+            self.synchronize_account(account, secs_depth).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> TelegramSource<S>
+where
+    S: Storage + Send + Sync,
+{
+    async fn synchronize_account(&self, account: &TelegramAccount, secs_depth: i32) -> Result<()> {
         let channels = {
-            let cr = self.collector.read().await;
+            let cr = account.collector.read().await;
             trace!("lock acquired");
             cr.get_all_channels(1000).await?
         };
@@ -76,9 +99,13 @@ where
                 .await?
                 .pop()
                 .unwrap();
+            let source = match self.claim_source(source, &account.name).await? {
+                Some(source) => source,
+                None => continue,
+            };
 
             let mut messages_stream = Box::pin(TgClient::get_chat_history_stream(
-                self.collector.clone(),
+                account.collector.clone(),
                 chat_id,
                 until.as_secs().try_into().unwrap(),
             ));
@@ -94,6 +121,7 @@ where
                                 title: None,
                                 source_record_id: message.id().to_string(),
                                 source_id: source.id,
+                                meta: None,
                                 content: c,
                                 date: Some(NaiveDateTime::from_timestamp(
                                     message.date().try_into().unwrap(),
@@ -125,14 +153,18 @@ where
 
             debug!("get {} records for {}", parsed_records.len(), source.name);
 
-            let records = self.storage.save_records(parsed_records).await?;
+            let records = self
+                .pipeline
+                .save_records(&self.storage, TELEGRAM, parsed_records)
+                .await?
+                .created;
             for rec in &records {
                 let rec_files =
                     files_by_rec.get(&(rec.source_record_id.parse().unwrap(), rec.source_id));
                 match rec_files {
                     None => {}
                     Some(f) => {
-                        if let Err(e) = self.handle_new_files(f, rec.id).await {
+                        if let Err(e) = self.handle_new_files(account, f, rec.id).await {
                             error!("{:?}", e)
                         };
                     }
@@ -1,3 +1,4 @@
+use super::source::TELEGRAM;
 use crate::models;
 use crate::result::{Error, Result};
 use crate::storage::Storage;
@@ -6,23 +7,32 @@ use crate::updates::UpdatesHandler;
 use async_trait::async_trait;
 use tg_collector::types::TelegramUpdate;
 
+/// Update received by the session of the account.
+#[derive(Debug)]
+pub struct AccountUpdate {
+    pub account: String,
+    pub update: TelegramUpdate,
+}
+
 #[async_trait]
-impl<S> UpdatesHandler<TelegramUpdate> for TelegramSource<S>
+impl<S> UpdatesHandler<AccountUpdate> for TelegramSource<S>
 where
     S: Storage + Send + Sync,
 {
-    async fn create_source(&self, updates: &TelegramUpdate) -> Result<models::Source> {
-        match updates {
+    async fn create_source(&self, updates: &AccountUpdate) -> Result<models::Source> {
+        match &updates.update {
             TelegramUpdate::FileDownloadFinished(_) => Err(Error::UpdateNotSupported(
                 "FileDownloadFinished".to_string(),
             )),
             TelegramUpdate::Message(message) => {
-                self.collector
+                let account = self.account(&updates.account)?;
+                account
+                    .collector
                     .read()
                     .await
                     .join_chat(&message.chat_id)
                     .await?;
-                let chann = self
+                let chann = account
                     .collector
                     .read()
                     .await
@@ -31,20 +41,27 @@ where
                 if chann.is_none() {
                     return Err(Error::SourceNotFound);
                 }
-                Ok(self
+                let source = self
                     .storage
                     .save_sources(vec![chann.unwrap().into()])
                     .await?
                     .pop()
-                    .unwrap())
+                    .unwrap();
+                // the channel is joined by the account
+                Ok(self
+                    .storage
+                    .set_source_account(source.id, Some(account.name.clone()))
+                    .await?
+                    .unwrap_or(source))
             }
         }
     }
 
-    async fn process_updates(&self, updates: &TelegramUpdate) -> Result<usize> {
-        match updates {
+    async fn process_updates(&self, updates: &AccountUpdate) -> Result<usize> {
+        let account = self.account(&updates.account)?;
+        match &updates.update {
             TelegramUpdate::FileDownloadFinished(file) => {
-                self.handle_file_downloaded(file).await?;
+                self.handle_file_downloaded(account, file).await?;
                 Ok(1)
             }
             TelegramUpdate::Message(message) => {
@@ -52,26 +69,38 @@ where
                     .storage
                     .search_source(message.chat_id.to_string().as_str())
                     .await?;
-                let source = match sources.len() {
-                    0 => self.create_source(updates).await?,
-                    _ => sources.pop().unwrap(),
+                let source = match sources.pop() {
+                    None => self.create_source(updates).await?,
+                    Some(source) => match self.claim_source(source, &account.name).await? {
+                        Some(source) => source,
+                        None => return Ok(0),
+                    },
                 };
                 let message_id = message.message_id;
-                let created = self
-                    .storage
-                    .save_records(vec![models::NewRecord {
-                        title: None,
-                        image: None,
-                        date: message
-                            .date
-                            .map(|d| chrono::NaiveDateTime::from_timestamp(d.into(), 0)),
-                        source_record_id: message_id.to_string(),
-                        source_id: source.id,
-                        content: message.content.clone().unwrap_or_default(),
-                    }])
-                    .await?
-                    .pop();
-                match created {
+                // edits come as messages without date
+                let mut saved = self
+                    .pipeline
+                    .save_or_update_records(
+                        &self.storage,
+                        TELEGRAM,
+                        vec![models::NewRecord {
+                            title: None,
+                            image: None,
+                            date: message
+                                .date
+                                .map(|d| chrono::NaiveDateTime::from_timestamp(d.into(), 0)),
+                            source_record_id: message_id.to_string(),
+                            source_id: source.id,
+                            meta: None,
+                            content: message.content.clone().unwrap_or_default(),
+                        }],
+                    )
+                    .await?;
+                match saved.created.pop() {
+                    None if !saved.updated.is_empty() => {
+                        debug!("message {} of {} edited", message_id, source.name);
+                        Ok(1)
+                    }
                     None => {
                         if message.files.is_some() {
                             debug!(
@@ -84,8 +113,9 @@ where
                     Some(rec) if message.files.is_some() => {
                         let files = message.files.as_ref().unwrap();
                         let (handle_file, handle_record) = tokio::join!(
-                            self.handle_new_files(files, rec.id),
+                            self.handle_new_files(account, files, rec.id),
                             self.handle_record_inserted(
+                                account,
                                 message.chat_id,
                                 message_id,
                                 vec![(rec.source_record_id, rec.source_id)],
@@ -101,6 +131,7 @@ where
                     }
                     Some(rec) if message.files.is_none() => Ok(self
                         .handle_record_inserted(
+                            account,
                             message.chat_id,
                             message_id,
                             vec![(rec.source_record_id, rec.source_id)],
@@ -112,3 +143,34 @@ where
         }
     }
 }
+
+impl<S> TelegramSource<S>
+where
+    S: Storage + Send + Sync,
+{
+    /// Assigns the source without an account to the account which sees it,
+    /// `None` if the source is collected by another account.
+    pub(super) async fn claim_source(
+        &self,
+        source: models::Source,
+        account: &str,
+    ) -> Result<Option<models::Source>> {
+        match source.account.as_deref() {
+            Some(assigned) if assigned == account => Ok(Some(source)),
+            Some(assigned) => {
+                trace!(
+                    "{} is collected by telegram account {}",
+                    source.name,
+                    assigned
+                );
+                Ok(None)
+            }
+            None => Ok(Some(
+                self.storage
+                    .set_source_account(source.id, Some(account.to_string()))
+                    .await?
+                    .unwrap_or(source),
+            )),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use async_trait::async_trait;
+use tg_collector::types::{
+    FilePath, FileType, ImageMeta, TelegramFileWithMeta, TelegramMessage, TelegramUpdate, VideoMeta,
+};
+use tg_web_collector::{
+    client::{normalize_username, TgWebClient},
+    result::{Error as CollectorError, Result as TgWebResult},
+    types::{Channel, ChannelPage, Media, MediaKind, Post},
+};
+
+use super::tg::TELEGRAM;
+use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::metrics;
+use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::queue;
+use crate::result::{Error, Result};
+use crate::schedule::ScrapeBounds;
+use crate::storage::Storage;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+// TODO: enum?
+pub(crate) const TELEGRAM_WEB: &str = "TELEGRAM_WEB";
+
+/// Telegram source which reads public channels from `t.me/s/<channel>` previews.
+///
+/// Unlike `TelegramSource` it needs neither tdlib session nor phone number,
+/// but only public channels with enabled previews can be followed.
+/// Posts come as `TelegramUpdate::Message` and are stored as `TELEGRAM` records,
+/// channels keep their own kind as they are scraped on schedule.
+pub struct TelegramWebSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_bounds: ScrapeBounds,
+    client: Arc<TgWebClient>,
+    storage: S,
+    pipeline: Pipeline,
+}
+
+impl<S> TelegramWebSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn builder() -> TelegramWebSourceBuilder<S> {
+        TelegramWebSourceBuilder::new()
+    }
+
+    async fn save_messages(
+        &self,
+        source: &models::Source,
+        messages: &[TelegramUpdate],
+        views: &HashMap<i64, u64>,
+    ) -> Result<usize> {
+        let messages: Vec<&TelegramMessage> = messages
+            .iter()
+            .filter_map(|update| match update {
+                TelegramUpdate::Message(message) => Some(message),
+                TelegramUpdate::FileDownloadFinished(_) => None,
+            })
+            .collect();
+        let created = self
+            .pipeline
+            .save_records(
+                &self.storage,
+                TELEGRAM,
+                messages
+                    .iter()
+                    .map(|m| models::NewRecord {
+                        title: None,
+                        source_record_id: m.message_id.to_string(),
+                        source_id: source.id,
+                        meta: views
+                            .get(&m.message_id)
+                            .map(|views| serde_json::json!({ "views": views }).to_string()),
+                        content: m.content.clone().unwrap_or_default(),
+                        date: m.date.map(|d| NaiveDateTime::from_timestamp(d.into(), 0)),
+                        image: m
+                            .files
+                            .iter()
+                            .flatten()
+                            .find(|f| matches!(f.file_type, FileType::Image(_)))
+                            .map(|f| f.path.remote_file.clone()),
+                    })
+                    .collect(),
+            )
+            .await?
+            .created;
+        for record in &created {
+            let message = match messages
+                .iter()
+                .find(|m| m.message_id.to_string() == record.source_record_id)
+            {
+                None => continue,
+                Some(m) => m,
+            };
+            self.storage
+                .set_record_external_link(
+                    record.source_record_id.clone(),
+                    record.source_id,
+                    format!("{}/{}", source.external_link, message.message_id),
+                )
+                .await?;
+            if let Some(files) = &message.files {
+                self.storage
+                    .save_files(
+                        files
+                            .iter()
+                            .map(|f| models::NewFile {
+                                record_id: record.id,
+                                kind: TELEGRAM.to_string(),
+                                local_path: None,
+                                remote_path: f.path.remote_file.clone(),
+                                remote_id: None,
+                                file_name: f.file_name.clone(),
+                                type_: match f.file_type {
+                                    FileType::Image(_) => "IMAGE",
+                                    FileType::Video(_) => "VIDEO",
+                                    FileType::Animation(_) => "ANIMATION",
+                                    FileType::Audio(_) => "AUDIO",
+                                    FileType::Document => "DOCUMENT",
+                                }
+                                .to_string(),
+                                meta: None,
+                            })
+                            .collect(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(created.len())
+    }
+}
+
+pub struct TelegramWebSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    sleep_secs: u64,
+    scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
+    storage: Option<S>,
+    pipeline: Pipeline,
+}
+
+impl<S> Default for TelegramWebSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> TelegramWebSourceBuilder<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            sleep_secs: 60,
+            scrape_source_secs_interval: 300,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
+            storage: None,
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    pub fn with_sleep_secs(mut self, sleep_secs: u64) -> Self {
+        self.sleep_secs = sleep_secs;
+        self
+    }
+
+    pub fn with_scrape_source_secs_interval(mut self, scrape_source_secs_interval: u64) -> Self {
+        self.scrape_source_secs_interval = scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_max_scrape_source_secs_interval(
+        mut self,
+        max_scrape_source_secs_interval: u64,
+    ) -> Self {
+        self.max_scrape_source_secs_interval = max_scrape_source_secs_interval;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(TELEGRAM);
+        self
+    }
+
+    pub fn build(self) -> TelegramWebSource<S> {
+        if self.storage.is_none() {
+            panic!("storage not specified")
+        }
+        TelegramWebSource {
+            sleep_secs: self.sleep_secs,
+            scrape_bounds: ScrapeBounds::new(
+                self.scrape_source_secs_interval,
+                self.max_scrape_source_secs_interval,
+            ),
+            client: Arc::new(TgWebClient::default()),
+            storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
+        }
+    }
+}
+
+/// Posts of a channel as `TelegramUpdate::Message`.
+///
+/// Previews don't expose the chat id, so `chat_id` of the messages is 0
+/// and the channel is known by its username.
+#[derive(Debug)]
+pub struct TelegramWebUpdate {
+    pub channel: Channel,
+    pub messages: Vec<TelegramUpdate>,
+    // view counters by message id, stored in the record meta
+    pub views: HashMap<i64, u64>,
+}
+
+impl From<ChannelPage> for TelegramWebUpdate {
+    fn from(page: ChannelPage) -> Self {
+        Self {
+            channel: page.channel,
+            messages: messages(&page.posts),
+            views: views(&page.posts),
+        }
+    }
+}
+
+// posts without text and media are service ones, e.g. pinned message
+fn messages(posts: &[Post]) -> Vec<TelegramUpdate> {
+    posts
+        .iter()
+        .filter(|p| p.content.is_some() || !p.media.is_empty())
+        .map(|p| {
+            TelegramUpdate::Message(TelegramMessage {
+                message_id: p.id,
+                chat_id: 0,
+                date: p.date.map(|d| d.timestamp() as i32),
+                content: p.content.clone(),
+                files: (!p.media.is_empty()).then(|| p.media.iter().map(file).collect()),
+            })
+        })
+        .collect()
+}
+
+fn views(posts: &[Post]) -> HashMap<i64, u64> {
+    posts
+        .iter()
+        .filter_map(|p| p.views.map(|views| (p.id, views)))
+        .collect()
+}
+
+// previews don't tell sizes of the media, so they are zeroed
+fn file(media: &Media) -> TelegramFileWithMeta {
+    TelegramFileWithMeta {
+        path: FilePath {
+            local_path: None,
+            remote_file: media.url.clone(),
+            remote_id: media.url.clone(),
+        },
+        file_type: match media.kind {
+            MediaKind::Photo => FileType::Image(ImageMeta {
+                width: 0,
+                height: 0,
+            }),
+            MediaKind::Video => FileType::Video(VideoMeta {
+                duration: 0,
+                width: 0,
+                height: 0,
+            }),
+            MediaKind::Document => FileType::Document,
+        },
+        file_name: None,
+    }
+}
+
+impl From<&Channel> for models::NewSource {
+    fn from(channel: &Channel) -> Self {
+        models::NewSource {
+            name: channel.title.clone(),
+            origin: channel.username.clone(),
+            kind: TELEGRAM_WEB.to_string(),
+            image: channel.image.clone(),
+            external_link: channel.link(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> UpdatesHandler<TelegramWebUpdate> for TelegramWebSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn create_source(&self, updates: &TelegramWebUpdate) -> Result<models::Source> {
+        Ok(self
+            .storage
+            .save_sources(vec![(&updates.channel).into()])
+            .await?
+            .pop()
+            .unwrap())
+    }
+
+    async fn process_updates(&self, updates: &TelegramWebUpdate) -> Result<usize> {
+        let source = match self
+            .storage
+            .get_exact_source(TELEGRAM_WEB.to_string(), updates.channel.username.clone())
+            .await?
+        {
+            None => self.create_source(updates).await?,
+            Some(s) => s,
+        };
+        let affected = self
+            .save_messages(&source, &updates.messages, &updates.views)
+            .await?;
+        self.storage
+            .schedule_source(source, self.scrape_bounds)
+            .await?;
+        Ok(affected)
+    }
+}
+
+#[async_trait]
+impl<S> SourceProvider for TelegramWebSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    fn kind(&self) -> &'static str {
+        TELEGRAM_WEB
+    }
+
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
+        let (sources_sender, sources_receiver) = mpsc::channel(2000);
+
+        let sleep_secs = self.sleep_secs;
+        let st = self.storage.clone();
+        let sources_gen_handle =
+            tokio::spawn(async move { sources_gen(st, sleep_secs, sources_sender).await });
+
+        let cl = self.client.clone();
+        let handler = Handler::new(updates_sender);
+        let scrapper_handle =
+            tokio::spawn(async move { run_scrapper(cl.as_ref(), sources_receiver, handler).await });
+        Ok(vec![sources_gen_handle, scrapper_handle])
+    }
+
+    async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
+        let username = normalize_username(query);
+        if username.is_empty() || username.contains(char::is_whitespace) {
+            return Ok(vec![]);
+        }
+        let page = match self.client.get_channel_page(username.as_str(), None).await {
+            Ok(page) => page,
+            Err(CollectorError::ChannelNotFound(_)) => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let update = TelegramWebUpdate::from(page);
+        let source = self.create_source(&update).await?;
+        if let Err(e) = self
+            .save_messages(&source, &update.messages, &update.views)
+            .await
+        {
+            error!("{:?}", e)
+        }
+        Ok(vec![source])
+    }
+
+    async fn synchronize(&self, secs_depth: i32) -> Result<()> {
+        debug!("start syncing {}", self.kind());
+        let until = Utc::now().naive_utc() - Duration::seconds(secs_depth.into());
+        let sources = self
+            .storage
+            .get_sources_by_kind(TELEGRAM_WEB.to_string())
+            .await?;
+        for source in sources {
+            let posts = self
+                .client
+                .get_channel_history(source.origin.as_str(), until)
+                .await?;
+            debug!("get {} posts for {}", posts.len(), source.name);
+            self.save_messages(&source, &messages(&posts), &views(&posts))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+async fn get_page_for_source(client: &TgWebClient, username: String, handler: &Handler) {
+    let started = time::Instant::now();
+    let update = client.get_channel_page(username.as_str(), None).await;
+    handler.process(username, update, started.elapsed()).await;
+}
+
+// TODO: generic scrapper. trait?
+async fn run_scrapper(
+    client: &TgWebClient,
+    mut sources_receiver: mpsc::Receiver<Vec<String>>,
+    handler: Handler,
+) {
+    while let Some(sources) = sources_receiver.recv().await {
+        let mut tasks = vec![];
+        for source in sources {
+            tasks.push(get_page_for_source(client, source, &handler));
+        }
+        join_all(tasks).await;
+    }
+}
+
+// TODO: generic generator
+async fn sources_gen<S: Storage>(storage: S, sleep_period: u64, sender: mpsc::Sender<Vec<String>>) {
+    let sleep_period = time::Duration::from_secs(sleep_period);
+    loop {
+        match get_sources(&storage).await {
+            Ok(sources) => {
+                debug!("found sources for scrape: {:?}", sources);
+                if let Err(err) = sender.send(sources).await {
+                    error!("{}", err)
+                };
+            }
+            Err(e) => error!("{}", e),
+        };
+
+        debug!("send sources delayed for {:?}", sleep_period);
+        tokio::time::sleep(sleep_period).await;
+    }
+}
+
+async fn get_sources<S: Storage>(storage: &S) -> Result<Vec<String>> {
+    Ok(storage
+        .claim_sources_for_scrape(TELEGRAM_WEB.to_string(), &queue::Lease::now())
+        .await?
+        .iter()
+        .map(|r| r.origin.clone())
+        .collect())
+}
+
+struct Handler {
+    sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>,
+}
+
+impl Handler {
+    pub fn new(sender: Arc<Mutex<mpsc::Sender<Result<SourceData>>>>) -> Self {
+        Self { sender }
+    }
+
+    async fn process(
+        &self,
+        username: String,
+        result: TgWebResult<ChannelPage>,
+        elapsed: time::Duration,
+    ) {
+        let update = match result {
+            Ok(page) => Ok(SourceData::new(TELEGRAM_WEB, TelegramWebUpdate::from(page))),
+            Err(err) => Err(Error::source_failed(TELEGRAM_WEB, username, err)),
+        };
+        metrics::observe_scrape(TELEGRAM_WEB, update.as_ref().err(), elapsed);
+        let local = self.sender.lock().await;
+        if local.send(update).await.is_err() {
+            error!("updates receiver dropped");
+        }
+    }
+}
@@ -1,35 +1,51 @@
 use async_trait::async_trait;
 use vk_collector::{
     client::VkClient,
-    result::Result as VkResult,
+    result::{Error as VkError, Result as VkResult},
     types::{Group, WallItem},
 };
 
 use super::{SourceData, SourceProvider, UpdatesHandler};
+use crate::config::DEFAULT_ACCOUNT;
+use crate::metrics;
 use crate::models;
+use crate::pipeline::{Pipeline, Pipelines};
+use crate::queue;
 use crate::result::{Error, Result};
+use crate::schedule::ScrapeBounds;
 use crate::storage::Storage;
 
-use crate::updates::Source;
 use chrono::NaiveDateTime;
 use futures::future::join_all;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once, Weak};
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time;
 
 // TODO: enum?
-const VK: &str = "VK";
+pub(crate) const VK: &str = "VK";
+
+static REGISTER_QUEUE_PROBE: Once = Once::new();
+
+lazy_static! {
+    // clients of the last built source, the source is rebuilt on config reload
+    // while the gauge stays registered, so the probe doesn't keep them alive
+    static ref QUEUE_CLIENTS: std::sync::Mutex<Weak<Clients>> = std::sync::Mutex::new(Weak::new());
+}
 
 pub struct VkSource<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
     sleep_secs: u64,
-    scrape_source_secs_interval: u64,
-    client: Arc<VkClient>,
+    scrape_bounds: ScrapeBounds,
+    clients: Arc<Clients>,
     storage: S,
+    pipeline: Pipeline,
 }
 
 impl<S> VkSource<S>
@@ -47,8 +63,12 @@ where
 {
     sleep_secs: u64,
     scrape_source_secs_interval: u64,
+    max_scrape_source_secs_interval: u64,
     storage: Option<S>,
+    pipeline: Pipeline,
     token: Option<String>,
+    // name and token of the other accounts
+    accounts: Vec<(String, String)>,
     // TODO: specify http client
 }
 
@@ -68,8 +88,11 @@ where
         Self {
             sleep_secs: 60,
             scrape_source_secs_interval: 60,
+            max_scrape_source_secs_interval: 24 * 60 * 60,
             storage: None,
+            pipeline: Pipeline::default(),
             token: None,
+            accounts: vec![],
         }
     }
 
@@ -83,16 +106,35 @@ where
         self
     }
 
+    pub fn with_max_scrape_source_secs_interval(
+        mut self,
+        max_scrape_source_secs_interval: u64,
+    ) -> Self {
+        self.max_scrape_source_secs_interval = max_scrape_source_secs_interval;
+        self
+    }
+
     pub fn with_storage(mut self, storage: S) -> Self {
         self.storage = Some(storage);
         self
     }
 
+    pub fn with_pipelines(mut self, pipelines: &Pipelines) -> Self {
+        self.pipeline = pipelines.for_kind(VK);
+        self
+    }
+
     pub fn with_token(mut self, token: String) -> Self {
         self.token = Some(token);
         self
     }
 
+    // token of one more account, requests are shared between the accounts
+    pub fn with_account(mut self, name: String, token: String) -> Self {
+        self.accounts.push((name, token));
+        self
+    }
+
     pub fn build(self) -> VkSource<S> {
         if self.storage.is_none() {
             panic!("storage not specified")
@@ -100,34 +142,97 @@ where
         if self.token.is_none() {
             panic!("vk token not specified")
         }
+        let http = reqwest::Client::new();
+        // every token has its own throttler
+        let clients = Arc::new(Clients {
+            clients: std::iter::once((DEFAULT_ACCOUNT.to_string(), self.token.unwrap()))
+                .chain(self.accounts)
+                .map(|(name, token)| (name, VkClient::new(token.as_str(), http.clone(), 3, 1)))
+                .collect(),
+            next: AtomicUsize::new(0),
+        });
+        *QUEUE_CLIENTS.lock().unwrap() = Arc::downgrade(&clients);
+        REGISTER_QUEUE_PROBE.call_once(|| {
+            metrics::register_probe(
+                "feeder_vk_throttler_queue_length",
+                "VK requests waiting for the throttler",
+                || {
+                    let clients = QUEUE_CLIENTS.try_lock().ok()?.upgrade();
+                    Some(clients.map_or(0, |clients| clients.queue_len()) as i64)
+                },
+            )
+        });
         VkSource {
             sleep_secs: self.sleep_secs,
-            scrape_source_secs_interval: self.scrape_source_secs_interval,
-            client: Arc::new(VkClient::new(
-                self.token.unwrap().as_str(),
-                reqwest::Client::new(),
-                3,
-                1,
-            )),
+            scrape_bounds: ScrapeBounds::new(
+                self.scrape_source_secs_interval,
+                self.max_scrape_source_secs_interval,
+            ),
+            clients,
             storage: self.storage.unwrap(),
+            pipeline: self.pipeline,
         }
     }
 }
 
+// clients of the accounts by name, requests for sources without an account
+// go round-robin and move on to the next account if the token is rate limited
+struct Clients {
+    clients: Vec<(String, VkClient)>,
+    next: AtomicUsize,
+}
+
+impl Clients {
+    fn queue_len(&self) -> usize {
+        self.clients
+            .iter()
+            .map(|(_, client)| client.queue_len())
+            .sum()
+    }
+
+    fn account(&self, name: &str) -> VkResult<&VkClient> {
+        self.clients
+            .iter()
+            .find(|(account, _)| account == name)
+            .map(|(_, client)| client)
+            .ok_or_else(|| VkError::Internal(format!("vk account {} isn't configured", name)))
+    }
+
+    async fn call<T, F, Fut>(&self, account: Option<&str>, f: F) -> VkResult<T>
+    where
+        F: Fn(VkClient) -> Fut,
+        Fut: Future<Output = VkResult<T>>,
+    {
+        if let Some(name) = account {
+            return f(self.account(name)?.clone()).await;
+        }
+        let first = self.next.fetch_add(1, Ordering::Relaxed);
+        let count = self.clients.len();
+        for i in 0..count {
+            let (name, client) = &self.clients[(first + i) % count];
+            match f(client.clone()).await {
+                Err(VkError::VkError(e)) if e.is_rate_limited() && i + 1 < count => {
+                    warn!("vk account {} is rate limited, trying the next one", name)
+                }
+                result => return result,
+            }
+        }
+        unreachable!("there is always the default account")
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct VkUpdate {
+pub struct VkPost {
     id: i64,
-    owner_id: i64,
     from_id: i64,
     date: i64,
     text: String,
 }
 
-impl From<WallItem> for VkUpdate {
+impl From<WallItem> for VkPost {
     fn from(i: WallItem) -> Self {
-        VkUpdate {
+        VkPost {
             id: i.id(),
-            owner_id: i.owner_id(),
             from_id: i.from_id(),
             date: i.date(),
             text: i.text().to_string(),
@@ -135,15 +240,26 @@ impl From<WallItem> for VkUpdate {
     }
 }
 
+/// Posts of one wall scrape, empty if nothing is published.
+#[derive(Debug, Clone)]
+pub struct VkUpdate {
+    owner_id: i64,
+    posts: Vec<VkPost>,
+}
+
 #[async_trait]
 impl<S> UpdatesHandler<VkUpdate> for VkSource<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
     async fn create_source(&self, updates: &VkUpdate) -> Result<models::Source> {
+        let owner_id = updates.owner_id.to_string();
         let groups = self
-            .client
-            .get_groups_by_ids(vec![updates.owner_id.to_string()])
+            .clients
+            .call(None, |client| {
+                let owner_id = owner_id.clone();
+                async move { client.get_groups_by_ids(vec![owner_id]).await }
+            })
             .await?;
         if groups.is_empty() {
             return Err(Error::SourceNotFound);
@@ -166,28 +282,44 @@ where
             None => self.create_source(updates).await?,
             Some(s) => s,
         };
-        let affected = self
-            .storage
-            .save_records(vec![models::NewRecord {
-                title: None,
-                source_record_id: updates.id.to_string(),
-                source_id: source.id,
-                content: updates.text.clone(),
-                date: Some(NaiveDateTime::from_timestamp(updates.date, 0)),
-                image: None,
-            }])
+        let saved = self
+            .pipeline
+            .save_or_update_records(
+                &self.storage,
+                VK,
+                updates
+                    .posts
+                    .iter()
+                    .map(|post| models::NewRecord {
+                        title: None,
+                        source_record_id: post.id.to_string(),
+                        source_id: source.id,
+                        meta: None,
+                        content: post.text.clone(),
+                        date: Some(NaiveDateTime::from_timestamp(post.date, 0)),
+                        image: None,
+                    })
+                    .collect(),
+            )
             .await?;
-        if affected.is_empty() {
+        for post in &updates.posts {
+            let post_id = post.id.to_string();
+            if saved
+                .created
+                .iter()
+                .any(|record| record.source_record_id == post_id)
+            {
+                continue;
+            }
             self.storage
-                .set_record_external_link(
-                    updates.id.to_string(),
-                    source.id,
-                    updates.owner_id.to_string(),
-                )
+                .set_record_external_link(post_id, source.id, updates.owner_id.to_string())
                 .await?;
         }
-        self.storage.set_source_scraped_now(source).await?;
-        Ok(affected.len())
+        // every successful scrape moves the source, even if the wall is empty
+        self.storage
+            .schedule_source(source, self.scrape_bounds)
+            .await?;
+        Ok(saved.created.len() + saved.updated.len())
     }
 }
 
@@ -196,28 +328,37 @@ impl<S> SourceProvider for VkSource<S>
 where
     S: Storage + Send + Sync + Clone + 'static,
 {
-    fn get_source(&self) -> Source {
-        Source::Vk
+    fn kind(&self) -> &'static str {
+        VK
     }
 
-    async fn run(&self, updates_sender: Arc<Mutex<Sender<Result<SourceData>>>>) -> Result<()> {
+    async fn run(
+        &self,
+        updates_sender: Arc<Mutex<Sender<Result<SourceData>>>>,
+    ) -> Result<Vec<JoinHandle<()>>> {
         let (sources_sender, sources_receiver) = mpsc::channel(2000);
 
         let sleep_secs = self.sleep_secs;
-        let scrape_source_secs_interval = self.scrape_source_secs_interval;
         let st = self.storage.clone();
-        tokio::spawn(async move {
-            sources_gen(st, scrape_source_secs_interval, sleep_secs, sources_sender).await
-        });
+        let sources_gen_handle =
+            tokio::spawn(async move { sources_gen(st, sleep_secs, sources_sender).await });
 
-        let cl = self.client.clone();
+        let clients = self.clients.clone();
         let handler = Handler::new(updates_sender);
-        tokio::spawn(async move { run_scrapper(cl.as_ref(), sources_receiver, handler).await });
-        Ok(())
+        let scrapper_handle =
+            tokio::spawn(
+                async move { run_scrapper(clients.as_ref(), sources_receiver, handler).await },
+            );
+        Ok(vec![sources_gen_handle, scrapper_handle])
     }
 
     async fn search_source(&self, query: &str) -> Result<Vec<models::Source>> {
-        let groups = self.client.search_group(query, 0, 20).await?;
+        let groups = self
+            .clients
+            .call(None, |client| async move {
+                client.search_group(query, 0, 20).await
+            })
+            .await?;
         let mut sources = vec![];
         for gr in groups {
             let source: models::NewSource = gr.into();
@@ -230,8 +371,22 @@ where
     }
 
     async fn synchronize(&self, secs_depth: i32) -> Result<()> {
-        debug!("start syncing {:?}", self.get_source());
-        let groups = self.client.get_my_groups(0, 1000).await?;
+        debug!("start syncing {}", self.kind());
+        // groups of every account are synced by the account itself
+        for (account, client) in self.clients.clients.iter() {
+            debug!("syncing groups of vk account {}", account);
+            self.synchronize_account(client).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> VkSource<S>
+where
+    S: Storage + Send + Sync + Clone + 'static,
+{
+    async fn synchronize_account(&self, client: &VkClient) -> Result<()> {
+        let groups = client.get_my_groups(0, 1000).await?;
         let group_to_source: HashMap<i64, i32> = self
             .storage
             .save_sources(groups.iter().map(models::NewSource::from).collect())
@@ -240,44 +395,56 @@ where
             .map(|s| (s.origin.parse().unwrap(), s.id))
             .collect();
         for group in groups {
-            let wall_items = self.client.get_wall(group.id(), 0, 100).await?;
-            self.storage
+            let wall_items = client.get_wall(group.id(), 0, 100).await?;
+            self.pipeline
                 .save_records(
+                    &self.storage,
+                    VK,
                     wall_items
                         .into_iter()
                         .map(|wall| models::NewRecord {
                             title: None,
                             source_record_id: wall.id().to_string(),
                             source_id: *group_to_source.get(&wall.owner_id()).unwrap(),
+                            meta: None,
                             content: wall.text().to_string(),
                             date: Some(NaiveDateTime::from_timestamp(wall.date(), 0)),
                             image: None,
                         })
                         .collect(),
                 )
-                .await?;
+                .await?
+                .created;
         }
         Ok(())
     }
 }
 
-impl<S> VkSource<S> where S: Storage + Send + Sync + Clone + 'static {}
-
-async fn get_records_for_source(client: &VkClient, source_id: String, handler: &Handler) {
-    let update = client.get_wall(source_id.parse().unwrap(), 0, 25).await;
-    handler.process(update).await;
+async fn get_records_for_source(
+    clients: &Clients,
+    (source_id, account): (String, Option<String>),
+    handler: &Handler,
+) {
+    let started = time::Instant::now();
+    let owner_id = source_id.parse().unwrap();
+    let update = clients
+        .call(account.as_deref(), |client| async move {
+            client.get_wall(owner_id, 0, 25).await
+        })
+        .await;
+    handler.process(source_id, update, started.elapsed()).await;
 }
 
 // TODO: generic scrapper. trait?
 async fn run_scrapper(
-    client: &VkClient,
-    mut sources_receiver: mpsc::Receiver<Vec<String>>,
+    clients: &Clients,
+    mut sources_receiver: mpsc::Receiver<Vec<(String, Option<String>)>>,
     handler: Handler,
 ) {
     while let Some(sources) = sources_receiver.recv().await {
         let mut tasks = vec![];
         for source in sources {
-            tasks.push(get_records_for_source(client, source, &handler));
+            tasks.push(get_records_for_source(clients, source, &handler));
         }
         join_all(tasks).await;
     }
@@ -310,13 +477,12 @@ impl From<&Group> for crate::models::NewSource {
 // TODO: generic generator
 async fn sources_gen<S: Storage>(
     storage: S,
-    source_check_period: u64,
     sleep_period: u64,
-    sender: mpsc::Sender<Vec<String>>,
+    sender: mpsc::Sender<Vec<(String, Option<String>)>>,
 ) {
     let sleep_period = time::Duration::from_secs(sleep_period);
     loop {
-        match get_sources(&storage, source_check_period).await {
+        match get_sources(&storage).await {
             Ok(sources) => {
                 debug!("found sources for scrape: {:?}", sources);
                 if let Err(err) = sender.send(sources).await {
@@ -331,15 +497,13 @@ async fn sources_gen<S: Storage>(
     }
 }
 
-async fn get_sources<S: Storage>(
-    storage: &S,
-    source_check_period_secs: u64,
-) -> Result<Vec<String>> {
+// origins of the sources with their accounts
+async fn get_sources<S: Storage>(storage: &S) -> Result<Vec<(String, Option<String>)>> {
     Ok(storage
-        .get_sources_by_kind_for_scrape(VK.to_string(), source_check_period_secs)
+        .claim_sources_for_scrape(VK.to_string(), &queue::Lease::now())
         .await?
-        .iter()
-        .map(|r| r.origin.clone())
+        .into_iter()
+        .map(|r| (r.origin, r.account))
         .collect())
 }
 
@@ -352,7 +516,12 @@ impl Handler {
         Self { sender }
     }
 
-    async fn process(&self, result: VkResult<Vec<WallItem>>) {
+    async fn process(
+        &self,
+        source_id: String,
+        result: VkResult<Vec<WallItem>>,
+        elapsed: time::Duration,
+    ) {
         let send = |d| async {
             let local = self.sender.lock().await;
             if local.send(d).await.is_err() {
@@ -360,13 +529,24 @@ impl Handler {
             }
         };
 
+        let owner_id = match source_id.parse() {
+            Ok(owner_id) => owner_id,
+            Err(_) => {
+                error!("invalid vk source {}", source_id);
+                return;
+            }
+        };
+        let result = result.map_err(|err| Error::source_failed(VK, source_id, err));
+        metrics::observe_scrape(VK, result.as_ref().err(), elapsed);
         match result {
-            Ok(updates) => {
-                for update in updates {
-                    send(Ok(SourceData::Vk(VkUpdate::from(update)))).await
-                }
+            Ok(items) => {
+                let update = VkUpdate {
+                    owner_id,
+                    posts: items.into_iter().map(VkPost::from).collect(),
+                };
+                send(Ok(SourceData::new(VK, update))).await
             }
-            Err(err) => send(Err(Error::VkCollectorError(err))).await,
+            Err(err) => send(Err(err)).await,
         };
     }
 }
@@ -0,0 +1,16 @@
+use fs_collector::parser::parse_document;
+use fs_collector::watcher::Watcher;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// try with: cargo run --example main -- ~/notes
+fn main() {
+    env_logger::init();
+    let directory = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".to_string()));
+    let (sender, receiver) = mpsc::channel();
+    let watcher = Watcher::new(&directory);
+    std::thread::spawn(move || watcher.run(sender));
+    for path in receiver {
+        println!("{:?}", parse_document(&directory, &path));
+    }
+}
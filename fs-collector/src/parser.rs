@@ -0,0 +1,345 @@
+use crate::result::{Error, Result};
+use crate::types::{is_maildir_message, Attachment, Document, Format};
+use chrono::NaiveDateTime;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use std::path::{Component, Path};
+use std::time::UNIX_EPOCH;
+
+/// Parses supported file of the watched `directory`, `None` for unsupported files.
+pub fn parse_document(directory: &Path, path: &Path) -> Result<Option<Document>> {
+    let format = match Format::detect(path) {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+    let data = std::fs::read(path)?;
+    let modified = std::fs::metadata(path)?.modified()?;
+    let modified = NaiveDateTime::from_timestamp(
+        modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default(),
+        0,
+    );
+    let id = document_id(directory, path)?;
+    let document = match format {
+        Format::Markdown => {
+            let text = String::from_utf8_lossy(&data);
+            let (title, content, references) = parse_markdown(&text);
+            Document {
+                id,
+                format,
+                title,
+                content,
+                date: modified,
+                attachments: local_attachments(directory, path, references),
+            }
+        }
+        Format::Html => {
+            let text = String::from_utf8_lossy(&data);
+            let references = attribute_values(&text, "src")
+                .into_iter()
+                .chain(attribute_values(&text, "href"))
+                .collect();
+            Document {
+                id,
+                format,
+                title: html_title(&text),
+                content: html_body(&text).trim().to_string(),
+                date: modified,
+                attachments: local_attachments(directory, path, references),
+            }
+        }
+        Format::Eml => {
+            let email = email_collector::parser::parse_email(&data)?;
+            Document {
+                id,
+                format,
+                title: email.subject,
+                content: match email.is_html {
+                    true => email.content,
+                    false => format!("<pre>{}</pre>", escape_html(email.content.as_str())),
+                },
+                date: email.date.unwrap_or(modified),
+                attachments: email
+                    .inline_images
+                    .into_iter()
+                    .map(Attachment::Inline)
+                    .collect(),
+            }
+        }
+    };
+    Ok(Some(document))
+}
+
+// Maildir moves messages from `new` to `cur` and appends flags after `:`,
+// so only the unique part of the name identifies the message
+fn document_id(directory: &Path, path: &Path) -> Result<String> {
+    let relative = path
+        .strip_prefix(directory)
+        .map_err(|_| Error::ParseError(format!("{:?} is outside of {:?}", path, directory)))?;
+    if !is_maildir_message(path) {
+        return Ok(relative.to_string_lossy().to_string());
+    }
+    let file_name = relative
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let unique = file_name
+        .split_once(':')
+        .map(|(unique, _)| unique)
+        .unwrap_or_else(|| file_name.as_str());
+    Ok(match relative.parent().and_then(|p| p.parent()) {
+        Some(folder) if !folder.as_os_str().is_empty() => {
+            format!("{}/{}", folder.to_string_lossy(), unique)
+        }
+        _ => unique.to_string(),
+    })
+}
+
+fn parse_markdown(text: &str) -> (Option<String>, String, Vec<String>) {
+    let mut title: Option<String> = None;
+    let mut in_title = false;
+    let mut references = vec![];
+    let events: Vec<Event> = Parser::new_ext(text, Options::all()).collect();
+    for event in &events {
+        match event {
+            Event::Start(Tag::Heading(1)) if title.is_none() => {
+                in_title = true;
+                title = Some(String::new());
+            }
+            Event::End(Tag::Heading(1)) => in_title = false,
+            Event::Text(t) | Event::Code(t) if in_title => {
+                if let Some(title) = title.as_mut() {
+                    title.push_str(t)
+                }
+            }
+            Event::Start(Tag::Image(_, destination, _))
+            | Event::Start(Tag::Link(_, destination, _)) => {
+                references.push(destination.to_string())
+            }
+            _ => {}
+        }
+    }
+    let mut content = String::new();
+    html::push_html(&mut content, events.into_iter());
+    (
+        title
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty()),
+        content.trim().to_string(),
+        references,
+    )
+}
+
+// only files inside of the watched directory, other documents are records on their own
+fn local_attachments(directory: &Path, path: &Path, references: Vec<String>) -> Vec<Attachment> {
+    let base = match path.parent() {
+        Some(base) => base,
+        None => return vec![],
+    };
+    let mut attachments: Vec<Attachment> = vec![];
+    for reference in references {
+        let relative = reference.split(&['#', '?'][..]).next().unwrap_or_default();
+        if relative.is_empty() || relative.contains("://") || relative.starts_with("mailto:") {
+            continue;
+        }
+        let relative = Path::new(relative);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            continue;
+        }
+        let attachment_path = base.join(relative);
+        if !attachment_path.starts_with(directory)
+            || !attachment_path.is_file()
+            || Format::detect(&attachment_path).is_some()
+        {
+            continue;
+        }
+        if attachments.iter().any(|a| match a {
+            Attachment::Local { path, .. } => path == &attachment_path,
+            _ => false,
+        }) {
+            continue;
+        }
+        attachments.push(Attachment::Local {
+            path: attachment_path,
+            reference,
+        })
+    }
+    attachments
+}
+
+fn find_ignore_case(text: &str, pattern: &str, from: usize) -> Option<usize> {
+    text.get(from..)?
+        .to_ascii_lowercase()
+        .find(pattern)
+        .map(|i| i + from)
+}
+
+fn tag_content(text: &str, tag: &str) -> Option<(usize, usize)> {
+    let start = find_ignore_case(text, format!("<{}", tag).as_str(), 0)?;
+    let start = text[start..].find('>')? + start + 1;
+    let end = find_ignore_case(text, format!("</{}>", tag).as_str(), start)?;
+    Some((start, end))
+}
+
+fn strip_tags(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => result.push(c),
+            _ => {}
+        }
+    }
+    result.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn html_title(text: &str) -> Option<String> {
+    tag_content(text, "title")
+        .or_else(|| tag_content(text, "h1"))
+        .map(|(start, end)| strip_tags(&text[start..end]))
+        .filter(|t| !t.is_empty())
+}
+
+fn html_body(text: &str) -> &str {
+    match tag_content(text, "body") {
+        Some((start, end)) => &text[start..end],
+        None => text,
+    }
+}
+
+fn attribute_values(text: &str, attribute: &str) -> Vec<String> {
+    let pattern = format!("{}=", attribute);
+    let lowercase = text.to_ascii_lowercase();
+    let mut values = vec![];
+    let mut from = 0;
+    while let Some(i) = lowercase[from..].find(pattern.as_str()) {
+        let start = from + i + pattern.len();
+        from = start;
+        // attribute name must not be a suffix of another one, e.g. `data-src`
+        if !text[..start - pattern.len()].ends_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let quote = match text[start..].chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => continue,
+        };
+        if let Some(end) = text[start + 1..].find(quote) {
+            values.push(text[start + 1..start + 1 + end].to_string());
+            from = start + 1 + end;
+        }
+    }
+    values
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_document;
+    use crate::types::{Attachment, Format};
+    use std::fs;
+
+    #[test]
+    fn test_parse_markdown() {
+        let directory = tempfile::tempdir().unwrap();
+        let notes = directory.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        fs::write(notes.join("ferris.png"), b"PNG!").unwrap();
+        fs::write(
+            notes.join("2021.md"),
+            "# Release `1.53`\n\nSee ![ferris](ferris.png), [next](2022.md), \
+            [missing](missing.png) and [rust](https://www.rust-lang.org).\n",
+        )
+        .unwrap();
+
+        let document = parse_document(directory.path(), &notes.join("2021.md"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(document.id, "notes/2021.md");
+        assert_eq!(document.format, Format::Markdown);
+        assert_eq!(document.title, Some("Release 1.53".to_string()));
+        assert!(document
+            .content
+            .starts_with("<h1>Release <code>1.53</code></h1>"));
+        assert!(document
+            .content
+            .contains("<img src=\"ferris.png\" alt=\"ferris\" />"));
+        assert_eq!(
+            document.attachments,
+            vec![Attachment::Local {
+                path: notes.join("ferris.png"),
+                reference: "ferris.png".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_html() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join("report.pdf"), b"%PDF").unwrap();
+        fs::write(directory.path().join("secret.txt"), b"").unwrap();
+        let path = directory.path().join("index.html");
+        fs::write(
+            &path,
+            "<html><head><TITLE>Weekly\n report</TITLE></head>\
+            <body><a href=\"report.pdf#page=2\">report</a>\
+            <img data-src='secret.txt'><a href=\"../index.html\">up</a></body></html>",
+        )
+        .unwrap();
+
+        let document = parse_document(directory.path(), &path).unwrap().unwrap();
+        assert_eq!(document.id, "index.html");
+        assert_eq!(document.title, Some("Weekly report".to_string()));
+        assert!(document
+            .content
+            .starts_with("<a href=\"report.pdf#page=2\">"));
+        assert_eq!(document.attachments.len(), 1);
+        assert_eq!(
+            document.attachments[0].content_type(),
+            "application/pdf".to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_maildir_message() {
+        let directory = tempfile::tempdir().unwrap();
+        let cur = directory.path().join("Archive").join("cur");
+        fs::create_dir_all(&cur).unwrap();
+        let path = cur.join("1623925800.M1P2.host:2,S");
+        fs::write(
+            &path,
+            "From: <digest@example.org>\r\n\
+            Subject: Digest\r\n\
+            Date: Wed, 16 Jun 2021 04:00:00 +0000\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            \r\n\
+            1 < 2\r\n",
+        )
+        .unwrap();
+
+        let document = parse_document(directory.path(), &path).unwrap().unwrap();
+        assert_eq!(document.id, "Archive/1623925800.M1P2.host");
+        assert_eq!(document.format, Format::Eml);
+        assert_eq!(document.title, Some("Digest".to_string()));
+        assert_eq!(document.content, "<pre>1 &lt; 2</pre>");
+        assert_eq!(document.date.timestamp(), 1623816000);
+    }
+
+    #[test]
+    fn test_skip_unsupported() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("image.png");
+        fs::write(&path, b"PNG!").unwrap();
+        assert!(parse_document(directory.path(), &path).unwrap().is_none());
+    }
+}
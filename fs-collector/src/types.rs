@@ -0,0 +1,136 @@
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+
+/// Maildir folders, messages of `tmp` aren't delivered yet.
+pub(crate) const MAILDIR_DIRECTORIES: [&str; 2] = ["cur", "new"];
+pub(crate) const MAILDIR_TMP_DIRECTORY: &str = "tmp";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+    Eml,
+}
+
+impl Format {
+    /// Detected by extension, files of Maildir `cur` and `new` folders are always messages.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        if file_name.starts_with('.') {
+            return None;
+        }
+        if is_maildir_message(path) {
+            return Some(Format::Eml);
+        }
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "html" | "htm" => Some(Format::Html),
+            "eml" => Some(Format::Eml),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn is_maildir_message(path: &Path) -> bool {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .and_then(|p| p.to_str())
+        .map(|p| MAILDIR_DIRECTORIES.contains(&p))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attachment {
+    // file next to the document, `reference` is the link as it's written in the content
+    Local { path: PathBuf, reference: String },
+    // part of the message, e.g. inline image of `.eml`
+    Inline(email_collector::types::Attachment),
+}
+
+impl Attachment {
+    pub fn file_name(&self) -> Option<String> {
+        match self {
+            Attachment::Local { path, .. } => {
+                path.file_name().and_then(|f| f.to_str()).map(String::from)
+            }
+            Attachment::Inline(attachment) => attachment.file_name.clone(),
+        }
+    }
+
+    /// Guessed by extension for local files.
+    pub fn content_type(&self) -> String {
+        match self {
+            Attachment::Local { path, .. } => {
+                let extension = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                match extension.as_str() {
+                    "png" => "image/png",
+                    "jpg" | "jpeg" => "image/jpeg",
+                    "gif" => "image/gif",
+                    "webp" => "image/webp",
+                    "svg" => "image/svg+xml",
+                    "mp4" => "video/mp4",
+                    "webm" => "video/webm",
+                    "mp3" => "audio/mpeg",
+                    "ogg" => "audio/ogg",
+                    "pdf" => "application/pdf",
+                    "txt" => "text/plain",
+                    _ => "application/octet-stream",
+                }
+                .to_string()
+            }
+            Attachment::Inline(attachment) => attachment.content_type.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    // path relative to the watched directory, Maildir messages keep only the unique part of the name
+    pub id: String,
+    pub format: Format,
+    pub title: Option<String>,
+    // html
+    pub content: String,
+    pub date: NaiveDateTime,
+    pub attachments: Vec<Attachment>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attachment, Format};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            Format::detect(Path::new("notes/2021.md")),
+            Some(Format::Markdown)
+        );
+        assert_eq!(Format::detect(Path::new("a/index.HTM")), Some(Format::Html));
+        assert_eq!(Format::detect(Path::new("digest.eml")), Some(Format::Eml));
+        assert_eq!(
+            Format::detect(Path::new("mail/cur/1623925800.M1P2.host:2,S")),
+            Some(Format::Eml)
+        );
+        assert_eq!(
+            Format::detect(Path::new("mail/tmp/1623925800.M1P2.host")),
+            None
+        );
+        assert_eq!(Format::detect(Path::new("notes/.draft.md")), None);
+        assert_eq!(Format::detect(Path::new("notes/image.png")), None);
+    }
+
+    #[test]
+    fn test_attachment_content_type() {
+        let attachment = Attachment::Local {
+            path: PathBuf::from("notes/ferris.PNG"),
+            reference: "ferris.PNG".to_string(),
+        };
+        assert_eq!(attachment.content_type(), "image/png");
+        assert_eq!(attachment.file_name(), Some("ferris.PNG".to_string()));
+    }
+}
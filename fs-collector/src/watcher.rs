@@ -0,0 +1,268 @@
+use crate::result::Result;
+use crate::types::{Format, MAILDIR_TMP_DIRECTORY};
+use notify::{DebouncedEvent, RecursiveMode, Watcher as _};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant, SystemTime};
+
+const STOP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reports new and changed documents of the directory.
+///
+/// Uses inotify-style notifications when the platform supports them and
+/// rescans the whole directory every `poll_interval` anyway, so changes
+/// missed by notifications (e.g. on network mounts) are picked up too.
+pub struct Watcher {
+    directory: PathBuf,
+    poll_interval: Duration,
+    // modification time and size of already reported files
+    known: HashMap<PathBuf, (SystemTime, u64)>,
+}
+
+impl Watcher {
+    pub fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            poll_interval: Duration::from_secs(60),
+            known: HashMap::new(),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Walks the directory, returns files which are new or changed since the previous call.
+    ///
+    /// Unreadable subdirectories are skipped, only the watched directory itself must be readable.
+    pub fn scan(&mut self) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        walk(self.directory.as_path(), &mut files)?;
+        files.sort();
+        Ok(files.into_iter().filter(|f| self.track(f)).collect())
+    }
+
+    // failed scan is retried after the poll interval, watching goes on
+    fn rescan(&mut self) -> Vec<PathBuf> {
+        self.scan().unwrap_or_else(|e| {
+            warn!("can't scan {:?}: {}", self.directory, e);
+            vec![]
+        })
+    }
+
+    // remembers file state, returns true when it's changed
+    fn track(&mut self, path: &Path) -> bool {
+        if Format::detect(path).is_none() {
+            return false;
+        }
+        let state = match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => match metadata.modified() {
+                Ok(modified) => (modified, metadata.len()),
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        self.known.insert(path.to_path_buf(), state) != Some(state)
+    }
+
+    /// Sends paths of new and changed files until receiver is dropped, blocks current thread.
+    pub fn run(self, sender: Sender<PathBuf>) -> Result<()> {
+        self.run_until(sender, || false)
+    }
+
+    /// Same as `run`, also stops once `stopped` returns true, it's checked every second.
+    pub fn run_until(mut self, sender: Sender<PathBuf>, stopped: impl Fn() -> bool) -> Result<()> {
+        let (events_sender, events) = mpsc::channel();
+        // dropped watcher closes the channel, so failed watcher switches to polling
+        let _watcher = match notify::watcher(events_sender, Duration::from_secs(1)) {
+            Ok(mut watcher) => match watcher.watch(&self.directory, RecursiveMode::Recursive) {
+                Ok(_) => Some(watcher),
+                Err(e) => {
+                    warn!("can't watch {:?}, polling: {}", self.directory, e);
+                    None
+                }
+            },
+            Err(e) => {
+                warn!("can't create watcher, polling {:?}: {}", self.directory, e);
+                None
+            }
+        };
+        let wait = self.poll_interval.min(STOP_CHECK_INTERVAL);
+        let mut changed = self.rescan();
+        let mut scanned_at = Instant::now();
+        loop {
+            for path in changed.drain(..) {
+                if sender.send(path).is_err() {
+                    debug!("receiver dropped, stop watching {:?}", self.directory);
+                    return Ok(());
+                }
+            }
+            if stopped() {
+                debug!("stop watching {:?}", self.directory);
+                return Ok(());
+            }
+            if scanned_at.elapsed() >= self.poll_interval {
+                changed = self.rescan();
+                scanned_at = Instant::now();
+                continue;
+            }
+            match events.recv_timeout(wait) {
+                Ok(DebouncedEvent::Create(path))
+                | Ok(DebouncedEvent::Write(path))
+                | Ok(DebouncedEvent::Rename(_, path)) => {
+                    // directories are moved in with their content
+                    if path.is_dir() {
+                        changed = self.rescan();
+                    } else if self.track(&path) {
+                        changed.push(path)
+                    }
+                }
+                Ok(DebouncedEvent::Rescan) => {
+                    changed = self.rescan();
+                    scanned_at = Instant::now();
+                }
+                Ok(DebouncedEvent::Error(e, path)) => {
+                    warn!("watch error for {:?}: {}", path, e)
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(wait),
+            }
+        }
+    }
+}
+
+fn walk(directory: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("can't read entry of {:?}: {}", directory, e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        let hidden = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .map(|f| f.starts_with('.') || f == MAILDIR_TMP_DIRECTORY)
+            .unwrap_or(true);
+        if hidden {
+            continue;
+        }
+        // file type of the entry itself, symlinked directories aren't followed
+        // as they may point to their parents
+        let is_dir = match entry.file_type() {
+            Ok(file_type) if file_type.is_symlink() => {
+                if path.is_dir() {
+                    debug!("skip symlinked directory {:?}", path);
+                    continue;
+                }
+                false
+            }
+            Ok(file_type) => file_type.is_dir(),
+            Err(e) => {
+                warn!("can't get type of {:?}: {}", path, e);
+                continue;
+            }
+        };
+        if !is_dir {
+            files.push(path)
+        } else if let Err(e) = walk(&path, files) {
+            warn!("can't read {:?}: {}", path, e)
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Watcher;
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    #[test]
+    fn test_scan() {
+        let directory = tempfile::tempdir().unwrap();
+        let notes = directory.path().join("notes");
+        fs::create_dir_all(notes.join(".git")).unwrap();
+        fs::create_dir_all(directory.path().join("mail").join("tmp")).unwrap();
+        fs::write(notes.join("a.md"), "# A").unwrap();
+        fs::write(notes.join("b.png"), "PNG!").unwrap();
+        fs::write(notes.join(".git").join("c.md"), "# C").unwrap();
+        fs::write(directory.path().join("mail").join("tmp").join("1.host"), "").unwrap();
+
+        let mut watcher = Watcher::new(directory.path());
+        assert_eq!(watcher.scan().unwrap(), vec![notes.join("a.md")]);
+        assert!(watcher.scan().unwrap().is_empty());
+
+        fs::write(notes.join("a.md"), "# A, changed").unwrap();
+        fs::write(notes.join("d.html"), "<h1>D</h1>").unwrap();
+        assert_eq!(
+            watcher.scan().unwrap(),
+            vec![notes.join("a.md"), notes.join("d.html")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_symlinks() {
+        let directory = tempfile::tempdir().unwrap();
+        let notes = directory.path().join("notes");
+        fs::create_dir_all(&notes).unwrap();
+        fs::write(notes.join("a.md"), "# A").unwrap();
+        std::os::unix::fs::symlink(directory.path(), notes.join("loop")).unwrap();
+        std::os::unix::fs::symlink(notes.join("a.md"), notes.join("b.md")).unwrap();
+
+        let mut watcher = Watcher::new(directory.path());
+        assert_eq!(
+            watcher.scan().unwrap(),
+            vec![notes.join("a.md"), notes.join("b.md")]
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let directory = tempfile::tempdir().unwrap();
+        fs::write(directory.path().join("a.md"), "# A").unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = Watcher::new(directory.path()).with_poll_interval(Duration::from_millis(100));
+        std::thread::spawn(move || watcher.run(sender).unwrap());
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            directory.path().join("a.md")
+        );
+        fs::write(directory.path().join("b.md"), "# B").unwrap();
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            directory.path().join("b.md")
+        );
+    }
+
+    #[test]
+    fn test_run_until() {
+        let directory = tempfile::tempdir().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let watcher_stopped = stopped.clone();
+        let watcher = Watcher::new(directory.path());
+        let handle = std::thread::spawn(move || {
+            watcher
+                .run_until(sender, || watcher_stopped.load(Ordering::SeqCst))
+                .unwrap()
+        });
+
+        stopped.store(true, Ordering::SeqCst);
+        // watcher drops the sender once it's stopped
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+        handle.join().unwrap();
+    }
+}
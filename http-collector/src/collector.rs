@@ -6,6 +6,7 @@ use reqwest::{Client, Response};
 use rss::Channel;
 use scraper::{Html, Selector};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use url::{ParseError, Url};
 
 use crate::models::*;
@@ -14,7 +15,9 @@ use tokio::sync::mpsc;
 
 #[async_trait]
 pub trait ResultsHandler {
-    async fn process(&self, result: Result<(&Feed, FeedKind, String)>);
+    // `link` is the scraped link, the feed link may differ from it;
+    // `elapsed` is the time of the scrape
+    async fn process(&self, link: &str, result: Result<&Feed>, elapsed: Duration);
 }
 
 #[async_trait]
@@ -87,13 +90,20 @@ where
         link: String,
         process_results: &impl ResultsHandler,
     ) {
-        match self.scrape_feed(kind, link.as_str()).await {
+        let started = Instant::now();
+        let scraped = self.scrape_feed(kind, link.as_str()).await;
+        let elapsed = started.elapsed();
+        match scraped {
             Ok(content) => {
                 process_results
-                    .process(Ok((&content, content.kind, link)))
+                    .process(link.as_str(), Ok(&content), elapsed)
+                    .await
+            }
+            Err(err) => {
+                process_results
+                    .process(link.as_str(), Err(err), elapsed)
                     .await
             }
-            Err(err) => process_results.process(Err(err)).await,
         };
     }
 
@@ -146,6 +156,9 @@ where
         debug!("start scrape {} {:?}", link, std::thread::current().id());
         let response: Response = self.client.get(link).send().await?;
         debug!("scraped {} {:?}", link, std::thread::current().id());
+        if !response.status().is_success() {
+            return Err(Error::HttpError(response.status().as_u16()));
+        }
         Ok(response.text().await?)
     }
 
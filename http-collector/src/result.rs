@@ -11,6 +11,8 @@ pub enum Error {
     ScrapeTimeout,
     InvalidUrl(url::ParseError),
     RequestError,
+    // unsuccessful response status
+    HttpError(u16),
     // can't decode raw content
     DecodeError,
     // can't parse particular format
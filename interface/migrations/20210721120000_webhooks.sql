@@ -0,0 +1,6 @@
+CREATE TABLE webhooks (
+                          source_id int primary key constraint webhooks_source_id_fk references sources,
+                          token text not null unique,
+                          secret text not null,
+                          created_at timestamp not null default now()
+);
@@ -0,0 +1,30 @@
+CREATE TABLE outgoing_webhooks (
+                                   id serial primary key,
+                                   user_id int not null constraint outgoing_webhooks_user_id references users,
+                                   url text not null,
+                                   secret text not null,
+                                   -- filters, records of the subscribed sources match all the set ones
+                                   source_id int null constraint outgoing_webhooks_source_id references sources,
+                                   folder_id int null constraint outgoing_webhooks_folder_id references user_folders on delete cascade,
+                                   tag text null,
+                                   keyword text null,
+                                   created_at timestamp not null default now()
+);
+
+CREATE TABLE outgoing_webhook_deliveries (
+                                             id serial primary key,
+                                             webhook_id int not null constraint outgoing_webhook_deliveries_webhook_id references outgoing_webhooks on delete cascade,
+                                             record_id int not null constraint outgoing_webhook_deliveries_record_id references records,
+                                             -- PENDING, DELIVERED or FAILED
+                                             status text not null default 'PENDING',
+                                             attempts int not null default 0,
+                                             response_status int null,
+                                             last_error text null,
+                                             next_attempt_at timestamp not null default now(),
+                                             delivered_at timestamp null,
+                                             created_at timestamp not null default now(),
+                                             unique(webhook_id, record_id)
+);
+
+CREATE INDEX outgoing_webhook_deliveries_status_next_attempt_at_idx
+    ON outgoing_webhook_deliveries (status, next_attempt_at);
@@ -0,0 +1,38 @@
+-- full-text index of the records, kept up to date by the trigger
+CREATE TABLE record_search (
+                               record_id int primary key constraint record_search_record_id references records on delete cascade,
+                               -- text search config of the record language
+                               config regconfig not null,
+                               search_vector tsvector not null
+);
+
+CREATE INDEX record_search_search_vector_idx ON record_search USING gin (search_vector);
+
+-- russian config stems latin words with the english stemmer as well
+CREATE FUNCTION record_search_config(text text) RETURNS regconfig AS $$
+    SELECT CASE WHEN text ~* '[а-яё]' THEN 'russian'::regconfig ELSE 'english'::regconfig END
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE FUNCTION record_search_update() RETURNS trigger AS $$
+DECLARE
+    config regconfig := record_search_config(coalesce(NEW.title, '') || ' ' || NEW.content);
+BEGIN
+    INSERT INTO record_search (record_id, config, search_vector)
+    VALUES (NEW.id, config,
+            setweight(to_tsvector(config, coalesce(NEW.title, '')), 'A') ||
+            setweight(to_tsvector(config, NEW.content), 'B'))
+    ON CONFLICT (record_id) DO UPDATE SET
+        config = EXCLUDED.config,
+        search_vector = EXCLUDED.search_vector;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER records_search_update AFTER INSERT OR UPDATE OF title, content ON records
+    FOR EACH ROW EXECUTE FUNCTION record_search_update();
+
+INSERT INTO record_search (record_id, config, search_vector)
+SELECT id, config,
+       setweight(to_tsvector(config, coalesce(title, '')), 'A') ||
+       setweight(to_tsvector(config, content), 'B')
+FROM (SELECT *, record_search_config(coalesce(title, '') || ' ' || content) AS config FROM records) r;
@@ -0,0 +1,6 @@
+-- retention of the source overriding the global one, null inherits it, 0 keeps records forever
+CREATE TABLE source_retention (
+                                  source_id int primary key constraint source_retention_source_id references sources,
+                                  max_age_days int null,
+                                  max_count int null
+);
@@ -0,0 +1,52 @@
+-- languages of the records hidden from the subscription of the user
+CREATE TABLE source_hidden_languages (
+                                         id serial primary key,
+                                         user_id int not null constraint source_hidden_languages_user_id references users,
+                                         source_id int not null constraint source_hidden_languages_source_id references sources,
+                                         language text not null,
+                                         unique(user_id, source_id, language)
+);
+
+-- text search config of the detected language, null if the language isn't known;
+-- languages without a stemmer are indexed as they are
+CREATE FUNCTION language_search_config(language text) RETURNS regconfig AS $$
+    SELECT CASE language
+        WHEN 'eng' THEN 'english'
+        WHEN 'rus' THEN 'russian'
+        WHEN 'deu' THEN 'german'
+        WHEN 'fra' THEN 'french'
+        WHEN 'spa' THEN 'spanish'
+        WHEN 'ita' THEN 'italian'
+        WHEN 'por' THEN 'portuguese'
+        WHEN 'nld' THEN 'dutch'
+        WHEN 'swe' THEN 'swedish'
+        WHEN 'dan' THEN 'danish'
+        WHEN 'nob' THEN 'norwegian'
+        WHEN 'fin' THEN 'finnish'
+        WHEN 'hun' THEN 'hungarian'
+        WHEN 'ron' THEN 'romanian'
+        WHEN 'tur' THEN 'turkish'
+        ELSE 'simple'
+    END::regconfig WHERE language IS NOT NULL
+$$ LANGUAGE sql IMMUTABLE;
+
+-- the script decides the config of the records of unknown language as before
+CREATE OR REPLACE FUNCTION record_search_update() RETURNS trigger AS $$
+DECLARE
+    config regconfig := coalesce(language_search_config(NEW.language),
+                                 record_search_config(coalesce(NEW.title, '') || ' ' || NEW.content));
+BEGIN
+    INSERT INTO record_search (record_id, config, search_vector)
+    VALUES (NEW.id, config,
+            setweight(to_tsvector(config, coalesce(NEW.title, '')), 'A') ||
+            setweight(to_tsvector(config, NEW.content), 'B'))
+    ON CONFLICT (record_id) DO UPDATE SET
+        config = EXCLUDED.config,
+        search_vector = EXCLUDED.search_vector;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER records_search_update ON records;
+CREATE TRIGGER records_search_update AFTER INSERT OR UPDATE OF title, content, language ON records
+    FOR EACH ROW EXECUTE FUNCTION record_search_update();
@@ -0,0 +1,4 @@
+-- deliveries are claimed by one worker process at a time, IN_PROGRESS ones whose
+-- lease expired (the worker died) are claimed again
+ALTER TABLE outgoing_webhook_deliveries add column leased_by text null;
+ALTER TABLE outgoing_webhook_deliveries add column lease_expires_at timestamp null;
@@ -0,0 +1,62 @@
+CREATE TABLE users (
+                       id integer primary key,
+                       last_read_date timestamp not null,
+                       token text null,
+                       login text not null unique,
+                       password text not null
+);
+
+CREATE TABLE records_user_settings (
+                                       id integer primary key,
+                                       user_id int not null constraint records_meta_user_id references users,
+                                       record_id int not null constraint records_meta_record_id references records,
+                                       starred bool not null default false,
+                                       unique(user_id, record_id)
+);
+
+CREATE TABLE sources_user_settings (
+                                       id integer primary key,
+                                       user_id int not null constraint sources_user_settings_user_id references users,
+                                       source_id int not null constraint sources_user_settings_source_id references sources,
+                                       unique(user_id, source_id)
+);
+
+CREATE TABLE record_tags (
+                             id integer primary key,
+                             tag text not null,
+                             user_id int not null constraint record_tags_user_id references users,
+                             record_id int not null constraint record_tags_record_id references records,
+                             unique(tag, user_id, record_id)
+);
+
+CREATE TABLE user_folders (
+                              id integer primary key,
+                              name text not null,
+                              user_id int not null constraint user_folders_user_id references users,
+                              parent_folder_id int null constraint user_folders_parent_folder references user_folders,
+                              unique(name, user_id)
+);
+
+CREATE TABLE user_source_to_folder (
+                                       id integer primary key,
+                                       user_source_id int not null constraint user_source_to_folder_user_sources references sources_user_settings,
+                                       folder_id int not null constraint user_source_to_folder_folder_id references user_folders
+);
+
+create unique index user_source_to_folder_user_source_id_uindex
+	on user_source_to_folder (user_source_id);
+
+CREATE TABLE source_tags (
+                             id integer primary key,
+                             tag text not null,
+                             user_id int not null constraint source_tags_user_id references users,
+                             source_id int not null constraint source_tags_record_id references sources,
+                             unique(tag, user_id, source_id)
+);
+
+CREATE TABLE webhooks (
+                          source_id int primary key constraint webhooks_source_id_fk references sources,
+                          token text not null unique,
+                          secret text not null,
+                          created_at timestamp not null default current_timestamp
+);
@@ -0,0 +1,23 @@
+ALTER TABLE records_user_settings ADD COLUMN hidden bool not null default false;
+ALTER TABLE records_user_settings ADD COLUMN read bool not null default false;
+
+CREATE TABLE user_rules (
+                            id integer primary key,
+                            user_id int not null constraint user_rules_user_id references users,
+                            name text not null,
+                            -- conditions, a record of the subscribed source matches all the set ones
+                            source_id int null constraint user_rules_source_id references sources,
+                            folder_id int null constraint user_rules_folder_id references user_folders on delete cascade,
+                            kind text null,
+                            pattern text null,
+                            -- one per line, any of them matches
+                            keywords text null,
+                            has_attachment bool not null default false,
+                            -- actions
+                            tag text null,
+                            star bool not null default false,
+                            hide bool not null default false,
+                            mark_read bool not null default false,
+                            notify bool not null default false,
+                            created_at timestamp not null default current_timestamp
+);
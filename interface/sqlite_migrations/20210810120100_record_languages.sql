@@ -0,0 +1,8 @@
+-- languages of the records hidden from the subscription of the user
+CREATE TABLE source_hidden_languages (
+                                         id integer primary key,
+                                         user_id int not null constraint source_hidden_languages_user_id references users,
+                                         source_id int not null constraint source_hidden_languages_source_id references sources,
+                                         language text not null,
+                                         unique(user_id, source_id, language)
+);
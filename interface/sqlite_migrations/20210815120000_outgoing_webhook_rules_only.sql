@@ -0,0 +1,2 @@
+-- webhooks receiving only the records of the rules with the notify action
+ALTER TABLE outgoing_webhooks add column rules_only bool not null default false;
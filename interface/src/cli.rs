@@ -1,7 +1,18 @@
+use crate::db::models::{NewOutgoingWebhook, NewUserRule};
 use crate::db::{migrate, queries};
 use crate::init;
-use clap::{arg_enum, value_t, App, Arg, SubCommand};
+use crate::outgoing_webhooks;
+use crate::reload;
+use crate::retention;
+use crate::rules;
+use crate::settings::SETTINGS;
+use chrono::NaiveDate;
+use clap::{value_t, App, Arg, SubCommand};
+use feeder::storage::Storage;
+use feeder::CancellationToken;
+use std::path::Path;
 use std::process::exit;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::time::Duration;
 
 macro_rules! parse_arg {
@@ -13,28 +24,18 @@ macro_rules! parse_arg {
     };
 }
 
-arg_enum! {
-    #[allow(non_camel_case_types)]
-    pub enum ArgSource {
-        web,
-        tg,
-        vk,
-    }
-}
-
-impl Into<feeder::Source> for ArgSource {
-    fn into(self) -> feeder::Source {
-        match self {
-            ArgSource::web => feeder::Source::Web,
-            ArgSource::tg => feeder::Source::Telegram,
-            ArgSource::vk => feeder::Source::Vk,
-        }
-    }
-}
-
 pub async fn run() {
+    let app = init::build_app().await;
+    let source_kinds = app.source_kinds();
     let cli_app = App::new("feeder")
         .arg(Arg::with_name("background").long("background").short("b").help("Enables background routines"))
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .global(true)
+                .help("config file, config/default and config/local are read if not specified"),
+        )
         .subcommand(SubCommand::with_name("migrate").about("Runs databases migration"))
         .subcommand(
             SubCommand::with_name("sync").about("Synchronizes sources").args(&[
@@ -43,7 +44,8 @@ pub async fn run() {
                     .short("s")
                     .long("source")
                     .takes_value(true)
-                    .possible_values(&ArgSource::variants()),
+                    .case_insensitive(true)
+                    .possible_values(&source_kinds),
                 Arg::with_name("secs_depth")
                     .help("seconds ago for source")
                     .short("d")
@@ -55,6 +57,7 @@ pub async fn run() {
         .subcommand(
             SubCommand::with_name("sources")
                 .subcommands(vec![
+                    SubCommand::with_name("kinds").about("list kinds of enabled sources"),
                     SubCommand::with_name("list").about("list sources")
                         .arg(
                             Arg::with_name("user_id").required(true).index(1),
@@ -73,7 +76,45 @@ pub async fn run() {
                         .args(&[
                             Arg::with_name("user_id").required(true).index(1),
                             Arg::with_name("source_id").required(true).index(2),
-                        ])
+                        ]),
+                    SubCommand::with_name("schedule")
+                        .about("set schedule of the source, adapts interval to publishing frequency if not specified")
+                        .args(&[
+                            Arg::with_name("source_id").required(true).index(1),
+                            Arg::with_name("schedule")
+                                .help("interval like 30m, 6h or cron expression like \"0 9 * * *\"")
+                                .index(2),
+                        ]),
+                    SubCommand::with_name("enable")
+                        .about("enable source disabled after errors, it's scraped again")
+                        .arg(
+                            Arg::with_name("source_id").required(true).index(1),
+                        ),
+                    SubCommand::with_name("retention")
+                        .about("override retention of the source, the global one is used if none is specified")
+                        .args(&[
+                            Arg::with_name("source_id").required(true).index(1),
+                            Arg::with_name("max_age_days").long("max-age-days").takes_value(true)
+                                .help("0 keeps records forever"),
+                            Arg::with_name("max_count").long("max-count").takes_value(true)
+                                .help("0 keeps all records"),
+                        ]),
+                    SubCommand::with_name("account")
+                        .about("assign source to the collector account which sees it, any account collects it if none is specified")
+                        .args(&[
+                            Arg::with_name("source_id").required(true).index(1),
+                            Arg::with_name("account")
+                                .help("name of vk or telegram account, \"default\" is the main one")
+                                .index(2),
+                        ]),
+                    SubCommand::with_name("hide_languages")
+                        .about("hide records of the languages from the subscription, none shows all of them again")
+                        .args(&[
+                            Arg::with_name("user_id").required(true).index(1),
+                            Arg::with_name("source_id").required(true).index(2),
+                            Arg::with_name("language").multiple(true).index(3)
+                                .help("ISO 639-3 code like eng"),
+                        ]),
                 ])
         )
         .subcommand(
@@ -86,6 +127,11 @@ pub async fn run() {
                             Arg::with_name("source_id").short("s").long("source").takes_value(true),
                             Arg::with_name("limit").short("l").long("limit").takes_value(true),
                             Arg::with_name("offset").short("o").long("offset").takes_value(true),
+                            Arg::with_name("collapse").short("c").long("collapse")
+                                .help("show one article per story published by several sources"),
+                            Arg::with_name("language").long("language").takes_value(true)
+                                .multiple(true).number_of_values(1)
+                                .help("ISO 639-3 code like eng, any of them"),
                         ]),
 
                     SubCommand::with_name("star")
@@ -108,6 +154,29 @@ pub async fn run() {
                             Arg::with_name("record_id").required(true).index(2),
                             Arg::with_name("tag").required(true).index(3),
                         ]),
+
+                    SubCommand::with_name("search").about("full-text search of articles, the best matches first")
+                        .args(&[
+                            Arg::with_name("user_id").required(true).index(1),
+                            Arg::with_name("query").required(true).index(2)
+                                .help("words, \"quoted phrase\", or, -excluded"),
+                            Arg::with_name("from").long("from").takes_value(true)
+                                .help("date like 2021-07-01"),
+                            Arg::with_name("to").long("to").takes_value(true)
+                                .help("date like 2021-07-31, exclusive"),
+                            Arg::with_name("source_id").short("s").long("source").takes_value(true),
+                            Arg::with_name("folder_id").short("f").long("folder").takes_value(true),
+                            Arg::with_name("tag").short("t").long("tag").takes_value(true),
+                            Arg::with_name("language").long("language").takes_value(true)
+                                .help("ISO 639-3 code like eng"),
+                            Arg::with_name("limit").short("l").long("limit").takes_value(true),
+                            Arg::with_name("offset").short("o").long("offset").takes_value(true),
+                        ]),
+
+                    SubCommand::with_name("versions").about("previous versions of the edited article")
+                        .args(&[
+                            Arg::with_name("record_id").required(true).index(1),
+                        ]),
                 ])
         )
         .subcommand(SubCommand::with_name("folders")
@@ -128,15 +197,120 @@ pub async fn run() {
                         Arg::with_name("folder_id").required(true).index(2),
                     ]),
             ])
+        )
+        .subcommand(SubCommand::with_name("webhooks")
+            .subcommands(vec![
+                SubCommand::with_name("list").about("list webhook sources"),
+                SubCommand::with_name("create").about("create webhook source, prints its url and secret")
+                    .arg(
+                        Arg::with_name("name").required(true).index(1),
+                    ),
+            ])
+        )
+        .subcommand(SubCommand::with_name("outgoing_webhooks")
+            .about("webhooks receiving new records of the subscribed sources")
+            .subcommands(vec![
+                SubCommand::with_name("list")
+                    .arg(
+                        Arg::with_name("user_id").required(true).index(1),
+                    ),
+                SubCommand::with_name("add").about("add webhook, prints its secret")
+                    .args(&[
+                        Arg::with_name("user_id").required(true).index(1),
+                        Arg::with_name("url").required(true).index(2),
+                        Arg::with_name("source_id").long("source").takes_value(true),
+                        Arg::with_name("folder_id").long("folder").takes_value(true),
+                        Arg::with_name("tag").long("tag").takes_value(true),
+                        Arg::with_name("keyword").long("keyword").takes_value(true)
+                            .help("case insensitive substring of the title or content"),
+                        Arg::with_name("rules_only").long("rules-only")
+                            .help("deliver only the records of the rules with --notify"),
+                    ]),
+                SubCommand::with_name("remove")
+                    .args(&[
+                        Arg::with_name("user_id").required(true).index(1),
+                        Arg::with_name("webhook_id").required(true).index(2),
+                    ]),
+                SubCommand::with_name("deliveries").about("delivery log of the webhook")
+                    .args(&[
+                        Arg::with_name("user_id").required(true).index(1),
+                        Arg::with_name("webhook_id").required(true).index(2),
+                        Arg::with_name("limit").short("l").long("limit").takes_value(true),
+                    ]),
+            ])
+        )
+        .subcommand(SubCommand::with_name("rules")
+            .about("actions applied to the new records of the subscribed sources")
+            .subcommands(vec![
+                SubCommand::with_name("list")
+                    .arg(
+                        Arg::with_name("user_id").required(true).index(1),
+                    ),
+                SubCommand::with_name("add").about("add rule, a record matches all the set conditions")
+                    .args(&[
+                        Arg::with_name("user_id").required(true).index(1),
+                        Arg::with_name("name").required(true).index(2),
+                        Arg::with_name("source_id").long("source").takes_value(true),
+                        Arg::with_name("folder_id").long("folder").takes_value(true),
+                        Arg::with_name("kind").long("kind").takes_value(true)
+                            .case_insensitive(true)
+                            .possible_values(&source_kinds),
+                        Arg::with_name("pattern").long("pattern").takes_value(true)
+                            .help("regular expression searched in the title and content"),
+                        Arg::with_name("keyword").long("keyword").takes_value(true)
+                            .multiple(true).number_of_values(1)
+                            .help("case insensitive, any of them in the title or content"),
+                        Arg::with_name("has_attachment").long("has-attachment"),
+                        Arg::with_name("tag").long("tag").takes_value(true),
+                        Arg::with_name("star").long("star"),
+                        Arg::with_name("hide").long("hide"),
+                        Arg::with_name("mark_read").long("mark-read"),
+                        Arg::with_name("notify").long("notify")
+                            .help("deliver to the --rules-only outgoing webhooks of the user"),
+                    ]),
+                SubCommand::with_name("remove")
+                    .args(&[
+                        Arg::with_name("user_id").required(true).index(1),
+                        Arg::with_name("rule_id").required(true).index(2),
+                    ]),
+            ])
+        )
+        .subcommand(
+            SubCommand::with_name("prune")
+                .about("removes records beyond retention with their files, starred and tagged records are kept")
+                .arg(
+                    Arg::with_name("dry_run").long("dry-run").help("only report what would be removed"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import_tg").about("imports telegram desktop export of channels")
+                .arg(
+                    Arg::with_name("path").help("result.json or export directory").required(true).index(1),
+                ),
         );
 
-    let app = init::build_app().await;
     let matches = cli_app.clone().get_matches();
 
+    let shutdown = CancellationToken::new();
     // TODO: app (tg source) must start without background
-    if matches.is_present("background") {
+    let background = if matches.is_present("background") {
         let app_runner = app.clone();
-        tokio::spawn(async move { app_runner.run().await });
+        let shutdown = shutdown.clone();
+        Some(tokio::spawn(async move {
+            let db_pool = app_runner.storage().pool();
+            tokio::join!(
+                app_runner.run(shutdown.clone()),
+                reload::run(app_runner.clone(), shutdown.clone()),
+                outgoing_webhooks::run(db_pool.clone(), shutdown.clone()),
+                retention::run(db_pool, shutdown)
+            )
+        }))
+    } else {
+        None
+    };
+    // other commands are short, default signal handling terminates them
+    if background.is_some() || matches.subcommand_name() == Some("server") {
+        tokio::spawn(cancel_on_signal(shutdown.clone()));
     }
 
     // TODO: wait until start properly
@@ -161,9 +335,14 @@ pub async fn run() {
                     .value_of("offset")
                     .map(|v| v.parse().expect("invalid source id"))
                     .unwrap_or(0);
+                let collapse = list_command.is_present("collapse");
+                let languages: Vec<String> = list_command
+                    .values_of("language")
+                    .map(|v| v.map(String::from).collect())
+                    .unwrap_or_default();
                 let pool = app.storage().pool();
                 let found = queries::records::get_records(
-                    &pool, user_id, source_id, None, false, limit, offset,
+                    &pool, user_id, source_id, None, false, collapse, &languages, limit, offset,
                 )
                 .await
                 .expect("can't get records");
@@ -196,6 +375,65 @@ pub async fn run() {
                     .await
                     .expect("can't perform tag removing");
             }
+
+            ("search", Some(search_cmd)) => {
+                let user_id = parse_arg!(search_cmd, "user_id");
+                let query: String = parse_arg!(search_cmd, "query");
+                let parse_date = |arg| {
+                    search_cmd.value_of(arg).map(|v| {
+                        NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                            .expect("invalid date")
+                            .and_hms(0, 0, 0)
+                    })
+                };
+                let filter = queries::records::SearchFilter {
+                    from_date: parse_date("from"),
+                    to_date: parse_date("to"),
+                    source_id: search_cmd
+                        .value_of("source_id")
+                        .map(|v| v.parse().expect("invalid source id")),
+                    folder_id: search_cmd
+                        .value_of("folder_id")
+                        .map(|v| v.parse().expect("invalid folder id")),
+                    tag: search_cmd.value_of("tag").map(String::from),
+                    language: search_cmd.value_of("language").map(String::from),
+                };
+                let limit = search_cmd
+                    .value_of("limit")
+                    .map(|v| v.parse().expect("invalid limit"))
+                    .unwrap_or(20);
+                let offset = search_cmd
+                    .value_of("offset")
+                    .map(|v| v.parse().expect("invalid offset"))
+                    .unwrap_or(0);
+                let found = queries::records::search_records(
+                    &app.storage().pool(),
+                    user_id,
+                    query.as_str(),
+                    &filter,
+                    limit,
+                    offset,
+                )
+                .await
+                .expect("can't search records");
+                for record in found {
+                    println!(
+                        "{}\t{:.3}\t{}\t{}",
+                        record.id,
+                        record.rank,
+                        record.title.unwrap_or_default(),
+                        record.snippet
+                    );
+                }
+            }
+
+            ("versions", Some(versions_cmd)) => {
+                let record_id = parse_arg!(versions_cmd, "record_id");
+                let versions = queries::records::get_versions(&app.storage().pool(), record_id)
+                    .await
+                    .expect("can't get record versions");
+                println!("{:?}", versions);
+            }
             _ => panic!(
                 "unexpected command: {:?}",
                 articles_command.subcommand_name()
@@ -206,6 +444,11 @@ pub async fn run() {
                 eprintln!("subcommand not specified");
                 exit(1)
             }
+            ("kinds", _) => {
+                for kind in &source_kinds {
+                    println!("{}", kind);
+                }
+            }
             ("list", Some(list_sub_cmd)) => {
                 let user_id = parse_arg!(list_sub_cmd, "user_id");
                 let list = queries::sources::get_for_user(&app.storage().pool(), user_id).await;
@@ -247,13 +490,84 @@ pub async fn run() {
                     .expect("subscription failed")
                     .map(|_| println!("subscription created"));
             }
+            ("schedule", Some(schedule_sub_cm)) => {
+                let source_id = parse_arg!(schedule_sub_cm, "source_id");
+                let source = app
+                    .storage()
+                    .set_source_schedule(source_id, schedule_sub_cm.value_of("schedule"))
+                    .await
+                    .expect("can't set schedule")
+                    .expect("source not found");
+                println!("next scrape at {}", source.next_scrape_at);
+            }
+            ("enable", Some(enable_sub_cm)) => {
+                let source_id = parse_arg!(enable_sub_cm, "source_id");
+                app.storage()
+                    .enable_source(source_id)
+                    .await
+                    .expect("can't enable source")
+                    .expect("source not found");
+                println!("source enabled");
+            }
+            ("retention", Some(retention_sub_cm)) => {
+                let source_id = parse_arg!(retention_sub_cm, "source_id");
+                let max_age_days = retention_sub_cm
+                    .value_of("max_age_days")
+                    .map(|v| v.parse().expect("invalid max_age_days specified"));
+                let max_count = retention_sub_cm
+                    .value_of("max_count")
+                    .map(|v| v.parse().expect("invalid max_count specified"));
+                queries::retention::set_source_retention(
+                    &app.storage().pool(),
+                    source_id,
+                    max_age_days,
+                    max_count,
+                )
+                .await
+                .expect("can't set retention");
+            }
+            ("account", Some(account_sub_cm)) => {
+                let source_id = parse_arg!(account_sub_cm, "source_id");
+                let account = account_sub_cm.value_of("account");
+                if let Some(account) = account {
+                    if !SETTINGS.collectors.account_names().contains(&account) {
+                        panic!("account {} isn't configured", account);
+                    }
+                }
+                let source = app
+                    .storage()
+                    .set_source_account(source_id, account.map(str::to_string))
+                    .await
+                    .expect("can't set account")
+                    .expect("source not found");
+                match source.account {
+                    Some(account) => println!("{} is collected by {}", source.name, account),
+                    None => println!("{} is collected by any account", source.name),
+                }
+            }
+            ("hide_languages", Some(hide_sub_cm)) => {
+                let user_id = parse_arg!(hide_sub_cm, "user_id");
+                let source_id = parse_arg!(hide_sub_cm, "source_id");
+                let languages = hide_sub_cm
+                    .values_of("language")
+                    .map(|v| v.map(str::to_lowercase).collect())
+                    .unwrap_or_default();
+                queries::sources::set_hidden_languages(
+                    &app.storage().pool(),
+                    user_id,
+                    source_id,
+                    languages,
+                )
+                .await
+                .expect("can't hide languages");
+            }
             _ => panic!(
                 "unexpected command: {:?}",
                 sources_command.subcommand_name()
             ),
         },
         ("server", _) => {
-            crate::grpc::server::run_server(app)
+            crate::grpc::server::run_server(app, shutdown.clone())
                 .await
                 .expect("can't run server");
         }
@@ -287,13 +601,216 @@ pub async fn run() {
             }
             _ => panic!("unexpected command: {:?}", folders_sub_cm.subcommand_name()),
         },
+        ("webhooks", Some(webhooks_sub_cm)) => match webhooks_sub_cm.subcommand() {
+            ("list", _) => {
+                let webhooks = queries::webhooks::get_webhooks(&app.storage().pool())
+                    .await
+                    .expect("can't load webhooks");
+                for webhook in webhooks {
+                    println!(
+                        "{}\t{}",
+                        webhook.source_id,
+                        webhook_url(webhook.token.as_str())
+                    );
+                }
+            }
+            ("create", Some(webhooks_create_sub_cm)) => {
+                let name = parse_arg!(webhooks_create_sub_cm, "name");
+                match queries::webhooks::create_webhook(&app.storage().pool(), name)
+                    .await
+                    .expect("can't create webhook")
+                {
+                    Some(webhook) => {
+                        println!("source: {}", webhook.source_id);
+                        println!("url: {}", webhook_url(webhook.token.as_str()));
+                        println!("secret: {}", webhook.secret);
+                    }
+                    None => {
+                        eprintln!("source with this name already exists");
+                        exit(1)
+                    }
+                }
+            }
+            _ => panic!(
+                "unexpected command: {:?}",
+                webhooks_sub_cm.subcommand_name()
+            ),
+        },
+        ("outgoing_webhooks", Some(outgoing_sub_cm)) => match outgoing_sub_cm.subcommand() {
+            ("list", Some(list_sub_cm)) => {
+                let user_id = parse_arg!(list_sub_cm, "user_id");
+                let webhooks = queries::outgoing_webhooks::get_outgoing_webhooks(
+                    &app.storage().pool(),
+                    user_id,
+                )
+                .await
+                .expect("can't load outgoing webhooks");
+                println!("{:?}", webhooks);
+            }
+            ("add", Some(add_sub_cm)) => {
+                let user_id = parse_arg!(add_sub_cm, "user_id");
+                let url: String = parse_arg!(add_sub_cm, "url");
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    eprintln!("url must be http or https");
+                    exit(1)
+                }
+                let webhook = queries::outgoing_webhooks::create_outgoing_webhook(
+                    &app.storage().pool(),
+                    user_id,
+                    NewOutgoingWebhook {
+                        url,
+                        source_id: add_sub_cm
+                            .value_of("source_id")
+                            .map(|v| v.parse().expect("invalid source id")),
+                        folder_id: add_sub_cm
+                            .value_of("folder_id")
+                            .map(|v| v.parse().expect("invalid folder id")),
+                        tag: add_sub_cm.value_of("tag").map(String::from),
+                        keyword: add_sub_cm.value_of("keyword").map(String::from),
+                        rules_only: add_sub_cm.is_present("rules_only"),
+                    },
+                )
+                .await
+                .expect("can't add outgoing webhook");
+                println!("id: {}", webhook.id);
+                println!("secret: {}", webhook.secret);
+            }
+            ("remove", Some(remove_sub_cm)) => {
+                let user_id = parse_arg!(remove_sub_cm, "user_id");
+                let webhook_id = parse_arg!(remove_sub_cm, "webhook_id");
+                queries::outgoing_webhooks::remove_outgoing_webhook(
+                    &app.storage().pool(),
+                    user_id,
+                    webhook_id,
+                )
+                .await
+                .expect("can't remove outgoing webhook");
+            }
+            ("deliveries", Some(deliveries_sub_cm)) => {
+                let user_id = parse_arg!(deliveries_sub_cm, "user_id");
+                let webhook_id = parse_arg!(deliveries_sub_cm, "webhook_id");
+                let limit = deliveries_sub_cm
+                    .value_of("limit")
+                    .map(|v| v.parse().expect("invalid limit"))
+                    .unwrap_or(50);
+                let deliveries = queries::outgoing_webhooks::get_deliveries(
+                    &app.storage().pool(),
+                    user_id,
+                    webhook_id,
+                    limit,
+                )
+                .await
+                .expect("can't load deliveries");
+                for delivery in deliveries {
+                    println!("{:?}", delivery);
+                }
+            }
+            _ => panic!(
+                "unexpected command: {:?}",
+                outgoing_sub_cm.subcommand_name()
+            ),
+        },
+        ("rules", Some(rules_sub_cm)) => match rules_sub_cm.subcommand() {
+            ("list", Some(list_sub_cm)) => {
+                let user_id = parse_arg!(list_sub_cm, "user_id");
+                let rules = queries::rules::get_rules(&app.storage().pool(), user_id)
+                    .await
+                    .expect("can't load rules");
+                for rule in rules {
+                    println!("{:?}", rule);
+                }
+            }
+            ("add", Some(add_sub_cm)) => {
+                let user_id = parse_arg!(add_sub_cm, "user_id");
+                let rule = NewUserRule {
+                    name: parse_arg!(add_sub_cm, "name"),
+                    source_id: add_sub_cm
+                        .value_of("source_id")
+                        .map(|v| v.parse().expect("invalid source id")),
+                    folder_id: add_sub_cm
+                        .value_of("folder_id")
+                        .map(|v| v.parse().expect("invalid folder id")),
+                    kind: add_sub_cm.value_of("kind").map(|v| {
+                        source_kinds
+                            .iter()
+                            .find(|k| k.eq_ignore_ascii_case(v))
+                            .expect("get invalid source")
+                            .to_string()
+                    }),
+                    pattern: add_sub_cm.value_of("pattern").map(String::from),
+                    keywords: add_sub_cm
+                        .values_of("keyword")
+                        .map(|v| v.map(String::from).collect())
+                        .unwrap_or_default(),
+                    has_attachment: add_sub_cm.is_present("has_attachment"),
+                    tag: add_sub_cm.value_of("tag").map(String::from),
+                    star: add_sub_cm.is_present("star"),
+                    hide: add_sub_cm.is_present("hide"),
+                    mark_read: add_sub_cm.is_present("mark_read"),
+                    notify: add_sub_cm.is_present("notify"),
+                };
+                match rules::create_rule(&app.storage().pool(), user_id, rule).await {
+                    Ok(rule) => println!("rule {} created", rule.id),
+                    Err(e) => {
+                        eprintln!("can't create rule: {}", e);
+                        exit(1)
+                    }
+                }
+            }
+            ("remove", Some(remove_sub_cm)) => {
+                let user_id = parse_arg!(remove_sub_cm, "user_id");
+                let rule_id = parse_arg!(remove_sub_cm, "rule_id");
+                queries::rules::remove_rule(&app.storage().pool(), user_id, rule_id)
+                    .await
+                    .expect("can't remove rule");
+            }
+            _ => panic!("unexpected command: {:?}", rules_sub_cm.subcommand_name()),
+        },
+        ("prune", Some(prune_sub_cm)) => {
+            let dry_run = prune_sub_cm.is_present("dry_run");
+            let report = retention::prune(&app.storage().pool(), dry_run)
+                .await
+                .expect("can't prune records");
+            for pruned in &report.sources {
+                println!(
+                    "source {}: {} records, {} files, {} bytes",
+                    pruned.source_id, pruned.records, pruned.files, pruned.bytes
+                );
+            }
+            println!(
+                "{} {} records, {} files, {} bytes",
+                if dry_run { "would remove" } else { "removed" },
+                report.records,
+                report.files,
+                report.bytes
+            );
+        }
+        ("import_tg", Some(import_tg_sub_cm)) => {
+            let path = import_tg_sub_cm
+                .value_of("path")
+                .expect("path not specified");
+            let importer = feeder::TelegramExportImporter::new(
+                app.storage(),
+                SETTINGS.collectors.tg.files_directory.as_str(),
+            )
+            .with_pipelines(app.pipelines());
+            let imported = importer
+                .import(Path::new(path))
+                .await
+                .expect("can't import telegram export");
+            println!("{} records imported", imported);
+        }
         ("sync", Some(sub_m)) => {
             let secs = value_t!(sub_m, "secs_depth", i32).expect("can't parse secs argument");
-            let source = sub_m
-                .value_of("source")
-                .map(|v| v.parse::<ArgSource>().expect("get invalid source"));
+            // possible values are case insensitive, registry keeps kinds as is
+            let kind = sub_m.value_of("source").map(|v| {
+                *source_kinds
+                    .iter()
+                    .find(|k| k.eq_ignore_ascii_case(v))
+                    .expect("get invalid source")
+            });
 
-            app.synchronize(secs, source.map(|s| s.into()))
+            app.synchronize(secs, kind)
                 .await
                 .expect("can't synchronize")
         }
@@ -303,4 +820,33 @@ pub async fn run() {
         }
         _ => panic!("unexpected command: {:?}", matches.subcommand_name()),
     }
+
+    if let Some(background) = background {
+        shutdown.cancel();
+        background.await.expect("background routines failed");
+    }
+}
+
+// cancels the token on the first SIGINT or SIGTERM, terminates on the second one
+async fn cancel_on_signal(shutdown: CancellationToken) {
+    let mut terminate = signal(SignalKind::terminate()).expect("can't listen SIGTERM");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("can't listen SIGINT");
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = interrupt.recv() => {},
+    }
+    log::info!("shutting down, pending updates are stored");
+    shutdown.cancel();
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = interrupt.recv() => {},
+    }
+    exit(1)
+}
+
+fn webhook_url(token: &str) -> String {
+    format!(
+        "http://{}:{}/webhooks/{}",
+        SETTINGS.server.host, SETTINGS.server.port, token
+    )
 }
@@ -1,21 +1,42 @@
 pub mod models;
+
+// runs the body with `$p` bound to the pool of either database, so queries
+// which both of them understand are written once
+macro_rules! on_pool {
+    ($pool:expr, $p:ident => $body:expr) => {
+        match $pool {
+            $crate::db::Pool::Pg($p) => $body,
+            $crate::db::Pool::Sqlite($p) => $body,
+        }
+    };
+}
+
 pub mod queries;
-use sqlx::postgres::{PgPool, PgPoolOptions};
 
-pub type Pool = PgPool;
+pub use feeder::storage::Pool;
+
+// sqlite has no arrays, aggregated tags are joined by the separator
+pub(crate) const SQLITE_TAGS_SEPARATOR: &str = "\u{1f}";
+
+pub(crate) fn split_tags(tags: Option<String>) -> Option<Vec<String>> {
+    tags.map(|t| t.split(SQLITE_TAGS_SEPARATOR).map(String::from).collect())
+}
 
 pub async fn init_pool(db_url: &str) -> Pool {
-    PgPoolOptions::new()
-        .max_connections(5)
-        .connect(db_url)
-        .await
-        .expect("can't initialize pool")
+    Pool::connect(db_url).await.expect("can't initialize pool")
 }
 
 pub async fn migrate(db_pool: Pool) {
-    sqlx::migrate!()
-        .set_ignore_missing(true)
-        .run(&db_pool)
-        .await
-        .expect("can't migrate");
+    match db_pool {
+        Pool::Pg(db_pool) => sqlx::migrate!()
+            .set_ignore_missing(true)
+            .run(&db_pool)
+            .await
+            .expect("can't migrate"),
+        Pool::Sqlite(db_pool) => sqlx::migrate!("./sqlite_migrations")
+            .set_ignore_missing(true)
+            .run(&db_pool)
+            .await
+            .expect("can't migrate"),
+    }
 }
@@ -12,6 +12,14 @@ pub struct RecordWithMeta {
     pub image: Option<String>,
     pub starred: Option<bool>,
     pub tags: Option<Vec<String>>,
+    pub edited: bool,
+    // other sources of the same story
+    pub also_seen_in: Vec<i32>,
+    pub read: bool,
+    // ISO 639-3 code, `None` if not detected
+    pub language: Option<String>,
+    // json of the kind specific fields, e.g. reddit score or telegram views
+    pub meta: Option<String>,
 }
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
@@ -23,8 +31,17 @@ pub struct SourceWithMeta {
     pub image: Option<String>,
     pub last_scrape_time: NaiveDateTime,
     pub external_link: String,
+    pub next_scrape_at: NaiveDateTime,
+    pub schedule: Option<String>,
+    pub last_success_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub last_error_class: Option<String>,
+    pub consecutive_failures: i32,
+    pub disabled_at: Option<NaiveDateTime>,
     pub folder_id: Option<i32>,
     pub tags: Option<Vec<String>>,
+    // ISO 639-3 codes of the languages hidden by the user
+    pub hidden_languages: Vec<String>,
 }
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
@@ -43,3 +60,132 @@ pub struct UserFolder {
     pub user_id: i32,
     pub parent_folder_id: Option<i32>,
 }
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct Webhook {
+    pub source_id: i32,
+    pub token: String,
+    pub secret: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct OutgoingWebhook {
+    pub id: i32,
+    pub user_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub source_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    pub tag: Option<String>,
+    pub keyword: Option<String>,
+    pub created_at: NaiveDateTime,
+    // only the records of the rules with the notify action are delivered
+    pub rules_only: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NewOutgoingWebhook {
+    pub url: String,
+    pub source_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    pub tag: Option<String>,
+    pub keyword: Option<String>,
+    pub rules_only: bool,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct OutgoingWebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub record_id: i32,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    // worker process sending the IN_PROGRESS delivery
+    pub leased_by: Option<String>,
+    pub lease_expires_at: Option<NaiveDateTime>,
+}
+
+/// Delivery claimed by the worker with its webhook and record.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct PendingDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+    pub record_id: i32,
+    pub source_id: i32,
+    pub source_name: String,
+    pub source_kind: String,
+    pub title: Option<String>,
+    pub content: String,
+    pub date: NaiveDateTime,
+    pub image: Option<String>,
+    pub external_link: String,
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct UserRule {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub source_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    pub kind: Option<String>,
+    pub pattern: Option<String>,
+    pub keywords: Option<String>,
+    pub has_attachment: bool,
+    pub tag: Option<String>,
+    pub star: bool,
+    pub hide: bool,
+    pub mark_read: bool,
+    pub notify: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NewUserRule {
+    pub name: String,
+    pub source_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    pub kind: Option<String>,
+    pub pattern: Option<String>,
+    pub keywords: Vec<String>,
+    pub has_attachment: bool,
+    pub tag: Option<String>,
+    pub star: bool,
+    pub hide: bool,
+    pub mark_read: bool,
+    pub notify: bool,
+}
+
+/// Record found by the full-text search.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct FoundRecord {
+    pub id: i32,
+    pub title: Option<String>,
+    pub source_record_id: String,
+    pub source_id: i32,
+    pub content: String,
+    pub date: NaiveDateTime,
+    pub image: Option<String>,
+    pub language: Option<String>,
+    pub rank: f32,
+    // fragments of the content with the matches in <b></b>
+    pub snippet: String,
+    pub meta: Option<String>,
+}
+
+/// Retention overrides of the source, `None` inherits the global limit.
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug)]
+pub struct SourceRetention {
+    pub source_id: i32,
+    pub max_age_days: Option<i32>,
+    pub max_count: Option<i32>,
+}
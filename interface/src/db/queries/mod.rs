@@ -1,4 +1,8 @@
 pub mod folders;
+pub mod outgoing_webhooks;
 pub mod records;
+pub mod retention;
+pub mod rules;
 pub mod sources;
 pub mod users;
+pub mod webhooks;
@@ -0,0 +1,224 @@
+use crate::db::models::{
+    NewOutgoingWebhook, OutgoingWebhook, OutgoingWebhookDelivery, PendingDelivery,
+};
+use crate::db::queries::webhooks::generate_secret;
+use crate::db::Pool;
+use crate::result::Result;
+use chrono::NaiveDateTime;
+use feeder::queue::Lease;
+
+// TODO: enum?
+pub const PENDING: &str = "PENDING";
+pub const IN_PROGRESS: &str = "IN_PROGRESS";
+pub const DELIVERED: &str = "DELIVERED";
+pub const FAILED: &str = "FAILED";
+
+pub async fn create_outgoing_webhook(
+    db_pool: &Pool,
+    user_id: i32,
+    webhook: NewOutgoingWebhook,
+) -> Result<OutgoingWebhook> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "INSERT INTO outgoing_webhooks \
+            (user_id, url, secret, source_id, folder_id, tag, keyword, rules_only) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *",
+        )
+        .bind(user_id)
+        .bind(webhook.url)
+        .bind(generate_secret(32))
+        .bind(webhook.source_id)
+        .bind(webhook.folder_id)
+        .bind(webhook.tag)
+        .bind(webhook.keyword)
+        .bind(webhook.rules_only)
+        .fetch_one(pool)
+        .await?
+    }))
+}
+
+pub async fn get_outgoing_webhooks(db_pool: &Pool, user_id: i32) -> Result<Vec<OutgoingWebhook>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * FROM outgoing_webhooks WHERE user_id = $1 ORDER BY id")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+    }))
+}
+
+/// Removes the webhook with its delivery log.
+pub async fn remove_outgoing_webhook(db_pool: &Pool, user_id: i32, id: i32) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        sqlx::query("DELETE FROM outgoing_webhooks WHERE user_id = $1 AND id = $2")
+            .bind(user_id)
+            .bind(id)
+            .execute(pool)
+            .await?;
+    });
+    Ok(())
+}
+
+/// Webhooks of the source subscribers whose source, folder and tag filters match it.
+/// Keywords depend on the record and are left to the caller.
+pub async fn get_matching_webhooks(db_pool: &Pool, source_id: i32) -> Result<Vec<OutgoingWebhook>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT w.* FROM outgoing_webhooks w \
+            JOIN sources_user_settings s ON s.user_id = w.user_id AND s.source_id = $1 \
+            WHERE (w.source_id IS NULL OR w.source_id = $1) \
+            AND (w.folder_id IS NULL OR EXISTS (SELECT 1 FROM user_source_to_folder f \
+                WHERE f.user_source_id = s.id AND f.folder_id = w.folder_id)) \
+            AND (w.tag IS NULL OR EXISTS (SELECT 1 FROM source_tags t \
+                WHERE t.user_id = w.user_id AND t.source_id = $1 AND t.tag = w.tag))",
+        )
+        .bind(source_id)
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+/// Queues the record for the webhook, a record is delivered to a webhook once.
+pub async fn add_delivery(db_pool: &Pool, webhook_id: i32, record_id: i32) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "INSERT INTO outgoing_webhook_deliveries (webhook_id, record_id) VALUES ($1, $2) \
+            ON CONFLICT (webhook_id, record_id) DO NOTHING",
+        )
+        .bind(webhook_id)
+        .bind(record_id)
+        .execute(pool)
+        .await?;
+    });
+    Ok(())
+}
+
+const PENDING_DELIVERY_FIELDS: &str = "d.id, d.webhook_id, d.attempts, w.url, w.secret, \
+    d.record_id, r.source_id, s.name AS source_name, s.kind AS source_kind, \
+    r.title, r.content, r.date, r.image, r.external_link";
+
+const PENDING_DELIVERY_JOINS: &str = "JOIN outgoing_webhooks w ON w.id = d.webhook_id \
+    JOIN records r ON r.id = d.record_id \
+    JOIN sources s ON s.id = r.source_id";
+
+/// Claims pending deliveries due by `now`, the oldest first, for the worker of the lease.
+/// Claimed ones are skipped by other workers until their result is stored or the lease expires.
+pub async fn claim_deliveries(
+    db_pool: &Pool,
+    now: NaiveDateTime,
+    lease: &Lease,
+) -> Result<Vec<PendingDelivery>> {
+    let db_pool = match db_pool {
+        Pool::Pg(db_pool) => db_pool,
+        Pool::Sqlite(db_pool) => {
+            // sqlite has no RETURNING in CTE, claimed rows are read back by the lease
+            let mut tx = db_pool.begin().await?;
+            sqlx::query(
+                "UPDATE outgoing_webhook_deliveries \
+                SET status = $1, leased_by = $3, lease_expires_at = $4 \
+                WHERE id IN ( \
+                    SELECT id FROM outgoing_webhook_deliveries \
+                    WHERE (status = $2 AND next_attempt_at <= $5) \
+                        OR (status = $1 AND lease_expires_at <= $5) \
+                    ORDER BY next_attempt_at LIMIT $6 \
+                )",
+            )
+            .bind(IN_PROGRESS)
+            .bind(PENDING)
+            .bind(&lease.worker)
+            .bind(lease.expires_at)
+            .bind(now)
+            .bind(lease.limit)
+            .execute(&mut tx)
+            .await?;
+            let deliveries = sqlx::query_as(&format!(
+                "SELECT {} FROM outgoing_webhook_deliveries d {} \
+                WHERE d.status = $1 AND d.leased_by = $2 AND d.lease_expires_at = $3 \
+                ORDER BY d.next_attempt_at",
+                PENDING_DELIVERY_FIELDS, PENDING_DELIVERY_JOINS
+            ))
+            .bind(IN_PROGRESS)
+            .bind(&lease.worker)
+            .bind(lease.expires_at)
+            .fetch_all(&mut tx)
+            .await?;
+            tx.commit().await?;
+            return Ok(deliveries);
+        }
+    };
+    Ok(sqlx::query_as(&format!(
+        "WITH claimed AS ( \
+            UPDATE outgoing_webhook_deliveries \
+            SET status = $1, leased_by = $3, lease_expires_at = $4 \
+            WHERE id IN ( \
+                SELECT id FROM outgoing_webhook_deliveries \
+                WHERE (status = $2 AND next_attempt_at <= $5) \
+                    OR (status = $1 AND lease_expires_at <= $5) \
+                ORDER BY next_attempt_at LIMIT $6 \
+                FOR UPDATE SKIP LOCKED \
+            ) RETURNING * \
+        ) \
+        SELECT {} FROM claimed d {} ORDER BY d.next_attempt_at",
+        PENDING_DELIVERY_FIELDS, PENDING_DELIVERY_JOINS
+    ))
+    .bind(IN_PROGRESS)
+    .bind(PENDING)
+    .bind(&lease.worker)
+    .bind(lease.expires_at)
+    .bind(now)
+    .bind(lease.limit)
+    .fetch_all(db_pool)
+    .await?)
+}
+
+/// Result of a delivery attempt, releases the lease.
+#[derive(Debug)]
+pub struct DeliveryResult {
+    pub status: &'static str,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+pub async fn set_delivery_result(db_pool: &Pool, id: i32, result: DeliveryResult) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "UPDATE outgoing_webhook_deliveries SET status = $2, attempts = $3, \
+            response_status = $4, last_error = $5, next_attempt_at = $6, delivered_at = $7, \
+            leased_by = NULL, lease_expires_at = NULL \
+            WHERE id = $1",
+        )
+        .bind(id)
+        .bind(result.status)
+        .bind(result.attempts)
+        .bind(result.response_status)
+        .bind(result.last_error)
+        .bind(result.next_attempt_at)
+        .bind(result.delivered_at)
+        .execute(pool)
+        .await?;
+    });
+    Ok(())
+}
+
+/// Delivery log of the user's webhook, the latest first.
+pub async fn get_deliveries(
+    db_pool: &Pool,
+    user_id: i32,
+    webhook_id: i32,
+    limit: i64,
+) -> Result<Vec<OutgoingWebhookDelivery>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT d.* FROM outgoing_webhook_deliveries d \
+            JOIN outgoing_webhooks w ON w.id = d.webhook_id \
+            WHERE w.user_id = $1 AND d.webhook_id = $2 ORDER BY d.id DESC LIMIT $3",
+        )
+        .bind(user_id)
+        .bind(webhook_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?
+    }))
+}
@@ -1,18 +1,83 @@
-use crate::db::models::RecordWithMeta;
-use crate::db::Pool;
+use crate::db::models::{FoundRecord, RecordWithMeta};
+use crate::db::{split_tags, Pool, SQLITE_TAGS_SEPARATOR};
 use crate::result::Result;
-use feeder::models::Record;
+use chrono::NaiveDateTime;
+use feeder::models::{Record, RecordVersion};
 use sql_builder::SqlBuilder;
 
+// `RecordWithMeta` with joined tags
+#[derive(sqlx::FromRow)]
+struct SqliteRecordWithMeta {
+    id: i32,
+    title: Option<String>,
+    guid: String,
+    source_id: i32,
+    content: String,
+    date: NaiveDateTime,
+    image: Option<String>,
+    starred: Option<bool>,
+    tags: Option<String>,
+    edited: bool,
+    also_seen_in: Option<String>,
+    read: bool,
+    language: Option<String>,
+    meta: Option<String>,
+}
+
+impl From<SqliteRecordWithMeta> for RecordWithMeta {
+    fn from(record: SqliteRecordWithMeta) -> Self {
+        Self {
+            id: record.id,
+            title: record.title,
+            guid: record.guid,
+            source_id: record.source_id,
+            content: record.content,
+            date: record.date,
+            image: record.image,
+            starred: record.starred,
+            tags: split_tags(record.tags),
+            edited: record.edited,
+            also_seen_in: record
+                .also_seen_in
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|id| id.parse().ok())
+                .collect(),
+            read: record.read,
+            language: record.language,
+            meta: record.meta,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn get_records(
     db_pool: &Pool,
     user_id: i32,
     source_id: Option<i32>,
     record_id: Option<i32>,
     only_starred: bool,
+    collapse_duplicates: bool,
+    languages: &[String],
     limit: i64,
     offset: i64,
 ) -> Result<Vec<RecordWithMeta>> {
+    let tags_field = match db_pool {
+        Pool::Pg(_) => "array_agg(rt.tag) filter(where rt.tag is not null) as tags".to_string(),
+        Pool::Sqlite(_) => format!("group_concat(rt.tag, '{}') as tags", SQLITE_TAGS_SEPARATOR),
+    };
+    let duplicates =
+        "FROM records d WHERE d.cluster_id = r.cluster_id AND d.source_id <> r.source_id";
+    let also_seen_in_field = match db_pool {
+        Pool::Pg(_) => format!(
+            "array(SELECT DISTINCT d.source_id {}) as also_seen_in",
+            duplicates
+        ),
+        Pool::Sqlite(_) => format!(
+            "(SELECT group_concat(DISTINCT d.source_id) {}) as also_seen_in",
+            duplicates
+        ),
+    };
     let mut query = SqlBuilder::select_from("records as r");
     query
         .fields(&[
@@ -24,14 +89,22 @@ pub async fn get_records(
             "r.date",
             "r.image",
             "coalesce(rus.starred, false) as starred",
-            "array_agg(rt.tag) filter(where rt.tag is not null) as tags",
+            tags_field.as_str(),
+            "r.updated_at is not null as edited",
+            also_seen_in_field.as_str(),
+            "coalesce(rus.read, false) as read",
+            "r.language",
+            "r.meta",
         ])
         .left()
         .join("records_user_settings as rus")
-        .on("r.id = rus.record_id")
+        .on(format!(
+            "r.id = rus.record_id AND rus.user_id = {}",
+            user_id
+        ))
         .left()
         .join("record_tags as rt")
-        .on("rt.user_id = rus.user_id AND rt.record_id = r.id")
+        .on(format!("rt.record_id = r.id AND rt.user_id = {}", user_id))
         .left()
         .join("sources_user_settings as sus")
         .on("sus.source_id = r.source_id")
@@ -39,7 +112,7 @@ pub async fn get_records(
         .join("sources as s")
         .on("s.id = sus.source_id")
         .and_where_eq("sus.user_id", user_id)
-        .group_by("r.id, rus.starred")
+        .group_by("r.id, rus.starred, rus.read")
         .limit(limit)
         .offset(offset)
         .order_desc("r.date");
@@ -47,18 +120,47 @@ pub async fn get_records(
         query.and_where_eq("r.source_id", sid);
     }
 
+    // hidden by the rules, still available by id
     if let Some(rid) = record_id {
         query.and_where_eq("r.id", rid);
+    } else {
+        query.and_where("NOT coalesce(rus.hidden, false)");
+        query.and_where(format!(
+            "NOT EXISTS (SELECT 1 FROM source_hidden_languages hl \
+            WHERE hl.user_id = {} AND hl.source_id = r.source_id AND hl.language = r.language)",
+            user_id
+        ));
+    }
+
+    if !languages.is_empty() {
+        query.and_where_in_quoted("r.language", languages);
     }
 
     if only_starred {
         query.and_where("rus.starred");
     }
 
+    // the earliest record of the story among subscribed sources stands for the cluster
+    if collapse_duplicates {
+        query.and_where(format!(
+            "r.cluster_id IS NULL OR NOT EXISTS (SELECT 1 FROM records d \
+            INNER JOIN sources_user_settings dus ON dus.source_id = d.source_id \
+            WHERE d.cluster_id = r.cluster_id AND d.id < r.id AND dus.user_id = {})",
+            user_id
+        ));
+    }
+
     let query = query
         .sql()
         .map_err(|e| crate::result::Error::InternalServerError(e.to_string()))?;
-    Ok(sqlx::query_as(query.as_str()).fetch_all(db_pool).await?)
+    Ok(match db_pool {
+        Pool::Pg(db_pool) => sqlx::query_as(query.as_str()).fetch_all(db_pool).await?,
+        Pool::Sqlite(db_pool) => {
+            let records: Vec<SqliteRecordWithMeta> =
+                sqlx::query_as(query.as_str()).fetch_all(db_pool).await?;
+            records.into_iter().map(From::from).collect()
+        }
+    })
 }
 
 pub async fn mark_record(
@@ -67,67 +169,74 @@ pub async fn mark_record(
     record_id: i32,
     starred: bool,
 ) -> Result<RecordWithMeta> {
-    sqlx::query!(
-        r#"
-    INSERT INTO records_user_settings (record_id, user_id, starred) 
-    VALUES ($1, $2, $3)
-    ON CONFLICT (user_id, record_id) 
-    DO UPDATE SET 
-        starred = EXCLUDED.starred"#,
-        record_id,
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "INSERT INTO records_user_settings (record_id, user_id, starred) \
+            VALUES ($1, $2, $3) \
+            ON CONFLICT (user_id, record_id) DO UPDATE SET starred = EXCLUDED.starred",
+        )
+        .bind(record_id)
+        .bind(user_id)
+        .bind(starred)
+        .execute(pool)
+        .await?;
+    });
+
+    Ok(get_records(
+        db_pool,
         user_id,
-        starred
-    )
-    .execute(db_pool)
-    .await?;
-
-    Ok(
-        get_records(db_pool, user_id, None, Some(record_id), starred, 1, 0)
-            .await?
-            .first()
-            .cloned()
-            .unwrap(),
+        None,
+        Some(record_id),
+        starred,
+        false,
+        &[],
+        1,
+        0,
     )
+    .await?
+    .first()
+    .cloned()
+    .unwrap())
 }
 
 pub async fn get_tags(db_pool: &Pool, user_id: i32, record_id: i32) -> Result<Vec<String>> {
-    let record = sqlx::query!(
-        "SELECT array_agg(tag) as tags FROM record_tags WHERE user_id = $1 AND record_id = $2",
-        user_id,
-        record_id
-    )
-    .fetch_one(db_pool)
-    .await?;
-    Ok(record.tags.unwrap_or_default())
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar(
+            "SELECT tag FROM record_tags WHERE user_id = $1 AND record_id = $2",
+        )
+        .bind(user_id)
+        .bind(record_id)
+        .fetch_all(pool)
+        .await?
+    }))
 }
 
 pub async fn add_tag(db_pool: &Pool, user_id: i32, record_id: i32, tag: String) -> Result<()> {
-    sqlx::query!(
-        r#"
-    INSERT INTO record_tags (record_id, user_id, tag)
-    VALUES ($1, $2, $3)
-    ON CONFLICT (record_id, user_id) DO NOTHING
-        "#,
-        record_id,
-        user_id,
-        tag
-    )
-    .execute(db_pool)
-    .await?;
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "INSERT INTO record_tags (record_id, user_id, tag) VALUES ($1, $2, $3) \
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(record_id)
+        .bind(user_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    });
     Ok(())
 }
 
 pub async fn remove_tag(db_pool: &Pool, user_id: i32, record_id: i32, tag: String) -> Result<()> {
-    sqlx::query!(
-        r#"
-    DELETE FROM record_tags
-    WHERE record_id = $1 AND user_id = $2 and tag = $3"#,
-        record_id,
-        user_id,
-        tag
-    )
-    .execute(db_pool)
-    .await?;
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "DELETE FROM record_tags WHERE record_id = $1 AND user_id = $2 and tag = $3",
+        )
+        .bind(record_id)
+        .bind(user_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+    });
     Ok(())
 }
 
@@ -137,13 +246,206 @@ pub async fn get_by_source_id(
     limit: i64,
     offset: i64,
 ) -> Result<Vec<Record>> {
-    Ok(sqlx::query_as!(
-        Record,
-        "SELECT * FROM records WHERE source_id = $1 ORDER BY date DESC limit $2 offset $3",
-        source_id,
-        limit,
-        offset
-    )
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT * FROM records WHERE source_id = $1 ORDER BY date DESC limit $2 offset $3",
+        )
+        .bind(source_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+/// Previous versions of the record, the latest first.
+pub async fn get_versions(db_pool: &Pool, record_id: i32) -> Result<Vec<RecordVersion>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT * FROM record_versions WHERE record_id = $1 \
+            ORDER BY replaced_at DESC, id DESC",
+        )
+        .bind(record_id)
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+const HEADLINE_OPTIONS: &str =
+    "StartSel=<b>, StopSel=</b>, MaxWords=30, MinWords=10, MaxFragments=2, FragmentDelimiter=\" … \"";
+const SQLITE_SNIPPET_CHARS: usize = 100;
+
+// conditions of the search filter shared by both databases, `$1` is the user
+// and `$3`..`$7`, `$10` are the filter fields
+const SEARCH_FILTER: &str = "AND ($3 IS NULL OR r.date >= $3) \
+    AND ($4 IS NULL OR r.date < $4) \
+    AND ($5 IS NULL OR r.source_id = $5) \
+    AND ($6 IS NULL OR EXISTS (SELECT 1 FROM user_source_to_folder f \
+        WHERE f.user_source_id = sus.id AND f.folder_id = $6)) \
+    AND ($7 IS NULL OR EXISTS (SELECT 1 FROM record_tags t \
+        WHERE t.record_id = r.id AND t.user_id = $1 AND t.tag = $7)) \
+    AND NOT EXISTS (SELECT 1 FROM records_user_settings rus \
+        WHERE rus.record_id = r.id AND rus.user_id = $1 AND rus.hidden) \
+    AND ($10 IS NULL OR r.language = $10) \
+    AND NOT EXISTS (SELECT 1 FROM source_hidden_languages hl \
+        WHERE hl.user_id = $1 AND hl.source_id = r.source_id AND hl.language = r.language)";
+
+// text search configs of language_search_config
+const SEARCH_CONFIGS: &[&str] = &[
+    "english",
+    "russian",
+    "german",
+    "french",
+    "spanish",
+    "italian",
+    "portuguese",
+    "dutch",
+    "swedish",
+    "danish",
+    "norwegian",
+    "finnish",
+    "hungarian",
+    "romanian",
+    "turkish",
+    "simple",
+];
+
+/// Filters of the search, records match all the set ones.
+#[derive(Clone, Debug, Default)]
+pub struct SearchFilter {
+    pub from_date: Option<NaiveDateTime>,
+    pub to_date: Option<NaiveDateTime>,
+    pub source_id: Option<i32>,
+    pub folder_id: Option<i32>,
+    // tag of the record
+    pub tag: Option<String>,
+    // ISO 639-3 code of the detected language
+    pub language: Option<String>,
+}
+
+/// Full-text search over the records of the subscribed sources, the best matches first.
+/// Query is in the web search syntax: `"quoted phrase"`, `or`, `-excluded`.
+///
+/// Every record is matched with the text search config of its language.
+/// Sqlite has no text search, records containing the query are found, the latest first.
+pub async fn search_records(
+    db_pool: &Pool,
+    user_id: i32,
+    query: &str,
+    filter: &SearchFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<FoundRecord>> {
+    let db_pool = match db_pool {
+        Pool::Pg(db_pool) => db_pool,
+        Pool::Sqlite(db_pool) => {
+            let records: Vec<FoundRecord> = sqlx::query_as(&format!(
+                "SELECT r.id, r.title, r.source_record_id, r.source_id, r.content, r.date, \
+                r.image, r.language, 0.0 AS rank, '' AS snippet, r.meta \
+                FROM records r \
+                JOIN sources_user_settings sus ON sus.source_id = r.source_id AND sus.user_id = $1 \
+                WHERE (lower(coalesce(r.title, '')) LIKE $2 OR lower(r.content) LIKE $2) {} \
+                ORDER BY r.date DESC LIMIT $8 OFFSET $9",
+                SEARCH_FILTER
+            ))
+            .bind(user_id)
+            .bind(format!("%{}%", query.to_lowercase()))
+            .bind(filter.from_date)
+            .bind(filter.to_date)
+            .bind(filter.source_id)
+            .bind(filter.folder_id)
+            .bind(filter.tag.as_ref())
+            .bind(limit)
+            .bind(offset)
+            .bind(filter.language.as_ref())
+            .fetch_all(db_pool)
+            .await?;
+            return Ok(records
+                .into_iter()
+                .map(|record| FoundRecord {
+                    snippet: highlight(record.content.as_str(), query),
+                    ..record
+                })
+                .collect());
+        }
+    };
+    // the query is built per config with a constant config, so the match can use
+    // record_search_search_vector_idx
+    let matches = SEARCH_CONFIGS
+        .iter()
+        .map(|config| {
+            format!(
+                "(rs.config = '{0}'::regconfig \
+                AND rs.search_vector @@ websearch_to_tsquery('{0}', $2))",
+                config
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    Ok(sqlx::query_as(&format!(
+        "SELECT r.id, r.title, r.source_record_id, r.source_id, r.content, r.date, r.image, \
+        r.language, \
+        ts_rank_cd(rs.search_vector, websearch_to_tsquery(rs.config, $2), 32) AS rank, \
+        ts_headline(rs.config, regexp_replace(r.content, '<[^>]*>', ' ', 'g'), \
+            websearch_to_tsquery(rs.config, $2), $11) AS snippet, \
+        r.meta \
+        FROM records r \
+        JOIN record_search rs ON rs.record_id = r.id \
+        JOIN sources_user_settings sus ON sus.source_id = r.source_id AND sus.user_id = $1 \
+        WHERE ({}) {} \
+        ORDER BY 9 DESC, r.date DESC LIMIT $8 OFFSET $9",
+        matches, SEARCH_FILTER
+    ))
+    .bind(user_id)
+    .bind(query)
+    .bind(filter.from_date)
+    .bind(filter.to_date)
+    .bind(filter.source_id)
+    .bind(filter.folder_id)
+    .bind(filter.tag.as_ref())
+    .bind(limit)
+    .bind(offset)
+    .bind(filter.language.as_ref())
+    .bind(HEADLINE_OPTIONS)
     .fetch_all(db_pool)
     .await?)
 }
+
+// text around the first occurrence of the query, the markup is stripped
+fn highlight(content: &str, query: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut in_tag = false;
+    for c in content.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let needle: Vec<char> = query.to_lowercase().chars().collect();
+    let start = match lower
+        .windows(needle.len().max(1))
+        .position(|w| w == needle.as_slice())
+    {
+        Some(start) if !needle.is_empty() => start,
+        _ => return chars.iter().take(SQLITE_SNIPPET_CHARS * 2).collect(),
+    };
+    let end = start + needle.len();
+    let from = start.saturating_sub(SQLITE_SNIPPET_CHARS);
+    let to = (end + SQLITE_SNIPPET_CHARS).min(chars.len());
+    format!(
+        "{}<b>{}</b>{}",
+        chars[from..start].iter().collect::<String>(),
+        chars[start..end].iter().collect::<String>(),
+        chars[end..to].iter().collect::<String>()
+    )
+}
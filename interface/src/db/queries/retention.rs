@@ -0,0 +1,200 @@
+use crate::db::models::SourceRetention;
+use crate::db::Pool;
+use crate::result::Result;
+use chrono::NaiveDateTime;
+
+// sqlite has no arrays, ids are integers, so they are inlined for both databases,
+// `IN (NULL)` matches nothing and unlike `IN ()` is valid in postgres
+fn inline_ids(ids: &[i32]) -> String {
+    if ids.is_empty() {
+        return "NULL".to_string();
+    }
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Retention of every source, overrides are `None` if not set.
+pub async fn get_source_retentions(db_pool: &Pool) -> Result<Vec<SourceRetention>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT s.id AS source_id, sr.max_age_days, sr.max_count FROM sources s \
+            LEFT JOIN source_retention sr ON sr.source_id = s.id ORDER BY s.id",
+        )
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+/// Overrides the retention of the source, the override is removed if both are `None`.
+pub async fn set_source_retention(
+    db_pool: &Pool,
+    source_id: i32,
+    max_age_days: Option<i32>,
+    max_count: Option<i32>,
+) -> Result<()> {
+    let remove = max_age_days.is_none() && max_count.is_none();
+    on_pool!(db_pool, pool => {
+        if remove {
+            sqlx::query("DELETE FROM source_retention WHERE source_id = $1")
+                .bind(source_id)
+                .execute(pool)
+                .await?;
+        } else {
+            sqlx::query(
+                "INSERT INTO source_retention (source_id, max_age_days, max_count) \
+                VALUES ($1, $2, $3) ON CONFLICT (source_id) DO UPDATE SET \
+                max_age_days = EXCLUDED.max_age_days, max_count = EXCLUDED.max_count",
+            )
+            .bind(source_id)
+            .bind(max_age_days)
+            .bind(max_count)
+            .execute(pool)
+            .await?;
+        }
+    });
+    Ok(())
+}
+
+/// Records of the source published before `before` or beyond the latest `keep` ones.
+/// Starred or tagged records are never returned.
+pub async fn get_expired_records(
+    db_pool: &Pool,
+    source_id: i32,
+    before: Option<NaiveDateTime>,
+    keep: Option<i64>,
+    limit: i64,
+) -> Result<Vec<i32>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar(
+            "SELECT r.id FROM (SELECT id, date, \
+                ROW_NUMBER() OVER (ORDER BY date DESC, id DESC) AS position \
+                FROM records WHERE source_id = $1) r \
+            WHERE (r.date < $2 OR r.position > $3) \
+            AND NOT EXISTS (SELECT 1 FROM records_user_settings rus \
+                WHERE rus.record_id = r.id AND rus.starred) \
+            AND NOT EXISTS (SELECT 1 FROM record_tags t WHERE t.record_id = r.id) \
+            ORDER BY r.id LIMIT $4",
+        )
+        .bind(source_id)
+        .bind(before)
+        .bind(keep)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+/// Local paths of the downloaded files of the records.
+pub async fn get_local_files(db_pool: &Pool, record_ids: &[i32]) -> Result<Vec<String>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar(
+            format!(
+                "SELECT local_path FROM files \
+                WHERE record_id IN ({}) AND local_path IS NOT NULL",
+                inline_ids(record_ids)
+            )
+            .as_str(),
+        )
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+/// Records deleted by the retention and the local paths of their files.
+#[derive(Debug, Default)]
+pub struct DeletedRecords {
+    pub count: u64,
+    pub local_files: Vec<String>,
+}
+
+/// Deletes the records with their files, versions, user settings and deliveries.
+///
+/// Expiration and the starred and tagged exclusions are checked again together
+/// with the deletion, so records starred or tagged in the meantime are kept.
+pub async fn delete_expired_records(
+    db_pool: &Pool,
+    source_id: i32,
+    before: Option<NaiveDateTime>,
+    keep: Option<i64>,
+    record_ids: &[i32],
+) -> Result<DeletedRecords> {
+    // new settings and tags wait for the records in postgres, so the existing settings
+    // can't be starred, sqlite fails the writes of the transaction if the records are
+    // changed after it has read them, so the check can't go stale
+    let locks = match db_pool {
+        Pool::Pg(_) => vec![
+            format!(
+                "SELECT id FROM records WHERE id IN ({}) FOR UPDATE",
+                inline_ids(record_ids)
+            ),
+            format!(
+                "SELECT id FROM records_user_settings WHERE record_id IN ({}) FOR UPDATE",
+                inline_ids(record_ids)
+            ),
+        ],
+        Pool::Sqlite(_) => vec![],
+    };
+    on_pool!(db_pool, pool => {
+        let mut tx = pool.begin().await?;
+        for lock in &locks {
+            sqlx::query(lock.as_str()).execute(&mut tx).await?;
+        }
+        let record_ids: Vec<i32> = sqlx::query_scalar(
+            format!(
+                "SELECT r.id FROM (SELECT id, date, \
+                    ROW_NUMBER() OVER (ORDER BY date DESC, id DESC) AS position \
+                    FROM records WHERE source_id = $1) r \
+                WHERE r.id IN ({}) AND (r.date < $2 OR r.position > $3) \
+                AND NOT EXISTS (SELECT 1 FROM records_user_settings rus \
+                    WHERE rus.record_id = r.id AND rus.starred) \
+                AND NOT EXISTS (SELECT 1 FROM record_tags t WHERE t.record_id = r.id)",
+                inline_ids(record_ids)
+            )
+            .as_str(),
+        )
+        .bind(source_id)
+        .bind(before)
+        .bind(keep)
+        .fetch_all(&mut tx)
+        .await?;
+        let ids = inline_ids(&record_ids);
+        let local_files = sqlx::query_scalar(
+            format!(
+                "SELECT local_path FROM files \
+                WHERE record_id IN ({}) AND local_path IS NOT NULL",
+                ids
+            )
+            .as_str(),
+        )
+        .fetch_all(&mut tx)
+        .await?;
+        for table in &[
+            "files",
+            "records_user_settings",
+            "record_tags",
+            "record_versions",
+            "outgoing_webhook_deliveries",
+        ] {
+            sqlx::query(format!("DELETE FROM {} WHERE record_id IN ({})", table, ids).as_str())
+                .execute(&mut tx)
+                .await?;
+        }
+        sqlx::query(
+            format!(
+                "UPDATE records SET cluster_id = NULL WHERE cluster_id IN ({})",
+                ids
+            )
+            .as_str(),
+        )
+        .execute(&mut tx)
+        .await?;
+        let count = sqlx::query(format!("DELETE FROM records WHERE id IN ({})", ids).as_str())
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(DeletedRecords { count, local_files })
+    })
+}
@@ -0,0 +1,118 @@
+use crate::db::models::{NewUserRule, UserRule};
+use crate::db::Pool;
+use crate::result::Result;
+
+pub const KEYWORDS_SEPARATOR: &str = "\n";
+
+pub fn split_keywords(keywords: &Option<String>) -> Vec<String> {
+    keywords
+        .iter()
+        .flat_map(|k| k.split(KEYWORDS_SEPARATOR))
+        .filter(|k| !k.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub async fn create_rule(db_pool: &Pool, user_id: i32, rule: NewUserRule) -> Result<UserRule> {
+    let keywords = Some(rule.keywords.join(KEYWORDS_SEPARATOR)).filter(|k| !k.is_empty());
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "INSERT INTO user_rules (user_id, name, source_id, folder_id, kind, pattern, \
+            keywords, has_attachment, tag, star, hide, mark_read, notify) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING *",
+        )
+        .bind(user_id)
+        .bind(rule.name)
+        .bind(rule.source_id)
+        .bind(rule.folder_id)
+        .bind(rule.kind)
+        .bind(rule.pattern)
+        .bind(keywords)
+        .bind(rule.has_attachment)
+        .bind(rule.tag)
+        .bind(rule.star)
+        .bind(rule.hide)
+        .bind(rule.mark_read)
+        .bind(rule.notify)
+        .fetch_one(pool)
+        .await?
+    }))
+}
+
+pub async fn get_rules(db_pool: &Pool, user_id: i32) -> Result<Vec<UserRule>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * FROM user_rules WHERE user_id = $1 ORDER BY id")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+    }))
+}
+
+pub async fn remove_rule(db_pool: &Pool, user_id: i32, id: i32) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        sqlx::query("DELETE FROM user_rules WHERE user_id = $1 AND id = $2")
+            .bind(user_id)
+            .bind(id)
+            .execute(pool)
+            .await?;
+    });
+    Ok(())
+}
+
+/// Rules of the source subscribers whose source and folder conditions match it.
+/// Other conditions depend on the record and are left to the caller.
+pub async fn get_matching_rules(db_pool: &Pool, source_id: i32) -> Result<Vec<UserRule>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "SELECT r.* FROM user_rules r \
+            JOIN sources_user_settings s ON s.user_id = r.user_id AND s.source_id = $1 \
+            WHERE (r.source_id IS NULL OR r.source_id = $1) \
+            AND (r.folder_id IS NULL OR EXISTS (SELECT 1 FROM user_source_to_folder f \
+                WHERE f.user_source_id = s.id AND f.folder_id = r.folder_id)) \
+            ORDER BY r.id",
+        )
+        .bind(source_id)
+        .fetch_all(pool)
+        .await?
+    }))
+}
+
+pub async fn has_files(db_pool: &Pool, record_id: i32) -> Result<bool> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM files WHERE record_id = $1)",
+        )
+        .bind(record_id)
+        .fetch_one(pool)
+        .await?
+    }))
+}
+
+/// Sets the flags of the user's record, flags which are already set are kept.
+pub async fn set_record_flags(
+    db_pool: &Pool,
+    user_id: i32,
+    record_id: i32,
+    starred: bool,
+    hidden: bool,
+    read: bool,
+) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        sqlx::query(
+            "INSERT INTO records_user_settings (record_id, user_id, starred, hidden, read) \
+            VALUES ($1, $2, $3, $4, $5) \
+            ON CONFLICT (user_id, record_id) DO UPDATE SET \
+            starred = records_user_settings.starred OR EXCLUDED.starred, \
+            hidden = records_user_settings.hidden OR EXCLUDED.hidden, \
+            read = records_user_settings.read OR EXCLUDED.read",
+        )
+        .bind(record_id)
+        .bind(user_id)
+        .bind(starred)
+        .bind(hidden)
+        .bind(read)
+        .execute(pool)
+        .await?;
+    });
+    Ok(())
+}
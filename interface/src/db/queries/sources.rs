@@ -1,95 +1,159 @@
-use crate::db::{models::SourceWithMeta, Pool};
+use crate::db::{models::SourceWithMeta, split_tags, Pool, SQLITE_TAGS_SEPARATOR};
 use crate::result::Result;
+use chrono::NaiveDateTime;
 
-pub async fn unsubscribe(db_pool: &Pool, source_id: i32, user_id: i32) -> Result<()> {
-    let mut tx = db_pool.begin().await?;
-    sqlx::query!(
-        r#"
-    DELETE FROM records_user_settings 
-    WHERE 
-        record_id IN (SELECT id FROM records WHERE source_id = $1) 
-        AND user_id = $2
-    "#,
-        source_id,
-        user_id,
-    )
-    .execute(&mut tx)
-    .await?;
-    sqlx::query!(
-        r#"
-    DELETE FROM user_source_to_folder 
-    WHERE 
-        user_source_id IN (SELECT id FROM sources_user_settings WHERE source_id = $1 and user_id = $2)
-    "#,
-        source_id,
-        user_id
-    )
-    .execute(&mut tx)
-    .await?;
-    sqlx::query!(
-        r#"
-    DELETE FROM sources_user_settings 
-    WHERE 
-        source_id = $1 AND user_id = $2
-    "#,
-        source_id,
-        user_id
+// `SourceWithMeta` with joined tags
+#[derive(sqlx::FromRow)]
+struct SqliteSourceWithMeta {
+    id: i32,
+    name: String,
+    origin: String,
+    kind: String,
+    image: Option<String>,
+    last_scrape_time: NaiveDateTime,
+    external_link: String,
+    next_scrape_at: NaiveDateTime,
+    schedule: Option<String>,
+    last_success_at: Option<NaiveDateTime>,
+    last_error: Option<String>,
+    last_error_class: Option<String>,
+    consecutive_failures: i32,
+    disabled_at: Option<NaiveDateTime>,
+    folder_id: Option<i32>,
+    tags: Option<String>,
+    hidden_languages: Option<String>,
+}
+
+impl From<SqliteSourceWithMeta> for SourceWithMeta {
+    fn from(source: SqliteSourceWithMeta) -> Self {
+        Self {
+            id: source.id,
+            name: source.name,
+            origin: source.origin,
+            kind: source.kind,
+            image: source.image,
+            last_scrape_time: source.last_scrape_time,
+            external_link: source.external_link,
+            next_scrape_at: source.next_scrape_at,
+            schedule: source.schedule,
+            last_success_at: source.last_success_at,
+            last_error: source.last_error,
+            last_error_class: source.last_error_class,
+            consecutive_failures: source.consecutive_failures,
+            disabled_at: source.disabled_at,
+            folder_id: source.folder_id,
+            tags: split_tags(source.tags),
+            hidden_languages: split_tags(source.hidden_languages).unwrap_or_default(),
+        }
+    }
+}
+
+// postgres aggregates tags into arrays, sqlite joins them by the separator,
+// `$1` of the condition is the user
+fn sources_query(db_pool: &Pool, condition: &str) -> String {
+    let (tags, hidden_languages) = match db_pool {
+        Pool::Pg(_) => (
+            "array_agg(st.tag) FILTER (WHERE st.tag IS NOT NULL)".to_string(),
+            "array(SELECT hl.language".to_string(),
+        ),
+        Pool::Sqlite(_) => (
+            format!("group_concat(st.tag, '{}')", SQLITE_TAGS_SEPARATOR),
+            format!(
+                "(SELECT group_concat(hl.language, '{}')",
+                SQLITE_TAGS_SEPARATOR
+            ),
+        ),
+    };
+    format!(
+        "SELECT \
+        s.id, s.name, s.origin, s.kind, s.image, s.last_scrape_time, s.external_link, \
+        s.next_scrape_at, s.schedule, s.last_success_at, s.last_error, s.last_error_class, \
+        s.consecutive_failures, s.disabled_at, usf.folder_id, \
+        {} as tags, \
+        {} FROM source_hidden_languages hl \
+            WHERE hl.user_id = $1 AND hl.source_id = s.id) as hidden_languages \
+        FROM sources s \
+        INNER JOIN sources_user_settings sus ON sus.source_id = s.id \
+        LEFT JOIN user_source_to_folder usf ON usf.user_source_id = sus.id \
+        LEFT JOIN source_tags st ON st.source_id = s.id AND st.user_id = sus.user_id \
+        WHERE {} \
+        GROUP BY s.id, usf.folder_id",
+        tags, hidden_languages, condition
     )
-    .execute(db_pool)
-    .await?;
-    tx.commit().await?;
+}
+
+pub async fn unsubscribe(db_pool: &Pool, source_id: i32, user_id: i32) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "DELETE FROM records_user_settings \
+            WHERE record_id IN (SELECT id FROM records WHERE source_id = $1) AND user_id = $2",
+        )
+        .bind(source_id)
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM user_source_to_folder \
+            WHERE user_source_id IN \
+            (SELECT id FROM sources_user_settings WHERE source_id = $1 and user_id = $2)",
+        )
+        .bind(source_id)
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query("DELETE FROM sources_user_settings WHERE source_id = $1 AND user_id = $2")
+            .bind(source_id)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+    });
     Ok(())
 }
 
 pub async fn subscribe(db_pool: &Pool, source_id: i32, user_id: i32) -> Result<()> {
-    sqlx::query!(
-        "INSERT INTO sources_user_settings (source_id, user_id) VALUES ($1, $2) RETURNING id",
-        source_id,
-        user_id
-    )
-    .fetch_one(db_pool)
-    .await?;
+    on_pool!(db_pool, pool => {
+        sqlx::query("INSERT INTO sources_user_settings (source_id, user_id) VALUES ($1, $2)")
+            .bind(source_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+    });
     Ok(())
 }
 
 pub async fn get_by_id(db_pool: &Pool, user_id: i32, source_id: i32) -> Result<SourceWithMeta> {
-    let source = sqlx::query_as!(
-        SourceWithMeta,
-        r#"SELECT 
-        s.id, s.name, s.origin, s.kind, s.image, s.last_scrape_time, s.external_link, usf.folder_id as "folder_id?",
-        array_agg(st.tag) filter(where st.tag is not null) as tags 
-        FROM sources s
-        INNER JOIN sources_user_settings sus ON sus.source_id = s.id
-        LEFT JOIN user_source_to_folder usf ON usf.user_source_id = sus.id
-        LEFT JOIN source_tags st ON st.source_id = s.id AND st.user_id = sus.user_id
-        WHERE sus.user_id = $1 AND s.id = $2
-        GROUP BY s.id, usf.folder_id
-        "#,
-        user_id, source_id
-    )
-        .fetch_one(db_pool)
-        .await?;
-    Ok(source)
+    let sql = sources_query(db_pool, "sus.user_id = $1 AND s.id = $2");
+    Ok(match db_pool {
+        Pool::Pg(pool) => {
+            sqlx::query_as(&sql)
+                .bind(user_id)
+                .bind(source_id)
+                .fetch_one(pool)
+                .await?
+        }
+        Pool::Sqlite(pool) => sqlx::query_as::<_, SqliteSourceWithMeta>(&sql)
+            .bind(user_id)
+            .bind(source_id)
+            .fetch_one(pool)
+            .await?
+            .into(),
+    })
 }
 
 pub async fn get_for_user(db_pool: &Pool, user_id: i32) -> Result<Vec<SourceWithMeta>> {
-    let sources = sqlx::query_as!(
-        SourceWithMeta,
-        r#"SELECT 
-        s.id, s.name, s.origin, s.kind, s.image, s.last_scrape_time, s.external_link, usf.folder_id as "folder_id?",
-        array_agg(st.tag) filter(where st.tag is not null) as tags 
-        FROM sources s
-        INNER JOIN sources_user_settings sus ON sus.source_id = s.id
-        LEFT JOIN user_source_to_folder usf ON usf.user_source_id = sus.id
-        LEFT JOIN source_tags st ON st.source_id = s.id AND st.user_id = sus.user_id
-        WHERE sus.user_id = $1
-        GROUP BY s.id, usf.folder_id
-        "#,
-        user_id
-    )
-    .fetch_all(db_pool)
-    .await?;
-    Ok(sources)
+    let sql = sources_query(db_pool, "sus.user_id = $1");
+    Ok(match db_pool {
+        Pool::Pg(pool) => sqlx::query_as(&sql).bind(user_id).fetch_all(pool).await?,
+        Pool::Sqlite(pool) => sqlx::query_as::<_, SqliteSourceWithMeta>(&sql)
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(From::from)
+            .collect(),
+    })
 }
 
 pub async fn move_to_folder(
@@ -98,33 +162,30 @@ pub async fn move_to_folder(
     source_id: i32,
     folder_id: i32,
 ) -> Result<()> {
-    if folder_id == 0 {
-        sqlx::query!(
-            r#"
-        DELETE FROM user_source_to_folder 
-        WHERE user_source_id = (SELECT id FROM sources_user_settings 
-                                WHERE source_id = $1 AND user_id = $2 LIMIT 1)"#,
-            source_id,
-            user_id
+    on_pool!(db_pool, pool => {
+        if folder_id == 0 {
+            sqlx::query(
+                "DELETE FROM user_source_to_folder \
+                WHERE user_source_id = (SELECT id FROM sources_user_settings \
+                                        WHERE source_id = $1 AND user_id = $2 LIMIT 1)",
+            )
+            .bind(source_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+            return Ok(());
+        }
+        sqlx::query(
+            "INSERT INTO user_source_to_folder (user_source_id, folder_id) \
+            SELECT id, $3 FROM sources_user_settings WHERE source_id = $1 AND user_id = $2 LIMIT 1 \
+            ON CONFLICT (user_source_id) DO UPDATE SET folder_id = EXCLUDED.folder_id",
         )
-        .execute(db_pool)
+        .bind(source_id)
+        .bind(user_id)
+        .bind(folder_id)
+        .execute(pool)
         .await?;
-        return Ok(());
-    }
-    sqlx::query!(
-        r#"
-    INSERT INTO user_source_to_folder (user_source_id, folder_id) 
-    
-        SELECT id, $3 FROM sources_user_settings WHERE source_id = $1 AND user_id = $2 LIMIT 1
-    
-    ON CONFLICT (user_source_id)
-    DO UPDATE SET folder_id=EXCLUDED.folder_id"#,
-        source_id,
-        user_id,
-        folder_id
-    )
-    .execute(db_pool)
-    .await?;
+    });
     Ok(())
 }
 
@@ -134,24 +195,55 @@ pub async fn set_tags(
     source_id: i32,
     tags: Vec<String>,
 ) -> Result<()> {
-    let mut tx = db_pool.begin().await?;
-    sqlx::query!(
-        "DELETE FROM source_tags WHERE user_id=$1 AND source_id=$2",
-        user_id,
-        source_id
-    )
-    .execute(&mut tx)
-    .await?;
-    sqlx::query!(
-        "INSERT INTO source_tags (source_id, user_id, tag)  \
-        SELECT $1, $2, UNNEST($3::text[])",
-        source_id,
-        user_id,
-        &tags
-    )
-    .execute(&mut tx)
-    .await?;
-    tx.commit().await?;
+    on_pool!(db_pool, pool => {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM source_tags WHERE user_id=$1 AND source_id=$2")
+            .bind(user_id)
+            .bind(source_id)
+            .execute(&mut tx)
+            .await?;
+        for tag in tags {
+            sqlx::query(
+                "INSERT INTO source_tags (source_id, user_id, tag) VALUES ($1, $2, $3)",
+            )
+            .bind(source_id)
+            .bind(user_id)
+            .bind(tag)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+    });
+    Ok(())
+}
+
+/// Records of the languages are hidden from the subscription, replaces the previous list.
+pub async fn set_hidden_languages(
+    db_pool: &Pool,
+    user_id: i32,
+    source_id: i32,
+    languages: Vec<String>,
+) -> Result<()> {
+    on_pool!(db_pool, pool => {
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM source_hidden_languages WHERE user_id=$1 AND source_id=$2")
+            .bind(user_id)
+            .bind(source_id)
+            .execute(&mut tx)
+            .await?;
+        for language in languages {
+            sqlx::query(
+                "INSERT INTO source_hidden_languages (source_id, user_id, language) \
+                VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            )
+            .bind(source_id)
+            .bind(user_id)
+            .bind(language)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+    });
     Ok(())
 }
 
@@ -162,13 +254,14 @@ pub async fn search_tags(
     limit: i32,
 ) -> Result<Vec<String>> {
     let query = format!("%{}%", query.to_lowercase());
-    let res = sqlx::query!(
-        "SELECT (array_agg(tag))[1:$3] as tags from source_tags WHERE user_id=$1 and tag ilike $2",
-        user_id,
-        query,
-        limit
-    )
-    .fetch_one(db_pool)
-    .await?;
-    Ok(res.tags.unwrap_or_default())
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar(
+            "SELECT tag FROM source_tags WHERE user_id = $1 AND lower(tag) LIKE $2 LIMIT $3",
+        )
+        .bind(user_id)
+        .bind(query)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?
+    }))
 }
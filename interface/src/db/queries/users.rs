@@ -7,12 +7,16 @@ use crate::result::Result;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 
+// extended result code of unique constraint violation
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
+
 pub async fn get_user_by_token(db_pool: &Pool, token: String) -> Result<Option<User>> {
-    Ok(
-        sqlx::query_as!(User, "SELECT * from users WHERE token = $1", token)
-            .fetch_optional(db_pool)
-            .await?,
-    )
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * from users WHERE token = $1")
+            .bind(token)
+            .fetch_optional(pool)
+            .await?
+    }))
 }
 
 pub fn generate_token() -> String {
@@ -28,24 +32,26 @@ pub async fn create_user(
     login: String,
     hashed_password: String,
 ) -> Result<Option<User>> {
-    match sqlx::query_as!(
-        User,
-        r#"INSERT INTO users (login, token, password, last_read_date) VALUES 
-        ($1, $2, $3, now()) RETURNING *"#,
-        login,
-        generate_token(),
-        hashed_password,
-    )
-    .fetch_one(db_pool)
-    .await
-    {
+    let result = on_pool!(db_pool, pool => {
+        sqlx::query_as(
+            "INSERT INTO users (login, token, password, last_read_date) VALUES \
+            ($1, $2, $3, CURRENT_TIMESTAMP) RETURNING *",
+        )
+        .bind(login)
+        .bind(generate_token())
+        .bind(hashed_password)
+        .fetch_one(pool)
+        .await
+    });
+    match result {
         Ok(user) => Ok(Some(user)),
         Err(err) => {
             if let sqlx::Error::Database(db_err) = &err {
-                if let Some(constraint) = db_err.constraint() {
-                    if constraint == "users_login_key" {
-                        return Ok(None);
-                    }
+                // sqlite doesn't report constraint names
+                if db_err.constraint() == Some("users_login_key")
+                    || db_err.code().as_deref() == Some(SQLITE_CONSTRAINT_UNIQUE)
+                {
+                    return Ok(None);
                 }
             }
             Err(err)?
@@ -54,9 +60,10 @@ pub async fn create_user(
 }
 
 pub async fn get_user_by_login(db_pool: &Pool, login: String) -> Result<User> {
-    Ok(
-        sqlx::query_as!(User, "SELECT * FROM users WHERE login = $1", login)
-            .fetch_one(db_pool)
-            .await?,
-    )
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * FROM users WHERE login = $1")
+            .bind(login)
+            .fetch_one(pool)
+            .await?
+    }))
 }
@@ -0,0 +1,78 @@
+use crate::db::models::Webhook;
+use crate::db::Pool;
+use crate::result::Result;
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+// TODO: enum?
+pub const WEBHOOK: &str = "WEBHOOK";
+
+pub(crate) fn generate_secret(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Creates `WEBHOOK` source with the given name, `None` if it already exists.
+pub async fn create_webhook(db_pool: &Pool, name: String) -> Result<Option<Webhook>> {
+    on_pool!(db_pool, pool => {
+        let mut tx = pool.begin().await?;
+        let source_id: i32 = match sqlx::query_scalar(
+            "INSERT INTO sources (name, origin, kind, external_link) VALUES \
+            ($1, $1, $2, '') ON CONFLICT (origin, kind) DO NOTHING RETURNING id",
+        )
+        .bind(name)
+        .bind(WEBHOOK)
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            Some(source_id) => source_id,
+            None => return Ok(None),
+        };
+        let webhook = sqlx::query_as(
+            "INSERT INTO webhooks (source_id, token, secret) VALUES ($1, $2, $3) RETURNING *",
+        )
+        .bind(source_id)
+        .bind(generate_secret(24))
+        .bind(generate_secret(32))
+        .fetch_one(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(webhook))
+    })
+}
+
+pub async fn get_webhook_by_token(db_pool: &Pool, token: String) -> Result<Option<Webhook>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * FROM webhooks WHERE token = $1")
+            .bind(token)
+            .fetch_optional(pool)
+            .await?
+    }))
+}
+
+pub async fn get_webhooks(db_pool: &Pool) -> Result<Vec<Webhook>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_as("SELECT * FROM webhooks ORDER BY source_id")
+            .fetch_all(pool)
+            .await?
+    }))
+}
+
+/// Id of already pushed record, client supplied ids are unique within a webhook.
+pub async fn get_record_id(
+    db_pool: &Pool,
+    source_id: i32,
+    source_record_id: String,
+) -> Result<Option<i32>> {
+    Ok(on_pool!(db_pool, pool => {
+        sqlx::query_scalar("SELECT id FROM records WHERE source_id = $1 AND source_record_id = $2")
+            .bind(source_id)
+            .bind(source_record_id)
+            .fetch_optional(pool)
+            .await?
+    }))
+}
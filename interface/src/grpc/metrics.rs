@@ -0,0 +1,134 @@
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+    TextEncoder,
+};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Never};
+use tonic::transport::{Body, NamedService};
+
+lazy_static! {
+    static ref GRPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "grpc_requests_total",
+        "gRPC requests by method",
+        &["method"]
+    )
+    .unwrap();
+    static ref GRPC_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "grpc_request_duration_seconds",
+        "Time of the gRPC request until the response headers",
+        &["method"]
+    )
+    .unwrap();
+}
+
+/// Counts and times gRPC requests, plain http requests (webhooks, metrics) are skipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Layer;
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Instrumented<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Instrumented { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Instrumented<S> {
+    inner: S,
+}
+
+impl<S, B> tower::Service<http::Request<Body>> for Instrumented<S>
+where
+    S: tower::Service<http::Request<Body>, Response = http::Response<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = request
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .filter(|c| c.starts_with("application/grpc"))
+            .map(|_| request.uri().path().to_string());
+        let started = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            if let Some(method) = method {
+                GRPC_REQUESTS.with_label_values(&[&method]).inc();
+                GRPC_REQUEST_DURATION
+                    .with_label_values(&[&method])
+                    .observe(started.elapsed().as_secs_f64());
+            }
+            response
+        })
+    }
+}
+
+/// Plain http endpoint with the metrics in the prometheus text format: `GET /metrics`.
+#[derive(Clone, Debug, Default)]
+pub struct Service;
+
+impl Service {
+    fn handle(&self) -> http::Response<BoxBody> {
+        let encoder = TextEncoder::new();
+        let mut buffer = vec![];
+        let (status, content_type, body) = match encoder.encode(&prometheus::gather(), &mut buffer)
+        {
+            Ok(()) => (
+                http::StatusCode::OK,
+                encoder.format_type().to_string(),
+                buffer,
+            ),
+            Err(e) => {
+                log::error!("cannot encode metrics: {}", e);
+                (
+                    http::StatusCode::INTERNAL_SERVER_ERROR,
+                    "text/plain".to_string(),
+                    e.to_string().into_bytes(),
+                )
+            }
+        };
+        http::Response::builder()
+            .status(status)
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(
+                http_body::Full::new(Bytes::from(body))
+                    .map_err(|err| match err {})
+                    .boxed(),
+            )
+            .unwrap()
+    }
+}
+
+impl NamedService for Service {
+    const NAME: &'static str = "metrics";
+}
+
+impl tonic::codegen::Service<http::Request<Body>> for Service {
+    type Response = http::Response<BoxBody>;
+    type Error = Never;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Never>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _request: http::Request<Body>) -> Self::Future {
+        let response = self.handle();
+        Box::pin(async move { Ok(response) })
+    }
+}
@@ -1,9 +1,11 @@
 use crate::result::Error;
 
+mod metrics;
 mod pb;
 mod records;
 mod sources;
 mod users;
+mod webhooks;
 
 pub mod server;
 
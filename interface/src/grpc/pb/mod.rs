@@ -32,9 +32,28 @@ impl From<crate::db::models::RecordWithMeta> for records::RecordWithMeta {
                 title: record.title.unwrap_or_default(),
                 source_id: record.source_id,
                 image: record.image.unwrap_or_default(),
+                language: record.language.unwrap_or_default(),
+                meta: record.meta.unwrap_or_default(),
             }),
             starred: record.starred.map_or(false, |v| v),
             tags: record.tags.unwrap_or_default(),
+            edited: record.edited,
+            also_seen_in: record.also_seen_in,
+            read: record.read,
+        }
+    }
+}
+
+impl From<feeder::models::RecordVersion> for records::RecordVersion {
+    fn from(version: feeder::models::RecordVersion) -> Self {
+        Self {
+            id: version.id,
+            record_id: version.record_id,
+            title: version.title.unwrap_or_default(),
+            content: version.content,
+            image: version.image.unwrap_or_default(),
+            date: version.date.timestamp(),
+            replaced_at: version.replaced_at.timestamp(),
         }
     }
 }
@@ -50,9 +69,23 @@ impl From<crate::db::models::SourceWithMeta> for sources::SourceWithMeta {
                 kind: source.kind,
                 image: source.image.unwrap_or_default(),
                 last_scrape_time: source.last_scrape_time.timestamp(),
+                next_scrape_time: source.next_scrape_at.timestamp(),
+                schedule: source.schedule.unwrap_or_default(),
+                last_success_time: source
+                    .last_success_at
+                    .map(|t| t.timestamp())
+                    .unwrap_or_default(),
+                last_error: source.last_error.unwrap_or_default(),
+                last_error_class: source.last_error_class.unwrap_or_default(),
+                consecutive_failures: source.consecutive_failures,
+                disabled_time: source
+                    .disabled_at
+                    .map(|t| t.timestamp())
+                    .unwrap_or_default(),
             }),
             folder_id: source.folder_id.unwrap_or(0),
             tags: source.tags.unwrap_or_default(),
+            hidden_languages: source.hidden_languages,
         }
     }
 }
@@ -67,6 +100,19 @@ pub fn adapt_source(source: feeder::models::Source) -> sources::Source {
         image: source.image.unwrap_or_default(),
         last_scrape_time: source.last_scrape_time.timestamp(),
         external_link: source.external_link,
+        next_scrape_time: source.next_scrape_at.timestamp(),
+        schedule: source.schedule.unwrap_or_default(),
+        last_success_time: source
+            .last_success_at
+            .map(|t| t.timestamp())
+            .unwrap_or_default(),
+        last_error: source.last_error.unwrap_or_default(),
+        last_error_class: source.last_error_class.unwrap_or_default(),
+        consecutive_failures: source.consecutive_failures,
+        disabled_time: source
+            .disabled_at
+            .map(|t| t.timestamp())
+            .unwrap_or_default(),
     }
 }
 
@@ -79,3 +125,100 @@ impl From<crate::db::models::UserFolder> for users::get_folders_response::Folder
         }
     }
 }
+
+impl From<crate::db::models::OutgoingWebhook> for users::OutgoingWebhook {
+    fn from(webhook: crate::db::models::OutgoingWebhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            secret: webhook.secret,
+            source_id: webhook.source_id.unwrap_or(0),
+            folder_id: webhook.folder_id.unwrap_or(0),
+            tag: webhook.tag.unwrap_or_default(),
+            keyword: webhook.keyword.unwrap_or_default(),
+            rules_only: webhook.rules_only,
+        }
+    }
+}
+
+impl From<crate::db::models::OutgoingWebhookDelivery>
+    for users::get_outgoing_webhook_deliveries_response::Delivery
+{
+    fn from(delivery: crate::db::models::OutgoingWebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            record_id: delivery.record_id,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status.unwrap_or(0),
+            last_error: delivery.last_error.unwrap_or_default(),
+            next_attempt_time: delivery.next_attempt_at.timestamp(),
+            delivered_time: delivery.delivered_at.map(|d| d.timestamp()).unwrap_or(0),
+            created_time: delivery.created_at.timestamp(),
+        }
+    }
+}
+
+impl From<crate::db::models::UserRule> for users::Rule {
+    fn from(rule: crate::db::models::UserRule) -> Self {
+        Self {
+            id: rule.id,
+            keywords: crate::db::queries::rules::split_keywords(&rule.keywords),
+            name: rule.name,
+            source_id: rule.source_id.unwrap_or(0),
+            folder_id: rule.folder_id.unwrap_or(0),
+            kind: rule.kind.unwrap_or_default(),
+            pattern: rule.pattern.unwrap_or_default(),
+            has_attachment: rule.has_attachment,
+            tag: rule.tag.unwrap_or_default(),
+            star: rule.star,
+            hide: rule.hide,
+            mark_read: rule.mark_read,
+            notify: rule.notify,
+        }
+    }
+}
+
+impl From<users::Rule> for crate::db::models::NewUserRule {
+    fn from(rule: users::Rule) -> Self {
+        Self {
+            name: rule.name,
+            source_id: Some(rule.source_id).filter(|id| *id != 0),
+            folder_id: Some(rule.folder_id).filter(|id| *id != 0),
+            kind: Some(rule.kind).filter(|k| !k.is_empty()),
+            pattern: Some(rule.pattern).filter(|p| !p.is_empty()),
+            keywords: rule
+                .keywords
+                .into_iter()
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect(),
+            has_attachment: rule.has_attachment,
+            tag: Some(rule.tag).filter(|t| !t.is_empty()),
+            star: rule.star,
+            hide: rule.hide,
+            mark_read: rule.mark_read,
+            notify: rule.notify,
+        }
+    }
+}
+
+impl From<crate::db::models::FoundRecord> for records::search_records_response::FoundRecord {
+    fn from(record: crate::db::models::FoundRecord) -> Self {
+        Self {
+            record: Some(records::Record {
+                source_record_id: record.source_record_id,
+                content: record.content,
+                date: record.date.timestamp(),
+                id: record.id,
+                title: record.title.unwrap_or_default(),
+                source_id: record.source_id,
+                image: record.image.unwrap_or_default(),
+                language: record.language.unwrap_or_default(),
+                meta: record.meta.unwrap_or_default(),
+            }),
+            rank: record.rank,
+            snippet: record.snippet,
+        }
+    }
+}
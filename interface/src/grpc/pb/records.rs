@@ -14,6 +14,12 @@ pub struct Record {
     pub date: i64,
     #[prost(string, tag = "7")]
     pub image: ::prost::alloc::string::String,
+    /// ISO 639-3 code of the detected language, empty if unknown
+    #[prost(string, tag = "8")]
+    pub language: ::prost::alloc::string::String,
+    /// json of the kind specific fields, e.g. reddit score or telegram views, empty if not set
+    #[prost(string, tag = "9")]
+    pub meta: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RecordWithMeta {
@@ -23,6 +29,13 @@ pub struct RecordWithMeta {
     pub starred: bool,
     #[prost(string, repeated, tag = "3")]
     pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(bool, tag = "4")]
+    pub edited: bool,
+    /// ids of other sources of the same story
+    #[prost(int32, repeated, tag = "5")]
+    pub also_seen_in: ::prost::alloc::vec::Vec<i32>,
+    #[prost(bool, tag = "6")]
+    pub read: bool,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetRecordsListRequest {
@@ -36,6 +49,12 @@ pub struct GetRecordsListRequest {
     pub offset: u32,
     #[prost(bool, tag = "5")]
     pub only_starred: bool,
+    /// show one record per story published by several sources
+    #[prost(bool, tag = "6")]
+    pub collapse_duplicates: bool,
+    /// ISO 639-3 codes, records of any language if empty
+    #[prost(string, repeated, tag = "7")]
+    pub languages: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetRecordsListResponse {
@@ -88,6 +107,77 @@ pub struct RemoveRecordTagResponse {
     #[prost(string, repeated, tag = "1")]
     pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
+/// content of the record before an edit
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecordVersion {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+    #[prost(int32, tag = "2")]
+    pub record_id: i32,
+    #[prost(string, tag = "3")]
+    pub title: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub content: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(int64, tag = "6")]
+    pub date: i64,
+    #[prost(int64, tag = "7")]
+    pub replaced_at: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRecordVersionsRequest {
+    #[prost(int32, tag = "1")]
+    pub record_id: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRecordVersionsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub versions: ::prost::alloc::vec::Vec<RecordVersion>,
+}
+/// full-text search over the records of the subscribed sources, the best matches first
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchRecordsRequest {
+    #[prost(string, tag = "1")]
+    pub query: ::prost::alloc::string::String,
+    /// filters, 0 or empty if not set
+    #[prost(int64, tag = "2")]
+    pub from_date: i64,
+    #[prost(int64, tag = "3")]
+    pub to_date: i64,
+    #[prost(int32, tag = "4")]
+    pub source_id: i32,
+    #[prost(int32, tag = "5")]
+    pub folder_id: i32,
+    #[prost(string, tag = "6")]
+    pub tag: ::prost::alloc::string::String,
+    /// 20 if not set
+    #[prost(uint32, tag = "7")]
+    pub limit: u32,
+    #[prost(uint32, tag = "8")]
+    pub offset: u32,
+    /// ISO 639-3 code, empty if not set
+    #[prost(string, tag = "9")]
+    pub language: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchRecordsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub records: ::prost::alloc::vec::Vec<search_records_response::FoundRecord>,
+}
+/// Nested message and enum types in `SearchRecordsResponse`.
+pub mod search_records_response {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct FoundRecord {
+        #[prost(message, optional, tag = "1")]
+        pub record: ::core::option::Option<super::Record>,
+        #[prost(float, tag = "2")]
+        pub rank: f32,
+        /// fragments of the content with the matches in <b></b>
+        #[prost(string, tag = "3")]
+        pub snippet: ::prost::alloc::string::String,
+    }
+}
 #[doc = r" Generated client implementations."]
 pub mod records_service_client {
     #![allow(unused_variables, dead_code, missing_docs)]
@@ -221,6 +311,36 @@ pub mod records_service_client {
                 http::uri::PathAndQuery::from_static("/records.RecordsService/RemoveRecordTag");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn get_record_versions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetRecordVersionsRequest>,
+        ) -> Result<tonic::Response<super::GetRecordVersionsResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/records.RecordsService/GetRecordVersions");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn search_records(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchRecordsRequest>,
+        ) -> Result<tonic::Response<super::SearchRecordsResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/records.RecordsService/SearchRecords");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 #[doc = r" Generated server implementations."]
@@ -250,6 +370,14 @@ pub mod records_service_server {
             &self,
             request: tonic::Request<super::RemoveRecordTagRequest>,
         ) -> Result<tonic::Response<super::RemoveRecordTagResponse>, tonic::Status>;
+        async fn get_record_versions(
+            &self,
+            request: tonic::Request<super::GetRecordVersionsRequest>,
+        ) -> Result<tonic::Response<super::GetRecordVersionsResponse>, tonic::Status>;
+        async fn search_records(
+            &self,
+            request: tonic::Request<super::SearchRecordsRequest>,
+        ) -> Result<tonic::Response<super::SearchRecordsResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct RecordsServiceServer<T: RecordsService> {
@@ -456,6 +584,73 @@ pub mod records_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/records.RecordsService/GetRecordVersions" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetRecordVersionsSvc<T: RecordsService>(pub Arc<T>);
+                    impl<T: RecordsService>
+                        tonic::server::UnaryService<super::GetRecordVersionsRequest>
+                        for GetRecordVersionsSvc<T>
+                    {
+                        type Response = super::GetRecordVersionsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetRecordVersionsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_record_versions(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetRecordVersionsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/records.RecordsService/SearchRecords" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchRecordsSvc<T: RecordsService>(pub Arc<T>);
+                    impl<T: RecordsService> tonic::server::UnaryService<super::SearchRecordsRequest>
+                        for SearchRecordsSvc<T>
+                    {
+                        type Response = super::SearchRecordsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchRecordsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).search_records(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SearchRecordsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
@@ -8,6 +8,9 @@ pub struct SourceWithMeta {
     pub folder_id: i32,
     #[prost(string, repeated, tag = "3")]
     pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// ISO 639-3 codes of the languages whose records are hidden
+    #[prost(string, repeated, tag = "4")]
+    pub hidden_languages: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetSourcesListResponse {
@@ -35,6 +38,25 @@ pub struct Source {
     pub last_scrape_time: i64,
     #[prost(string, tag = "7")]
     pub external_link: ::prost::alloc::string::String,
+    #[prost(int64, tag = "8")]
+    pub next_scrape_time: i64,
+    /// explicit schedule, empty when the interval is adapted to publishing frequency
+    #[prost(string, tag = "9")]
+    pub schedule: ::prost::alloc::string::String,
+    /// 0 if the source is never scraped successfully
+    #[prost(int64, tag = "10")]
+    pub last_success_time: i64,
+    #[prost(string, tag = "11")]
+    pub last_error: ::prost::alloc::string::String,
+    /// TRANSIENT or PERMANENT
+    #[prost(string, tag = "12")]
+    pub last_error_class: ::prost::alloc::string::String,
+    /// failed scrapes since the last success
+    #[prost(int32, tag = "13")]
+    pub consecutive_failures: i32,
+    /// 0 if the source is enabled, sources are disabled after several permanent errors
+    #[prost(int64, tag = "14")]
+    pub disabled_time: i64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SearchSourcesResponse {
@@ -95,6 +117,37 @@ pub struct SetSourceTagsRequest {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SetSourceTagsResponse {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSourceKindsRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSourceKindsResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub kinds: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetSourceScheduleRequest {
+    #[prost(int32, tag = "1")]
+    pub source_id: i32,
+    /// interval like `30m` or cron expression, empty to adapt the interval again
+    #[prost(string, tag = "2")]
+    pub schedule: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetSourceScheduleResponse {
+    #[prost(int64, tag = "1")]
+    pub next_scrape_time: i64,
+}
+/// records of the languages are hidden from the subscription, the previous list is replaced
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetSourceHiddenLanguagesRequest {
+    #[prost(int32, tag = "1")]
+    pub source_id: i32,
+    /// ISO 639-3 codes like eng or rus
+    #[prost(string, repeated, tag = "2")]
+    pub languages: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetSourceHiddenLanguagesResponse {}
 #[doc = r" Generated client implementations."]
 pub mod sources_service_client {
     #![allow(unused_variables, dead_code, missing_docs)]
@@ -271,6 +324,53 @@ pub mod sources_service_client {
                 http::uri::PathAndQuery::from_static("/sources.SourcesService/SetSourceTags");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn get_source_kinds(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSourceKindsRequest>,
+        ) -> Result<tonic::Response<super::GetSourceKindsResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/sources.SourcesService/GetSourceKinds");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn set_source_schedule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetSourceScheduleRequest>,
+        ) -> Result<tonic::Response<super::SetSourceScheduleResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/sources.SourcesService/SetSourceSchedule");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn set_source_hidden_languages(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetSourceHiddenLanguagesRequest>,
+        ) -> Result<tonic::Response<super::SetSourceHiddenLanguagesResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/sources.SourcesService/SetSourceHiddenLanguages",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 #[doc = r" Generated server implementations."]
@@ -312,6 +412,18 @@ pub mod sources_service_server {
             &self,
             request: tonic::Request<super::SetSourceTagsRequest>,
         ) -> Result<tonic::Response<super::SetSourceTagsResponse>, tonic::Status>;
+        async fn get_source_kinds(
+            &self,
+            request: tonic::Request<super::GetSourceKindsRequest>,
+        ) -> Result<tonic::Response<super::GetSourceKindsResponse>, tonic::Status>;
+        async fn set_source_schedule(
+            &self,
+            request: tonic::Request<super::SetSourceScheduleRequest>,
+        ) -> Result<tonic::Response<super::SetSourceScheduleResponse>, tonic::Status>;
+        async fn set_source_hidden_languages(
+            &self,
+            request: tonic::Request<super::SetSourceHiddenLanguagesRequest>,
+        ) -> Result<tonic::Response<super::SetSourceHiddenLanguagesResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct SourcesServiceServer<T: SourcesService> {
@@ -613,6 +725,109 @@ pub mod sources_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/sources.SourcesService/GetSourceKinds" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetSourceKindsSvc<T: SourcesService>(pub Arc<T>);
+                    impl<T: SourcesService>
+                        tonic::server::UnaryService<super::GetSourceKindsRequest>
+                        for GetSourceKindsSvc<T>
+                    {
+                        type Response = super::GetSourceKindsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetSourceKindsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_source_kinds(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetSourceKindsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sources.SourcesService/SetSourceSchedule" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetSourceScheduleSvc<T: SourcesService>(pub Arc<T>);
+                    impl<T: SourcesService>
+                        tonic::server::UnaryService<super::SetSourceScheduleRequest>
+                        for SetSourceScheduleSvc<T>
+                    {
+                        type Response = super::SetSourceScheduleResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SetSourceScheduleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).set_source_schedule(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SetSourceScheduleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sources.SourcesService/SetSourceHiddenLanguages" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetSourceHiddenLanguagesSvc<T: SourcesService>(pub Arc<T>);
+                    impl<T: SourcesService>
+                        tonic::server::UnaryService<super::SetSourceHiddenLanguagesRequest>
+                        for SetSourceHiddenLanguagesSvc<T>
+                    {
+                        type Response = super::SetSourceHiddenLanguagesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SetSourceHiddenLanguagesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut =
+                                async move { (*inner).set_source_hidden_languages(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SetSourceHiddenLanguagesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
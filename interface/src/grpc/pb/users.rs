@@ -71,6 +71,167 @@ pub struct RemoveFolderRequest {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RemoveFolderResponse {}
+/// new records of the subscribed sources matching all the set filters are posted to the url
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OutgoingWebhook {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+    #[prost(string, tag = "2")]
+    pub url: ::prost::alloc::string::String,
+    /// key of `X-Feeder-Signature: sha256=<hex encoded hmac of the body>`
+    #[prost(string, tag = "3")]
+    pub secret: ::prost::alloc::string::String,
+    /// filters, 0 or empty if not set
+    #[prost(int32, tag = "4")]
+    pub source_id: i32,
+    #[prost(int32, tag = "5")]
+    pub folder_id: i32,
+    /// tag of the source
+    #[prost(string, tag = "6")]
+    pub tag: ::prost::alloc::string::String,
+    /// case insensitive substring of the title or content
+    #[prost(string, tag = "7")]
+    pub keyword: ::prost::alloc::string::String,
+    /// only the records of the rules with the notify action are delivered
+    #[prost(bool, tag = "8")]
+    pub rules_only: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetOutgoingWebhooksRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetOutgoingWebhooksResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub webhooks: ::prost::alloc::vec::Vec<OutgoingWebhook>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddOutgoingWebhookRequest {
+    #[prost(string, tag = "1")]
+    pub url: ::prost::alloc::string::String,
+    #[prost(int32, tag = "2")]
+    pub source_id: i32,
+    #[prost(int32, tag = "3")]
+    pub folder_id: i32,
+    #[prost(string, tag = "4")]
+    pub tag: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub keyword: ::prost::alloc::string::String,
+    #[prost(bool, tag = "6")]
+    pub rules_only: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddOutgoingWebhookResponse {
+    #[prost(message, optional, tag = "1")]
+    pub webhook: ::core::option::Option<OutgoingWebhook>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveOutgoingWebhookRequest {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveOutgoingWebhookResponse {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetOutgoingWebhookDeliveriesRequest {
+    #[prost(int32, tag = "1")]
+    pub webhook_id: i32,
+    /// 50 if not set
+    #[prost(int32, tag = "2")]
+    pub limit: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetOutgoingWebhookDeliveriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub deliveries: ::prost::alloc::vec::Vec<get_outgoing_webhook_deliveries_response::Delivery>,
+}
+/// Nested message and enum types in `GetOutgoingWebhookDeliveriesResponse`.
+pub mod get_outgoing_webhook_deliveries_response {
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Delivery {
+        #[prost(int32, tag = "1")]
+        pub id: i32,
+        #[prost(int32, tag = "2")]
+        pub record_id: i32,
+        /// PENDING, IN_PROGRESS (being sent), DELIVERED or FAILED
+        #[prost(string, tag = "3")]
+        pub status: ::prost::alloc::string::String,
+        #[prost(int32, tag = "4")]
+        pub attempts: i32,
+        /// 0 if there is no response
+        #[prost(int32, tag = "5")]
+        pub response_status: i32,
+        #[prost(string, tag = "6")]
+        pub last_error: ::prost::alloc::string::String,
+        #[prost(int64, tag = "7")]
+        pub next_attempt_time: i64,
+        /// 0 if not delivered
+        #[prost(int64, tag = "8")]
+        pub delivered_time: i64,
+        #[prost(int64, tag = "9")]
+        pub created_time: i64,
+    }
+}
+/// actions applied to the new records of the subscribed sources matching all the set conditions
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Rule {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    /// conditions, 0 or empty if not set
+    #[prost(int32, tag = "3")]
+    pub source_id: i32,
+    #[prost(int32, tag = "4")]
+    pub folder_id: i32,
+    /// source kind, e.g. WEB or VK
+    #[prost(string, tag = "5")]
+    pub kind: ::prost::alloc::string::String,
+    /// regular expression searched in the title and content
+    #[prost(string, tag = "6")]
+    pub pattern: ::prost::alloc::string::String,
+    /// case insensitive, any of them in the title or content
+    #[prost(string, repeated, tag = "7")]
+    pub keywords: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// only records with an image or files
+    #[prost(bool, tag = "8")]
+    pub has_attachment: bool,
+    /// actions
+    #[prost(string, tag = "9")]
+    pub tag: ::prost::alloc::string::String,
+    #[prost(bool, tag = "10")]
+    pub star: bool,
+    #[prost(bool, tag = "11")]
+    pub hide: bool,
+    #[prost(bool, tag = "12")]
+    pub mark_read: bool,
+    /// deliver to the rules only outgoing webhooks of the user whose filters match the record
+    #[prost(bool, tag = "13")]
+    pub notify: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRulesRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRulesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub rules: ::prost::alloc::vec::Vec<Rule>,
+}
+/// id is ignored
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddRuleRequest {
+    #[prost(message, optional, tag = "1")]
+    pub rule: ::core::option::Option<Rule>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddRuleResponse {
+    #[prost(message, optional, tag = "1")]
+    pub rule: ::core::option::Option<Rule>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveRuleRequest {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RemoveRuleResponse {}
 #[doc = r" Generated client implementations."]
 pub mod users_service_client {
     #![allow(unused_variables, dead_code, missing_docs)]
@@ -201,6 +362,110 @@ pub mod users_service_client {
             let path = http::uri::PathAndQuery::from_static("/users.UsersService/RemoveFolder");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn get_outgoing_webhooks(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetOutgoingWebhooksRequest>,
+        ) -> Result<tonic::Response<super::GetOutgoingWebhooksResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/users.UsersService/GetOutgoingWebhooks");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn add_outgoing_webhook(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddOutgoingWebhookRequest>,
+        ) -> Result<tonic::Response<super::AddOutgoingWebhookResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/users.UsersService/AddOutgoingWebhook");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn remove_outgoing_webhook(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RemoveOutgoingWebhookRequest>,
+        ) -> Result<tonic::Response<super::RemoveOutgoingWebhookResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/users.UsersService/RemoveOutgoingWebhook");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn get_outgoing_webhook_deliveries(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetOutgoingWebhookDeliveriesRequest>,
+        ) -> Result<tonic::Response<super::GetOutgoingWebhookDeliveriesResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/users.UsersService/GetOutgoingWebhookDeliveries",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn get_rules(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetRulesRequest>,
+        ) -> Result<tonic::Response<super::GetRulesResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/users.UsersService/GetRules");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn add_rule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddRuleRequest>,
+        ) -> Result<tonic::Response<super::AddRuleResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/users.UsersService/AddRule");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn remove_rule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RemoveRuleRequest>,
+        ) -> Result<tonic::Response<super::RemoveRuleResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/users.UsersService/RemoveRule");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 #[doc = r" Generated server implementations."]
@@ -230,6 +495,34 @@ pub mod users_service_server {
             &self,
             request: tonic::Request<super::RemoveFolderRequest>,
         ) -> Result<tonic::Response<super::RemoveFolderResponse>, tonic::Status>;
+        async fn get_outgoing_webhooks(
+            &self,
+            request: tonic::Request<super::GetOutgoingWebhooksRequest>,
+        ) -> Result<tonic::Response<super::GetOutgoingWebhooksResponse>, tonic::Status>;
+        async fn add_outgoing_webhook(
+            &self,
+            request: tonic::Request<super::AddOutgoingWebhookRequest>,
+        ) -> Result<tonic::Response<super::AddOutgoingWebhookResponse>, tonic::Status>;
+        async fn remove_outgoing_webhook(
+            &self,
+            request: tonic::Request<super::RemoveOutgoingWebhookRequest>,
+        ) -> Result<tonic::Response<super::RemoveOutgoingWebhookResponse>, tonic::Status>;
+        async fn get_outgoing_webhook_deliveries(
+            &self,
+            request: tonic::Request<super::GetOutgoingWebhookDeliveriesRequest>,
+        ) -> Result<tonic::Response<super::GetOutgoingWebhookDeliveriesResponse>, tonic::Status>;
+        async fn get_rules(
+            &self,
+            request: tonic::Request<super::GetRulesRequest>,
+        ) -> Result<tonic::Response<super::GetRulesResponse>, tonic::Status>;
+        async fn add_rule(
+            &self,
+            request: tonic::Request<super::AddRuleRequest>,
+        ) -> Result<tonic::Response<super::AddRuleResponse>, tonic::Status>;
+        async fn remove_rule(
+            &self,
+            request: tonic::Request<super::RemoveRuleRequest>,
+        ) -> Result<tonic::Response<super::RemoveRuleResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct UsersServiceServer<T: UsersService> {
@@ -427,6 +720,238 @@ pub mod users_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/users.UsersService/GetOutgoingWebhooks" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetOutgoingWebhooksSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService>
+                        tonic::server::UnaryService<super::GetOutgoingWebhooksRequest>
+                        for GetOutgoingWebhooksSvc<T>
+                    {
+                        type Response = super::GetOutgoingWebhooksResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetOutgoingWebhooksRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_outgoing_webhooks(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetOutgoingWebhooksSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/AddOutgoingWebhook" => {
+                    #[allow(non_camel_case_types)]
+                    struct AddOutgoingWebhookSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService>
+                        tonic::server::UnaryService<super::AddOutgoingWebhookRequest>
+                        for AddOutgoingWebhookSvc<T>
+                    {
+                        type Response = super::AddOutgoingWebhookResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddOutgoingWebhookRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).add_outgoing_webhook(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AddOutgoingWebhookSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/RemoveOutgoingWebhook" => {
+                    #[allow(non_camel_case_types)]
+                    struct RemoveOutgoingWebhookSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService>
+                        tonic::server::UnaryService<super::RemoveOutgoingWebhookRequest>
+                        for RemoveOutgoingWebhookSvc<T>
+                    {
+                        type Response = super::RemoveOutgoingWebhookResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RemoveOutgoingWebhookRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut =
+                                async move { (*inner).remove_outgoing_webhook(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RemoveOutgoingWebhookSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/GetOutgoingWebhookDeliveries" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetOutgoingWebhookDeliveriesSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService>
+                        tonic::server::UnaryService<super::GetOutgoingWebhookDeliveriesRequest>
+                        for GetOutgoingWebhookDeliveriesSvc<T>
+                    {
+                        type Response = super::GetOutgoingWebhookDeliveriesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetOutgoingWebhookDeliveriesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_outgoing_webhook_deliveries(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetOutgoingWebhookDeliveriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/GetRules" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetRulesSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService> tonic::server::UnaryService<super::GetRulesRequest> for GetRulesSvc<T> {
+                        type Response = super::GetRulesResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetRulesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_rules(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetRulesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/AddRule" => {
+                    #[allow(non_camel_case_types)]
+                    struct AddRuleSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService> tonic::server::UnaryService<super::AddRuleRequest> for AddRuleSvc<T> {
+                        type Response = super::AddRuleResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddRuleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).add_rule(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AddRuleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/users.UsersService/RemoveRule" => {
+                    #[allow(non_camel_case_types)]
+                    struct RemoveRuleSvc<T: UsersService>(pub Arc<T>);
+                    impl<T: UsersService> tonic::server::UnaryService<super::RemoveRuleRequest> for RemoveRuleSvc<T> {
+                        type Response = super::RemoveRuleResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RemoveRuleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).remove_rule(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RemoveRuleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
@@ -1,9 +1,13 @@
 use super::pb::records;
 use crate::db::queries::records as records_queries;
+use crate::db::queries::records::SearchFilter;
 use crate::db::Pool;
 use crate::grpc::pb::records::{
-    AddRecordTagRequest, AddRecordTagResponse, RemoveRecordTagRequest, RemoveRecordTagResponse,
+    AddRecordTagRequest, AddRecordTagResponse, GetRecordVersionsRequest, GetRecordVersionsResponse,
+    RemoveRecordTagRequest, RemoveRecordTagResponse, SearchRecordsRequest, SearchRecordsResponse,
 };
+use crate::result::Error;
+use chrono::NaiveDateTime;
 use tonic::{Request, Response, Status};
 
 #[derive(Clone)]
@@ -37,6 +41,8 @@ impl records::records_service_server::RecordsService for Service {
                 _ => Some(message.record_id),
             },
             message.only_starred,
+            message.collapse_duplicates,
+            &message.languages,
             message.limit.into(),
             message.offset.into(),
         )
@@ -64,6 +70,8 @@ impl records::records_service_server::RecordsService for Service {
                     title: rec.title.unwrap_or_default(),
                     source_id: rec.source_id,
                     image: rec.image.unwrap_or_default(),
+                    language: rec.language.unwrap_or_default(),
+                    meta: rec.meta.unwrap_or_default(),
                 })
                 .collect(),
         }))
@@ -110,4 +118,62 @@ impl records::records_service_server::RecordsService for Service {
             tags,
         }))
     }
+
+    async fn get_record_versions(
+        &self,
+        request: Request<GetRecordVersionsRequest>,
+    ) -> Result<Response<GetRecordVersionsResponse>, Status> {
+        super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: records::GetRecordVersionsRequest = request.into_inner();
+        let versions = records_queries::get_versions(&self.db_pool, message.record_id).await?;
+        Ok(tonic::Response::new(records::GetRecordVersionsResponse {
+            versions: versions.into_iter().map(From::from).collect(),
+        }))
+    }
+
+    async fn search_records(
+        &self,
+        request: Request<SearchRecordsRequest>,
+    ) -> Result<Response<SearchRecordsResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: SearchRecordsRequest = request.into_inner();
+        if message.query.trim().is_empty() {
+            return Err(Error::BadRequest("query not specified".to_string()).into());
+        }
+        let filter = SearchFilter {
+            from_date: match message.from_date {
+                0 => None,
+                _ => Some(NaiveDateTime::from_timestamp(message.from_date, 0)),
+            },
+            to_date: match message.to_date {
+                0 => None,
+                _ => Some(NaiveDateTime::from_timestamp(message.to_date, 0)),
+            },
+            source_id: match message.source_id {
+                0 => None,
+                _ => Some(message.source_id),
+            },
+            folder_id: match message.folder_id {
+                0 => None,
+                _ => Some(message.folder_id),
+            },
+            tag: Some(message.tag).filter(|t| !t.is_empty()),
+            language: Some(message.language).filter(|l| !l.is_empty()),
+        };
+        let records = records_queries::search_records(
+            &self.db_pool,
+            user.id,
+            message.query.trim(),
+            &filter,
+            match message.limit {
+                0 => 20,
+                _ => message.limit.into(),
+            },
+            message.offset.into(),
+        )
+        .await?;
+        Ok(tonic::Response::new(SearchRecordsResponse {
+            records: records.into_iter().map(From::from).collect(),
+        }))
+    }
 }
@@ -1,17 +1,27 @@
+use super::metrics::{Layer as MetricsLayer, Service as MetricsService};
 use super::pb::records::records_service_server::RecordsServiceServer;
 use super::pb::sources::sources_service_server::SourcesServiceServer;
 use super::pb::users::users_service_server::UsersServiceServer;
 use super::records::Service as RecordsService;
 use super::sources::Service as SourcesService;
 use super::users::Service as UsersService;
+use super::webhooks::Service as WebhooksService;
+use crate::db::queries::webhooks as webhooks_queries;
 use crate::init::App;
 use crate::settings::SETTINGS;
+use feeder::CancellationToken;
 use tonic::transport::Server;
 
-pub async fn run_server(app: App) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_server(
+    app: App,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error>> {
     let db_pool = app.storage().pool();
 
     Server::builder()
+        // webhooks are plain http requests sharing the grpc port
+        .accept_http1(true)
+        .layer(MetricsLayer)
         .add_service(UsersServiceServer::with_interceptor(
             UsersService::new(db_pool.clone()),
             logging_interceptor,
@@ -20,11 +30,20 @@ pub async fn run_server(app: App) -> Result<(), Box<dyn std::error::Error>> {
             RecordsService::new(db_pool.clone()),
             logging_interceptor,
         ))
+        .add_service(WebhooksService::new(
+            db_pool.clone(),
+            app.storage(),
+            app.pipeline(webhooks_queries::WEBHOOK),
+        ))
+        .add_service(MetricsService)
         .add_service(SourcesServiceServer::with_interceptor(
             SourcesService::new(db_pool.clone(), app),
             logging_interceptor,
         ))
-        .serve(format!("{}:{}", SETTINGS.server.host, SETTINGS.server.port).parse()?)
+        .serve_with_shutdown(
+            format!("{}:{}", SETTINGS.server.host, SETTINGS.server.port).parse()?,
+            shutdown.cancelled(),
+        )
         .await?;
 
     Ok(())
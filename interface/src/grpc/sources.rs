@@ -3,6 +3,7 @@ use crate::db::queries::sources as sources_queries;
 use crate::db::Pool;
 use crate::init::App;
 use feeder::result::Error;
+use feeder::storage::Storage;
 use tonic::{Code, Request, Response, Status};
 
 #[derive(Clone)]
@@ -58,11 +59,18 @@ impl sources::sources_service_server::SourcesService for Service {
                 Error::DbError(e) => tonic::Status::internal(e),
                 Error::HttpCollectorError(e) => tonic::Status::internal(e.to_string()),
                 Error::TgCollectorError(e) => tonic::Status::internal(e.to_string()),
+                Error::TgWebCollectorError(e) => tonic::Status::internal(e.to_string()),
                 Error::VkCollectorError(e) => tonic::Status::internal(e.to_string()),
+                Error::MastodonCollectorError(e) => tonic::Status::internal(e.to_string()),
+                Error::RedditCollectorError(e) => tonic::Status::internal(e.to_string()),
+                Error::FsCollectorError(e) => tonic::Status::internal(e.to_string()),
                 Error::UpdateNotSupported(e) => tonic::Status::internal(e),
                 Error::SourceKindConflict(e) => tonic::Status::internal(e),
                 Error::SourceNotFound => tonic::Status::not_found("source not found"),
                 Error::SourceCreationError => tonic::Status::internal("cannot create source"),
+                Error::InvalidSchedule(e) => tonic::Status::invalid_argument(e),
+                Error::InvalidConfig(e) => tonic::Status::internal(e.join("; ")),
+                Error::SourceFailed { error, .. } => tonic::Status::internal(error.to_string()),
                 Error::IOError(e) => tonic::Status::internal(e.to_string()),
             })?;
         Ok(tonic::Response::new(sources::SearchSourcesResponse {
@@ -136,4 +144,63 @@ impl sources::sources_service_server::SourcesService for Service {
         sources_queries::set_tags(&self.db_pool, user.id, message.source_id, message.tags).await?;
         Ok(tonic::Response::new(sources::SetSourceTagsResponse {}))
     }
+
+    async fn get_source_kinds(
+        &self,
+        request: Request<sources::GetSourceKindsRequest>,
+    ) -> Result<Response<sources::GetSourceKindsResponse>, Status> {
+        super::auth_user(&self.db_pool, request.metadata()).await?;
+        Ok(tonic::Response::new(sources::GetSourceKindsResponse {
+            kinds: self
+                .aggregator
+                .source_kinds()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }))
+    }
+
+    async fn set_source_schedule(
+        &self,
+        request: Request<sources::SetSourceScheduleRequest>,
+    ) -> Result<Response<sources::SetSourceScheduleResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: sources::SetSourceScheduleRequest = request.into_inner();
+        // only subscribers manage the schedule
+        sources_queries::get_by_id(&self.db_pool, user.id, message.source_id).await?;
+        let schedule = Some(message.schedule.trim()).filter(|s| !s.is_empty());
+        let source = self
+            .aggregator
+            .storage()
+            .set_source_schedule(message.source_id, schedule)
+            .await
+            .map_err(|e| match e {
+                Error::InvalidSchedule(e) => tonic::Status::invalid_argument(e),
+                Error::SourceFailed { error, .. } => tonic::Status::internal(error.to_string()),
+                e => tonic::Status::internal(e.to_string()),
+            })?
+            .ok_or_else(|| tonic::Status::not_found("source not found"))?;
+        Ok(tonic::Response::new(sources::SetSourceScheduleResponse {
+            next_scrape_time: source.next_scrape_at.timestamp(),
+        }))
+    }
+
+    async fn set_source_hidden_languages(
+        &self,
+        request: Request<sources::SetSourceHiddenLanguagesRequest>,
+    ) -> Result<Response<sources::SetSourceHiddenLanguagesResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: sources::SetSourceHiddenLanguagesRequest = request.into_inner();
+        let languages = message
+            .languages
+            .into_iter()
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect();
+        sources_queries::set_hidden_languages(&self.db_pool, user.id, message.source_id, languages)
+            .await?;
+        Ok(tonic::Response::new(
+            sources::SetSourceHiddenLanguagesResponse {},
+        ))
+    }
 }
@@ -1,10 +1,21 @@
 use super::pb::users;
 use crate::auth;
-use crate::db::queries::{folders as folders_queries, users as users_queries};
+use crate::db::models::NewOutgoingWebhook;
+use crate::db::queries::{
+    folders as folders_queries, outgoing_webhooks as outgoing_webhooks_queries,
+    rules as rules_queries, users as users_queries,
+};
 use crate::db::Pool;
 use crate::grpc::pb::users::{
-    AddFolderRequest, AddFolderResponse, RemoveFolderRequest, RemoveFolderResponse,
+    AddFolderRequest, AddFolderResponse, AddOutgoingWebhookRequest, AddOutgoingWebhookResponse,
+    AddRuleRequest, AddRuleResponse, GetOutgoingWebhookDeliveriesRequest,
+    GetOutgoingWebhookDeliveriesResponse, GetOutgoingWebhooksRequest, GetOutgoingWebhooksResponse,
+    GetRulesRequest, GetRulesResponse, RemoveFolderRequest, RemoveFolderResponse,
+    RemoveOutgoingWebhookRequest, RemoveOutgoingWebhookResponse, RemoveRuleRequest,
+    RemoveRuleResponse,
 };
+use crate::result::Error;
+use crate::rules;
 use std::convert::TryInto;
 use tonic::{Request, Response, Status};
 
@@ -91,4 +102,125 @@ impl users::users_service_server::UsersService for Service {
         folders_queries::remove_user_folder(&self.db_pool, user.id, message.id).await?;
         Ok(tonic::Response::new(users::RemoveFolderResponse {}))
     }
+
+    async fn get_outgoing_webhooks(
+        &self,
+        request: Request<GetOutgoingWebhooksRequest>,
+    ) -> Result<Response<GetOutgoingWebhooksResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let webhooks =
+            outgoing_webhooks_queries::get_outgoing_webhooks(&self.db_pool, user.id).await?;
+        Ok(tonic::Response::new(GetOutgoingWebhooksResponse {
+            webhooks: webhooks.into_iter().map(From::from).collect(),
+        }))
+    }
+
+    async fn add_outgoing_webhook(
+        &self,
+        request: Request<AddOutgoingWebhookRequest>,
+    ) -> Result<Response<AddOutgoingWebhookResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: AddOutgoingWebhookRequest = request.into_inner();
+        crate::outgoing_webhooks::check_url(message.url.as_str())
+            .await
+            .map_err(Error::BadRequest)?;
+        let folder_id = match message.folder_id {
+            0 => None,
+            folder_id => {
+                folders_queries::get_user_folders(&self.db_pool, user.id)
+                    .await?
+                    .iter()
+                    .find(|f| f.id == folder_id)
+                    .ok_or_else(|| Error::NotFound("folder not found".to_string()))?;
+                Some(folder_id)
+            }
+        };
+        let webhook = outgoing_webhooks_queries::create_outgoing_webhook(
+            &self.db_pool,
+            user.id,
+            NewOutgoingWebhook {
+                url: message.url,
+                source_id: match message.source_id {
+                    0 => None,
+                    source_id => Some(source_id),
+                },
+                folder_id,
+                tag: Some(message.tag).filter(|t| !t.is_empty()),
+                keyword: Some(message.keyword).filter(|k| !k.is_empty()),
+                rules_only: message.rules_only,
+            },
+        )
+        .await?;
+        Ok(tonic::Response::new(AddOutgoingWebhookResponse {
+            webhook: Some(webhook.into()),
+        }))
+    }
+
+    async fn remove_outgoing_webhook(
+        &self,
+        request: Request<RemoveOutgoingWebhookRequest>,
+    ) -> Result<Response<RemoveOutgoingWebhookResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: RemoveOutgoingWebhookRequest = request.into_inner();
+        outgoing_webhooks_queries::remove_outgoing_webhook(&self.db_pool, user.id, message.id)
+            .await?;
+        Ok(tonic::Response::new(RemoveOutgoingWebhookResponse {}))
+    }
+
+    async fn get_outgoing_webhook_deliveries(
+        &self,
+        request: Request<GetOutgoingWebhookDeliveriesRequest>,
+    ) -> Result<Response<GetOutgoingWebhookDeliveriesResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: GetOutgoingWebhookDeliveriesRequest = request.into_inner();
+        let deliveries = outgoing_webhooks_queries::get_deliveries(
+            &self.db_pool,
+            user.id,
+            message.webhook_id,
+            match message.limit {
+                0 => 50,
+                limit => limit.max(1) as i64,
+            },
+        )
+        .await?;
+        Ok(tonic::Response::new(GetOutgoingWebhookDeliveriesResponse {
+            deliveries: deliveries.into_iter().map(From::from).collect(),
+        }))
+    }
+
+    async fn get_rules(
+        &self,
+        request: Request<GetRulesRequest>,
+    ) -> Result<Response<GetRulesResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let rules = rules_queries::get_rules(&self.db_pool, user.id).await?;
+        Ok(tonic::Response::new(GetRulesResponse {
+            rules: rules.into_iter().map(From::from).collect(),
+        }))
+    }
+
+    async fn add_rule(
+        &self,
+        request: Request<AddRuleRequest>,
+    ) -> Result<Response<AddRuleResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let rule = request
+            .into_inner()
+            .rule
+            .ok_or_else(|| Error::BadRequest("rule not specified".to_string()))?;
+        let rule = rules::create_rule(&self.db_pool, user.id, rule.into()).await?;
+        Ok(tonic::Response::new(AddRuleResponse {
+            rule: Some(rule.into()),
+        }))
+    }
+
+    async fn remove_rule(
+        &self,
+        request: Request<RemoveRuleRequest>,
+    ) -> Result<Response<RemoveRuleResponse>, Status> {
+        let user = super::auth_user(&self.db_pool, request.metadata()).await?;
+        let message: RemoveRuleRequest = request.into_inner();
+        rules_queries::remove_rule(&self.db_pool, user.id, message.id).await?;
+        Ok(tonic::Response::new(RemoveRuleResponse {}))
+    }
 }
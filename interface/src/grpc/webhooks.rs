@@ -0,0 +1,277 @@
+use crate::db::queries::webhooks as webhooks_queries;
+use crate::db::Pool;
+use crate::result::{Error, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use feeder::models::{NewFile, NewRecord};
+use feeder::pipeline::Pipeline;
+use feeder::storage::{AnyStorage, Storage};
+use hmac::{Hmac, Mac, NewMac};
+use http_body::Body as HttpBody;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Never};
+use tonic::transport::{Body, NamedService};
+
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+const SIGNATURE_HEADER: &str = "x-feeder-signature";
+const FILE_TYPES: [&str; 5] = ["IMAGE", "ANIMATION", "VIDEO", "AUDIO", "DOCUMENT"];
+
+#[derive(Deserialize, Debug)]
+struct Attachment {
+    url: String,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    type_: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Payload {
+    // client side id, repeated deliveries of the same id are ignored
+    id: String,
+    title: Option<String>,
+    #[serde(default)]
+    content: String,
+    link: Option<String>,
+    date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+/// Plain http endpoint for `WEBHOOK` sources: `POST /webhooks/<token>`.
+///
+/// Body is a json `Payload` signed with the webhook secret,
+/// `X-Feeder-Signature: sha256=<hex encoded hmac of the body>`.
+#[derive(Clone)]
+pub struct Service {
+    db_pool: Pool,
+    storage: AnyStorage,
+    pipeline: Pipeline,
+}
+
+impl Service {
+    pub fn new(db_pool: Pool, storage: AnyStorage, pipeline: Pipeline) -> Self {
+        Self {
+            db_pool,
+            storage,
+            pipeline,
+        }
+    }
+
+    async fn handle(&self, request: http::Request<Body>) -> Result<http::Response<BoxBody>> {
+        if request.method() != http::Method::POST {
+            return Err(Error::BadRequest("only POST is supported".to_string()));
+        }
+        let token = request
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .strip_prefix(Self::NAME)
+            .map(|t| t.trim_matches('/'))
+            .filter(|t| !t.is_empty() && t.chars().all(|c| c.is_ascii_alphanumeric()))
+            .ok_or_else(|| Error::NotFound("webhook not found".to_string()))?
+            .to_string();
+        let webhook = webhooks_queries::get_webhook_by_token(&self.db_pool, token)
+            .await?
+            .ok_or_else(|| Error::NotFound("webhook not found".to_string()))?;
+        // token is a credential, so it's not logged
+        log::info!("webhook request for source {}", webhook.source_id);
+        let signature = request
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|s| s.to_str().ok())
+            .and_then(|s| s.strip_prefix("sha256="))
+            .and_then(|s| hex::decode(s).ok())
+            .ok_or_else(|| Error::Unauthorized("signature not specified".to_string()))?;
+        let body = read_body(request.into_body()).await?;
+        let mut mac = Hmac::<Sha256>::new_varkey(webhook.secret.as_bytes())
+            .map_err(|e| Error::InternalServerError(e.to_string()))?;
+        mac.update(&body);
+        mac.verify(&signature)
+            .map_err(|_| Error::Unauthorized("invalid signature".to_string()))?;
+
+        let payload: Payload = serde_json::from_slice(&body)
+            .map_err(|e| Error::BadRequest(format!("invalid payload: {}", e)))?;
+        if payload.id.is_empty() {
+            return Err(Error::BadRequest("id not specified".to_string()));
+        }
+        for attachment in &payload.attachments {
+            if let Some(type_) = &attachment.type_ {
+                if !FILE_TYPES.contains(&type_.as_str()) {
+                    return Err(Error::BadRequest(format!(
+                        "unknown attachment type: {}",
+                        type_
+                    )));
+                }
+            }
+        }
+        if let Some(record_id) =
+            webhooks_queries::get_record_id(&self.db_pool, webhook.source_id, payload.id.clone())
+                .await?
+        {
+            // the retry finishes what a failed delivery may have left out
+            self.save_link_and_files(webhook.source_id, record_id, &payload)
+                .await?;
+            return Ok(json_response(http::StatusCode::OK, record_id, false));
+        }
+        match self.save_payload(webhook.source_id, &payload).await {
+            Ok(record_id) => Ok(json_response(http::StatusCode::CREATED, record_id, true)),
+            // concurrent delivery of the same id has won the race
+            Err(err) => match webhooks_queries::get_record_id(
+                &self.db_pool,
+                webhook.source_id,
+                payload.id.clone(),
+            )
+            .await?
+            {
+                Some(record_id) => {
+                    self.save_link_and_files(webhook.source_id, record_id, &payload)
+                        .await?;
+                    Ok(json_response(http::StatusCode::OK, record_id, false))
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    async fn save_payload(&self, source_id: i32, payload: &Payload) -> Result<i32> {
+        let record = self
+            .pipeline
+            .save_records(
+                &self.storage,
+                webhooks_queries::WEBHOOK,
+                vec![NewRecord {
+                    title: payload.title.clone(),
+                    source_record_id: payload.id.clone(),
+                    source_id,
+                    content: payload.content.clone(),
+                    date: Some(payload.date.unwrap_or_else(Utc::now).naive_utc()),
+                    image: payload
+                        .attachments
+                        .iter()
+                        .find(|a| a.type_.as_deref() == Some("IMAGE"))
+                        .map(|a| a.url.clone()),
+                    meta: None,
+                }],
+            )
+            .await?
+            .created
+            .pop()
+            // dropped by the records pipeline
+            .ok_or_else(|| Error::BadRequest("record is filtered out".to_string()))?;
+        self.save_link_and_files(source_id, record.id, payload)
+            .await?;
+        Ok(record.id)
+    }
+
+    // the record is saved first, so the link and files are written separately;
+    // files have stable remote ids and saving them again is a no-op
+    async fn save_link_and_files(
+        &self,
+        source_id: i32,
+        record_id: i32,
+        payload: &Payload,
+    ) -> Result<()> {
+        if let Some(link) = &payload.link {
+            self.storage
+                .set_record_external_link(payload.id.clone(), source_id, link.clone())
+                .await?;
+        }
+        if !payload.attachments.is_empty() {
+            self.storage
+                .save_files(
+                    payload
+                        .attachments
+                        .iter()
+                        .enumerate()
+                        .map(|(i, a)| NewFile {
+                            record_id,
+                            kind: webhooks_queries::WEBHOOK.to_string(),
+                            local_path: None,
+                            remote_path: a.url.clone(),
+                            remote_id: Some(format!(
+                                "{}:{}:{}:{}",
+                                webhooks_queries::WEBHOOK,
+                                source_id,
+                                payload.id,
+                                i
+                            )),
+                            file_name: a.name.clone(),
+                            type_: a.type_.clone().unwrap_or_else(|| "DOCUMENT".to_string()),
+                            meta: None,
+                        })
+                        .collect(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+async fn read_body(mut body: Body) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| Error::BadRequest(e.to_string()))?;
+        if data.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(Error::BadRequest("payload too large".to_string()));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+fn response(status: http::StatusCode, body: serde_json::Value) -> http::Response<BoxBody> {
+    http::Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(
+            http_body::Full::new(Bytes::from(body.to_string()))
+                .map_err(|err| match err {})
+                .boxed(),
+        )
+        .unwrap()
+}
+
+fn json_response(
+    status: http::StatusCode,
+    record_id: i32,
+    created: bool,
+) -> http::Response<BoxBody> {
+    response(
+        status,
+        serde_json::json!({ "record_id": record_id, "created": created }),
+    )
+}
+
+fn error_response(err: Error) -> http::Response<BoxBody> {
+    let (status, message) = match err {
+        Error::BadRequest(m) => (http::StatusCode::BAD_REQUEST, m),
+        Error::NotFound(m) => (http::StatusCode::NOT_FOUND, m),
+        Error::Unauthorized(m) => (http::StatusCode::UNAUTHORIZED, m),
+        Error::InternalServerError(m) | Error::PoolError(m) => {
+            (http::StatusCode::INTERNAL_SERVER_ERROR, m)
+        }
+    };
+    response(status, serde_json::json!({ "error": message }))
+}
+
+impl NamedService for Service {
+    const NAME: &'static str = "webhooks";
+}
+
+impl tonic::codegen::Service<http::Request<Body>> for Service {
+    type Response = http::Response<BoxBody>;
+    type Error = Never;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Never>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { Ok(service.handle(request).await.unwrap_or_else(error_response)) })
+    }
+}
@@ -1,70 +1,144 @@
 use crate::settings;
 
 use crate::db;
+use crate::outgoing_webhooks;
+use crate::rules;
 use feeder::aggregator;
 use feeder::aggregator::AggApp;
 use feeder::config;
-use feeder::storage::pg::PgStorage;
+use feeder::storage::AnyStorage;
 use std::sync::Arc;
 use tg_collector::parsers::DefaultTelegramParser;
 
-fn init_app_config() -> config::AppConfig {
+/// Config of the collectors from the settings.
+pub fn app_config(settings: &settings::Settings) -> config::AppConfig {
     let http_config = config::HttpConfigBuilder::default()
-        .enabled(settings::SETTINGS.collectors.http.enabled)
-        .sleep_secs(settings::SETTINGS.collectors.http.sleep_secs)
-        .scrape_source_secs_interval(
-            settings::SETTINGS
-                .collectors
-                .http
-                .scrape_source_secs_interval,
-        )
+        .enabled(settings.collectors.http.enabled)
+        .sleep_secs(settings.collectors.http.sleep_secs)
+        .scrape_source_secs_interval(settings.collectors.http.scrape_source_secs_interval)
+        .max_scrape_source_secs_interval(settings.collectors.http.max_scrape_source_secs_interval)
         .build()
         .expect("can't create http collector config");
     let tg_config = config::TelegramConfigBuilder::default()
-        .enabled(settings::SETTINGS.collectors.tg.enabled)
-        .database_directory(settings::SETTINGS.collectors.tg.database_directory.clone())
-        .files_directory(settings::SETTINGS.collectors.tg.files_directory.clone())
-        .log_verbosity_level(settings::SETTINGS.collectors.tg.log_verbosity_level)
-        .max_download_queue_size(settings::SETTINGS.collectors.tg.max_download_queue_size)
-        .encryption_key(settings::SETTINGS.collectors.tg.encryption_key.clone())
-        .phone(settings::SETTINGS.collectors.tg.phone.clone())
-        .api_hash(settings::SETTINGS.collectors.tg.api_hash.clone())
-        .log_download_state_secs_interval(
-            settings::SETTINGS
+        .enabled(settings.collectors.tg.enabled)
+        .database_directory(settings.collectors.tg.database_directory.clone())
+        .files_directory(settings.collectors.tg.files_directory.clone())
+        .log_verbosity_level(settings.collectors.tg.log_verbosity_level)
+        .max_download_queue_size(settings.collectors.tg.max_download_queue_size)
+        .encryption_key(settings.collectors.tg.encryption_key.clone())
+        .phone(settings.collectors.tg.phone.clone())
+        .api_hash(settings.collectors.tg.api_hash.clone())
+        .log_download_state_secs_interval(settings.collectors.tg.log_download_state_secs_interval)
+        .api_id(settings.collectors.tg.api_id)
+        .accounts(
+            settings
                 .collectors
                 .tg
-                .log_download_state_secs_interval,
+                .accounts
+                .iter()
+                .map(|account| {
+                    config::TelegramAccountConfigBuilder::default()
+                        .name(account.name.clone())
+                        .phone(account.phone.clone())
+                        .database_directory(account.database_directory.clone())
+                        .encryption_key(account.encryption_key.clone())
+                        .build()
+                        .expect("can't create telegram account config")
+                })
+                .collect(),
         )
-        .api_id(settings::SETTINGS.collectors.tg.api_id)
         .build()
         .expect("can't create telegram collector config");
+    let tg_web_config = config::TelegramWebConfigBuilder::default()
+        .enabled(settings.collectors.tg_web.enabled)
+        .sleep_secs(settings.collectors.tg_web.sleep_secs)
+        .scrape_source_secs_interval(settings.collectors.tg_web.scrape_source_secs_interval)
+        .max_scrape_source_secs_interval(settings.collectors.tg_web.max_scrape_source_secs_interval)
+        .build()
+        .expect("can't create telegram web collector config");
     let vk_config = config::VkConfigBuilder::default()
-        .enabled(settings::SETTINGS.collectors.vk.enabled)
-        .token(settings::SETTINGS.collectors.vk.token.clone())
-        .sleep_secs(settings::SETTINGS.collectors.vk.sleep_secs)
-        .scrape_source_secs_interval(settings::SETTINGS.collectors.vk.scrape_source_secs_interval)
+        .enabled(settings.collectors.vk.enabled)
+        .token(settings.collectors.vk.token.clone())
+        .accounts(
+            settings
+                .collectors
+                .vk
+                .accounts
+                .iter()
+                .map(|account| {
+                    config::VkAccountConfigBuilder::default()
+                        .name(account.name.clone())
+                        .token(account.token.clone())
+                        .build()
+                        .expect("can't create vk account config")
+                })
+                .collect(),
+        )
+        .sleep_secs(settings.collectors.vk.sleep_secs)
+        .scrape_source_secs_interval(settings.collectors.vk.scrape_source_secs_interval)
+        .max_scrape_source_secs_interval(settings.collectors.vk.max_scrape_source_secs_interval)
         .build()
         .expect("can't create vk collector config");
+    let mastodon_config = config::MastodonConfigBuilder::default()
+        .enabled(settings.collectors.mastodon.enabled)
+        .sleep_secs(settings.collectors.mastodon.sleep_secs)
+        .scrape_source_secs_interval(settings.collectors.mastodon.scrape_source_secs_interval)
+        .max_scrape_source_secs_interval(
+            settings.collectors.mastodon.max_scrape_source_secs_interval,
+        )
+        .build()
+        .expect("can't create mastodon collector config");
+    let reddit_config = config::RedditConfigBuilder::default()
+        .enabled(settings.collectors.reddit.enabled)
+        .sleep_secs(settings.collectors.reddit.sleep_secs)
+        .scrape_source_secs_interval(settings.collectors.reddit.scrape_source_secs_interval)
+        .max_scrape_source_secs_interval(settings.collectors.reddit.max_scrape_source_secs_interval)
+        .build()
+        .expect("can't create reddit collector config");
+    let email_config = config::EmailConfigBuilder::default()
+        .enabled(settings.collectors.email.enabled)
+        .listen_address(settings.collectors.email.listen_address.clone())
+        .domain(settings.collectors.email.domain.clone())
+        .protocol(settings.collectors.email.protocol.clone())
+        .max_message_size(settings.collectors.email.max_message_size)
+        .files_directory(settings.collectors.email.files_directory.clone())
+        .build()
+        .expect("can't create email collector config");
+    let fs_config = config::FilesystemConfigBuilder::default()
+        .enabled(settings.collectors.fs.enabled)
+        .directory(settings.collectors.fs.directory.clone())
+        .poll_interval_secs(settings.collectors.fs.poll_interval_secs)
+        .files_directory(settings.collectors.fs.files_directory.clone())
+        .build()
+        .expect("can't create filesystem collector config");
     config::AppConfigBuilder::default()
         .http(http_config)
         .telegram(tg_config)
+        .telegram_web(tg_web_config)
         .vk(vk_config)
+        .mastodon(mastodon_config)
+        .reddit(reddit_config)
+        .email(email_config)
+        .filesystem(fs_config)
+        .record_processors(settings.processors.clone())
         .build()
         .expect("can't create collector config")
 }
 
-pub type App = Arc<AggApp<PgStorage>>;
+pub type App = Arc<AggApp<AnyStorage>>;
 
 pub async fn build_app() -> App {
     let db_pool = db::init_pool(settings::SETTINGS.database.url.as_str()).await;
-    let storage = PgStorage::new(db_pool.clone());
-    let app_config = init_app_config();
+    let storage = AnyStorage::new(db_pool);
+    let app_config = app_config(&settings::SETTINGS);
     Arc::new(
         aggregator::AppBuilder::new(
             &app_config,
             storage.clone(),
             Arc::new(Box::new(DefaultTelegramParser::new())),
         )
+        .with_processor(Arc::new(rules::Engine::new(storage.pool())))
+        .with_processor(Arc::new(outgoing_webhooks::Trigger::new(storage.pool())))
         .build(),
     )
 }
@@ -8,7 +8,11 @@ mod settings;
 mod auth;
 mod db;
 mod grpc;
+mod outgoing_webhooks;
+mod reload;
 mod result;
+mod retention;
+mod rules;
 
 #[tokio::main]
 async fn main() {
@@ -0,0 +1,323 @@
+use crate::db::models::{OutgoingWebhook, PendingDelivery};
+use crate::db::queries::outgoing_webhooks as queries;
+use crate::db::queries::outgoing_webhooks::DeliveryResult;
+use crate::db::Pool;
+use crate::settings::SETTINGS;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use feeder::models::{NewRecord, Record};
+use feeder::net;
+use feeder::pipeline::RecordProcessor;
+use feeder::queue::Lease;
+use feeder::CancellationToken;
+use hmac::{Hmac, Mac, NewMac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 20;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// the delivery fails after this many attempts, the delays between them are
+// 30s, 1m, 2m, ... up to 6h
+const MAX_ATTEMPTS: i32 = 8;
+const MIN_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 60 * 60;
+const SIGNATURE_HEADER: &str = "x-feeder-signature";
+const DELIVERY_HEADER: &str = "x-feeder-delivery";
+const MAX_ERROR_LEN: usize = 1024;
+
+/// Checks that the webhook url is http or https and its host resolves to public addresses only,
+/// so webhooks can't reach the services of the server network.
+/// Hosts listed in `outgoing_webhooks.allowed_hosts` of the settings aren't checked.
+pub async fn check_url(url: &str) -> std::result::Result<(), String> {
+    public_address(&parse_url(url)?).await.map(|_| ())
+}
+
+fn parse_url(url: &str) -> std::result::Result<reqwest::Url, String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("url must be http or https".to_string());
+    }
+    Ok(url)
+}
+
+// checked address to connect to, `None` for allowed hosts
+async fn public_address(url: &reqwest::Url) -> std::result::Result<Option<SocketAddr>, String> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| "url has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+    if SETTINGS
+        .outgoing_webhooks
+        .allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host.as_str()))
+    {
+        return Ok(None);
+    }
+    let port = url.port_or_known_default().unwrap_or(80);
+    net::public_address(host.as_str(), port).await.map(Some)
+}
+
+/// Queues newly created records for the matching outgoing webhooks,
+/// the last stage of every pipeline.
+pub struct Trigger {
+    db_pool: Pool,
+}
+
+impl Trigger {
+    pub fn new(db_pool: Pool) -> Self {
+        Self { db_pool }
+    }
+
+    // source, folder and tag filters are checked by the query
+    pub(crate) fn matches(webhook: &OutgoingWebhook, record: &Record) -> bool {
+        let keyword = match &webhook.keyword {
+            Some(keyword) => keyword.to_lowercase(),
+            None => return true,
+        };
+        record.content.to_lowercase().contains(&keyword)
+            || record
+                .title
+                .iter()
+                .any(|t| t.to_lowercase().contains(&keyword))
+    }
+}
+
+#[async_trait]
+impl RecordProcessor for Trigger {
+    fn name(&self) -> &str {
+        "outgoing_webhooks"
+    }
+
+    async fn process(
+        &self,
+        _kind: &str,
+        record: NewRecord,
+    ) -> feeder::result::Result<Option<NewRecord>> {
+        Ok(Some(record))
+    }
+
+    async fn saved(&self, _kind: &str, record: &Record) -> feeder::result::Result<()> {
+        // edits aren't delivered, only new records
+        if record.updated_at.is_some() {
+            return Ok(());
+        }
+        let webhooks = queries::get_matching_webhooks(&self.db_pool, record.source_id)
+            .await
+            .map_err(|e| feeder::result::Error::DbError(e.to_string()))?;
+        // rules only webhooks are queued by the rules engine
+        for webhook in webhooks
+            .iter()
+            .filter(|w| !w.rules_only && Self::matches(w, record))
+        {
+            queries::add_delivery(&self.db_pool, webhook.id, record.id)
+                .await
+                .map_err(|e| feeder::result::Error::DbError(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct PayloadRecord<'a> {
+    id: i32,
+    source_id: i32,
+    source_name: &'a str,
+    source_kind: &'a str,
+    title: Option<&'a str>,
+    content: &'a str,
+    link: &'a str,
+    date: i64,
+    image: Option<&'a str>,
+}
+
+/// Json body of the delivery, signed with the webhook secret:
+/// `X-Feeder-Signature: sha256=<hex encoded hmac of the body>`.
+#[derive(Serialize, Debug)]
+struct Payload<'a> {
+    delivery_id: i32,
+    webhook_id: i32,
+    record: PayloadRecord<'a>,
+}
+
+impl<'a> From<&'a PendingDelivery> for Payload<'a> {
+    fn from(delivery: &'a PendingDelivery) -> Self {
+        Self {
+            delivery_id: delivery.id,
+            webhook_id: delivery.webhook_id,
+            record: PayloadRecord {
+                id: delivery.record_id,
+                source_id: delivery.source_id,
+                source_name: delivery.source_name.as_str(),
+                source_kind: delivery.source_kind.as_str(),
+                title: delivery.title.as_deref(),
+                content: delivery.content.as_str(),
+                link: delivery.external_link.as_str(),
+                date: delivery.date.timestamp(),
+                image: delivery.image.as_deref(),
+            },
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    let secs = MIN_RETRY_DELAY_SECS
+        .checked_shl((attempts - 1).max(0) as u32)
+        .unwrap_or(MAX_RETRY_DELAY_SECS)
+        .min(MAX_RETRY_DELAY_SECS);
+    chrono::Duration::seconds(secs)
+}
+
+// redirects aren't followed as their hosts aren't checked
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+/// Status code or the error of the request.
+async fn send(
+    client: &reqwest::Client,
+    delivery: &PendingDelivery,
+) -> std::result::Result<u16, String> {
+    // the host may resolve to another address since the webhook was added,
+    // so the request goes to the address which is checked
+    let url = parse_url(delivery.url.as_str())?;
+    let pinned = match (url.host_str(), public_address(&url).await?) {
+        (Some(domain), Some(address)) => Some(
+            client_builder()
+                .resolve(domain, address)
+                .build()
+                .map_err(|e| e.to_string())?,
+        ),
+        _ => None,
+    };
+    let client = pinned.as_ref().unwrap_or(client);
+    let body = serde_json::to_vec(&Payload::from(delivery)).map_err(|e| e.to_string())?;
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(delivery.secret.as_str(), &body))
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(response.status().as_u16())
+}
+
+async fn deliver(client: &reqwest::Client, delivery: &PendingDelivery) -> DeliveryResult {
+    let attempts = delivery.attempts + 1;
+    let now = Utc::now().naive_utc();
+    let (response_status, last_error) = match send(client, delivery).await {
+        Ok(status) if (200..300).contains(&status) => {
+            log::info!(
+                "record {} delivered to webhook {}",
+                delivery.record_id,
+                delivery.webhook_id
+            );
+            return DeliveryResult {
+                status: queries::DELIVERED,
+                attempts,
+                response_status: Some(status as i32),
+                last_error: None,
+                next_attempt_at: now,
+                delivered_at: Some(now),
+            };
+        }
+        Ok(status) => (Some(status as i32), Some(format!("status {}", status))),
+        Err(mut e) => {
+            e.truncate(MAX_ERROR_LEN);
+            (None, Some(e))
+        }
+    };
+    let status = if attempts >= MAX_ATTEMPTS {
+        queries::FAILED
+    } else {
+        queries::PENDING
+    };
+    log::warn!(
+        "delivery {} of record {} to webhook {} failed, attempt {}: {}",
+        delivery.id,
+        delivery.record_id,
+        delivery.webhook_id,
+        attempts,
+        last_error.as_deref().unwrap_or_default()
+    );
+    DeliveryResult {
+        status,
+        attempts,
+        response_status,
+        last_error,
+        next_attempt_at: now + retry_delay(attempts),
+        delivered_at: None,
+    }
+}
+
+async fn deliver_pending(
+    db_pool: &Pool,
+    client: &reqwest::Client,
+    now: NaiveDateTime,
+) -> crate::result::Result<usize> {
+    let lease = Lease {
+        limit: BATCH_SIZE,
+        ..Lease::new(now)
+    };
+    let deliveries = queries::claim_deliveries(db_pool, now, &lease).await?;
+    for delivery in &deliveries {
+        let result = deliver(client, delivery).await;
+        queries::set_delivery_result(db_pool, delivery.id, result).await?;
+    }
+    Ok(deliveries.len())
+}
+
+/// Sends the queued deliveries until the shutdown.
+pub async fn run(db_pool: Pool, shutdown: CancellationToken) {
+    let client = client_builder().build().expect("can't build http client");
+    log::info!("outgoing webhooks started");
+    loop {
+        let delivered = match deliver_pending(&db_pool, &client, Utc::now().naive_utc()).await {
+            Ok(delivered) => delivered,
+            Err(e) => {
+                log::error!("outgoing webhooks deliveries failed: {}", e);
+                0
+            }
+        };
+        // full batch, there may be more due ones
+        if delivered as i64 == BATCH_SIZE && !shutdown.is_cancelled() {
+            continue;
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+    log::info!("outgoing webhooks stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_delay, MAX_RETRY_DELAY_SECS, MIN_RETRY_DELAY_SECS};
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(0).num_seconds(), MIN_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay(1).num_seconds(), MIN_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay(2).num_seconds(), 2 * MIN_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay(4).num_seconds(), 8 * MIN_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay(20).num_seconds(), MAX_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay(100).num_seconds(), MAX_RETRY_DELAY_SECS);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::init::{self, App};
+use crate::settings;
+use feeder::CancellationToken;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Applies the config of the collectors on SIGHUP or once its files change.
+/// Database, server and other settings of the interface are read on start only.
+pub async fn run(app: App, shutdown: CancellationToken) {
+    let mut hangup = signal(SignalKind::hangup()).expect("can't listen SIGHUP");
+    let mut modified = settings::modified();
+    log::info!("config reload started");
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = hangup.recv() => log::info!("SIGHUP received, reloading config"),
+            _ = tokio::time::sleep(POLL_INTERVAL) => {
+                if settings::modified() == modified {
+                    continue;
+                }
+                log::info!("config changed, reloading");
+            }
+        }
+        modified = settings::modified();
+        reload(&app).await;
+    }
+    log::info!("config reload stopped");
+}
+
+async fn reload(app: &App) {
+    // invalid config is reported and the running one is kept
+    let settings = match settings::load() {
+        Ok(settings) => settings,
+        Err(problems) => {
+            log::error!("config isn't reloaded: {}", problems.join("; "));
+            return;
+        }
+    };
+    match app.reload(init::app_config(&settings)).await {
+        Ok(()) => log::info!("config reloaded"),
+        Err(e) => log::error!("config isn't reloaded: {}", e),
+    }
+}
@@ -12,7 +12,10 @@ pub enum Error {
 
 impl From<feeder::result::Error> for Error {
     fn from(error: feeder::result::Error) -> Self {
-        Error::InternalServerError(error.to_string())
+        match error {
+            feeder::result::Error::InvalidSchedule(e) => Error::BadRequest(e),
+            _ => Error::InternalServerError(error.to_string()),
+        }
     }
 }
 
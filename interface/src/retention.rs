@@ -0,0 +1,172 @@
+use crate::db::models::SourceRetention;
+use crate::db::queries::retention as queries;
+use crate::db::Pool;
+use crate::result::Result;
+use crate::settings::SETTINGS;
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use feeder::CancellationToken;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const BATCH_SIZE: i64 = 1000;
+
+/// Records and files removed from a source.
+#[derive(Debug, Default)]
+pub struct Pruned {
+    pub source_id: i32,
+    pub records: u64,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub sources: Vec<Pruned>,
+    pub records: u64,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+// limits of the source, the override of a limit replaces the global one,
+// 0 is unlimited
+fn limits(retention: &SourceRetention, now: NaiveDateTime) -> (Option<NaiveDateTime>, Option<i64>) {
+    let max_age_days = retention
+        .max_age_days
+        .map(|days| days.max(0) as i64)
+        .unwrap_or(SETTINGS.retention.max_age_days as i64);
+    let max_count = retention
+        .max_count
+        .map(|count| count.max(0) as i64)
+        .unwrap_or(SETTINGS.retention.max_count as i64);
+    (
+        Some(max_age_days)
+            .filter(|days| *days > 0)
+            .map(|days| now - ChronoDuration::days(days)),
+        Some(max_count).filter(|count| *count > 0),
+    )
+}
+
+// directories the collectors download files to, the fs collector also references
+// the watched files in place, those are never removed
+fn files_directories() -> Vec<PathBuf> {
+    let collectors = &SETTINGS.collectors;
+    [
+        &collectors.tg.files_directory,
+        &collectors.email.files_directory,
+        &collectors.fs.files_directory,
+    ]
+    .iter()
+    .filter_map(|directory| Path::new(directory.as_str()).canonicalize().ok())
+    .collect()
+}
+
+async fn downloaded_file(path: &str, directories: &[PathBuf]) -> Option<(PathBuf, u64)> {
+    let path = tokio::fs::canonicalize(path).await.ok()?;
+    if !directories
+        .iter()
+        .any(|directory| path.starts_with(directory))
+    {
+        return None;
+    }
+    let size = tokio::fs::metadata(&path).await.ok()?.len();
+    Some((path, size))
+}
+
+async fn prune_source(
+    db_pool: &Pool,
+    retention: &SourceRetention,
+    now: NaiveDateTime,
+    directories: &[PathBuf],
+    dry_run: bool,
+) -> Result<Pruned> {
+    let mut pruned = Pruned {
+        source_id: retention.source_id,
+        ..Default::default()
+    };
+    let (before, keep) = limits(retention, now);
+    if before.is_none() && keep.is_none() {
+        return Ok(pruned);
+    }
+    // nothing is deleted on a dry run, so everything is fetched at once
+    let limit = if dry_run { i64::MAX } else { BATCH_SIZE };
+    loop {
+        let ids =
+            queries::get_expired_records(db_pool, retention.source_id, before, keep, limit).await?;
+        if ids.is_empty() {
+            break;
+        }
+        if dry_run {
+            for path in queries::get_local_files(db_pool, &ids).await? {
+                if let Some((_, size)) = downloaded_file(path.as_str(), directories).await {
+                    pruned.files += 1;
+                    pruned.bytes += size;
+                }
+            }
+            pruned.records += ids.len() as u64;
+            break;
+        }
+        // records starred or tagged since they were found are skipped
+        let deleted =
+            queries::delete_expired_records(db_pool, retention.source_id, before, keep, &ids)
+                .await?;
+        pruned.records += deleted.count;
+        for path in deleted.local_files {
+            let (path, size) = match downloaded_file(path.as_str(), directories).await {
+                Some(file) => file,
+                None => continue,
+            };
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {
+                    pruned.files += 1;
+                    pruned.bytes += size;
+                }
+                Err(e) => log::warn!("can't remove {}: {}", path.display(), e),
+            }
+        }
+        if (ids.len() as i64) < limit {
+            break;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Removes the records beyond the retention of their sources with their downloaded files.
+/// Starred and tagged records are kept, nothing is removed on a dry run.
+pub async fn prune(db_pool: &Pool, dry_run: bool) -> Result<Report> {
+    let now = Utc::now().naive_utc();
+    let directories = files_directories();
+    let mut report = Report::default();
+    for retention in queries::get_source_retentions(db_pool).await? {
+        let pruned = prune_source(db_pool, &retention, now, &directories, dry_run).await?;
+        if pruned.records == 0 {
+            continue;
+        }
+        report.records += pruned.records;
+        report.files += pruned.files;
+        report.bytes += pruned.bytes;
+        report.sources.push(pruned);
+    }
+    Ok(report)
+}
+
+pub async fn run(db_pool: Pool, shutdown: CancellationToken) {
+    let interval = Duration::from_secs(SETTINGS.retention.interval_secs.max(1));
+    log::info!("retention started");
+    loop {
+        match prune(&db_pool, false).await {
+            Ok(report) if report.records > 0 => log::info!(
+                "pruned {} records and {} files of {} sources",
+                report.records,
+                report.files,
+                report.sources.len()
+            ),
+            Ok(_) => {}
+            Err(e) => log::error!("pruning failed: {}", e),
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+    }
+    log::info!("retention stopped");
+}
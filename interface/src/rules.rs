@@ -0,0 +1,326 @@
+use crate::db::models::{NewUserRule, UserRule};
+use crate::db::queries::{
+    folders as folders_queries, outgoing_webhooks as outgoing_webhooks_queries,
+    records as records_queries, rules as rules_queries,
+};
+use crate::db::Pool;
+use crate::outgoing_webhooks::Trigger;
+use crate::result::{Error, Result};
+use async_trait::async_trait;
+use feeder::models::{NewRecord, Record};
+use feeder::pipeline::RecordProcessor;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Checks the rule and stores it.
+pub async fn create_rule(db_pool: &Pool, user_id: i32, rule: NewUserRule) -> Result<UserRule> {
+    if !rule.star && !rule.hide && !rule.mark_read && !rule.notify && rule.tag.is_none() {
+        return Err(Error::BadRequest("rule has no actions".to_string()));
+    }
+    if let Some(pattern) = &rule.pattern {
+        Regex::new(pattern).map_err(|e| Error::BadRequest(format!("invalid pattern: {}", e)))?;
+    }
+    if rule
+        .keywords
+        .iter()
+        .any(|k| k.contains(rules_queries::KEYWORDS_SEPARATOR))
+    {
+        return Err(Error::BadRequest(
+            "keywords must be single line".to_string(),
+        ));
+    }
+    if let Some(folder_id) = rule.folder_id {
+        folders_queries::get_user_folders(db_pool, user_id)
+            .await?
+            .iter()
+            .find(|f| f.id == folder_id)
+            .ok_or_else(|| Error::NotFound("folder not found".to_string()))?;
+    }
+    rules_queries::create_rule(db_pool, user_id, rule).await
+}
+
+/// Applies the rules of the subscribed users to the newly created records.
+pub struct Engine {
+    db_pool: Pool,
+    // compiled patterns by rule id, recompiled once the pattern differs
+    patterns: Mutex<HashMap<i32, Regex>>,
+}
+
+impl Engine {
+    pub fn new(db_pool: Pool) -> Self {
+        Self {
+            db_pool,
+            patterns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn regex(&self, rule_id: i32, pattern: &str) -> Option<Regex> {
+        let mut patterns = self.patterns.lock().unwrap();
+        if let Some(regex) = patterns.get(&rule_id).filter(|r| r.as_str() == pattern) {
+            return Some(regex.clone());
+        }
+        // patterns are checked when the rules are created
+        match Regex::new(pattern) {
+            Ok(regex) => {
+                patterns.insert(rule_id, regex.clone());
+                Some(regex)
+            }
+            Err(e) => {
+                log::warn!("invalid pattern of rule {}: {}", rule_id, e);
+                None
+            }
+        }
+    }
+
+    async fn matches(&self, rule: &UserRule, kind: &str, record: &Record) -> Result<bool> {
+        if let Some(rule_kind) = &rule.kind {
+            if !rule_kind.eq_ignore_ascii_case(kind) {
+                return Ok(false);
+            }
+        }
+        let title = record.title.as_deref().unwrap_or_default();
+        if let Some(pattern) = &rule.pattern {
+            let regex = match self.regex(rule.id, pattern) {
+                Some(regex) => regex,
+                None => return Ok(false),
+            };
+            if !regex.is_match(title) && !regex.is_match(record.content.as_str()) {
+                return Ok(false);
+            }
+        }
+        let keywords = rules_queries::split_keywords(&rule.keywords);
+        if !keywords.is_empty() {
+            let title = title.to_lowercase();
+            let content = record.content.to_lowercase();
+            let found = keywords
+                .iter()
+                .map(|k| k.to_lowercase())
+                .any(|k| title.contains(k.as_str()) || content.contains(k.as_str()));
+            if !found {
+                return Ok(false);
+            }
+        }
+        if rule.has_attachment
+            && record.image.is_none()
+            && !rules_queries::has_files(&self.db_pool, record.id).await?
+        {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    async fn apply(&self, rule: &UserRule, record: &Record) -> Result<()> {
+        log::debug!("rule {} matches record {}", rule.id, record.id);
+        if let Some(tag) = &rule.tag {
+            records_queries::add_tag(&self.db_pool, rule.user_id, record.id, tag.clone()).await?;
+        }
+        if rule.star || rule.hide || rule.mark_read {
+            rules_queries::set_record_flags(
+                &self.db_pool,
+                rule.user_id,
+                record.id,
+                rule.star,
+                rule.hide,
+                rule.mark_read,
+            )
+            .await?;
+        }
+        if rule.notify {
+            // only the rules only webhooks of the rule owner whose filters match the record,
+            // the rest get every matching record anyway
+            let webhooks =
+                outgoing_webhooks_queries::get_matching_webhooks(&self.db_pool, record.source_id)
+                    .await?;
+            for webhook in webhooks.iter().filter(|w| {
+                w.rules_only && w.user_id == rule.user_id && Trigger::matches(w, record)
+            }) {
+                outgoing_webhooks_queries::add_delivery(&self.db_pool, webhook.id, record.id)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn evaluate(&self, kind: &str, record: &Record) -> Result<()> {
+        let rules = rules_queries::get_matching_rules(&self.db_pool, record.source_id).await?;
+        for rule in &rules {
+            if self.matches(rule, kind, record).await? {
+                self.apply(rule, record).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecordProcessor for Engine {
+    fn name(&self) -> &str {
+        "rules"
+    }
+
+    async fn process(
+        &self,
+        _kind: &str,
+        record: NewRecord,
+    ) -> feeder::result::Result<Option<NewRecord>> {
+        Ok(Some(record))
+    }
+
+    async fn saved(&self, kind: &str, record: &Record) -> feeder::result::Result<()> {
+        // edits keep the actions applied to the record
+        if record.updated_at.is_some() {
+            return Ok(());
+        }
+        self.evaluate(kind, record)
+            .await
+            .map_err(|e| feeder::result::Error::DbError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Engine;
+    use crate::db::models::UserRule;
+    use crate::db::Pool;
+    use chrono::NaiveDateTime;
+    use feeder::models::Record;
+    use feeder::storage::AnyStorage;
+
+    // connections of `sqlite::memory:` don't share the database, so it's a temp file
+    async fn engine(name: &str) -> Engine {
+        let path =
+            std::env::temp_dir().join(format!("feeder-rules-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let pool = Pool::connect(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+        let storage = AnyStorage::new(pool);
+        storage.migrate().await;
+        Engine::new(storage.pool())
+    }
+
+    fn rule() -> UserRule {
+        UserRule {
+            id: 1,
+            user_id: 1,
+            name: "rule".to_string(),
+            source_id: None,
+            folder_id: None,
+            kind: None,
+            pattern: None,
+            keywords: None,
+            has_attachment: false,
+            tag: None,
+            star: true,
+            hide: false,
+            mark_read: false,
+            notify: false,
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+        }
+    }
+
+    fn record(title: &str, content: &str) -> Record {
+        Record {
+            id: 1,
+            title: Some(title.to_string()),
+            source_record_id: "1".to_string(),
+            source_id: 1,
+            content: content.to_string(),
+            date: NaiveDateTime::from_timestamp(0, 0),
+            image: None,
+            external_link: String::new(),
+            meta: None,
+            content_hash: None,
+            updated_at: None,
+            simhash: None,
+            canonical_link: None,
+            cluster_id: None,
+            language: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matches() {
+        let engine = engine("matches").await;
+        let record = record("Release 1.0", "<p>Rust is out</p>");
+        assert!(engine.matches(&rule(), "WEB", &record).await.unwrap());
+
+        let kind = UserRule {
+            kind: Some("web".to_string()),
+            ..rule()
+        };
+        assert!(engine.matches(&kind, "WEB", &record).await.unwrap());
+        assert!(!engine.matches(&kind, "EMAIL", &record).await.unwrap());
+
+        for (pattern, matches) in &[
+            (r"^Release \d", true),
+            (r"Rust\s+is", true),
+            (r"^Rust", false),
+            // invalid patterns match nothing
+            (r"(", false),
+        ] {
+            let rule = UserRule {
+                id: 2,
+                pattern: Some(pattern.to_string()),
+                ..rule()
+            };
+            assert_eq!(
+                engine.matches(&rule, "WEB", &record).await.unwrap(),
+                *matches,
+                "{}",
+                pattern
+            );
+        }
+
+        for (keywords, matches) in &[
+            ("release", true),
+            ("go\nRUST", true),
+            ("go\npython", false),
+            ("", true),
+        ] {
+            let rule = UserRule {
+                keywords: Some(keywords.to_string()),
+                ..rule()
+            };
+            assert_eq!(
+                engine.matches(&rule, "WEB", &record).await.unwrap(),
+                *matches,
+                "{:?}",
+                keywords
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matches_attachment() {
+        let engine = engine("attachment").await;
+        let rule = UserRule {
+            has_attachment: true,
+            ..rule()
+        };
+        let mut record = record("title", "content");
+        assert!(!engine.matches(&rule, "WEB", &record).await.unwrap());
+
+        let pool = match &engine.db_pool {
+            Pool::Sqlite(pool) => pool,
+            Pool::Pg(_) => unreachable!(),
+        };
+        for query in &[
+            "INSERT INTO sources (id, name, origin, kind, external_link) \
+            VALUES (1, 'source', 'source', 'WEB', '')",
+            "INSERT INTO records (id, source_record_id, source_id, content, date) \
+            VALUES (1, '1', 1, 'content', CURRENT_TIMESTAMP)",
+            "INSERT INTO files (record_id, kind, remote_path, type) \
+            VALUES (1, 'web', 'https://example.com/a.png', 'image')",
+        ] {
+            sqlx::query(query).execute(pool).await.unwrap();
+        }
+        assert!(engine.matches(&rule, "WEB", &record).await.unwrap());
+
+        record.id = 2;
+        assert!(!engine.matches(&rule, "WEB", &record).await.unwrap());
+        record.image = Some("https://example.com/b.png".to_string());
+        assert!(engine.matches(&rule, "WEB", &record).await.unwrap());
+    }
+}
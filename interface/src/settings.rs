@@ -1,15 +1,90 @@
 use config::{Config, ConfigError, Environment, File};
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::exit;
+use std::time::SystemTime;
+
+// files read when `--config` isn't passed, the local one is optional
+const DEFAULT_FILES: [&str; 2] = ["config/default", "config/local"];
+const EXTENSIONS: [&str; 4] = ["yaml", "yml", "toml", "json"];
 
 lazy_static! {
-    pub static ref SETTINGS: Settings = Settings::new().expect("can't read settings");
+    pub static ref SETTINGS: Settings = load().unwrap_or_else(|problems| {
+        eprintln!("invalid config:");
+        for problem in problems {
+            eprintln!("  {}", problem);
+        }
+        exit(1)
+    });
 }
 
 pub fn init() {
     lazy_static::initialize(&SETTINGS);
 }
 
+// settings are needed to build the cli, so the path is taken before arguments are parsed
+fn config_path() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// Reads the config and checks it, every problem found is returned.
+pub fn load() -> Result<Settings, Vec<String>> {
+    let settings = Settings::new(config_path().as_deref()).map_err(|e| vec![e.to_string()])?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Modification times of the config files, `None` for the missing ones.
+pub fn modified() -> Vec<Option<SystemTime>> {
+    let files = match config_path() {
+        Some(path) => vec![path],
+        None => DEFAULT_FILES
+            .iter()
+            .flat_map(|name| {
+                EXTENSIONS
+                    .iter()
+                    .map(move |ext| format!("{}.{}", name, ext))
+            })
+            .collect(),
+    };
+    files
+        .iter()
+        .map(|file| Path::new(file).metadata().and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+// feeder names the problems by its config sections, they are renamed to the keys of the file
+fn settings_key(problem: String) -> String {
+    let renames = [
+        ("telegram_web.", "collectors.tg_web."),
+        ("telegram.", "collectors.tg."),
+        ("filesystem.", "collectors.fs."),
+        ("http.", "collectors.http."),
+        ("vk.", "collectors.vk."),
+        ("mastodon.", "collectors.mastodon."),
+        ("reddit.", "collectors.reddit."),
+        ("email.", "collectors.email."),
+        ("record processor", "processors: record processor"),
+    ];
+    for (section, key) in renames.iter() {
+        if let Some(rest) = problem.strip_prefix(section) {
+            return format!("{}{}", key, rest);
+        }
+    }
+    problem
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Database {
     pub url: String,
@@ -26,7 +101,8 @@ pub struct Server {
 pub struct HttpCollector {
     pub enabled: bool,
     pub sleep_secs: u64,
-    pub scrape_source_secs_interval: i32,
+    pub scrape_source_secs_interval: u64,
+    pub max_scrape_source_secs_interval: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,6 +117,26 @@ pub struct TgCollector {
     pub max_download_queue_size: usize,
     pub files_directory: String,
     pub log_download_state_secs_interval: u64,
+    // sessions besides the main one
+    #[serde(default)]
+    pub accounts: Vec<TgAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TgAccount {
+    pub name: String,
+    pub phone: String,
+    pub database_directory: String,
+    #[serde(default)]
+    pub encryption_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TgWebCollector {
+    pub enabled: bool,
+    pub sleep_secs: u64,
+    pub scrape_source_secs_interval: u64,
+    pub max_scrape_source_secs_interval: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -48,14 +144,100 @@ pub struct VkCollector {
     pub enabled: bool,
     pub sleep_secs: u64,
     pub scrape_source_secs_interval: u64,
+    pub max_scrape_source_secs_interval: u64,
     pub token: String,
+    // tokens pooled with the main one
+    #[serde(default)]
+    pub accounts: Vec<VkAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VkAccount {
+    pub name: String,
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MastodonCollector {
+    pub enabled: bool,
+    pub sleep_secs: u64,
+    pub scrape_source_secs_interval: u64,
+    pub max_scrape_source_secs_interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedditCollector {
+    pub enabled: bool,
+    pub sleep_secs: u64,
+    pub scrape_source_secs_interval: u64,
+    pub max_scrape_source_secs_interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailCollector {
+    pub enabled: bool,
+    pub listen_address: String,
+    pub domain: String,
+    pub protocol: String,
+    pub max_message_size: usize,
+    pub files_directory: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsCollector {
+    pub enabled: bool,
+    pub directory: String,
+    pub poll_interval_secs: u64,
+    pub files_directory: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Collectors {
     pub http: HttpCollector,
     pub tg: TgCollector,
+    pub tg_web: TgWebCollector,
     pub vk: VkCollector,
+    pub mastodon: MastodonCollector,
+    pub reddit: RedditCollector,
+    pub email: EmailCollector,
+    pub fs: FsCollector,
+}
+
+impl Collectors {
+    /// Names of the vk and telegram accounts sources can be assigned to.
+    pub fn account_names(&self) -> Vec<&str> {
+        std::iter::once(feeder::config::DEFAULT_ACCOUNT)
+            .chain(self.vk.accounts.iter().map(|a| a.name.as_str()))
+            .chain(self.tg.accounts.iter().map(|a| a.name.as_str()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct OutgoingWebhooks {
+    // hosts trusted to resolve to internal addresses, like a local automation service
+    pub allowed_hosts: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Retention {
+    // records older than this are pruned, 0 keeps them forever
+    pub max_age_days: u32,
+    // the latest records of a source which are kept, 0 keeps all of them
+    pub max_count: u32,
+    pub interval_secs: u64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_age_days: 0,
+            max_count: 0,
+            interval_secs: 3600,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,18 +245,33 @@ pub struct Settings {
     pub database: Database,
     pub collectors: Collectors,
     pub server: Server,
+    // built-in record processors by source kind
+    #[serde(default)]
+    pub processors: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub outgoing_webhooks: OutgoingWebhooks,
 }
 
 impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
+    pub fn new(path: Option<&str>) -> Result<Self, ConfigError> {
         let mut s = Config::new();
 
-        // Start off by merging in the "default" configuration file
-        s.merge(File::with_name("config/default"))?;
+        match path {
+            // explicit config replaces both default and local ones
+            Some(path) => {
+                s.merge(File::with_name(path))?;
+            }
+            None => {
+                // Start off by merging in the "default" configuration file
+                s.merge(File::with_name(DEFAULT_FILES[0]))?;
 
-        // Add in a local configuration file
-        // This file shouldn't be checked in to git
-        s.merge(File::with_name("config/local").required(false))?;
+                // Add in a local configuration file
+                // This file shouldn't be checked in to git
+                s.merge(File::with_name(DEFAULT_FILES[1]).required(false))?;
+            }
+        }
 
         // Add in settings from the environment (with a prefix of APP)
         // Eg.. `APP_DEBUG=1 ./target/app` would set the `debug` key
@@ -82,4 +279,34 @@ impl Settings {
         // You can deserialize (and thus freeze) the entire configuration as
         s.try_into()
     }
+
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = vec![];
+        let url = self.database.url.as_str();
+        if !url.starts_with("postgres://")
+            && !url.starts_with("postgresql://")
+            && !url.starts_with("sqlite:")
+        {
+            problems.push(format!(
+                "database.url {:?} isn't a postgresql:// or sqlite: url",
+                url
+            ));
+        }
+        if self.server.enabled && self.server.port == 0 {
+            problems.push("server.port must be set when server is enabled".to_string());
+        }
+        if self.retention.interval_secs == 0 {
+            problems.push("retention.interval_secs must be positive".to_string());
+        }
+        if let Err(feeder::result::Error::InvalidConfig(feeder_problems)) =
+            crate::init::app_config(self).validate()
+        {
+            problems.extend(feeder_problems.into_iter().map(settings_key));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
 }
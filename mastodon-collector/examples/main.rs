@@ -0,0 +1,16 @@
+use chrono::{Duration, Utc};
+use mastodon_collector::client::MastodonClient;
+use mastodon_collector::types::Handle;
+
+#[tokio::main]
+async fn main() {
+    let client = MastodonClient::default();
+    let handle = Handle::parse("@rust@fosstodon.org").unwrap();
+    let timeline = client.get_timeline(&handle, None).await.unwrap();
+    println!("{:?}", timeline.account);
+    let statuses = client
+        .get_history(&handle, Utc::now() - Duration::days(7))
+        .await
+        .unwrap();
+    println!("{} statuses for the last week", statuses.len());
+}
@@ -0,0 +1,308 @@
+use crate::result::{Error, Result};
+use crate::types::{Account, Handle, Status};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const PAGE_LIMIT: u8 = 40;
+
+/// Client for public Mastodon REST API.
+///
+/// Instances are taken from handles, so the same client works with any number of them.
+/// Only public endpoints are used, no application registration needed.
+#[derive(Debug, Clone)]
+pub struct MastodonClient {
+    client: Client,
+    scheme: String,
+    // resolved accounts by `@user@instance`
+    accounts: Arc<RwLock<HashMap<String, Account>>>,
+}
+
+/// Statuses of an account or a hashtag, newest first.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub handle: Handle,
+    // `None` for hashtags
+    pub account: Option<Account>,
+    pub statuses: Vec<Status>,
+}
+
+impl Default for MastodonClient {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+impl MastodonClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            scheme: "https".to_string(),
+            accounts: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    pub async fn lookup_account(&self, instance: &str, username: &str) -> Result<Account> {
+        let key = format!("@{}@{}", username, instance);
+        if let Some(account) = self.accounts.read().await.get(&key) {
+            return Ok(account.clone());
+        }
+        let account: Account = self
+            .send(
+                self.client
+                    .get(self.url(instance, "/api/v1/accounts/lookup").as_str())
+                    .query(&[("acct", username)]),
+            )
+            .await?;
+        self.accounts.write().await.insert(key, account.clone());
+        Ok(account)
+    }
+
+    pub async fn get_account_statuses(
+        &self,
+        instance: &str,
+        account_id: &str,
+        max_id: Option<&str>,
+    ) -> Result<Vec<Status>> {
+        let path = format!("/api/v1/accounts/{}/statuses", account_id);
+        self.get_page(instance, path.as_str(), max_id).await
+    }
+
+    pub async fn get_tag_timeline(
+        &self,
+        instance: &str,
+        tag: &str,
+        max_id: Option<&str>,
+    ) -> Result<Vec<Status>> {
+        let path = format!("/api/v1/timelines/tag/{}", tag);
+        self.get_page(instance, path.as_str(), max_id).await
+    }
+
+    /// Returns the latest statuses or statuses older than `max_id`.
+    pub async fn get_timeline(&self, handle: &Handle, max_id: Option<&str>) -> Result<Timeline> {
+        let (account, statuses) = match handle {
+            Handle::Account { username, instance } => {
+                let account = self.lookup_account(instance, username).await?;
+                let statuses = self
+                    .get_account_statuses(instance, account.id(), max_id)
+                    .await?;
+                (Some(account), statuses)
+            }
+            Handle::Tag { tag, instance } => {
+                (None, self.get_tag_timeline(instance, tag, max_id).await?)
+            }
+        };
+        Ok(Timeline {
+            handle: handle.clone(),
+            account,
+            statuses,
+        })
+    }
+
+    /// Walks the timeline from the newest statuses to the oldest ones until `until` reached.
+    pub async fn get_history(&self, handle: &Handle, until: DateTime<Utc>) -> Result<Vec<Status>> {
+        let mut result = vec![];
+        let mut max_id: Option<String> = None;
+        loop {
+            let timeline = self.get_timeline(handle, max_id.as_deref()).await?;
+            let next_max_id = timeline.statuses.last().map(|s| s.id().to_string());
+            let mut reached = timeline.statuses.is_empty();
+            for status in timeline.statuses {
+                match status.created_at() < until {
+                    true => reached = true,
+                    false => result.push(status),
+                }
+            }
+            trace!("got {} statuses of {}", result.len(), handle);
+            if reached || next_max_id.is_none() || next_max_id == max_id {
+                break;
+            }
+            max_id = next_max_id;
+        }
+        Ok(result)
+    }
+
+    async fn get_page(
+        &self,
+        instance: &str,
+        path: &str,
+        max_id: Option<&str>,
+    ) -> Result<Vec<Status>> {
+        let mut request = self
+            .client
+            .get(self.url(instance, path).as_str())
+            .query(&[("limit", PAGE_LIMIT)]);
+        if let Some(max_id) = max_id {
+            request = request.query(&[("max_id", max_id)]);
+        }
+        self.send(request).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Err(Error::NotFound),
+            StatusCode::TOO_MANY_REQUESTS => Err(Error::RateLimited),
+            status => Err(Error::HttpError(status.as_u16())),
+        }
+    }
+
+    fn url(&self, instance: &str, path: &str) -> String {
+        format!("{}://{}{}", self.scheme, instance, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MastodonClient;
+    use crate::result::Error;
+    use crate::types::Handle;
+    use chrono::{TimeZone, Utc};
+    use mockito::{Matcher, Mock, Server, ServerGuard};
+
+    fn json(server: &mut ServerGuard, path: &str, query: Matcher, body: &str) -> Mock {
+        server
+            .mock("GET", path)
+            .match_query(query)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+    }
+
+    struct MockServer {
+        server: ServerGuard,
+        lookup: Mock,
+        next_page: Mock,
+        // mocks are removed once they are dropped
+        _mocks: Vec<Mock>,
+    }
+
+    // serves the fixtures of the rust account and tag, every request matches one mock
+    async fn run_mock_server() -> MockServer {
+        let mut server = Server::new_async().await;
+        let statuses = "/api/v1/accounts/106432/statuses";
+        let lookup = json(
+            &mut server,
+            "/api/v1/accounts/lookup",
+            Matcher::UrlEncoded("acct".into(), "rust".into()),
+            include_str!("../fixtures/account.json"),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+        let next_page = json(
+            &mut server,
+            statuses,
+            Matcher::UrlEncoded("max_id".into(), "106432100000000001".into()),
+            "[]",
+        )
+        .expect(1)
+        .create_async()
+        .await;
+        let mocks = vec![
+            // the first page has no cursor
+            json(
+                &mut server,
+                statuses,
+                Matcher::Regex(r"^limit=\d+$".into()),
+                include_str!("../fixtures/statuses.json"),
+            )
+            .create_async()
+            .await,
+            json(
+                &mut server,
+                "/api/v1/timelines/tag/rust",
+                Matcher::Any,
+                include_str!("../fixtures/statuses.json"),
+            )
+            .create_async()
+            .await,
+            json(
+                &mut server,
+                "/api/v1/accounts/lookup",
+                Matcher::UrlEncoded("acct".into(), "nobody".into()),
+                r#"{"error":"Record not found"}"#,
+            )
+            .with_status(404)
+            .create_async()
+            .await,
+        ];
+        MockServer {
+            server,
+            lookup,
+            next_page,
+            _mocks: mocks,
+        }
+    }
+
+    fn account(server: &MockServer, username: &str) -> Handle {
+        Handle::Account {
+            username: username.to_string(),
+            instance: server.server.host_with_port(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_account_timeline() {
+        let server = run_mock_server().await;
+        let client = MastodonClient::default().with_scheme("http");
+        let handle = account(&server, "rust");
+
+        let timeline = client.get_timeline(&handle, None).await.unwrap();
+        assert_eq!(timeline.account.unwrap().display_name(), "Rust");
+        assert_eq!(timeline.statuses.len(), 2);
+
+        // account lookup is cached
+        client.get_timeline(&handle, None).await.unwrap();
+        server.lookup.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_history() {
+        let server = run_mock_server().await;
+        let client = MastodonClient::default().with_scheme("http");
+        let handle = account(&server, "rust");
+
+        let statuses = client
+            .get_history(&handle, Utc.ymd(2021, 1, 1).and_hms(0, 0, 0))
+            .await
+            .unwrap();
+        assert_eq!(statuses.len(), 2);
+
+        let statuses = client
+            .get_history(&handle, Utc.ymd(2021, 6, 18).and_hms(0, 0, 0))
+            .await
+            .unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].id(), "106432110000000002");
+        // only the first walk goes on to the next page
+        server.next_page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_tag_timeline() {
+        let server = run_mock_server().await;
+        let client = MastodonClient::default().with_scheme("http");
+
+        let handle = Handle::Tag {
+            tag: "rust".to_string(),
+            instance: server.server.host_with_port(),
+        };
+        let timeline = client.get_timeline(&handle, None).await.unwrap();
+        assert!(timeline.account.is_none());
+        assert_eq!(timeline.statuses.len(), 2);
+
+        assert!(matches!(
+            client.get_timeline(&account(&server, "nobody"), None).await,
+            Err(Error::NotFound)
+        ));
+    }
+}
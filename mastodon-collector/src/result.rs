@@ -0,0 +1,30 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    // `@user@instance` or `#tag@instance` expected
+    InvalidHandle(String),
+    NotFound,
+    RateLimited,
+    RequestTimeout,
+    HttpError(u16),
+    Internal(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::RequestTimeout
+        } else {
+            Error::Internal(err.to_string())
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::result::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt;
+
+/// Followed entity: account (`@user@instance`) or hashtag timeline (`#tag@instance`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Handle {
+    Account { username: String, instance: String },
+    Tag { tag: String, instance: String },
+}
+
+impl Handle {
+    pub fn parse(handle: &str) -> Result<Self> {
+        let handle = handle.trim();
+        let invalid = || Error::InvalidHandle(handle.to_string());
+        let (is_tag, rest) = if let Some(rest) = handle.strip_prefix('#') {
+            (true, rest)
+        } else if let Some(rest) = handle.strip_prefix('@') {
+            (false, rest)
+        } else {
+            return Err(invalid());
+        };
+        let mut parts = rest.splitn(2, '@');
+        let name = parts.next().filter(|n| !n.is_empty()).ok_or_else(invalid)?;
+        let instance = parts
+            .next()
+            .filter(|i| !i.is_empty() && !i.contains(|c: char| c == '/' || c.is_whitespace()))
+            .ok_or_else(invalid)?;
+        if name.contains(|c: char| c == '@' || c == '/' || c.is_whitespace()) {
+            return Err(invalid());
+        }
+        // instance is a domain, ip addresses and ports aren't accepted
+        // so handles can't point to the services of the server network
+        if instance.contains(&[':', '[', ']'][..]) || instance.parse::<std::net::IpAddr>().is_ok() {
+            return Err(invalid());
+        }
+        let instance = instance.to_lowercase();
+        Ok(match is_tag {
+            true => Handle::Tag {
+                tag: name.to_lowercase(),
+                instance,
+            },
+            false => Handle::Account {
+                username: name.to_string(),
+                instance,
+            },
+        })
+    }
+
+    pub fn instance(&self) -> &str {
+        match self {
+            Handle::Account { instance, .. } => instance,
+            Handle::Tag { instance, .. } => instance,
+        }
+    }
+
+    /// Public web page of the account or the hashtag.
+    pub fn link(&self) -> String {
+        match self {
+            Handle::Account { username, instance } => format!("https://{}/@{}", instance, username),
+            Handle::Tag { tag, instance } => format!("https://{}/tags/{}", instance, tag),
+        }
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Handle::Account { username, instance } => write!(f, "@{}@{}", username, instance),
+            Handle::Tag { tag, instance } => write!(f, "#{}@{}", tag, instance),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Account {
+    id: String,
+    username: String,
+    acct: String,
+    display_name: String,
+    url: String,
+    avatar: Option<String>,
+}
+
+impl Account {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+    // `user` for local accounts, `user@instance` for remote ones
+    pub fn acct(&self) -> &str {
+        &self.acct
+    }
+    pub fn display_name(&self) -> &str {
+        match self.display_name.is_empty() {
+            true => &self.username,
+            false => &self.display_name,
+        }
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn avatar(&self) -> Option<&str> {
+        self.avatar.as_deref()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Image,
+    Gifv,
+    Video,
+    Audio,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Attachment {
+    id: String,
+    #[serde(rename = "type")]
+    kind: AttachmentKind,
+    url: String,
+    preview_url: Option<String>,
+    description: Option<String>,
+}
+
+impl Attachment {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn kind(&self) -> AttachmentKind {
+        self.kind
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn preview_url(&self) -> Option<&str> {
+        self.preview_url.as_deref()
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Status {
+    id: String,
+    created_at: DateTime<Utc>,
+    uri: String,
+    url: Option<String>,
+    content: String,
+    spoiler_text: String,
+    account: Account,
+    reblog: Option<Box<Status>>,
+    media_attachments: Vec<Attachment>,
+}
+
+impl Status {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    // `url` is missing for some remote statuses
+    pub fn url(&self) -> &str {
+        self.url.as_deref().unwrap_or(&self.uri)
+    }
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+    // content warning
+    pub fn spoiler_text(&self) -> Option<&str> {
+        Some(self.spoiler_text.as_str()).filter(|s| !s.is_empty())
+    }
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+    // boosted status
+    pub fn reblog(&self) -> Option<&Status> {
+        self.reblog.as_deref()
+    }
+    pub fn media_attachments(&self) -> &[Attachment] {
+        &self.media_attachments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttachmentKind, Handle, Status};
+
+    #[test]
+    fn test_parse_handle() {
+        assert_eq!(
+            Handle::parse("@rust@Fosstodon.org").unwrap(),
+            Handle::Account {
+                username: "rust".to_string(),
+                instance: "fosstodon.org".to_string()
+            }
+        );
+        assert_eq!(
+            Handle::parse("#RustLang@mastodon.social").unwrap(),
+            Handle::Tag {
+                tag: "rustlang".to_string(),
+                instance: "mastodon.social".to_string()
+            }
+        );
+        assert_eq!(
+            Handle::parse(" @rust@fosstodon.org ").unwrap().to_string(),
+            "@rust@fosstodon.org"
+        );
+        for invalid in &[
+            "rust",
+            "@rust",
+            "@rust@",
+            "@@fosstodon.org",
+            "#rust lang@mastodon.social",
+            "https://fosstodon.org/@rust",
+            "@x@127.0.0.1",
+            "@x@127.0.0.1:8080",
+            "@x@[::1]",
+            "#x@fosstodon.org:8080",
+        ] {
+            assert!(Handle::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_handle_link() {
+        assert_eq!(
+            Handle::parse("@rust@fosstodon.org").unwrap().link(),
+            "https://fosstodon.org/@rust"
+        );
+        assert_eq!(
+            Handle::parse("#rust@mastodon.social").unwrap().link(),
+            "https://mastodon.social/tags/rust"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_statuses() {
+        let statuses: Vec<Status> =
+            serde_json::from_str(include_str!("../fixtures/statuses.json")).unwrap();
+        assert_eq!(statuses.len(), 2);
+
+        let boost = &statuses[0];
+        assert_eq!(boost.id(), "106432110000000002");
+        assert_eq!(boost.content(), "");
+        let original = boost.reblog().unwrap();
+        assert_eq!(original.account().acct(), "ferris@social.rust-lang.org");
+        assert_eq!(original.media_attachments().len(), 1);
+        assert_eq!(
+            original.media_attachments()[0].kind(),
+            AttachmentKind::Image
+        );
+
+        let with_cw = &statuses[1];
+        assert_eq!(with_cw.spoiler_text(), Some("rust 1.53 spoilers"));
+        assert_eq!(with_cw.reblog().map(|s| s.id()), None);
+        assert_eq!(
+            with_cw.url(),
+            "https://fosstodon.org/@rust/106432100000000001"
+        );
+        assert_eq!(
+            with_cw.media_attachments()[0].kind(),
+            AttachmentKind::Unknown
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use reddit_collector::client::RedditClient;
+use reddit_collector::types::Listing;
+
+#[tokio::main]
+async fn main() {
+    let client = RedditClient::default();
+    let listing = Listing::parse("r/rust").unwrap();
+    println!("{:?}", client.get_info(&listing).await.unwrap());
+    let page = client.get_posts(&listing, None).await.unwrap();
+    for post in page.posts {
+        println!(
+            "{} ({} / {}): {:?}",
+            post.title,
+            post.score,
+            post.num_comments,
+            post.media()
+        );
+    }
+    let found = client.search_subreddits("rust lang", 10).await.unwrap();
+    println!("{:?}", found);
+}
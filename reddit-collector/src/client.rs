@@ -0,0 +1,295 @@
+use crate::result::{Error, Result};
+use crate::types::{Listing, ListingResponse, Post, Subreddit, Thing, User, BASE_URL};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+
+const PAGE_LIMIT: u8 = 100;
+// reddit throttles requests with default user agents
+const USER_AGENT: &str = concat!("feeder/", env!("CARGO_PKG_VERSION"));
+
+/// Client for public reddit JSON listings, no OAuth application needed.
+#[derive(Debug, Clone)]
+pub struct RedditClient {
+    client: Client,
+    base_url: String,
+}
+
+/// Title and icon of a subreddit or user.
+#[derive(Debug, Clone)]
+pub struct ListingInfo {
+    pub listing: Listing,
+    pub title: String,
+    pub icon: Option<String>,
+}
+
+/// Posts of a listing, newest first.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub listing: Listing,
+    pub posts: Vec<Post>,
+    pub after: Option<String>,
+}
+
+impl Default for RedditClient {
+    fn default() -> Self {
+        Self::new(
+            Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .expect("can't create http client"),
+        )
+    }
+}
+
+impl RedditClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            base_url: BASE_URL.to_string(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub async fn get_info(&self, listing: &Listing) -> Result<ListingInfo> {
+        let path = format!("{}/about.json", listing.path());
+        let (title, icon) = match listing {
+            Listing::Subreddit(_) => {
+                let subreddit: Thing<Subreddit> = self.get(path.as_str(), &[]).await?;
+                let title = match subreddit.data.title.is_empty() {
+                    true => listing.to_string(),
+                    false => subreddit.data.title.clone(),
+                };
+                (title, subreddit.data.icon())
+            }
+            Listing::User(_) => {
+                let user: Thing<User> = self.get(path.as_str(), &[]).await?;
+                (
+                    format!("u/{}", user.data.name),
+                    user.data.icon_img.filter(|i| !i.is_empty()),
+                )
+            }
+        };
+        Ok(ListingInfo {
+            listing: listing.clone(),
+            title,
+            icon,
+        })
+    }
+
+    /// Returns the newest posts or posts after `after` cursor.
+    pub async fn get_posts(&self, listing: &Listing, after: Option<&str>) -> Result<Page> {
+        let path = match listing {
+            Listing::Subreddit(_) => format!("{}/new.json", listing.path()),
+            Listing::User(_) => format!("{}/submitted.json", listing.path()),
+        };
+        let limit = PAGE_LIMIT.to_string();
+        let mut query = vec![("sort", "new"), ("limit", limit.as_str())];
+        if let Some(after) = after {
+            query.push(("after", after));
+        }
+        let response: ListingResponse<Post> = self.get(path.as_str(), &query).await?;
+        Ok(Page {
+            listing: listing.clone(),
+            posts: response
+                .data
+                .children
+                .into_iter()
+                .filter(|c| c.kind == "t3")
+                .map(|c| c.data)
+                .collect(),
+            after: response.data.after,
+        })
+    }
+
+    /// Walks the listing from the newest posts to the oldest ones until `until` timestamp reached.
+    pub async fn get_history(&self, listing: &Listing, until: i64) -> Result<Vec<Post>> {
+        let mut result = vec![];
+        let mut after: Option<String> = None;
+        loop {
+            let page = self.get_posts(listing, after.as_deref()).await?;
+            let mut reached = page.posts.is_empty();
+            for post in page.posts {
+                // pinned posts are always on top and may be old
+                match post.created_utc() < until {
+                    true if !post.stickied => reached = true,
+                    true => {}
+                    false => result.push(post),
+                }
+            }
+            trace!("got {} posts of {}", result.len(), listing);
+            if reached || page.after.is_none() || page.after == after {
+                break;
+            }
+            after = page.after;
+        }
+        Ok(result)
+    }
+
+    pub async fn search_subreddits(&self, query: &str, limit: u8) -> Result<Vec<Subreddit>> {
+        let limit = limit.to_string();
+        let response: ListingResponse<Subreddit> = self
+            .get(
+                "/subreddits/search.json",
+                &[("q", query), ("limit", limit.as_str())],
+            )
+            .await?;
+        Ok(response
+            .data
+            .children
+            .into_iter()
+            .filter(|c| c.kind == "t5")
+            .map(|c| c.data)
+            .collect())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path).as_str())
+            // otherwise urls are html-escaped
+            .query(&[("raw_json", "1")])
+            .query(query)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => return Err(Error::NotFound),
+            StatusCode::FORBIDDEN => return Err(Error::Forbidden),
+            StatusCode::TOO_MANY_REQUESTS => return Err(Error::RateLimited),
+            status => return Err(Error::HttpError(status.as_u16())),
+        }
+        // unknown subreddits are redirected to the search page
+        if !response.url().path().ends_with(".json") {
+            return Err(Error::NotFound);
+        }
+        let content = response.text().await?;
+        serde_json::from_str(content.as_str()).map_err(|e| Error::Internal(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RedditClient;
+    use crate::result::Error;
+    use crate::types::Listing;
+    use mockito::{Matcher, Mock, Server, ServerGuard};
+
+    fn json(server: &mut ServerGuard, path: &str, query: Matcher, body: &str) -> Mock {
+        server
+            .mock("GET", path)
+            .match_query(query)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+    }
+
+    struct MockServer {
+        server: ServerGuard,
+        next_page: Mock,
+        // mocks are removed once they are dropped
+        _mocks: Vec<Mock>,
+    }
+
+    // serves the fixtures of the rust subreddit, every request matches one mock
+    async fn run_mock_server() -> MockServer {
+        let mut server = Server::new_async().await;
+        let next_page = json(
+            &mut server,
+            "/r/rust/new.json",
+            Matcher::UrlEncoded("after".into(), "t3_o2b3c3".into()),
+            r#"{"kind":"Listing","data":{"after":null,"children":[]}}"#,
+        )
+        .expect(1)
+        .create_async()
+        .await;
+        let mocks = vec![
+            // the first page has no cursor
+            json(
+                &mut server,
+                "/r/rust/new.json",
+                Matcher::Regex(r"limit=\d+$".into()),
+                include_str!("../fixtures/new.json"),
+            )
+            .create_async()
+            .await,
+            json(
+                &mut server,
+                "/subreddits/search.json",
+                Matcher::UrlEncoded("q".into(), "rust".into()),
+                include_str!("../fixtures/subreddits.json"),
+            )
+            .create_async()
+            .await,
+            json(
+                &mut server,
+                "/r/private/new.json",
+                Matcher::Any,
+                r#"{"reason":"private","error":403}"#,
+            )
+            .with_status(403)
+            .create_async()
+            .await,
+            json(
+                &mut server,
+                "/user/nobody/about.json",
+                Matcher::Any,
+                r#"{"message":"Not Found","error":404}"#,
+            )
+            .with_status(404)
+            .create_async()
+            .await,
+        ];
+        MockServer {
+            server,
+            next_page,
+            _mocks: mocks,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_history() {
+        let server = run_mock_server().await;
+        let client = RedditClient::default().with_base_url(server.server.url().as_str());
+        let rust = Listing::Subreddit("rust".to_string());
+
+        let posts = client.get_history(&rust, 0).await.unwrap();
+        assert_eq!(posts.len(), 3);
+        server.next_page.assert_async().await;
+
+        // the stickied post is older but doesn't stop the walk
+        let posts = client.get_history(&rust, 1623930000).await.unwrap();
+        assert_eq!(
+            posts.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            vec!["o2b3c2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_subreddits() {
+        let server = run_mock_server().await;
+        let client = RedditClient::default().with_base_url(server.server.url().as_str());
+
+        let found = client.search_subreddits("rust", 10).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].display_name, "rust");
+        assert_eq!(
+            found[0].icon(),
+            Some("https://styles.redditmedia.com/rust_icon.png".to_string())
+        );
+        assert_eq!(found[1].icon(), None);
+
+        assert!(matches!(
+            client
+                .get_posts(&Listing::Subreddit("private".to_string()), None)
+                .await,
+            Err(Error::Forbidden)
+        ));
+        assert!(matches!(
+            client.get_info(&Listing::User("nobody".to_string())).await,
+            Err(Error::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,6 @@
+#[macro_use]
+extern crate log;
+
+pub mod client;
+pub mod result;
+pub mod types;
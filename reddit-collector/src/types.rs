@@ -0,0 +1,355 @@
+use crate::result::{Error, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const BASE_URL: &str = "https://www.reddit.com";
+
+/// Followed listing: subreddit (`r/rust`) or user submissions (`u/spez`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Listing {
+    Subreddit(String),
+    User(String),
+}
+
+impl Listing {
+    /// Accepts `r/rust`, `/r/rust/`, `https://www.reddit.com/r/rust/new`, `u/spez` and `user/spez`.
+    pub fn parse(listing: &str) -> Result<Self> {
+        let invalid = || Error::InvalidSource(listing.to_string());
+        let trimmed = listing
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .trim_start_matches("old.")
+            .trim_start_matches("reddit.com")
+            .trim_start_matches('/');
+        let mut parts = trimmed.split('/');
+        let prefix = parts.next().ok_or_else(invalid)?;
+        let name = parts
+            .next()
+            .filter(|n| is_valid_name(n))
+            .ok_or_else(invalid)?;
+        match prefix {
+            "r" => Ok(Listing::Subreddit(name.to_lowercase())),
+            "u" | "user" => Ok(Listing::User(name.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Listing::Subreddit(name) => name,
+            Listing::User(name) => name,
+        }
+    }
+
+    pub(crate) fn path(&self) -> String {
+        match self {
+            Listing::Subreddit(name) => format!("/r/{}", name),
+            Listing::User(name) => format!("/user/{}", name),
+        }
+    }
+
+    pub fn link(&self) -> String {
+        format!("{}{}", BASE_URL, self.path())
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Listing::Subreddit(name) => write!(f, "r/{}", name),
+            Listing::User(name) => write!(f, "u/{}", name),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Generic reddit listing response, `after` is a cursor of the next page.
+#[derive(Debug, Deserialize)]
+pub struct Thing<T> {
+    pub kind: String,
+    pub data: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListingData<T> {
+    pub after: Option<String>,
+    pub children: Vec<Thing<T>>,
+}
+
+pub type ListingResponse<T> = Thing<ListingData<T>>;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Subreddit {
+    pub display_name: String,
+    pub title: String,
+    pub public_description: String,
+    pub icon_img: Option<String>,
+    pub community_icon: Option<String>,
+    pub url: String,
+    #[serde(default)]
+    pub over18: bool,
+}
+
+impl Subreddit {
+    pub fn icon(&self) -> Option<String> {
+        self.community_icon
+            .iter()
+            .chain(self.icon_img.iter())
+            .find(|i| !i.is_empty())
+            .map(|i| i.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct User {
+    pub name: String,
+    pub icon_img: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Image {
+    #[serde(alias = "u")]
+    pub url: Option<String>,
+    #[serde(alias = "x")]
+    pub width: Option<u32>,
+    #[serde(alias = "y")]
+    pub height: Option<u32>,
+    pub gif: Option<String>,
+    pub mp4: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MediaMetadata {
+    // `Image` or `AnimatedImage`
+    pub e: String,
+    // missing for failed uploads
+    pub s: Option<Image>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GalleryItem {
+    pub media_id: String,
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GalleryData {
+    pub items: Vec<GalleryItem>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PreviewImage {
+    pub source: Image,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Preview {
+    pub images: Vec<PreviewImage>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RedditVideo {
+    pub fallback_url: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SecureMedia {
+    pub reddit_video: Option<RedditVideo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Image,
+    Animation,
+    Video,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Media {
+    pub kind: MediaKind,
+    pub url: String,
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Post {
+    pub id: String,
+    pub subreddit: String,
+    pub author: String,
+    pub title: String,
+    // html, `None` for link posts
+    pub selftext_html: Option<String>,
+    pub url: String,
+    pub permalink: String,
+    pub created_utc: f64,
+    pub score: i64,
+    pub num_comments: i64,
+    pub link_flair_text: Option<String>,
+    #[serde(default)]
+    pub is_self: bool,
+    #[serde(default)]
+    pub over_18: bool,
+    #[serde(default)]
+    pub stickied: bool,
+    pub media_metadata: Option<HashMap<String, MediaMetadata>>,
+    pub gallery_data: Option<GalleryData>,
+    pub preview: Option<Preview>,
+    pub secure_media: Option<SecureMedia>,
+}
+
+impl Post {
+    pub fn link(&self) -> String {
+        format!("{}{}", BASE_URL, self.permalink)
+    }
+
+    pub fn created_utc(&self) -> i64 {
+        self.created_utc as i64
+    }
+
+    /// Gallery items in gallery order, otherwise preview image and hosted video.
+    pub fn media(&self) -> Vec<Media> {
+        if let (Some(gallery), Some(metadata)) = (&self.gallery_data, &self.media_metadata) {
+            return gallery
+                .items
+                .iter()
+                .filter_map(|item| {
+                    let image = metadata.get(&item.media_id)?.s.as_ref()?;
+                    let (kind, url) = match (&image.mp4, &image.gif, &image.url) {
+                        (Some(mp4), _, _) => (MediaKind::Animation, mp4),
+                        (None, Some(gif), _) => (MediaKind::Animation, gif),
+                        (None, None, Some(url)) => (MediaKind::Image, url),
+                        _ => return None,
+                    };
+                    Some(Media {
+                        kind,
+                        url: url.clone(),
+                        caption: item.caption.clone(),
+                    })
+                })
+                .collect();
+        }
+        let mut media = vec![];
+        if let Some(url) = self
+            .preview
+            .as_ref()
+            .and_then(|p| p.images.first())
+            .and_then(|i| i.source.url.clone())
+        {
+            media.push(Media {
+                kind: MediaKind::Image,
+                url,
+                caption: None,
+            })
+        }
+        if let Some(video) = self
+            .secure_media
+            .as_ref()
+            .and_then(|m| m.reddit_video.as_ref())
+        {
+            media.push(Media {
+                kind: MediaKind::Video,
+                url: video.fallback_url.clone(),
+                caption: None,
+            })
+        }
+        media
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Listing, ListingResponse, Media, MediaKind, Post};
+
+    #[test]
+    fn test_parse_listing() {
+        let rust = Listing::Subreddit("rust".to_string());
+        assert_eq!(Listing::parse("r/rust").unwrap(), rust);
+        assert_eq!(Listing::parse("/r/Rust/").unwrap(), rust);
+        assert_eq!(
+            Listing::parse("https://www.reddit.com/r/rust/new/").unwrap(),
+            rust
+        );
+        assert_eq!(
+            Listing::parse("u/spez").unwrap(),
+            Listing::User("spez".to_string())
+        );
+        assert_eq!(Listing::parse("user/spez").unwrap().to_string(), "u/spez");
+        assert!(Listing::parse("rust").is_err());
+        assert!(Listing::parse("r/").is_err());
+        assert!(Listing::parse("r/rust lang").is_err());
+        assert!(Listing::parse("https://example.com/r/rust").is_err());
+    }
+
+    #[test]
+    fn test_parse_posts() {
+        let listing: ListingResponse<Post> =
+            serde_json::from_str(include_str!("../fixtures/new.json")).unwrap();
+        assert_eq!(listing.data.after, Some("t3_o2b3c3".to_string()));
+        let posts: Vec<Post> = listing.data.children.into_iter().map(|c| c.data).collect();
+        assert_eq!(posts.len(), 3);
+
+        let self_post = &posts[0];
+        assert!(self_post.is_self);
+        assert_eq!(self_post.score, 154);
+        assert_eq!(self_post.num_comments, 42);
+        assert_eq!(self_post.link_flair_text, Some("discussion".to_string()));
+        assert_eq!(
+            self_post.selftext_html,
+            Some("<div class=\"md\"><p>What are you working on?</p></div>".to_string())
+        );
+        assert_eq!(self_post.created_utc(), 1623925800);
+        assert_eq!(
+            self_post.link(),
+            "https://www.reddit.com/r/rust/comments/o2b3c1/whats_everyone_working_on/"
+        );
+        assert!(self_post.media().is_empty());
+
+        let gallery = &posts[1];
+        assert_eq!(
+            gallery.media(),
+            vec![
+                Media {
+                    kind: MediaKind::Image,
+                    url: "https://preview.redd.it/ferris.jpg?width=1024".to_string(),
+                    caption: Some("Ferris".to_string()),
+                },
+                Media {
+                    kind: MediaKind::Animation,
+                    url: "https://preview.redd.it/crab.gif?format=mp4".to_string(),
+                    caption: None,
+                },
+            ]
+        );
+
+        let link = &posts[2];
+        assert_eq!(link.selftext_html, None);
+        assert_eq!(
+            link.url,
+            "https://blog.rust-lang.org/2021/06/17/Rust-1.53.0.html"
+        );
+        assert_eq!(
+            link.media(),
+            vec![
+                Media {
+                    kind: MediaKind::Image,
+                    url: "https://external-preview.redd.it/rust.png?auto=webp".to_string(),
+                    caption: None,
+                },
+                Media {
+                    kind: MediaKind::Video,
+                    url: "https://v.redd.it/abc/DASH_720.mp4".to_string(),
+                    caption: None,
+                },
+            ]
+        );
+    }
+}
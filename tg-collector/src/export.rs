@@ -0,0 +1,316 @@
+/// Telegram Desktop export (`result.json`) reader, works without tdlib session.
+use crate::result::{Error, Result};
+use crate::types::{AnimationMeta, AudioMeta, FileType, ImageMeta, VideoMeta};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::path::Path;
+
+// tdlib id of a server message is the server id shifted by 20 bits
+const MESSAGE_ID_SHIFT: i64 = 20;
+// tdlib id of a supergroup or a channel chat is `-100<id>`
+const CHANNEL_CHAT_ID_OFFSET: i64 = 1_000_000_000_000;
+const CHANNEL_TYPES: [&str; 4] = [
+    "public_channel",
+    "private_channel",
+    "public_supergroup",
+    "private_supergroup",
+];
+
+/// Export of a single chat or the whole account.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Export {
+    Account { chats: ChatList },
+    Chat(ExportChat),
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatList {
+    list: Vec<ExportChat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportChat {
+    pub id: i64,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub messages: Vec<ExportMessage>,
+}
+
+impl ExportChat {
+    pub fn is_channel(&self) -> bool {
+        CHANNEL_TYPES.contains(&self.type_.as_str())
+    }
+
+    /// Chat id as tdlib reports it, so exported chats match channels of the live session.
+    pub fn chat_id(&self) -> i64 {
+        match self.is_channel() && self.id > 0 {
+            true => -(CHANNEL_CHAT_ID_OFFSET + self.id),
+            false => self.id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextEntity {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub text: String,
+    pub href: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TextPart {
+    Plain(String),
+    Entity(TextEntity),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Text {
+    Plain(String),
+    Parts(Vec<TextPart>),
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text::Plain(String::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportMessage {
+    pub id: i64,
+    // `message` or `service`
+    #[serde(rename = "type")]
+    pub type_: String,
+    // local time of the exporting machine
+    pub date: String,
+    // newer exports only
+    pub date_unixtime: Option<String>,
+    #[serde(default)]
+    pub text: Text,
+    pub photo: Option<String>,
+    pub file: Option<String>,
+    pub file_name: Option<String>,
+    pub media_type: Option<String>,
+    pub mime_type: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_seconds: Option<i32>,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+}
+
+/// Media of the message, `path` is relative to the export directory.
+#[derive(Debug)]
+pub struct ExportFile {
+    pub path: String,
+    pub file_type: FileType,
+    pub file_name: Option<String>,
+}
+
+impl ExportMessage {
+    pub fn is_service(&self) -> bool {
+        self.type_ != "message"
+    }
+
+    /// Message id as tdlib reports it.
+    pub fn message_id(&self) -> i64 {
+        self.id << MESSAGE_ID_SHIFT
+    }
+
+    pub fn timestamp(&self) -> Option<i64> {
+        self.date_unixtime
+            .as_ref()
+            .and_then(|d| d.parse().ok())
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(self.date.as_str(), "%Y-%m-%dT%H:%M:%S")
+                    .ok()
+                    .map(|d| d.timestamp())
+            })
+    }
+
+    /// Html with the same tags `DefaultTelegramParser` produces, `None` for empty text.
+    pub fn content(&self) -> Option<String> {
+        let content = match &self.text {
+            Text::Plain(text) => text.clone(),
+            Text::Parts(parts) => parts
+                .iter()
+                .map(|part| match part {
+                    TextPart::Plain(text) => text.clone(),
+                    TextPart::Entity(entity) => format_entity(entity),
+                })
+                .collect(),
+        };
+        Some(content).filter(|c| !c.is_empty())
+    }
+
+    pub fn files(&self) -> Vec<ExportFile> {
+        let mut files = vec![];
+        if let Some(photo) = self.photo.as_ref().filter(|p| is_included(p)) {
+            files.push(ExportFile {
+                path: photo.clone(),
+                file_type: FileType::Image(ImageMeta {
+                    width: self.width.unwrap_or_default(),
+                    height: self.height.unwrap_or_default(),
+                }),
+                file_name: None,
+            })
+        }
+        if let Some(file) = self.file.as_ref().filter(|f| is_included(f)) {
+            let file_type = match self.media_type.as_deref() {
+                Some("animation") => FileType::Animation(AnimationMeta {
+                    duration: self.duration_seconds.unwrap_or_default(),
+                    width: self.width.unwrap_or_default(),
+                    height: self.height.unwrap_or_default(),
+                    mime_type: self.mime_type.clone().unwrap_or_default(),
+                }),
+                Some("video_file") | Some("video_message") => FileType::Video(VideoMeta {
+                    duration: self.duration_seconds.unwrap_or_default(),
+                    width: self.width.unwrap_or_default(),
+                    height: self.height.unwrap_or_default(),
+                }),
+                Some("audio_file") | Some("voice_message") => FileType::Audio(AudioMeta {
+                    duration: self.duration_seconds.unwrap_or_default(),
+                    title: self.title.clone().unwrap_or_default(),
+                    performer: self.performer.clone().unwrap_or_default(),
+                }),
+                Some("sticker") => FileType::Image(ImageMeta {
+                    width: self.width.unwrap_or_default(),
+                    height: self.height.unwrap_or_default(),
+                }),
+                _ => FileType::Document,
+            };
+            files.push(ExportFile {
+                path: file.clone(),
+                file_type,
+                file_name: self.file_name.clone().or_else(|| {
+                    Path::new(file)
+                        .file_name()
+                        .and_then(|f| f.to_str())
+                        .map(String::from)
+                }),
+            })
+        }
+        files
+    }
+}
+
+// not exported media is replaced by a note like `(File not included. ...)`
+fn is_included(path: &str) -> bool {
+    !path.is_empty() && !path.starts_with('(')
+}
+
+fn format_entity(entity: &TextEntity) -> String {
+    let text = entity.text.as_str();
+    match entity.type_.as_str() {
+        "bold" => format!("<b>{}</b>", text),
+        "italic" => format!("<i>{}</i>", text),
+        "code" => format!("<code>{}</code>", text),
+        "pre" => match entity.language.as_deref() {
+            Some(language) if !language.is_empty() => format!("<pre><code>{}</code></pre>", text),
+            _ => format!("<pre>{}</pre>", text),
+        },
+        "strikethrough" => format!("<strike>{}</strike>", text),
+        "underline" => format!("<u>{}</u>", text),
+        "phone" => format!("<phone>{}</phone>", text),
+        "link" => format!("<a>{}</a>", text),
+        "text_link" => format!(
+            r#"<a href="{}">{}</a>"#,
+            entity.href.clone().unwrap_or_default(),
+            text
+        ),
+        _ => text.to_string(),
+    }
+}
+
+/// Parses `result.json`, returns all exported chats.
+pub fn parse_export(data: &str) -> Result<Vec<ExportChat>> {
+    let export: Export = serde_json::from_str(data)
+        .map_err(|e| Error::Common(format!("invalid telegram export: {}", e)))?;
+    Ok(match export {
+        Export::Account { chats } => chats.list,
+        Export::Chat(chat) => vec![chat],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_export;
+    use crate::types::FileType;
+
+    #[test]
+    fn test_parse_channel_export() {
+        let chats = parse_export(include_str!("../fixtures/result.json")).unwrap();
+        assert_eq!(chats.len(), 1);
+        let chat = &chats[0];
+        assert!(chat.is_channel());
+        assert_eq!(chat.chat_id(), -1001234567890);
+        assert_eq!(chat.name, Some("Rust News".to_string()));
+        assert_eq!(chat.messages.len(), 4);
+
+        let service = &chat.messages[0];
+        assert!(service.is_service());
+
+        let text = &chat.messages[1];
+        assert!(!text.is_service());
+        assert_eq!(text.message_id(), 2 << 20);
+        assert_eq!(text.timestamp(), Some(1623844800));
+        assert_eq!(
+            text.content(),
+            Some(
+                "Rust <b>1.53</b> is out, see <a href=\"https://blog.rust-lang.org\">blog</a> \
+                and <code>cargo update</code>"
+                    .to_string()
+            )
+        );
+        assert!(text.files().is_empty());
+
+        let photo = &chat.messages[2];
+        assert_eq!(photo.content(), None);
+        let files = photo.files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "photos/photo_1@16-06-2021_12-05-00.jpg");
+        match &files[0].file_type {
+            FileType::Image(meta) => assert_eq!((meta.width, meta.height), (1280, 720)),
+            t => panic!("unexpected file type: {:?}", t),
+        }
+
+        let animation = &chat.messages[3];
+        assert_eq!(animation.content(), Some("ferris".to_string()));
+        // no `date_unixtime` in older exports
+        assert_eq!(animation.timestamp(), Some(1623845100));
+        let files = animation.files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_name, Some("ferris.mp4".to_string()));
+        match &files[0].file_type {
+            FileType::Animation(meta) => assert_eq!(meta.mime_type, "video/mp4"),
+            t => panic!("unexpected file type: {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_parse_account_export() {
+        let chats = parse_export(
+            r#"{"chats": {"list": [
+                {"id": 42, "name": "Friend", "type": "personal_chat", "messages": []},
+                {"id": 1234567890, "name": "Rust News", "type": "public_channel", "messages": [
+                    {"id": 1, "type": "message", "date": "2021-06-16T12:00:00",
+                     "file": "(File not included. Change data exporting settings to download.)",
+                     "media_type": "video_file", "text": ""}
+                ]}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(chats.len(), 2);
+        assert!(!chats[0].is_channel());
+        assert_eq!(chats[0].chat_id(), 42);
+        assert!(chats[1].messages[0].files().is_empty());
+        assert!(parse_export("{}").is_err());
+    }
+}
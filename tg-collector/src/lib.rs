@@ -3,6 +3,7 @@ extern crate log;
 
 pub use rust_tdlib::types::*;
 
+pub mod export;
 pub mod result;
 pub mod tg_client;
 mod traits;
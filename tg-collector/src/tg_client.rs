@@ -26,7 +26,7 @@ use rust_tdlib::errors::RTDResult;
 use std::collections::VecDeque;
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::task::JoinHandle;
 
 pub type ApiId = i32;
@@ -161,6 +161,13 @@ impl traits::TelegramAsyncApi for Client<TdJson> {
     }
 }
 
+/// Files downloaded now and waiting for their turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadQueueState {
+    pub in_progress: usize,
+    pub queued: usize,
+}
+
 #[derive(Debug, Default)]
 struct DownloadQueue {
     queue_size: usize,
@@ -180,6 +187,13 @@ impl DownloadQueue {
         debug!("download queue state: {:?}", self);
     }
 
+    pub fn state(&self) -> DownloadQueueState {
+        DownloadQueueState {
+            in_progress: self.in_progress.len(),
+            queued: self.queue.len(),
+        }
+    }
+
     pub fn is_in_progress(&self, obj: &i32) -> bool {
         self.in_progress.contains(&obj)
     }
@@ -313,11 +327,17 @@ impl TgClientBuilder {
     }
 }
 
+// gets the final state once the opened client is closed
+type ClosedState = watch::Receiver<Option<std::result::Result<ClientState, String>>>;
+
 #[derive(Clone)]
 pub struct TgClient {
     client: Client<TdJson>,
     worker: Worker<AuthHandler, TdJson>,
     download_queue: Arc<Mutex<DownloadQueue>>,
+    worker_started: bool,
+    // none until the client is opened
+    closed: Option<ClosedState>,
 }
 
 impl TgClient {
@@ -363,10 +383,16 @@ impl TgClient {
             client,
             worker,
             download_queue,
+            worker_started: false,
+            closed: None,
         };
         tg
     }
 
+    pub fn download_queue_state(&self) -> DownloadQueueState {
+        self.download_queue.lock().unwrap().state()
+    }
+
     pub fn start_listen_updates(&mut self, updates_sender: mpsc::Sender<TgUpdate>) -> Result<()> {
         let (sx, mut rx) = mpsc::channel::<Box<Update>>(100);
         self.client.set_updates_sender(sx)?;
@@ -440,22 +466,52 @@ impl TgClient {
         Ok(())
     }
 
+    /// Starts the worker and opens the client, returned handle finishes once the client is closed.
+    ///
+    /// Starting the client which is still open only returns another handle of it.
     pub async fn start(&mut self) -> Result<JoinHandle<Result<ClientState>>> {
-        set_log_verbosity_level(1);
-        self.worker.start();
+        if !self.worker_started {
+            set_log_verbosity_level(1);
+            self.worker.start();
+            self.worker_started = true;
+        }
+        let mut closed = match &self.closed {
+            Some(closed) if closed.borrow().is_none() => closed.clone(),
+            _ => self.open().await?,
+        };
+        Ok(tokio::spawn(async move {
+            loop {
+                let state = closed.borrow().clone();
+                if let Some(state) = state {
+                    return state.map_err(Error::Common);
+                }
+                if closed.changed().await.is_err() {
+                    return Err(Error::Common("client state is lost".to_string()));
+                }
+            }
+        }))
+    }
+
+    async fn open(&mut self) -> Result<ClosedState> {
         let client = self.worker.bind_client(self.client.to_owned()).await?;
         log::debug!("telegram client bound");
         let state = self.worker.wait_client_state(&client).await?;
-        if state == ClientState::Opened {
-            log::debug!("client opened");
-            self.client = client.clone();
-            let worker = self.worker.clone();
-            Ok(tokio::spawn(async move {
-                Ok(worker.wait_client_state(&client).await?)
-            }))
-        } else {
-            Err(Error::Unauthorized("can't authorize client"))
+        if state != ClientState::Opened {
+            return Err(Error::Unauthorized("can't authorize client"));
         }
+        log::debug!("client opened");
+        self.client = client.clone();
+        let (closed_sender, closed) = watch::channel(None);
+        let worker = self.worker.clone();
+        tokio::spawn(async move {
+            let state = worker
+                .wait_client_state(&client)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = closed_sender.send(Some(state));
+        });
+        self.closed = Some(closed.clone());
+        Ok(closed)
     }
 
     pub async fn get_chat(&self, chat_id: &i64) -> Result<Chat> {
@@ -0,0 +1,11 @@
+use tg_web_collector::client::TgWebClient;
+
+#[tokio::main]
+async fn main() {
+    let client = TgWebClient::default();
+    let page = client.get_channel_page("durov", None).await.unwrap();
+    println!("{:?}", page.channel);
+    for post in page.posts {
+        println!("{:?}", post);
+    }
+}
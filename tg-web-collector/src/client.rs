@@ -0,0 +1,130 @@
+use crate::parser::parse_channel_page;
+use crate::result::{Error, Result};
+use crate::types::{ChannelPage, Post};
+use chrono::NaiveDateTime;
+use reqwest::{Client, StatusCode};
+
+const BASE_URL: &str = "https://t.me";
+
+/// Client for public channels previews (`https://t.me/s/<channel>`).
+///
+/// It doesn't need any authorization, but only public channels with enabled previews are available.
+#[derive(Debug, Clone)]
+pub struct TgWebClient {
+    client: Client,
+    base_url: String,
+}
+
+impl Default for TgWebClient {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+impl TgWebClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            base_url: BASE_URL.to_string(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns channel info and the latest posts or posts older than `before` message id.
+    pub async fn get_channel_page(
+        &self,
+        username: &str,
+        before: Option<i64>,
+    ) -> Result<ChannelPage> {
+        let username = normalize_username(username);
+        let mut request = self
+            .client
+            .get(format!("{}/s/{}", self.base_url, username).as_str());
+        if let Some(before) = before {
+            request = request.query(&[("before", before)]);
+        }
+        let response = request.send().await?;
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => return Err(Error::ChannelNotFound(username)),
+            status => return Err(Error::RequestError(status.to_string())),
+        }
+        // t.me redirects to the channel info page if previews are not available
+        if !response.url().path().starts_with("/s/") {
+            return Err(Error::ChannelNotFound(username));
+        }
+        let content = response.text().await?;
+        match parse_channel_page(content.as_str()) {
+            Ok(page) => Ok(page),
+            Err(Error::ParseError(e)) => {
+                debug!("{} is not a channel preview: {}", username, e);
+                Err(Error::ChannelNotFound(username))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Walks channel history from the newest posts to the oldest ones until `until` reached.
+    pub async fn get_channel_history(
+        &self,
+        username: &str,
+        until: NaiveDateTime,
+    ) -> Result<Vec<Post>> {
+        let mut result = vec![];
+        let mut before = None;
+        loop {
+            let page = self.get_channel_page(username, before).await?;
+            let next_before = page.next_before();
+            let mut reached = page.posts.is_empty();
+            for post in page.posts.into_iter().rev() {
+                match post.date {
+                    Some(date) if date < until => reached = true,
+                    _ => result.push(post),
+                }
+            }
+            trace!("got {} posts of {}", result.len(), username);
+            if reached || next_before.is_none() || next_before == before {
+                break;
+            }
+            before = next_before;
+        }
+        Ok(result)
+    }
+}
+
+/// Accepts `@channel`, `t.me/channel`, `https://t.me/s/channel` and plain `channel`.
+pub fn normalize_username(username: &str) -> String {
+    let username = username
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("t.me/")
+        .trim_start_matches("s/")
+        .trim_start_matches('@');
+    username
+        .split(['/', '?'].as_ref())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_username;
+
+    #[test]
+    fn test_normalize_username() {
+        assert_eq!(normalize_username("rust_news"), "rust_news");
+        assert_eq!(normalize_username("@rust_news"), "rust_news");
+        assert_eq!(normalize_username("t.me/rust_news"), "rust_news");
+        assert_eq!(normalize_username("https://t.me/s/rust_news"), "rust_news");
+        assert_eq!(
+            normalize_username("https://t.me/rust_news/101?single"),
+            "rust_news"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate log;
+
+pub mod client;
+pub mod parser;
+pub mod result;
+pub mod types;
@@ -0,0 +1,240 @@
+use crate::result::{Error, Result};
+use crate::types::{Channel, ChannelPage, Media, MediaKind, Post};
+use chrono::DateTime;
+use scraper::{ElementRef, Html, Selector};
+
+/// Parses `https://t.me/s/<channel>` page.
+pub fn parse_channel_page(content: &str) -> Result<ChannelPage> {
+    let doc = Html::parse_document(content);
+    let channel = parse_channel(&doc)?;
+    let message_selector = Selector::parse(".tgme_widget_message[data-post]").unwrap();
+    let mut posts = vec![];
+    for message in doc.select(&message_selector) {
+        match parse_post(&message) {
+            Some(post) => posts.push(post),
+            None => warn!("can't parse post: {:?}", message.value().attr("data-post")),
+        }
+    }
+    Ok(ChannelPage { channel, posts })
+}
+
+fn parse_channel(doc: &Html) -> Result<Channel> {
+    let username = select_text(doc.root_element(), ".tgme_channel_info_header_username")
+        .map(|u| u.trim_start_matches('@').to_string())
+        .ok_or_else(|| Error::ParseError("channel username not found".to_string()))?;
+    let title = select_text(doc.root_element(), ".tgme_channel_info_header_title")
+        .unwrap_or_else(|| username.clone());
+    let description = select_text(doc.root_element(), ".tgme_channel_info_description");
+    let image_selector =
+        Selector::parse(".tgme_channel_info_header .tgme_page_photo_image img").unwrap();
+    let image = doc
+        .select(&image_selector)
+        .next()
+        .and_then(|i| i.value().attr("src"))
+        .map(|i| i.to_string());
+    Ok(Channel {
+        username,
+        title,
+        description,
+        image,
+    })
+}
+
+fn parse_post(message: &ElementRef) -> Option<Post> {
+    // data-post looks like "channel/123"
+    let (channel, id) = {
+        let data_post = message.value().attr("data-post")?;
+        let mut parts = data_post.rsplitn(2, '/');
+        let id = parts.next()?.parse().ok()?;
+        (parts.next()?.to_string(), id)
+    };
+
+    let text_selector = Selector::parse(".tgme_widget_message_text").unwrap();
+    let content = message
+        .select(&text_selector)
+        .next()
+        .map(|t| t.inner_html().trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    let date_selector = Selector::parse(".tgme_widget_message_date time[datetime]").unwrap();
+    let date = message
+        .select(&date_selector)
+        .next()
+        .and_then(|t| t.value().attr("datetime"))
+        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+        .map(|d| d.naive_utc());
+
+    let views = select_text(*message, ".tgme_widget_message_views").and_then(|v| parse_views(&v));
+
+    Some(Post {
+        id,
+        channel,
+        date,
+        content,
+        media: parse_media(message),
+        views,
+    })
+}
+
+fn parse_media(message: &ElementRef) -> Vec<Media> {
+    let mut media = vec![];
+    let photo_selector = Selector::parse("a.tgme_widget_message_photo_wrap[style]").unwrap();
+    for photo in message.select(&photo_selector) {
+        if let Some(url) = photo.value().attr("style").and_then(extract_background_url) {
+            media.push(Media {
+                kind: MediaKind::Photo,
+                url,
+            })
+        }
+    }
+    let video_selector = Selector::parse("video.tgme_widget_message_video[src]").unwrap();
+    for video in message.select(&video_selector) {
+        media.push(Media {
+            kind: MediaKind::Video,
+            url: video.value().attr("src").unwrap().to_string(),
+        })
+    }
+    let document_selector = Selector::parse("a.tgme_widget_message_document_wrap[href]").unwrap();
+    for document in message.select(&document_selector) {
+        media.push(Media {
+            kind: MediaKind::Document,
+            url: document.value().attr("href").unwrap().to_string(),
+        })
+    }
+    media
+}
+
+fn select_text(element: ElementRef, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    element
+        .select(&selector)
+        .next()
+        .map(|e| e.text().collect::<String>().trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+// style="width:800px;background-image:url('https://cdn4.telesco.pe/file/a.jpg')"
+fn extract_background_url(style: &str) -> Option<String> {
+    let start = style.find("background-image:url(")? + "background-image:url(".len();
+    let rest = &style[start..];
+    let end = rest.find(')')?;
+    let url = rest[..end].trim_matches(|c| c == '\'' || c == '"');
+    match url.is_empty() {
+        true => None,
+        false => Some(url.to_string()),
+    }
+}
+
+// views are rendered as "987", "1.2K" or "3.4M"
+fn parse_views(views: &str) -> Option<u64> {
+    let views = views.trim();
+    let (number, multiplier) = match views.chars().last()? {
+        'K' => (&views[..views.len() - 1], 1_000f64),
+        'M' => (&views[..views.len() - 1], 1_000_000f64),
+        _ => (views, 1f64),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|n| (n * multiplier).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_background_url, parse_channel_page, parse_views};
+    use crate::types::{Media, MediaKind};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_views() {
+        assert_eq!(parse_views("987"), Some(987));
+        assert_eq!(parse_views("1.2K"), Some(1200));
+        assert_eq!(parse_views("3.45M"), Some(3_450_000));
+        assert_eq!(parse_views(""), None);
+        assert_eq!(parse_views("views"), None);
+    }
+
+    #[test]
+    fn test_extract_background_url() {
+        assert_eq!(
+            extract_background_url(
+                "width:800px;background-image:url('https://cdn4.telesco.pe/file/a.jpg')"
+            ),
+            Some("https://cdn4.telesco.pe/file/a.jpg".to_string())
+        );
+        assert_eq!(extract_background_url("width:800px"), None);
+    }
+
+    #[test]
+    fn test_parse_channel_page() {
+        let page = parse_channel_page(include_str!("../fixtures/channel.html")).unwrap();
+
+        assert_eq!(page.channel.username, "rust_news");
+        assert_eq!(page.channel.title, "Rust News");
+        assert_eq!(
+            page.channel.description,
+            Some("News about the Rust programming language".to_string())
+        );
+        assert_eq!(
+            page.channel.image,
+            Some("https://cdn4.telesco.pe/file/channel_photo.jpg".to_string())
+        );
+
+        assert_eq!(page.posts.len(), 3);
+        assert_eq!(page.next_before(), Some(101));
+
+        let text_post = &page.posts[0];
+        assert_eq!(text_post.id, 101);
+        assert_eq!(text_post.channel, "rust_news");
+        assert_eq!(
+            text_post.content,
+            Some("Rust 1.53 is out! <b>Or-patterns</b> are stable.".to_string())
+        );
+        assert_eq!(text_post.views, Some(1200));
+        assert_eq!(
+            text_post.date,
+            Some(NaiveDate::from_ymd(2021, 6, 17).and_hms(10, 30, 0))
+        );
+        assert!(text_post.media.is_empty());
+        assert_eq!(text_post.link(), "https://t.me/rust_news/101");
+
+        let photo_post = &page.posts[1];
+        assert_eq!(photo_post.id, 102);
+        assert_eq!(photo_post.views, Some(987));
+        assert_eq!(
+            photo_post.media,
+            vec![
+                Media {
+                    kind: MediaKind::Photo,
+                    url: "https://cdn4.telesco.pe/file/photo_1.jpg".to_string()
+                },
+                Media {
+                    kind: MediaKind::Photo,
+                    url: "https://cdn4.telesco.pe/file/photo_2.jpg".to_string()
+                },
+            ]
+        );
+
+        let media_post = &page.posts[2];
+        assert_eq!(media_post.id, 103);
+        assert_eq!(media_post.content, None);
+        assert_eq!(
+            media_post.media,
+            vec![
+                Media {
+                    kind: MediaKind::Video,
+                    url: "https://cdn4.telesco.pe/file/video.mp4".to_string()
+                },
+                Media {
+                    kind: MediaKind::Document,
+                    url: "https://t.me/rust_news/103?single".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_not_a_channel() {
+        assert!(parse_channel_page("<html><body>Not found</body></html>").is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    // channel is private, doesn't exist or has previews disabled
+    ChannelNotFound(String),
+    RequestTimeout,
+    RequestError(String),
+    // page doesn't look like a channel preview
+    ParseError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::RequestTimeout
+        } else {
+            Error::RequestError(err.to_string())
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Channel {
+    pub username: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+impl Channel {
+    pub fn link(&self) -> String {
+        format!("https://t.me/{}", self.username)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum MediaKind {
+    Photo,
+    Video,
+    Document,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Media {
+    pub kind: MediaKind,
+    pub url: String,
+}
+
+/// Single channel post as it rendered on `t.me/s/<channel>`.
+///
+/// `id` is the public message id, the one used in `t.me/<channel>/<id>` links.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Post {
+    pub id: i64,
+    pub channel: String,
+    pub date: Option<NaiveDateTime>,
+    pub content: Option<String>,
+    pub media: Vec<Media>,
+    pub views: Option<u64>,
+}
+
+impl Post {
+    pub fn link(&self) -> String {
+        format!("https://t.me/{}/{}", self.channel, self.id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelPage {
+    pub channel: Channel,
+    // posts are ordered from the oldest to the newest, as on the page
+    pub posts: Vec<Post>,
+}
+
+impl ChannelPage {
+    /// Value for the `before` parameter of the next (older) page.
+    pub fn next_before(&self) -> Option<i64> {
+        self.posts.iter().map(|p| p.id).min()
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::{sync, task::JoinHandle};
 
@@ -17,6 +18,7 @@ const API_VERSION: &str = "5.21";
 pub struct VkClient {
     runner: Arc<JoinHandle<()>>,
     jobs_sender: sync::mpsc::Sender<Job>,
+    queued: Arc<AtomicUsize>,
 }
 
 impl VkClient {
@@ -27,6 +29,7 @@ impl VkClient {
             Arc::new(worker),
         );
         let handle_run = throttler.run(max_tasks_per_tick);
+        let queued = throttler.queued();
 
         let (s, mut r) = sync::mpsc::channel(100);
         let handle_push = tokio::spawn(async move {
@@ -43,9 +46,15 @@ impl VkClient {
         Self {
             runner: Arc::new(handle),
             jobs_sender: s,
+            queued,
         }
     }
 
+    /// Number of the requests waiting for the throttler.
+    pub fn queue_len(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
     pub async fn get_my_groups(&self, offset: u32, count: u16) -> result::Result<Vec<Group>> {
         let (job, res) = JobGroupsGet::create(None, None, offset, count);
         self.jobs_sender.send(Job::GroupsGet(job)).await?;
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use futures::stream::FuturesUnordered;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{collections::VecDeque, sync::Arc};
 use tokio::{sync::Mutex, task::JoinHandle, time};
 use tokio_stream::StreamExt;
@@ -16,6 +17,8 @@ where
 {
     tick_interval: time::Duration,
     delayed: Arc<Mutex<VecDeque<T>>>,
+    // length of `delayed`, readable without the lock
+    queued: Arc<AtomicUsize>,
     worker: W,
 }
 
@@ -29,16 +32,25 @@ where
             tick_interval,
             worker,
             delayed: Default::default(),
+            queued: Default::default(),
         }
     }
 
     pub async fn push(&mut self, job: T) {
-        self.delayed.lock().await.push_back(job)
+        let mut delayed = self.delayed.lock().await;
+        delayed.push_back(job);
+        self.queued.store(delayed.len(), Ordering::Relaxed);
+    }
+
+    /// Number of the jobs waiting for their tick.
+    pub fn queued(&self) -> Arc<AtomicUsize> {
+        self.queued.clone()
     }
 
     pub fn run(&self, batch_size_per_tick: usize) -> JoinHandle<()> {
         let mut ticker = time::interval(self.tick_interval);
         let delayed = self.delayed.clone();
+        let queued = self.queued.clone();
         let worker = self.worker.clone();
         tokio::spawn(async move {
             let mut futures = FuturesUnordered::new();
@@ -61,6 +73,7 @@ where
                             }
                             log::trace!("delay {} new tasks", to_delay);
                             futures.extend(guard.drain(0..to_delay).map(|j|worker.call(j)));
+                            queued.store(guard.len(), Ordering::Relaxed);
                         }
                     }
                 }
@@ -73,6 +86,7 @@ where
 mod tests {
     use crate::throttler::{Throttler, Worker};
     use async_trait::async_trait;
+    use std::sync::atomic::Ordering;
     use std::sync::Arc;
     use tokio::{
         sync::{mpsc, Mutex},
@@ -123,4 +137,22 @@ mod tests {
         let res = time::timeout(time::Duration::from_millis(30), r.recv()).await;
         assert_eq!(res, Ok(Some(99)));
     }
+
+    #[tokio::test]
+    async fn test_throttler_queued() {
+        let (s, mut r) = mpsc::channel(10);
+        let mut t = Throttler::new(time::Duration::from_millis(30), Arc::new(Foo::new(s)));
+        let queued = t.queued();
+        for i in 1..=3 {
+            t.push(Job { i }).await;
+        }
+        assert_eq!(queued.load(Ordering::Relaxed), 3);
+
+        // the job started on a tick is awaited on the next one along with draining the next job
+        let _h = t.run(1);
+        let res = time::timeout(time::Duration::from_millis(100), r.recv()).await;
+        assert_eq!(res, Ok(Some(1)));
+        time::sleep(time::Duration::from_millis(10)).await;
+        assert_eq!(queued.load(Ordering::Relaxed), 1);
+    }
 }
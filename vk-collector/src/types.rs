@@ -4,6 +4,9 @@ use tokio::sync::oneshot;
 
 type ErrorCode = i16;
 const TOO_MANY_REQUESTS: ErrorCode = 6;
+const ACCESS_DENIED: [ErrorCode; 4] = [15, 18, 30, 203];
+// too many requests per second, flood control and rate limit of the method
+const RATE_LIMITED: [ErrorCode; 3] = [TOO_MANY_REQUESTS, 9, 29];
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct WallItem {
@@ -67,6 +70,16 @@ impl VkError {
     pub fn is_too_many_requests(&self) -> bool {
         self.error_code == TOO_MANY_REQUESTS
     }
+
+    // limits of the token, other tokens may still be used
+    pub fn is_rate_limited(&self) -> bool {
+        RATE_LIMITED.contains(&self.error_code)
+    }
+
+    // the wall is deleted, banned or private
+    pub fn is_access_denied(&self) -> bool {
+        ACCESS_DENIED.contains(&self.error_code)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]